@@ -0,0 +1,106 @@
+use std::{fs::File, io::{BufRead, BufReader}};
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::{MyResult, YearMonth};
+
+// カレンダーの下に一覧を出力する予定
+#[derive(Debug, PartialEq, Clone)]
+pub struct Event {
+    pub date: NaiveDate,
+    pub description: String,
+}
+
+// 1行に1つの「YYYY-MM-DD 説明」を記載したファイルを読み込む: 空行は無視する
+pub fn read_events(filename: &str) -> MyResult<Vec<Event>> {
+    let file = File::open(filename)
+        .map_err(|e| format!("{}: {}", filename, e))?;
+    let mut events = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let event = parse_event(text)
+            .ok_or_else(|| format!("{}: Invalid event \"{}\"", filename, text))?;
+        events.push(event);
+    }
+    // 同じ日の予定はファイルに記載した順に並べる
+    events.sort_by_key(|event| event.date);
+    Ok(events)
+}
+
+// 日付と説明は空白で区切る: 説明のない行は不正とする
+fn parse_event(text: &str) -> Option<Event> {
+    let (date, description) = text.split_once(char::is_whitespace)?;
+    let description = description.trim();
+    if description.is_empty() {
+        return None;
+    }
+    Some(Event {
+        date: NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?,
+        description: description.to_string(),
+    })
+}
+
+// 表示するfromからtoまでの月(両端を含む)の予定のみ
+pub fn in_range(events: &[Event], from: YearMonth, to: YearMonth) -> Vec<Event> {
+    events
+        .iter()
+        .filter(|event| (from..=to).contains(&(event.date.year(), event.date.month())))
+        .cloned()
+        .collect()
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{in_range, parse_event, read_events, Event};
+    use chrono::NaiveDate;
+
+    fn event(year: i32, month: u32, day: u32, description: &str) -> Event {
+        Event {
+            date: NaiveDate::from_ymd_opt(year, month, day).unwrap(),
+            description: description.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_event() {
+        assert_eq!(parse_event("2020-04-01 April Fools' Day"), Some(event(2020, 4, 1, "April Fools' Day")));
+        assert_eq!(parse_event("2020-04-01\t  Tab  "), Some(event(2020, 4, 1, "Tab")));
+        assert_eq!(parse_event("2020-04-01"), None);
+        assert_eq!(parse_event("2020-04-01 "), None);
+        assert_eq!(parse_event("2020-04-31 Nope"), None);
+        assert_eq!(parse_event("April 1 Nope"), None);
+    }
+
+    #[test]
+    fn test_read_events() {
+        let events = read_events("tests/inputs/events.txt").unwrap();
+        assert_eq!(events.first(), Some(&event(2020, 3, 31, "Quarter end")));
+        assert!(events.windows(2).all(|pair| pair[0].date <= pair[1].date));
+
+        let res = read_events("tests/inputs/bad-events.txt");
+        assert!(res.is_err());
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "tests/inputs/bad-events.txt: Invalid event \"2020-04-01\""
+        );
+    }
+
+    #[test]
+    fn test_in_range() {
+        let events = vec![
+            event(2020, 4, 1, "a"),
+            event(2020, 5, 1, "b"),
+            event(2021, 4, 1, "c"),
+        ];
+        assert_eq!(in_range(&events, (2020, 4), (2020, 4)), [event(2020, 4, 1, "a")]);
+        assert_eq!(in_range(&events, (2020, 1), (2020, 12)).len(), 2);
+        assert!(in_range(&events, (2019, 1), (2019, 12)).is_empty());
+        // 年をまたぐ期間
+        assert_eq!(in_range(&events, (2020, 5), (2021, 4)).len(), 2);
+    }
+}
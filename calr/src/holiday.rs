@@ -0,0 +1,224 @@
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+// 祝日の一覧を持つ地域: 振替休日や制定前の年は考慮せず、現行の規則をそのまま適用する
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Region {
+    Us, // アメリカ合衆国(連邦祝日)
+    Gb, // イギリス(イングランドとウェールズ)
+    De, // ドイツ(全国共通の祝日)
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "us" => Ok(Region::Us),
+            "gb" | "uk" => Ok(Region::Gb),
+            "de" => Ok(Region::De),
+            _ => Err(format!("Invalid --holidays \"{}\"", s)),
+        }
+    }
+}
+
+// 祝日の日付の決め方
+#[derive(Debug, Clone, Copy)]
+enum Rule {
+    Fixed(u32, u32),              // 月, 日
+    NthWeekday(u32, Weekday, i8), // 月, 曜日, 何番目か: 負の値は月末から数える
+    Easter(i64),                  // 復活祭からの日数
+}
+
+impl Rule {
+    fn date(&self, year: i32) -> Option<NaiveDate> {
+        match *self {
+            Rule::Fixed(month, day) => NaiveDate::from_ymd_opt(year, month, day),
+            Rule::NthWeekday(month, weekday, n) if n > 0 => {
+                NaiveDate::from_weekday_of_month_opt(year, month, weekday, n as u8)
+            }
+            Rule::NthWeekday(month, weekday, n) => {
+                // 翌月の初日から遡って数える
+                let next = match month {
+                    12 => NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+                    _ => NaiveDate::from_ymd_opt(year, month + 1, 1)?,
+                };
+                let last = next.pred_opt()?;
+                let back = (7 + last.weekday().num_days_from_monday()
+                    - weekday.num_days_from_monday())
+                    % 7;
+                last.checked_sub_days(Days::new(back as u64 + 7 * (n.unsigned_abs() as u64 - 1)))
+            }
+            Rule::Easter(offset) => {
+                let easter = easter(year)?;
+                if offset < 0 {
+                    easter.checked_sub_days(Days::new(offset.unsigned_abs()))
+                } else {
+                    easter.checked_add_days(Days::new(offset as u64))
+                }
+            }
+        }
+    }
+}
+
+const US: &[(&str, Rule)] = &[
+    ("New Year's Day", Rule::Fixed(1, 1)),
+    ("Martin Luther King Jr. Day", Rule::NthWeekday(1, Weekday::Mon, 3)),
+    ("Washington's Birthday", Rule::NthWeekday(2, Weekday::Mon, 3)),
+    ("Memorial Day", Rule::NthWeekday(5, Weekday::Mon, -1)),
+    ("Juneteenth", Rule::Fixed(6, 19)),
+    ("Independence Day", Rule::Fixed(7, 4)),
+    ("Labor Day", Rule::NthWeekday(9, Weekday::Mon, 1)),
+    ("Columbus Day", Rule::NthWeekday(10, Weekday::Mon, 2)),
+    ("Veterans Day", Rule::Fixed(11, 11)),
+    ("Thanksgiving Day", Rule::NthWeekday(11, Weekday::Thu, 4)),
+    ("Christmas Day", Rule::Fixed(12, 25)),
+];
+
+const GB: &[(&str, Rule)] = &[
+    ("New Year's Day", Rule::Fixed(1, 1)),
+    ("Good Friday", Rule::Easter(-2)),
+    ("Easter Monday", Rule::Easter(1)),
+    ("Early May Bank Holiday", Rule::NthWeekday(5, Weekday::Mon, 1)),
+    ("Spring Bank Holiday", Rule::NthWeekday(5, Weekday::Mon, -1)),
+    ("Summer Bank Holiday", Rule::NthWeekday(8, Weekday::Mon, -1)),
+    ("Christmas Day", Rule::Fixed(12, 25)),
+    ("Boxing Day", Rule::Fixed(12, 26)),
+];
+
+const DE: &[(&str, Rule)] = &[
+    ("Neujahr", Rule::Fixed(1, 1)),
+    ("Karfreitag", Rule::Easter(-2)),
+    ("Ostermontag", Rule::Easter(1)),
+    ("Tag der Arbeit", Rule::Fixed(5, 1)),
+    ("Christi Himmelfahrt", Rule::Easter(39)),
+    ("Pfingstmontag", Rule::Easter(50)),
+    ("Tag der Deutschen Einheit", Rule::Fixed(10, 3)),
+    ("1. Weihnachtstag", Rule::Fixed(12, 25)),
+    ("2. Weihnachtstag", Rule::Fixed(12, 26)),
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Holiday {
+    pub date: NaiveDate,
+    pub name: &'static str,
+}
+
+/// Returns the public holidays of `region` in `year`, sorted by date.
+pub fn holidays(region: Region, year: i32) -> Vec<Holiday> {
+    let rules = match region {
+        Region::Us => US,
+        Region::Gb => GB,
+        Region::De => DE,
+    };
+    let mut holidays: Vec<_> = rules
+        .iter()
+        .filter_map(|(name, rule)| rule.date(year).map(|date| Holiday { date, name }))
+        .collect();
+    holidays.sort_by_key(|holiday| holiday.date);
+    holidays
+}
+
+/// Computes the date of (Western) Easter Sunday in the Gregorian calendar
+/// using the anonymous Gregorian algorithm (Meeus/Jones/Butcher).
+pub fn easter(year: i32) -> Option<NaiveDate> {
+    let a = year % 19; // メトン周期での位置
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30; // 春分後の満月までの日数
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7; // 次の日曜日までの日数
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{easter, holidays, Holiday, Region};
+    use chrono::NaiveDate;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parse_region() {
+        assert_eq!("us".parse::<Region>(), Ok(Region::Us));
+        assert_eq!("GB".parse::<Region>(), Ok(Region::Gb));
+        assert_eq!("uk".parse::<Region>(), Ok(Region::Gb));
+        assert_eq!("de".parse::<Region>(), Ok(Region::De));
+        assert_eq!(
+            "xx".parse::<Region>(),
+            Err("Invalid --holidays \"xx\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_easter() {
+        assert_eq!(easter(1961), Some(date(1961, 4, 2)));
+        assert_eq!(easter(2000), Some(date(2000, 4, 23)));
+        assert_eq!(easter(2008), Some(date(2008, 3, 23))); // 早い年
+        assert_eq!(easter(2011), Some(date(2011, 4, 24)));
+        assert_eq!(easter(2019), Some(date(2019, 4, 21)));
+        assert_eq!(easter(2020), Some(date(2020, 4, 12)));
+        assert_eq!(easter(2038), Some(date(2038, 4, 25))); // 最も遅い日付
+        assert_eq!(easter(2285), Some(date(2285, 3, 22))); // 最も早い日付
+    }
+
+    #[test]
+    fn test_holidays_us() {
+        let days: Vec<_> = holidays(Region::Us, 2020).iter().map(|h| h.date).collect();
+        assert_eq!(
+            days,
+            [
+                date(2020, 1, 1),
+                date(2020, 1, 20),
+                date(2020, 2, 17),
+                date(2020, 5, 25), // 5月の最終月曜日
+                date(2020, 6, 19),
+                date(2020, 7, 4),
+                date(2020, 9, 7),
+                date(2020, 10, 12),
+                date(2020, 11, 11),
+                date(2020, 11, 26),
+                date(2020, 12, 25),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_holidays_gb_de() {
+        let gb = holidays(Region::Gb, 2020);
+        assert_eq!(
+            gb[1],
+            Holiday {
+                date: date(2020, 4, 10),
+                name: "Good Friday"
+            }
+        );
+        assert_eq!(gb[2].date, date(2020, 4, 13));
+        assert_eq!(gb[5].date, date(2020, 8, 31)); // 31日が月曜日
+
+        let de: Vec<_> = holidays(Region::De, 2020).iter().map(|h| h.date).collect();
+        assert_eq!(de[4], date(2020, 5, 21)); // 復活祭の39日後
+        assert_eq!(de[5], date(2020, 6, 1)); // 復活祭の50日後
+    }
+
+    #[test]
+    fn test_holidays_sorted() {
+        // 復活祭由来の祝日が固定日の祝日より前後しても日付順に並ぶ
+        let de = holidays(Region::De, 2008);
+        assert_eq!(de[1].date, date(2008, 3, 21));
+        assert_eq!(de[3].name, "Tag der Arbeit");
+        assert_eq!(de[4].name, "Christi Himmelfahrt"); // 5月1日と同じ日
+        assert!(de.windows(2).all(|pair| pair[0].date <= pair[1].date));
+    }
+}
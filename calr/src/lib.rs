@@ -1,35 +1,144 @@
-use std::{error::Error, str::FromStr};
+use std::{collections::HashSet, error::Error, fs::File, io::{BufRead, BufReader, Write}, str::FromStr};
 
-use ansi_term::Style;
-use chrono::{NaiveDate, Local, Datelike};
+use ansi_term::{Colour, Style};
+use chrono::{NaiveDate, Local, Datelike, Weekday};
 use clap::{App, Arg};
-use itertools::izip;
+use unicode_width::UnicodeWidthStr;
+
+mod event;
+use event::Event;
+
+mod holiday;
+use holiday::Region;
+
+mod locale;
+use locale::Locale;
+
+mod output;
+use output::{Calendar, Format};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
-const LINE_WIDTH: usize = 22;
-
-// キャパシティを定義したstr配列を作成
-const MONTH_NAMES: [&str; 12] = [
-    "January",
-    "February",
-    "March",
-    "April",
-    "May",
-    "June",
-    "July",
-    "August",
-    "September",
-    "October",
-    "November",
-    "December",
-];
+// 年と月の組: 年の順、同じ年では月の順に比較できる
+type YearMonth = (i32, u32);
 
 #[derive(Debug)]
 pub struct Config {
     month: Option<u32>, // chronoクレートの型に合わせてu32を利用(yearも同様)
     year: i32,
     today: NaiveDate,
+    highlights: HashSet<NaiveDate>, // 今日以外にハイライトする日付
+    style: HighlightStyle,
+    day_format: DayFormat,
+    holidays: Option<Region>, // 祝日をハイライトして一覧を出力する地域
+    events: Vec<Event>, // 日付を太字にしてカレンダーの下に一覧を出力する予定
+    format: Format,
+    locale: Locale,
+    layout: Layout,
+    range: Option<(YearMonth, YearMonth)>, // --from, --to: 年をまたいで連続する月を出力する
+}
+
+// 月の並べ方
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Layout {
+    #[default]
+    Classic,  // 曜日を列、週を行にする: -b
+    Vertical, // ncalのように曜日を行、週を列にする: -N
+}
+
+// 日付セルの表示形式
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum DayFormat {
+    #[default]
+    DayOfMonth, // 月内の日付(1-31)
+    DayOfYear,  // 年内の通算日(1-366)
+}
+
+impl DayFormat {
+    // 日付セルの幅
+    fn cell_width(&self) -> usize {
+        match self {
+            DayFormat::DayOfMonth => 2,
+            DayFormat::DayOfYear => 3,
+        }
+    }
+
+    // 年単位のカレンダーで1行に並べる月数
+    fn months_per_row(&self) -> usize {
+        match self {
+            DayFormat::DayOfMonth => 3,
+            DayFormat::DayOfYear => 2,
+        }
+    }
+
+    // 日付セルに表示する番号
+    fn label(&self, date: NaiveDate) -> u32 {
+        match self {
+            DayFormat::DayOfMonth => date.day(),
+            DayFormat::DayOfYear => date.ordinal(),
+        }
+    }
+}
+
+// 1ヶ月分の行サイズ: 7日分のセルと間の空白6マス、行末の2マス
+fn line_width(cell_width: usize) -> usize {
+    cell_width * 7 + 6 + 2
+}
+
+// 縦型の1ヶ月分の行サイズ: 曜日名の2マスと、最大6週分の空白1マス付きのセル、行末の2マス
+fn vertical_line_width(cell_width: usize) -> usize {
+    2 + (cell_width + 1) * 6 + 2
+}
+
+impl Layout {
+    fn line_width(&self, day_format: DayFormat) -> usize {
+        match self {
+            Layout::Classic => line_width(day_format.cell_width()),
+            Layout::Vertical => vertical_line_width(day_format.cell_width()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum HighlightStyle {
+    #[default]
+    Reverse, // 反転表示
+    Color,   // 色付き表示
+}
+
+impl HighlightStyle {
+    fn paint(&self, text: String) -> String {
+        match self {
+            HighlightStyle::Reverse => Style::new().reverse().paint(text).to_string(),
+            HighlightStyle::Color => Colour::Red.bold().paint(text).to_string(),
+        }
+    }
+}
+
+impl FromStr for HighlightStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reverse" => Ok(HighlightStyle::Reverse),
+            "color" => Ok(HighlightStyle::Color),
+            _ => Err(format!("Invalid highlight style \"{}\"", s)),
+        }
+    }
+}
+
+/// Display settings shared by [`format_year`], [`format_range`],
+/// [`format_month`] and [`format_month_vertical`]. Start from
+/// `RenderOptions::default()` and override the fields you need, so new
+/// settings can be added without changing the function signatures.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    pub highlights: HashSet<NaiveDate>, // 今日以外にハイライトする日付
+    pub events: Vec<Event>, // 日付を太字にして月の下に一覧を出力する予定
+    pub style: HighlightStyle,
+    pub day_format: DayFormat,
+    pub locale: Locale,
+    pub layout: Layout, // 年単位の表示で各月を描画する形式
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -58,17 +167,145 @@ pub fn get_args() -> MyResult<Config> {
                 .conflicts_with_all(&["month", "year"])
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("highlight")
+                .value_name("FILE")
+                .long("highlight")
+                .help("File of dates (YYYY-MM-DD) to highlight")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("style")
+                .value_name("STYLE")
+                .long("style")
+                .help("Highlight style [reverse|color]")
+                .takes_value(true)
+                .default_value("reverse"),
+        )
+        .arg(
+            Arg::with_name("julian")
+                .short("j")
+                .long("julian")
+                .help("Show day-of-year numbers")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("holidays")
+                .value_name("REGION")
+                .short("H")
+                .long("holidays")
+                .help("Highlight and list public holidays [us|gb|de]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("events")
+                .value_name("FILE")
+                .long("events")
+                .help("File of events (YYYY-MM-DD description) to list under the calendar")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .value_name("FORMAT")
+                .long("format")
+                .help("Output format [plain|json|ics]")
+                .takes_value(true)
+                .default_value("plain"),
+        )
+        .arg(
+            Arg::with_name("locale")
+                .value_name("LOCALE")
+                .long("locale")
+                .help("Language of month and weekday names [en|de|fr|es|ja, e.g. de_DE]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("vertical")
+                .short("N")
+                .help("Show weekdays as rows and weeks as columns, like ncal")
+                .takes_value(false)
+                .overrides_with("classic"), // 後に指定されたオプションを優先する
+        )
+        .arg(
+            Arg::with_name("classic")
+                .short("b")
+                .help("Show weekdays as columns (default)")
+                .takes_value(false)
+                .overrides_with("vertical"),
+        )
+        .arg(
+            Arg::with_name("from")
+                .value_name("YYYY-MM")
+                .long("from")
+                .help("First month of a range of months")
+                .takes_value(true)
+                .requires("to")
+                .conflicts_with_all(&["year", "month", "show_current_year"]),
+        )
+        .arg(
+            Arg::with_name("to")
+                .value_name("YYYY-MM")
+                .long("to")
+                .help("Last month of a range of months")
+                .takes_value(true)
+                .requires("from"),
+        )
         .get_matches();
 
+    let locale = matches.value_of("locale")
+        .map(str::parse)
+        .transpose()?
+        .unwrap_or_default();
     let mut year = matches.value_of("year")
         .map(parse_year)
         .transpose()?;
     let mut month = matches.value_of("month")
-        .map(parse_month)
+        .map(|month| parse_month(month, locale))
+        .transpose()?;
+
+    let highlights = matches.value_of("highlight")
+        .map(read_dates)
+        .transpose()?
+        .unwrap_or_default();
+    let style = matches.value_of("style")
+        .map(str::parse)
+        .transpose()?
+        .unwrap(); // デフォルト値があるのでunwrap可能
+    let holidays = matches.value_of("holidays")
+        .map(str::parse)
         .transpose()?;
+    let events = matches.value_of("events")
+        .map(event::read_events)
+        .transpose()?
+        .unwrap_or_default();
+    let format = matches.value_of("format")
+        .map(str::parse)
+        .transpose()?
+        .unwrap(); // デフォルト値があるのでunwrap可能
+
+    let from = matches.value_of("from")
+        .map(|val| parse_year_month(val, "from"))
+        .transpose()?;
+    let to = matches.value_of("to")
+        .map(|val| parse_year_month(val, "to"))
+        .transpose()?;
+    let range = from.zip(to);
+    if let Some((from, to)) = range {
+        if from > to {
+            return Err(format!(
+                "--from \"{}\" is after --to \"{}\"",
+                matches.value_of("from").unwrap(),
+                matches.value_of("to").unwrap()
+            ).into());
+        }
+        // 機械可読な形式は1年または1か月単位のため、期間の指定はテキストのみ
+        if format != Format::Plain {
+            return Err("--from and --to require --format plain".into());
+        }
+    }
 
     // ローカルな今日の日付情報を取得
-    let today = Local::today();
+    let today = Local::now().date_naive();
 
     if matches.is_present("show_current_year") {
         year  = Some(today.year());
@@ -83,7 +320,24 @@ pub fn get_args() -> MyResult<Config> {
         Config {
             month,
             year: year.unwrap_or_else(|| today.year()), // Noneの場合は今年
-            today: today.naive_local(), // 今日のローカル日付
+            today, // 今日のローカル日付
+            highlights,
+            style,
+            day_format: if matches.is_present("julian") {
+                DayFormat::DayOfYear
+            } else {
+                DayFormat::DayOfMonth
+            },
+            holidays,
+            events,
+            format,
+            locale,
+            layout: if matches.is_present("vertical") {
+                Layout::Vertical
+            } else {
+                Layout::Classic
+            },
+            range,
         }
     )
 }
@@ -105,8 +359,8 @@ fn parse_year(year: &str) -> MyResult<i32> {
     })
 }
 
-fn parse_month(month: &str) -> MyResult<u32> {
-    match parse_int(&month) {
+fn parse_month(month: &str, locale: Locale) -> MyResult<u32> {
+    match parse_int(month) {
         // 数値の場合
         Ok(num) => {
             if (1..=12).contains(&num) {
@@ -115,133 +369,353 @@ fn parse_month(month: &str) -> MyResult<u32> {
                 Err(format!("month \"{}\" not in the range 1 through 12", month).into())
             }
         },
-        // 月名の場合
-        Err(_) => {
-            let lower = &month.to_lowercase();
-            let matches: Vec<_> = MONTH_NAMES.iter()
-                // インデックス番号と月名でイテレーション
-                .enumerate()
-                .filter_map(|(i, name)| {
-                    // 先頭からの一致を確認
-                    if name.to_lowercase().starts_with(lower) {
-                        Some(i + 1) // 月の数値に変換
-                    } else {
-                        None // フィルタリングで除去される
-                    }
-                })
-                // Some(_)のみを集約
-                .collect();
-            // 該当した月名が1つだけの場合
-            if matches.len() == 1 {
-                Ok(matches[0] as u32)
-            // 該当なしまたは複数該当の場合
-            } else {
-                Err(format!("Invalid month \"{}\"", month).into())
-            }
+        // 月名の場合: 指定の言語の月名の先頭部分が1つだけ該当すればその月とし、英語の月名も受け付ける
+        Err(_) => locale
+            .find_month(month)
+            .or_else(|| Locale::English.find_month(month))
+            .ok_or_else(|| format!("Invalid month \"{}\"", month).into()),
+    }
+}
+
+// YYYY-MM形式の年月: nameはエラーメッセージに表示するオプション名
+fn parse_year_month(val: &str, name: &str) -> MyResult<YearMonth> {
+    val.split_once('-')
+        .and_then(|(year, month)| Some((year.parse().ok()?, month.parse().ok()?)))
+        .filter(|(year, month)| (1..=9999).contains(year) && (1..=12).contains(month))
+        .ok_or_else(|| format!("Invalid --{} \"{}\"", name, val).into())
+}
+
+// fromからtoまで(両端を含む)の月を順に列挙する
+fn months_between(from: YearMonth, to: YearMonth) -> Vec<YearMonth> {
+    let mut months = vec![];
+    let (mut year, mut month) = from;
+    while (year, month) <= to {
+        months.push((year, month));
+        (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    }
+    months
+}
+
+// 1行に1つのISO形式の日付を記載したファイルを読み込む: 空行は無視する
+fn read_dates(filename: &str) -> MyResult<HashSet<NaiveDate>> {
+    let file = File::open(filename)
+        .map_err(|e| format!("{}: {}", filename, e))?;
+    let mut dates = HashSet::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
         }
+        let date = NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .map_err(|_| format!("{}: Invalid date \"{}\"", filename, text))?;
+        dates.insert(date);
     }
+    Ok(dates)
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    match config.month {
-        // 月指定がある時: 当月カレンダーのみを出力
-        Some(month) => {
-            let lines = format_month(config.year, month, true, config.today);
-            println!("{}", lines.join("\n")); // カレンダーの各行を改行区切りで出力
-        },
-        // 月が未指定の時: 年単位のカレンダーを出力
-        None => {
-            println!("{:>32}", config.year);
-            // 各月のカレンダーを取得
-            let months: Vec<_> = (1..=12)
-                .into_iter()
-                .map(|month| {
-                    format_month(config.year, month, false, config.today)
-                })
-                .collect();
-
-            // 3ヶ月分ずつの並びで出力
-            for (i, chunk) in months.chunks(3).enumerate() {
-                if let [m1, m2, m3] = chunk {
-                    for lines in izip!(m1, m2, m3) { // 各月の行をまとめてループ処理
-                        println!("{}{}{}", lines.0, lines.1, lines.2);
-                    }
-                    // 次の3ヶ月との間に改行を挟む
-                    if i < 3 {
-                        println!();
-                    }
-                }
+// カレンダーを任意の出力先に書き込む
+pub fn run(config: Config, out: &mut impl Write) -> MyResult<()> {
+    // 表示する期間の最初と最後の月
+    let (from, to) = config.range.unwrap_or(match config.month {
+        Some(month) => ((config.year, month), (config.year, month)),
+        None => ((config.year, 1), (config.year, 12)),
+    });
+    // 表示する期間の祝日: 指定日と同様にハイライトする
+    let holidays: Vec<_> = config.holidays
+        .map(|region| (from.0..=to.0).flat_map(|year| holiday::holidays(region, year)).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|holiday| (from..=to).contains(&(holiday.date.year(), holiday.date.month())))
+        .collect();
+    let mut highlights = config.highlights;
+    highlights.extend(holidays.iter().map(|holiday| holiday.date));
+
+    // 機械可読な形式では今日を特別扱いせず、指定日と祝日のみを含める
+    let calendar = Calendar {
+        year: config.year,
+        month: config.month,
+        highlights: &highlights,
+        holidays: &holidays,
+    };
+    match config.format {
+        Format::Json => return output::write_json(out, &calendar, config.day_format),
+        Format::Ics => return output::write_ics(out, &calendar),
+        Format::Plain => {},
+    }
+
+    let options = RenderOptions {
+        highlights,
+        events: event::in_range(&config.events, from, to), // 表示する期間の予定
+        style: config.style,
+        day_format: config.day_format,
+        locale: config.locale,
+        layout: config.layout,
+    };
+    let format_month = match config.layout {
+        Layout::Classic => format_month,
+        Layout::Vertical => format_month_vertical,
+    };
+    let lines = match (config.range, config.month) {
+        (Some((from, to)), _) => format_range(from, to, config.today, &options),
+        (None, Some(month)) => format_month(config.year, month, true, config.today, &options),
+        (None, None) => format_year(config.year, config.today, &options),
+    };
+    for line in lines {
+        writeln!(out, "{}", line)?; // カレンダーの各行を改行区切りで出力
+    }
+    // カレンダーの下に祝日の一覧を出力
+    if !holidays.is_empty() {
+        writeln!(out)?;
+        for holiday in &holidays {
+            // 年をまたぐ期間では月日だけでは区別できないため年も出力する
+            if from.0 != to.0 {
+                write!(out, "{} ", holiday.date.year())?;
             }
+            writeln!(
+                out,
+                "{} {:>2}  {}",
+                config.locale.month_abbr(holiday.date.month()),
+                holiday.date.day(),
+                holiday.name
+            )?;
         }
     }
     Ok(())
 }
 
-fn format_month(
-    year: i32,
-    month: u32,
-    print_year: bool,
-    today: NaiveDate,
-) -> Vec<String> { // カレンダーを表す8行の文字列: 年月1行, 曜日1行, 日付6行
-    let first = NaiveDate::from_ymd(year, month, 1);
-
-    let mut days: Vec<String> = (1..first.weekday().number_from_sunday()) // 初日の曜日位置を数値で取得
-        .into_iter()
-        .map(|_| "  ".to_string()) // 初日の前の曜日を空白2マスで埋める: 日曜日から出力するため
-        .collect();
+/// Renders a whole year as lines: the year header followed by the months
+/// laid out side by side, with a blank line between each row of months.
+/// A row is as tall as the longest event footer among its months.
+pub fn format_year(year: i32, today: NaiveDate, options: &RenderOptions) -> Vec<String> {
+    format_range((year, 1), (year, 12), today, options)
+}
 
-    // 今日かどうかの判定式
-    let is_today = |day: u32| {
-        year == today.year() && month == today.month() && day == today.day()
+/// Renders the months from `from` through `to` like [`format_year`],
+/// inserting a year header and starting a new row of months wherever
+/// the year changes, so a range may span several years.
+pub fn format_range(from: (i32, u32), to: (i32, u32), today: NaiveDate, options: &RenderOptions) -> Vec<String> {
+    let per_row = options.day_format.months_per_row();
+    let width = options.layout.line_width(options.day_format); // 縦型では月の幅が異なる
+    let format_month = match options.layout {
+        Layout::Classic => format_month,
+        Layout::Vertical => format_month_vertical,
     };
+    let mut lines = vec![];
+    let months = months_between(from, to);
+    for (i, group) in months.chunk_by(|a, b| a.0 == b.0).enumerate() {
+        if i > 0 {
+            lines.push(String::new()); // 年の間にも行の間と同じ空行を入れる
+        }
+        let year = group[0].0;
+        lines.push(format!("{:>w$}", year, w = (per_row * width - 2) / 2));
+        let months: Vec<_> = group
+            .iter()
+            .map(|&(year, month)| format_month(year, month, false, today, options))
+            .collect();
+        lines.extend(layout_months(&months, per_row));
+    }
+    lines
+}
 
-    // 最終日の取得
-    let last = last_day_in_month(year, month);
+/// Lays out rendered months side by side, `columns` months per row, with
+/// a blank line between rows. Months shorter than the tallest month of
+/// their row are padded with blank lines as wide as their title line.
+pub fn layout_months(months: &[Vec<String>], columns: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for (i, chunk) in months.chunks(columns).enumerate() {
+        if i > 0 {
+            lines.push(String::new());
+        }
+        // 予定の一覧の長さは月ごとに異なるため、最も長い月に合わせて空白で埋める
+        let height = chunk.iter().map(Vec::len).max().unwrap_or(0);
+        let blanks: Vec<_> = chunk
+            .iter()
+            .map(|month| " ".repeat(month.first().map_or(0, |title| title.width())))
+            .collect();
+        for line_num in 0..height { // 各月の同じ行をまとめて出力
+            lines.push(
+                chunk
+                    .iter()
+                    .zip(&blanks)
+                    .map(|(month, blank)| month.get(line_num).unwrap_or(blank).as_str())
+                    .collect(),
+            );
+        }
+    }
+    lines
+}
 
-    // 初日から最終日までをフォーマットして配列に追加
-    days.extend((first.day()..=last.day()).into_iter()
-        .map(|num| {
-            let fmt = format!("{:>2}", num); // 右詰め2桁に整形
-            if is_today(num) {
-                Style::new().reverse().paint(fmt).to_string() // 今日の日付をハイライト
-            } else {
-                fmt
-            }
-        }));
+/// Renders one month as 8 lines: the title, the weekday header and six
+/// rows of days. `today` and `highlights` are painted with `style`, and
+/// the month and weekday names are taken from `locale`. Events of the
+/// month are bolded in the grid and listed after a blank line.
+pub fn format_month(year: i32, month: u32, print_year: bool, today: NaiveDate, options: &RenderOptions) -> Vec<String> { // カレンダーを表す8行の文字列: 年月1行, 曜日1行, 日付6行
+    let (highlights, events) = (&options.highlights, &options.events);
+    let (style, day_format, locale) = (options.style, options.day_format, options.locale);
+    let cell_width = day_format.cell_width();
+    let line_width = line_width(cell_width);
+    let blank = " ".repeat(cell_width);
+
+    // 今日または指定日かどうかの判定式
+    let is_highlighted = |date: &NaiveDate| *date == today || highlights.contains(date);
+
+    // この月の予定
+    let events: Vec<_> = events
+        .iter()
+        .filter(|event| event.date.year() == year && event.date.month() == month)
+        .collect();
+    let has_event = |date: &NaiveDate| events.iter().any(|event| event.date == *date);
 
-    let month_name = MONTH_NAMES[month as usize - 1];
+    let month_name = locale.month_name(month);
 
     let mut lines = Vec::with_capacity(8); // カレンダーを表す8行の文字列: 年月1行, 曜日1行, 日付6行
 
     // 年月の行を追加
-    lines.push(format!(
-        "{:^20}  ", // 20文字の中央揃え: 2マス空ける
-        if print_year {
-            format!("{} {}", month_name, year)
-        } else {
-            month_name.to_string()
-        }
-    ));
+    // 全角文字を含む月名もあるため、文字数ではなく表示幅で揃える
+    let title = if print_year {
+        format!("{} {}", month_name, year)
+    } else {
+        month_name.to_string()
+    };
+    lines.push(format!("{}  ", locale::center(&title, line_width - 2))); // 行末2マスを除くサイズで中央揃え: 2マス空ける
 
-    // 曜日の行を追加
-    lines.push("Su Mo Tu We Th Fr Sa  ".to_string()); // 2マス空ける
+    // 曜日の行を追加: 各曜日をセル幅で右詰め
+    let weekdays: Vec<_> = locale
+        .weekdays()
+        .iter()
+        .map(|name| locale::pad_left(name, cell_width))
+        .collect();
+    lines.push(format!("{}  ", weekdays.join(" "))); // 2マス空ける
 
-    // 各週の行を追加
-    for week in days.chunks(7) { // 日付の配列を7要素ずつの塊としてループ処理
+    // 各週の行を追加: 月の範囲外の曜日は空白セルで埋める(ハイライトのエスケープシーケンスが幅の計算に含まれないようにするため)
+    for week in month_weeks(year, month) {
+        let week: Vec<_> = week
+            .iter()
+            .map(|day| match day {
+                Some(date) => format_day(*date, is_highlighted(date), has_event(date), style, day_format),
+                None => blank.clone(),
+            })
+            .collect();
         lines.push(format!(
             "{:width$}  ", // 出力行サイズの指定 + 末尾$の追加 + 2マス空ける
             week.join(" "),
-            width = LINE_WIDTH - 2 // 行末2マスを除くサイズ
+            width = line_width - 2 // 行末2マスを除くサイズ
         ));
     }
 
     while lines.len() < 8 { // 週数が少ない場合
-        lines.push(" ".repeat(LINE_WIDTH)); // 行サイズ分の空白文字で埋める
+        lines.push(" ".repeat(line_width)); // 行サイズ分の空白文字で埋める
+    }
+
+    lines.extend(format_events(&events, print_year, day_format, line_width));
+    lines
+}
+
+/// Renders one month like BSD `ncal` as 8 lines: the title and a row per
+/// weekday from Monday to Sunday, with a column per week. Takes the same
+/// settings as [`format_month`] so either can render the months of a year.
+pub fn format_month_vertical(year: i32, month: u32, print_year: bool, today: NaiveDate, options: &RenderOptions) -> Vec<String> { // カレンダーを表す8行の文字列: 年月1行, 曜日7行
+    let (highlights, events) = (&options.highlights, &options.events);
+    let (style, day_format, locale) = (options.style, options.day_format, options.locale);
+    let cell_width = day_format.cell_width();
+    let line_width = vertical_line_width(cell_width);
+    let blank = " ".repeat(cell_width);
+
+    let is_highlighted = |date: &NaiveDate| *date == today || highlights.contains(date);
+    let events: Vec<_> = events
+        .iter()
+        .filter(|event| event.date.year() == year && event.date.month() == month)
+        .collect();
+    let has_event = |date: &NaiveDate| events.iter().any(|event| event.date == *date);
+
+    let mut lines = Vec::with_capacity(8);
+    let month_name = locale.month_name(month);
+    let title = if print_year {
+        format!("{} {}", month_name, year)
+    } else {
+        month_name.to_string()
+    };
+    lines.push(format!("{}  ", locale::center(&title, line_width - 2)));
+
+    // 月曜日始まりの週を列にする: 週数が少ない月も6週分の幅に揃える
+    let weeks = weeks_from(year, month, Weekday::Mon);
+    let weekdays = locale.weekdays(); // 日曜日始まり
+    for row in 0..7 {
+        let mut line = locale::pad_left(weekdays[(row + 1) % 7], 2);
+        for col in 0..6 {
+            line.push(' ');
+            match weeks.get(col).and_then(|week| week[row]) {
+                Some(date) => {
+                    line.push_str(&format_day(date, is_highlighted(&date), has_event(&date), style, day_format))
+                },
+                None => line.push_str(&blank),
+            }
+        }
+        lines.push(format!("{}  ", line));
+    }
+
+    lines.extend(format_events(&events, print_year, day_format, line_width));
+    lines
+}
+
+// 日付セル: セル幅で右詰めにし、今日と指定日はハイライト、予定のある日付は太字にする
+fn format_day(date: NaiveDate, highlighted: bool, has_event: bool, style: HighlightStyle, day_format: DayFormat) -> String {
+    let fmt = format!("{:>w$}", day_format.label(date), w = day_format.cell_width());
+    if highlighted {
+        style.paint(fmt)
+    } else if has_event {
+        Style::new().bold().paint(fmt).to_string()
+    } else {
+        fmt
     }
+}
 
+// 予定の一覧を空行に続けて出力する: 年単位の表示では隣の月と重ならないよう月の幅に切り詰める
+fn format_events(events: &[&Event], print_year: bool, day_format: DayFormat, line_width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    if events.is_empty() {
+        return lines;
+    }
+    lines.push(" ".repeat(line_width));
+    for event in events {
+        let line = format!(
+            "{:>w$} {}",
+            day_format.label(event.date),
+            event.description,
+            w = day_format.cell_width()
+        );
+        if print_year {
+            lines.push(line);
+        } else {
+            lines.push(format!("{}  ", locale::fit(&line, line_width - 2)));
+        }
+    }
     lines
 }
 
+// 日曜日始まりの週ごとの日付: 初日の前と最終日の後の曜日はNone
+fn month_weeks(year: i32, month: u32) -> Vec<[Option<NaiveDate>; 7]> {
+    weeks_from(year, month, Weekday::Sun)
+}
+
+// startの曜日から始まる週ごとの日付
+fn weeks_from(year: i32, month: u32, start: Weekday) -> Vec<[Option<NaiveDate>; 7]> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    let last = last_day_in_month(year, month);
+    let mut weeks = vec![];
+    let mut week = [None; 7];
+    for date in first.iter_days().take_while(|date| date <= &last) {
+        let weekday = date.weekday().days_since(start) as usize; // startを0とする曜日位置
+        week[weekday] = Some(date);
+        if weekday == 6 || date == last {
+            weeks.push(week);
+            week = [None; 7];
+        }
+    }
+    weeks
+}
+
 // 月末の日付情報を返す: うるう年の対策
 fn last_day_in_month(year: i32, month: u32) -> NaiveDate {
     // 次の(年)月を計算
@@ -251,19 +725,34 @@ fn last_day_in_month(year: i32, month: u32) -> NaiveDate {
         (year, month + 1)
     };
     //次の年月の初日をもとに前日を返す
-    NaiveDate::from_ymd(y, m, 1).pred()
+    NaiveDate::from_ymd_opt(y, m, 1).unwrap().pred_opt().unwrap()
 }
 
 // --------------------------------------------------
 #[cfg(test)]
 mod tests {
     use super::format_month;
+    use super::format_month_vertical;
+    use super::format_range;
+    use super::format_year;
+    use super::run;
+    use super::Config;
+    use super::DayFormat;
+    use super::Event;
+    use super::HighlightStyle;
+    use super::Layout;
+    use super::Locale;
+    use super::RenderOptions;
     use super::last_day_in_month;
+    use super::layout_months;
+    use super::months_between;
     use super::parse_int;
     use super::parse_month;
     use super::parse_year;
+    use super::parse_year_month;
 
     use chrono::NaiveDate;
+    use std::collections::HashSet;
 
     #[test]
     fn test_parse_int() {
@@ -314,40 +803,41 @@ mod tests {
 
     #[test]
     fn test_parse_month() {
-        let res = parse_month("1");
+        let res = parse_month("1", Locale::English);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), 1u32);
 
-        let res = parse_month("12");
+        let res = parse_month("12", Locale::English);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), 12u32);
 
-        let res = parse_month("jan");
+        let res = parse_month("jan", Locale::English);
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), 1u32);
 
-        let res = parse_month("0");
+        let res = parse_month("0", Locale::English);
         assert!(res.is_err());
         assert_eq!(
             res.unwrap_err().to_string(),
             "month \"0\" not in the range 1 through 12"
         );
 
-        let res = parse_month("13");
+        let res = parse_month("13", Locale::English);
         assert!(res.is_err());
         assert_eq!(
             res.unwrap_err().to_string(),
             "month \"13\" not in the range 1 through 12"
         );
 
-        let res = parse_month("foo");
+        let res = parse_month("foo", Locale::English);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().to_string(), "Invalid month \"foo\"");
     }
 
     #[test]
     fn test_format_month() {
-        let today = NaiveDate::from_ymd(0, 1, 1);
+        let today = NaiveDate::from_ymd_opt(0, 1, 1).unwrap();
+        let options = RenderOptions::default();
         let leap_february = vec![
             "   February 2020      ",
             "Su Mo Tu We Th Fr Sa  ",
@@ -358,7 +848,7 @@ mod tests {
             "23 24 25 26 27 28 29  ",
             "                      ",
         ];
-        assert_eq!(format_month(2020, 2, true, today), leap_february);
+        assert_eq!(format_month(2020, 2, true, today, &options), leap_february);
 
         let may = vec![
             "        May           ",
@@ -370,7 +860,7 @@ mod tests {
             "24 25 26 27 28 29 30  ",
             "31                    ",
         ];
-        assert_eq!(format_month(2020, 5, false, today), may);
+        assert_eq!(format_month(2020, 5, false, today, &options), may);
 
         let april_hl = vec![
             "     April 2021       ",
@@ -382,23 +872,237 @@ mod tests {
             "25 26 27 28 29 30     ",
             "                      ",
         ];
-        let today = NaiveDate::from_ymd(2021, 4, 7);
-        assert_eq!(format_month(2021, 4, true, today), april_hl);
+        let today = NaiveDate::from_ymd_opt(2021, 4, 7).unwrap();
+        assert_eq!(format_month(2021, 4, true, today, &options), april_hl);
+
+        let april_dates = vec![
+            "     April 2021       ",
+            "Su Mo Tu We Th Fr Sa  ",
+            "             1  2  3  ",
+            " 4  5  6 \u{1b}[1;31m 7\u{1b}[0m  8  9 10  ",
+            "11 12 13 14 15 16 17  ",
+            "18 19 20 21 22 23 24  ",
+            "25 26 27 28 29 \u{1b}[1;31m30\u{1b}[0m     ",
+            "                      ",
+        ];
+        let dates: HashSet<_> = vec![
+            NaiveDate::from_ymd_opt(2021, 4, 30).unwrap(),
+            NaiveDate::from_ymd_opt(2021, 5, 1).unwrap(),
+        ]
+        .into_iter()
+        .collect();
+        let colored = RenderOptions {
+            highlights: dates,
+            style: HighlightStyle::Color,
+            ..Default::default()
+        };
+        assert_eq!(format_month(2021, 4, true, today, &colored), april_dates);
+
+        let julian_march = vec![
+            "        March 2020           ",
+            " Su  Mo  Tu  We  Th  Fr  Sa  ",
+            " 61  62  63  64  65  66  67  ",
+            " 68  69  70  71  72  73  74  ",
+            " 75  76  77  78  79  80  81  ",
+            " 82  83  84  85  86  87  88  ",
+            " 89  90  91                  ",
+            "                             ",
+        ];
+        let julian = RenderOptions {
+            day_format: DayFormat::DayOfYear,
+            ..Default::default()
+        };
+        assert_eq!(format_month(2020, 3, true, today, &julian), julian_march);
+    }
+
+    #[test]
+    fn test_format_month_events() {
+        let today = NaiveDate::from_ymd_opt(0, 1, 1).unwrap();
+        let event = |month, day, description: &str| Event {
+            date: NaiveDate::from_ymd_opt(2020, month, day).unwrap(),
+            description: description.to_string(),
+        };
+        let options = RenderOptions {
+            events: vec![
+                event(2, 3, "Standup"),
+                event(2, 29, "Leap day party with everyone"),
+                event(3, 1, "Not in February"),
+            ],
+            ..Default::default()
+        };
+        let february = format_month(2020, 2, false, today, &options);
+        assert_eq!(february.len(), 8 + 1 + 2);
+        assert_eq!(february[3], " 2 \u{1b}[1m 3\u{1b}[0m  4  5  6  7  8  ");
+        assert_eq!(february[8], "                      ");
+        // 年単位の表示では月の幅に切り詰める
+        assert_eq!(february[9], " 3 Standup            ");
+        assert_eq!(february[10], "29 Leap day party wi  ");
+
+        let lines = format_year(2020, today, &options);
+        assert_eq!(lines.len(), 1 + 4 * 8 + 3 + 3);
+    }
+
+    #[test]
+    fn test_format_month_vertical() {
+        let today = NaiveDate::from_ymd_opt(2020, 3, 31).unwrap();
+        let options = RenderOptions::default();
+        let julian = RenderOptions {
+            day_format: DayFormat::DayOfYear,
+            layout: Layout::Vertical,
+            ..Default::default()
+        };
+        // 6週にまたがる月も同じ幅になる
+        let march = vec![
+            "       March          ",
+            "Mo     2  9 16 23 30  ",
+            "Tu     3 10 17 24 \u{1b}[7m31\u{1b}[0m  ",
+            "We     4 11 18 25     ",
+            "Th     5 12 19 26     ",
+            "Fr     6 13 20 27     ",
+            "Sa     7 14 21 28     ",
+            "Su  1  8 15 22 29     ",
+        ];
+        assert_eq!(format_month_vertical(2020, 3, false, today, &options), march);
+
+        let julian_february = vec![
+            "      February 2020         ",
+            "Mo      34  41  48  55      ",
+            "Tu      35  42  49  56      ",
+            "We      36  43  50  57      ",
+            "Th      37  44  51  58      ",
+            "Fr      38  45  52  59      ",
+            "Sa  32  39  46  53  60      ",
+            "Su  33  40  47  54          ",
+        ];
+        assert_eq!(format_month_vertical(2020, 2, true, today, &julian), julian_february);
+
+        // 縦型では年単位の表示の月の幅が異なる
+        let today = NaiveDate::from_ymd_opt(0, 1, 1).unwrap();
+        let lines = format_year(2020, today, &julian);
+        assert_eq!(lines.len(), 1 + 6 * 8 + 5);
+        assert!(lines[1..].iter().filter(|line| !line.is_empty()).all(|line| line.len() == 2 * 28));
     }
 
     #[test]
     fn test_last_day_in_month() {
         assert_eq!(
             last_day_in_month(2020, 1),
-            NaiveDate::from_ymd(2020, 1, 31)
+            NaiveDate::from_ymd_opt(2020, 1, 31).unwrap()
         );
         assert_eq!(
             last_day_in_month(2020, 2),
-            NaiveDate::from_ymd(2020, 2, 29)
+            NaiveDate::from_ymd_opt(2020, 2, 29).unwrap()
         );
         assert_eq!(
             last_day_in_month(2020, 4),
-            NaiveDate::from_ymd(2020, 4, 30)
+            NaiveDate::from_ymd_opt(2020, 4, 30).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_year() {
+        let today = NaiveDate::from_ymd_opt(0, 1, 1).unwrap();
+
+        // 期待する出力ファイルと行単位で比較する
+        let expected = std::fs::read_to_string("tests/expected/2020.txt").unwrap();
+        let lines = format_year(2020, today, &RenderOptions::default());
+        assert_eq!(lines, expected.lines().collect::<Vec<_>>());
+        assert_eq!(lines.len(), 1 + 4 * 8 + 3);
+
+        let expected = std::fs::read_to_string("tests/expected/2020.julian.txt").unwrap();
+        let julian = RenderOptions {
+            day_format: DayFormat::DayOfYear,
+            ..Default::default()
+        };
+        let lines = format_year(2020, today, &julian);
+        assert_eq!(lines, expected.lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_parse_year_month() {
+        assert_eq!(parse_year_month("2020-03", "from").unwrap(), (2020, 3));
+        assert_eq!(parse_year_month("1-12", "to").unwrap(), (1, 12));
+        for val in ["2020", "2020-13", "2020-00", "0-01", "10000-01", "2020-03-01", "foo-01", ""] {
+            let res = parse_year_month(val, "from");
+            assert_eq!(res.unwrap_err().to_string(), format!("Invalid --from \"{}\"", val));
+        }
+    }
+
+    #[test]
+    fn test_months_between() {
+        assert_eq!(months_between((2020, 11), (2021, 2)), [(2020, 11), (2020, 12), (2021, 1), (2021, 2)]);
+        assert_eq!(months_between((2020, 5), (2020, 5)), [(2020, 5)]);
+        assert_eq!(months_between((2020, 1), (2020, 12)).len(), 12);
+        assert!(months_between((2021, 1), (2020, 12)).is_empty());
+    }
+
+    #[test]
+    fn test_layout_months() {
+        let month = |name: &str, height: usize| -> Vec<String> {
+            let mut lines = vec![format!("{:<4}", name)];
+            lines.extend((1..height).map(|i| format!("{}{:<3}", name, i)));
+            lines
+        };
+        let months = vec![month("a", 2), month("b", 3), month("c", 2), month("d", 1)];
+
+        // Rows are padded to their tallest month, with a blank line between rows
+        assert_eq!(
+            layout_months(&months, 3),
+            ["a   b   c   ", "a1  b1  c1  ", "    b2      ", "", "d   "]
         );
+        assert_eq!(layout_months(&months, 4), ["a   b   c   d   ", "a1  b1  c1      ", "    b2          "]);
+        assert_eq!(layout_months(&months, 1).len(), 2 + 3 + 2 + 1 + 3);
+        assert!(layout_months(&[], 3).is_empty());
+
+        // Blank padding follows the display width of the title line
+        let wide = vec![vec!["３月".to_string()], vec!["b   ".to_string(), "b1  ".to_string()]];
+        assert_eq!(layout_months(&wide, 2), ["３月b   ", "    b1  "]);
+    }
+
+    #[test]
+    fn test_format_range() {
+        let today = NaiveDate::from_ymd_opt(0, 1, 1).unwrap();
+        let format = |from, to, day_format| {
+            format_range(from, to, today, &RenderOptions { day_format, ..Default::default() })
+        };
+
+        // A whole year is the same as the year view
+        let year = format_year(2020, today, &RenderOptions::default());
+        assert_eq!(format((2020, 1), (2020, 12), DayFormat::DayOfMonth), year);
+
+        // A header is inserted and a new row started when the year changes
+        let expected = std::fs::read_to_string("tests/expected/11-2020.2-2021.txt").unwrap();
+        let lines = format((2020, 11), (2021, 2), DayFormat::DayOfMonth);
+        assert_eq!(lines, expected.lines().collect::<Vec<_>>());
+        assert_eq!(lines.len(), 1 + 8 + 1 + 1 + 8);
+        assert_eq!(lines[0].trim(), "2020");
+        assert_eq!(lines[10].trim(), "2021");
+
+        // Rows within a year still wrap at the months per row
+        let lines = format((2020, 12), (2021, 4), DayFormat::DayOfYear);
+        assert_eq!(lines.len(), 1 + 8 + 1 + 1 + 8 + 1 + 8);
+        assert_eq!(lines[10].trim(), "2021");
+    }
+
+    #[test]
+    fn test_run() {
+        let config = Config {
+            month: Some(2),
+            year: 2020,
+            today: NaiveDate::from_ymd_opt(0, 1, 1).unwrap(),
+            highlights: HashSet::new(),
+            style: HighlightStyle::Reverse,
+            day_format: DayFormat::DayOfMonth,
+            holidays: None,
+            events: vec![],
+            format: super::Format::Plain,
+            locale: Locale::English,
+            layout: Layout::Classic,
+            range: None,
+        };
+        let mut out = vec![];
+        assert!(run(config, &mut out).is_ok());
+        let expected = std::fs::read_to_string("tests/expected/2-2020.txt").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
     }
 }
@@ -0,0 +1,171 @@
+use std::str::FromStr;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// 月名と曜日名の言語: 外部のロケールデータには依存せず、対応する言語の表を持つ
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    Japanese,
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    // "ja_JP.UTF-8"のような形式も受け付ける: 言語の部分のみで判定する
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.split(['.', '@']).next().unwrap_or(s);
+        let language = name.split(['_', '-']).next().unwrap_or(name);
+        match language.to_lowercase().as_str() {
+            "c" | "posix" | "en" => Ok(Locale::English),
+            "de" => Ok(Locale::German),
+            "fr" => Ok(Locale::French),
+            "es" => Ok(Locale::Spanish),
+            "ja" => Ok(Locale::Japanese),
+            _ => Err(format!("Invalid --locale \"{}\"", s)),
+        }
+    }
+}
+
+impl Locale {
+    pub fn month_names(&self) -> &'static [&'static str; 12] {
+        match self {
+            Locale::English => &[
+                "January", "February", "March", "April", "May", "June",
+                "July", "August", "September", "October", "November", "December",
+            ],
+            Locale::German => &[
+                "Januar", "Februar", "März", "April", "Mai", "Juni",
+                "Juli", "August", "September", "Oktober", "November", "Dezember",
+            ],
+            Locale::French => &[
+                "janvier", "février", "mars", "avril", "mai", "juin",
+                "juillet", "août", "septembre", "octobre", "novembre", "décembre",
+            ],
+            Locale::Spanish => &[
+                "enero", "febrero", "marzo", "abril", "mayo", "junio",
+                "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre",
+            ],
+            Locale::Japanese => &[
+                "1月", "2月", "3月", "4月", "5月", "6月",
+                "7月", "8月", "9月", "10月", "11月", "12月",
+            ],
+        }
+    }
+
+    // 日曜日始まりの曜日の略称: 日付セルに収まるよう表示幅2以内
+    pub fn weekdays(&self) -> [&'static str; 7] {
+        match self {
+            Locale::English => ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"],
+            Locale::German => ["So", "Mo", "Di", "Mi", "Do", "Fr", "Sa"],
+            Locale::French => ["di", "lu", "ma", "me", "je", "ve", "sa"],
+            Locale::Spanish => ["do", "lu", "ma", "mi", "ju", "vi", "sá"],
+            Locale::Japanese => ["日", "月", "火", "水", "木", "金", "土"],
+        }
+    }
+
+    // monthは1から12
+    pub fn month_name(&self, month: u32) -> &'static str {
+        self.month_names()[month as usize - 1]
+    }
+
+    // 祝日の一覧で使う月名の略称: 先頭の3文字
+    pub fn month_abbr(&self, month: u32) -> String {
+        self.month_name(month).chars().take(3).collect()
+    }
+
+    // 月名の先頭部分から月を求める: 一致する月が1つだけの場合のみ
+    pub fn find_month(&self, prefix: &str) -> Option<u32> {
+        let lower = prefix.to_lowercase();
+        let matches: Vec<_> = self
+            .month_names()
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| name.to_lowercase().starts_with(&lower))
+            .map(|(i, _)| i as u32 + 1)
+            .collect();
+        match matches[..] {
+            [month] => Some(month),
+            _ => None,
+        }
+    }
+}
+
+// 表示幅に合わせて右詰めにする: 全角文字は2マスとして数える
+pub fn pad_left(text: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(text.width())), text)
+}
+
+// 表示幅に合わせて中央揃えにする: 余りの1マスは右側に置く
+pub fn center(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(text.width());
+    format!("{}{}{}", " ".repeat(padding / 2), text, " ".repeat(padding - padding / 2))
+}
+
+// 表示幅に合わせて左詰めにする: 収まらない部分は切り捨てる
+pub fn fit(text: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width {
+            break;
+        }
+        fitted.push(c);
+        used += w;
+    }
+    format!("{}{}", fitted, " ".repeat(width - used))
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{center, fit, pad_left, Locale};
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!("C".parse::<Locale>(), Ok(Locale::English));
+        assert_eq!("en_US.UTF-8".parse::<Locale>(), Ok(Locale::English));
+        assert_eq!("de_DE".parse::<Locale>(), Ok(Locale::German));
+        assert_eq!("fr".parse::<Locale>(), Ok(Locale::French));
+        assert_eq!("es_ES@euro".parse::<Locale>(), Ok(Locale::Spanish));
+        assert_eq!("ja_JP.UTF-8".parse::<Locale>(), Ok(Locale::Japanese));
+        assert_eq!(
+            "xx_XX".parse::<Locale>(),
+            Err("Invalid --locale \"xx_XX\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(Locale::German.month_name(3), "März");
+        assert_eq!(Locale::German.month_abbr(3), "Mär");
+        assert_eq!(Locale::Japanese.month_abbr(10), "10月");
+        assert_eq!(Locale::Japanese.weekdays()[0], "日");
+    }
+
+    #[test]
+    fn test_find_month() {
+        assert_eq!(Locale::English.find_month("jan"), Some(1));
+        assert_eq!(Locale::English.find_month("ju"), None); // JuneとJuly
+        assert_eq!(Locale::German.find_month("MÄR"), Some(3));
+        assert_eq!(Locale::French.find_month("août"), Some(8));
+        assert_eq!(Locale::Japanese.find_month("1月"), Some(1));
+        assert_eq!(Locale::Japanese.find_month("月"), None);
+    }
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(pad_left("日", 3), " 日");
+        assert_eq!(pad_left("Su", 2), "Su");
+        assert_eq!(center("3月 2020", 20), "      3月 2020      ");
+        assert_eq!(center("März 2020", 20), "     März 2020      ");
+        assert_eq!(fit("Earth Day", 12), "Earth Day   ");
+        assert_eq!(fit("Earth Day", 5), "Earth");
+        assert_eq!(fit("会議の予定", 5), "会議 "); // 全角文字の途中では切らない
+    }
+}
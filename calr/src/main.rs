@@ -1,7 +1,7 @@
-use std::process::exit;
+use std::{io, process::exit};
 
 fn main() {
-    if let Err(e) = calr::get_args().and_then(calr::run) {
+    if let Err(e) = calr::get_args().and_then(|config| calr::run(config, &mut io::stdout())) {
         eprintln!("{}", e);
         exit(1);
     }
@@ -0,0 +1,251 @@
+use std::{collections::HashSet, io::Write, str::FromStr};
+
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+
+use crate::{holiday::Holiday, locale::Locale, month_weeks, DayFormat, MyResult};
+
+// 出力形式: plainは従来のテキストのカレンダー
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Plain,
+    Json,
+    Ics,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "ics" => Ok(Format::Ics),
+            _ => Err(format!("Invalid --format \"{}\"", s)),
+        }
+    }
+}
+
+// 出力する期間と、その期間内のハイライトする日付と祝日
+pub struct Calendar<'a> {
+    pub year: i32,
+    pub month: Option<u32>, // Noneの場合は1年分
+    pub highlights: &'a HashSet<NaiveDate>,
+    pub holidays: &'a [Holiday],
+}
+
+impl Calendar<'_> {
+    fn months(&self) -> Vec<u32> {
+        match self.month {
+            Some(month) => vec![month],
+            None => (1..=12).collect(),
+        }
+    }
+
+    // 期間内のハイライトする日付を昇順で返す
+    fn highlighted_dates(&self) -> Vec<NaiveDate> {
+        let mut dates: Vec<_> = self
+            .highlights
+            .iter()
+            .filter(|date| date.year() == self.year && self.month.is_none_or(|month| date.month() == month))
+            .copied()
+            .collect();
+        dates.sort();
+        dates
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JsonCalendar {
+    year: i32,
+    months: Vec<JsonMonth>,
+    holidays: Vec<JsonHoliday>,
+}
+
+// 週は日曜日始まりの7要素の配列: 月の範囲外はnull
+#[derive(Debug, Serialize)]
+struct JsonMonth {
+    month: u32,
+    name: &'static str,
+    weeks: Vec<[Option<u32>; 7]>,
+    highlights: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonHoliday {
+    date: String,
+    name: &'static str,
+}
+
+// 日付の番号は-jの指定に従う
+pub fn write_json(out: &mut impl Write, calendar: &Calendar, day_format: DayFormat) -> MyResult<()> {
+    let highlighted = calendar.highlighted_dates();
+    let months = calendar
+        .months()
+        .into_iter()
+        .map(|month| JsonMonth {
+            month,
+            name: Locale::English.month_name(month), // 機械可読な形式のため言語の指定によらず英語
+            weeks: month_weeks(calendar.year, month)
+                .iter()
+                .map(|week| week.map(|day| day.map(|date| day_format.label(date))))
+                .collect(),
+            highlights: highlighted
+                .iter()
+                .filter(|date| date.month() == month)
+                .map(|date| date.to_string())
+                .collect(),
+        })
+        .collect();
+    let json = JsonCalendar {
+        year: calendar.year,
+        months,
+        holidays: calendar
+            .holidays
+            .iter()
+            .map(|holiday| JsonHoliday {
+                date: holiday.date.to_string(),
+                name: holiday.name,
+            })
+            .collect(),
+    };
+    writeln!(out, "{}", serde_json::to_string(&json)?)?;
+    Ok(())
+}
+
+// 祝日とハイライトする日付を終日の予定として並べたiCalendar形式: 行末はCRLF
+pub fn write_ics(out: &mut impl Write, calendar: &Calendar) -> MyResult<()> {
+    let name = match calendar.month {
+        Some(month) => format!("{:04}-{:02}", calendar.year, month),
+        None => format!("{:04}", calendar.year),
+    };
+    let mut events: Vec<_> = calendar
+        .holidays
+        .iter()
+        .map(|holiday| (holiday.date, holiday.name))
+        .collect();
+    // 祝日以外のハイライトする日付も予定として追加する
+    events.extend(
+        calendar
+            .highlighted_dates()
+            .into_iter()
+            .filter(|date| calendar.holidays.iter().all(|holiday| holiday.date != *date))
+            .map(|date| (date, "Highlighted")),
+    );
+    events.sort();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//calr//EN".to_string(),
+        format!("X-WR-CALNAME:calr {}", name),
+    ];
+    for (i, (date, summary)) in events.into_iter().enumerate() {
+        let day = date.format("%Y%m%d");
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}-{}@calr", day, i),
+            format!("DTSTAMP:{}T000000Z", day), // 出力を再現可能にするため生成時刻ではなく日付を使う
+            format!("DTSTART;VALUE=DATE:{}", day),
+            format!("DTEND;VALUE=DATE:{}", date.succ_opt().unwrap().format("%Y%m%d")),
+            format!("SUMMARY:{}", escape_text(summary)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+    for line in lines {
+        write!(out, "{}\r\n", line)?;
+    }
+    Ok(())
+}
+
+// TEXT値の特殊文字をエスケープする
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(',', "\\,").replace(';', "\\;")
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{escape_text, write_ics, write_json, Calendar, Format};
+    use crate::{holiday::Holiday, DayFormat};
+    use chrono::NaiveDate;
+    use std::collections::HashSet;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("plain".parse::<Format>(), Ok(Format::Plain));
+        assert_eq!("json".parse::<Format>(), Ok(Format::Json));
+        assert_eq!("ics".parse::<Format>(), Ok(Format::Ics));
+        assert_eq!("csv".parse::<Format>(), Err("Invalid --format \"csv\"".to_string()));
+    }
+
+    #[test]
+    fn test_write_json() {
+        let highlights = [date(2020, 2, 14), date(2020, 3, 1)].into_iter().collect();
+        let calendar = Calendar {
+            year: 2020,
+            month: Some(2),
+            highlights: &highlights,
+            holidays: &[],
+        };
+        let mut out = vec![];
+        write_json(&mut out, &calendar, DayFormat::DayOfMonth).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"year\":2020,\"months\":[{\"month\":2,\"name\":\"February\",\"weeks\":[\
+            [null,null,null,null,null,null,1],[2,3,4,5,6,7,8],[9,10,11,12,13,14,15],\
+            [16,17,18,19,20,21,22],[23,24,25,26,27,28,29]],\
+            \"highlights\":[\"2020-02-14\"]}],\"holidays\":[]}\n"
+        );
+    }
+
+    #[test]
+    fn test_write_ics() {
+        let highlights = [date(2020, 4, 10), date(2020, 4, 20), date(2020, 5, 1)].into_iter().collect();
+        let holidays = [Holiday {
+            date: date(2020, 4, 10),
+            name: "Good Friday",
+        }];
+        let calendar = Calendar {
+            year: 2020,
+            month: Some(4),
+            highlights: &highlights,
+            holidays: &holidays,
+        };
+        let mut out = vec![];
+        write_ics(&mut out, &calendar).unwrap();
+        let ics = String::from_utf8(out).unwrap();
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2); // 祝日は重複させず、範囲外は含めない
+        assert!(ics.contains(
+            "DTSTART;VALUE=DATE:20200410\r\nDTEND;VALUE=DATE:20200411\r\nSUMMARY:Good Friday\r\n"
+        ));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20200420\r\n"));
+
+        // 期間内に予定がない場合も空のカレンダーを出力する
+        let none = HashSet::new();
+        let calendar = Calendar {
+            year: 2020,
+            month: None,
+            highlights: &none,
+            holidays: &[],
+        };
+        let mut out = vec![];
+        write_ics(&mut out, &calendar).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//calr//EN\r\nX-WR-CALNAME:calr 2020\r\nEND:VCALENDAR\r\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(escape_text("a,b;c\\d"), "a\\,b\\;c\\\\d");
+    }
+}
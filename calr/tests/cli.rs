@@ -43,7 +43,7 @@ fn dies_invalid_year() -> TestResult {
 #[test]
 fn dies_month_0() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "0"])
+        .args(["-m", "0"])
         .assert()
         .failure()
         .stderr("month \"0\" not in the range 1 through 12\n");
@@ -54,7 +54,7 @@ fn dies_month_0() -> TestResult {
 #[test]
 fn dies_month_13() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "13"])
+        .args(["-m", "13"])
         .assert()
         .failure()
         .stderr("month \"13\" not in the range 1 through 12\n");
@@ -65,7 +65,7 @@ fn dies_month_13() -> TestResult {
 #[test]
 fn dies_invalid_month() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "foo"])
+        .args(["-m", "foo"])
         .assert()
         .failure()
         .stderr("Invalid month \"foo\"\n");
@@ -77,7 +77,7 @@ fn dies_invalid_month() -> TestResult {
 fn dies_y_and_month() -> TestResult {
     let expected = "The argument '-m <MONTH>' cannot be used with '--year'";
     Command::cargo_bin(PRG)?
-        .args(&["-m", "1", "-y"])
+        .args(["-m", "1", "-y"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -89,7 +89,7 @@ fn dies_y_and_month() -> TestResult {
 fn dies_y_and_year() -> TestResult {
     let expected = "The argument '<YEAR>' cannot be used with '--year'";
     Command::cargo_bin(PRG)?
-        .args(&["-y", "2000"])
+        .args(["-y", "2000"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -116,7 +116,7 @@ fn month_num() -> TestResult {
 
     for (num, month) in expected {
         Command::cargo_bin(PRG)?
-            .args(&["-m", num])
+            .args(["-m", num])
             .assert()
             .success()
             .stdout(predicates::str::contains(month.to_string()));
@@ -143,7 +143,7 @@ fn partial_month() -> TestResult {
 
     for (arg, month) in expected {
         Command::cargo_bin(PRG)?
-            .args(&["-m", arg])
+            .args(["-m", arg])
             .assert()
             .success()
             .stdout(predicates::str::contains(month.to_string()));
@@ -207,3 +207,255 @@ fn year() -> TestResult {
     assert_eq!(lines.len(), 37);
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn test_4_2020_highlight() -> TestResult {
+    run(
+        &["-m", "4", "2020", "--highlight", "tests/inputs/dates.txt"],
+        "tests/expected/4-2020.highlight.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_4_2020_highlight_color() -> TestResult {
+    run(
+        &[
+            "-m",
+            "4",
+            "2020",
+            "--highlight",
+            "tests/inputs/dates.txt",
+            "--style",
+            "color",
+        ],
+        "tests/expected/4-2020.highlight.color.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_highlight_date() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--highlight", "tests/inputs/bad-dates.txt"])
+        .assert()
+        .failure()
+        .stderr("tests/inputs/bad-dates.txt: Invalid date \"foo\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_style() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--style", "foo"])
+        .assert()
+        .failure()
+        .stderr("Invalid highlight style \"foo\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_3_2020_julian() -> TestResult {
+    run(&["-j", "-m", "3", "2020"], "tests/expected/3-2020.julian.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_2020_julian() -> TestResult {
+    run(&["--julian", "2020"], "tests/expected/2020.julian.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_4_2020_holidays() -> TestResult {
+    run(
+        &["-m", "4", "2020", "--holidays", "gb"],
+        "tests/expected/4-2020.holidays.gb.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_holidays() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-H", "xx"])
+        .assert()
+        .failure()
+        .stderr("Invalid --holidays \"xx\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_4_2020_events() -> TestResult {
+    run(
+        &["-m", "4", "2020", "--events", "tests/inputs/events.txt"],
+        "tests/expected/4-2020.events.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_2020_events() -> TestResult {
+    run(
+        &["2020", "--events", "tests/inputs/events.txt"],
+        "tests/expected/2020.events.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_events() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--events", "tests/inputs/bad-events.txt"])
+        .assert()
+        .failure()
+        .stderr("tests/inputs/bad-events.txt: Invalid event \"2020-04-01\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_4_2020_holidays_json() -> TestResult {
+    run(
+        &["-m", "4", "2020", "--holidays", "gb", "--format", "json"],
+        "tests/expected/4-2020.holidays.gb.json",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_4_2020_holidays_ics() -> TestResult {
+    run(
+        &["-m", "4", "2020", "--holidays", "gb", "--format", "ics"],
+        "tests/expected/4-2020.holidays.gb.ics",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_format() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--format", "csv"])
+        .assert()
+        .failure()
+        .stderr("Invalid --format \"csv\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_3_2020_locale_ja() -> TestResult {
+    run(
+        &["-m", "3", "2020", "--locale", "ja_JP.UTF-8"],
+        "tests/expected/3-2020.ja.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_mai_2020_holidays_locale_de() -> TestResult {
+    run(
+        &["-m", "mai", "2020", "--holidays", "de", "--locale", "de_DE"],
+        "tests/expected/5-2020.holidays.de.locale_de.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_locale() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--locale", "xx_XX"])
+        .assert()
+        .failure()
+        .stderr("Invalid --locale \"xx_XX\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_2_2020_vertical() -> TestResult {
+    run(&["-N", "-m", "2", "2020"], "tests/expected/2-2020.vertical.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_2020_vertical() -> TestResult {
+    run(&["-N", "2020"], "tests/expected/2020.vertical.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_2_2020_vertical_then_classic() -> TestResult {
+    // 後に指定した-bが優先される
+    run(&["-N", "-b", "-m", "2", "2020"], "tests/expected/2-2020.txt")?;
+    run(&["-b", "-N", "-m", "2", "2020"], "tests/expected/2-2020.vertical.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_range_across_years() -> TestResult {
+    run(&["--from", "2020-11", "--to", "2021-02"], "tests/expected/11-2020.2-2021.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_range_holidays() -> TestResult {
+    // 年をまたぐ期間では祝日の一覧に年も出力する
+    run(
+        &["--from", "2020-12", "--to", "2021-01", "--holidays", "us"],
+        "tests/expected/12-2020.1-2021.holidays.us.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_range_one_year() -> TestResult {
+    // 1年分の期間は年の表示と同じ
+    run(&["--from", "2020-01", "--to", "2020-12"], "tests/expected/2020.txt")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_range() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--from", "2020-13", "--to", "2021-01"])
+        .assert()
+        .failure()
+        .stderr("Invalid --from \"2020-13\"\n");
+    Command::cargo_bin(PRG)?
+        .args(["--from", "2021-01", "--to", "2020"])
+        .assert()
+        .failure()
+        .stderr("Invalid --to \"2020\"\n");
+    Command::cargo_bin(PRG)?
+        .args(["--from", "2021-01", "--to", "2020-12"])
+        .assert()
+        .failure()
+        .stderr("--from \"2021-01\" is after --to \"2020-12\"\n");
+    Command::cargo_bin(PRG)?
+        .args(["--from", "2020-01", "--to", "2020-02", "--format", "json"])
+        .assert()
+        .failure()
+        .stderr("--from and --to require --format plain\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_range_with_year() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--from", "2020-01", "--to", "2020-02", "2020"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Command::cargo_bin(PRG)?
+        .args(["--from", "2020-01"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--to <YYYY-MM>"));
+    Ok(())
+}
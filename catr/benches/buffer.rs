@@ -0,0 +1,65 @@
+use catr::DEFAULT_BUFFER_SIZE;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+// 約32MBのログを1ファイル作成する
+fn gen_file() -> PathBuf {
+    let path = std::env::temp_dir().join(format!("catr-bench-buffer-{}.log", std::process::id()));
+    let mut out = BufWriter::new(File::create(&path).unwrap());
+    for i in 0..400_000 {
+        writeln!(out, "{} INFO request {} finished in {}ms on worker {}", i, i * 7, i % 97, i % 16).unwrap();
+    }
+    out.flush().unwrap();
+    path
+}
+
+// 書き込みのシステムコールの回数を測れるよう、破棄する出力先にも実際に書き込む
+// catrの出力先と同様にBoxに包み、ファイル間のコピーの最適化(copy_file_range等)を使わせない
+fn null_file() -> Box<dyn Write> {
+    Box::new(
+        OpenOptions::new()
+            .write(true)
+            .open(if cfg!(windows) { "NUL" } else { "/dev/null" })
+            .unwrap(),
+    )
+}
+
+fn bench_buffer_size(c: &mut Criterion) {
+    let path = gen_file();
+    let mut group = c.benchmark_group("buffer_size");
+    group.throughput(Throughput::Bytes(fs::metadata(&path).unwrap().len()));
+    group.sample_size(20);
+    for size in [8 * 1024, DEFAULT_BUFFER_SIZE, 256 * 1024, 1024 * 1024] {
+        // 行番号なし: io::copyでそのままコピーする
+        group.bench_function(format!("copy/{}K", size / 1024), |b| b.iter(|| {
+            let mut file = BufReader::with_capacity(size, File::open(&path).unwrap());
+            let mut out = BufWriter::with_capacity(size, null_file());
+            io::copy(&mut file, &mut out).unwrap();
+            out.flush().unwrap();
+        }));
+        // 行番号あり: 1行ずつ小さな書き込みを繰り返す
+        group.bench_function(format!("number/{}K", size / 1024), |b| b.iter(|| {
+            let mut file = BufReader::with_capacity(size, File::open(&path).unwrap());
+            let mut out = BufWriter::with_capacity(size, null_file());
+            let mut line = vec![];
+            let mut num = 1;
+            while file.read_until(b'\n', &mut line).unwrap() > 0 {
+                write!(out, "{:>6}\t", num).unwrap();
+                out.write_all(&line).unwrap();
+                num += 1;
+                line.clear();
+            }
+            out.flush().unwrap();
+        }));
+    }
+    group.finish();
+
+    fs::remove_file(path).unwrap();
+}
+
+criterion_group!(benches, bench_buffer_size);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+use catr::Prefetcher;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::{
+    fs,
+    io::{self, BufReader, Read},
+    path::PathBuf,
+};
+
+// 1ファイル数百バイトのログを2000個作成する
+fn gen_files() -> (PathBuf, Vec<String>) {
+    let dir = std::env::temp_dir().join(format!("catr-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let filenames = (0..2000)
+        .map(|i| {
+            let path = dir.join(format!("{:04}.log", i));
+            let text: String = (0..8).map(|n| format!("{} INFO request {} finished in {}ms\n", i, n, i % 97)).collect();
+            fs::write(&path, text).unwrap();
+            path.display().to_string()
+        })
+        .collect();
+    (dir, filenames)
+}
+
+fn bench_prefetch(c: &mut Criterion) {
+    let (dir, filenames) = gen_files();
+    let mut out = Vec::with_capacity(1 << 20);
+
+    let mut group = c.benchmark_group("cat");
+    group.bench_function("sequential", |b| b.iter(|| {
+        out.clear();
+        for filename in &filenames {
+            let mut file = BufReader::new(fs::File::open(filename).unwrap());
+            io::copy(&mut file, &mut out).unwrap();
+        }
+    }));
+    for depth in [1, 16] {
+        group.bench_function(format!("prefetch{}", depth), |b| b.iter(|| {
+            out.clear();
+            for file in Prefetcher::spawn(filenames.clone(), depth, 8 * 1024) {
+                file.unwrap().read_to_end(&mut out).unwrap();
+            }
+        }));
+    }
+    group.finish();
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+criterion_group!(benches, bench_prefetch);
+criterion_main!(benches);
@@ -0,0 +1,81 @@
+use std::{io::{BufRead, BufReader}, str::FromStr};
+
+use encoding_rs::Encoding;
+use encoding_rs_io::DecodeReaderBytesBuilder;
+
+// --from-encodingで指定する入力の文字コード
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SourceEncoding {
+    Auto,                     // BOMから判定する: BOMがなければ変換しない
+    Label(&'static Encoding), // sjis, latin1などのWHATWGのラベルで指定する
+}
+
+impl FromStr for SourceEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(SourceEncoding::Auto),
+            _ => Encoding::for_label(s.as_bytes())
+                .map(SourceEncoding::Label)
+                .ok_or_else(|| format!("Invalid --from-encoding \"{}\"", s)),
+        }
+    }
+}
+
+// 読み込みながらUTF-8に変換する: 行番号の付与も変換後の内容に対して行う
+// 変換できないバイト列は置換文字(U+FFFD)になり、BOMは出力しない
+pub fn decoding_reader(file: Box<dyn BufRead>, encoding: SourceEncoding) -> Box<dyn BufRead> {
+    let mut builder = DecodeReaderBytesBuilder::new();
+    builder.strip_bom(true);
+    if let SourceEncoding::Label(encoding) = encoding {
+        builder.encoding(Some(encoding));
+    }
+    Box::new(BufReader::new(builder.build(file)))
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{decoding_reader, SourceEncoding};
+    use std::io::{Cursor, Read};
+
+    fn decode(bytes: &'static [u8], encoding: &str) -> String {
+        let mut text = String::new();
+        decoding_reader(Box::new(Cursor::new(bytes)), encoding.parse().unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_parse_encoding() {
+        assert_eq!("auto".parse::<SourceEncoding>(), Ok(SourceEncoding::Auto));
+        assert_eq!(
+            "sjis".parse::<SourceEncoding>(),
+            Ok(SourceEncoding::Label(encoding_rs::SHIFT_JIS))
+        );
+        // Labels are case-insensitive; Latin-1 is decoded as windows-1252 per WHATWG
+        assert_eq!(
+            "Latin1".parse::<SourceEncoding>(),
+            Ok(SourceEncoding::Label(encoding_rs::WINDOWS_1252))
+        );
+        assert_eq!(
+            "klingon".parse::<SourceEncoding>(),
+            Err("Invalid --from-encoding \"klingon\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decoding_reader() {
+        assert_eq!(decode(b"\x82\xb1\x82\xf1\n", "sjis"), "こん\n");
+        assert_eq!(decode(b"caf\xe9\n", "latin1"), "café\n");
+        // Invalid sequences become replacement characters
+        assert_eq!(decode(b"a\x82\n", "sjis"), "a\u{FFFD}\n");
+
+        // Auto detects the BOM and strips it, and passes other input through
+        assert_eq!(decode(b"\xff\xfea\x00\n\x00", "auto"), "a\n");
+        assert_eq!(decode(b"\xef\xbb\xbfab\n", "auto"), "ab\n");
+        assert_eq!(decode(b"plain\n", "auto"), "plain\n");
+    }
+}
@@ -1,14 +1,87 @@
-use std::{error::Error, io::{BufRead, BufReader, stdin}, fs::File};
+use std::{error::Error, io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write, stdin}, fs::{self, File, OpenOptions}, path::PathBuf};
 
 use clap::{App, Arg};
 
+mod decode;
+use decode::SourceEncoding;
+
+mod prefetch;
+pub use prefetch::Prefetcher;
+
+mod tee;
+use tee::Tee;
+
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+/// Default capacity of the input and output buffers, in bytes.
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
     number_lines: bool,
     number_nonblank_lines: bool,
+    number_format: NumberFormat,
+    out_file: Option<String>,
+    tee_files: Vec<String>, // --tee: 出力内容を複製して書き込むファイル
+    append: bool,           // --teeのファイルを切り詰めずに追記する
+    verbose: bool,
+    selection: Selection,
+    prefetch: Option<usize>, // --prefetch: 別スレッドで先読みするファイル数
+    from_encoding: Option<SourceEncoding>, // 入力をUTF-8に変換して出力する: Noneはバイト列をそのまま出力
+    unbuffered: bool,   // -u: 出力を溜めずに、読み込んだ内容を行ごとに書き出す
+    buffer_size: usize, // --buffer-size: 入力と出力のバッファの大きさ(バイト)
+}
+
+// 行番号の付け方
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Numbering {
+    None,
+    All,      // -n: 全ての行
+    NonBlank, // -b: 空行以外
+}
+
+// --rangeで指定された出力対象の行: 1始まりで両端を含む。空の場合は全ての行
+#[derive(Debug, PartialEq, Default)]
+struct Selection {
+    ranges: Vec<(usize, usize)>,
+}
+
+impl Selection {
+    fn contains(&self, line_num: usize) -> bool {
+        self.ranges.is_empty()
+            || self.ranges.iter().any(|&(start, end)| start <= line_num && line_num <= end)
+    }
+
+    // 全ての範囲を過ぎた行以降は読み込む必要がない
+    fn is_done(&self, line_num: usize) -> bool {
+        self.ranges.iter().map(|&(_, end)| end).max().is_some_and(|end| line_num > end)
+    }
+}
+
+// 行番号の表示形式: デフォルトは6桁右寄せ + タブ区切り
+#[derive(Debug, PartialEq, Clone)]
+struct NumberFormat {
+    width: usize,
+    start: i64,
+    separator: String,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            width: 6,
+            start: 1,
+            separator: "\t".to_string(),
+        }
+    }
+}
+
+impl NumberFormat {
+    // 行番号を指定の桁数で右寄せし、区切り文字を付与する
+    fn format_line_number(&self, line_num: i64) -> String {
+        format!("{:>width$}{}", line_num, self.separator, width = self.width)
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -38,51 +111,486 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Number non-blank lines")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("number_width")
+                .long("number-width")
+                .value_name("WIDTH")
+                .help("Width of line numbers")
+                .default_value("6"),
+        )
+        .arg(
+            Arg::with_name("number_start")
+                .long("number-start")
+                .value_name("NUM")
+                .help("First line number")
+                .allow_hyphen_values(true) // 負の開始番号を値として受け付ける
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("number_separator")
+                .long("number-separator")
+                .value_name("STRING")
+                .help("Separator between line numbers and lines")
+                .default_value("\t"),
+        )
+        .arg(
+            Arg::with_name("out_file")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("Write output to FILE instead of stdout")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tee")
+                .long("tee")
+                .value_name("FILE")
+                .help("Also copy output to FILE (repeatable)")
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("append")
+                .short("a")
+                .long("append")
+                .help("Append to the --tee files instead of overwriting them")
+                .takes_value(false)
+                .requires("tee"),
+        )
+        .arg(
+            Arg::with_name("range")
+                .long("range")
+                .value_name("START:END")
+                .help("Print only lines START to END (either may be omitted; repeatable)")
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("prefetch")
+                .long("prefetch")
+                .value_name("N")
+                .help("Open and read up to N upcoming files on a background thread while printing")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("from_encoding")
+                .long("from-encoding")
+                .value_name("ENCODING")
+                .help("Convert input from ENCODING (e.g. sjis, latin1, utf-16le) to UTF-8, or detect it from a BOM with \"auto\"")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("unbuffered")
+                .short("u")
+                .long("unbuffered")
+                .help("Write output as soon as it is read, flushing after each line")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("buffer_size")
+                .long("buffer-size")
+                .value_name("SIZE")
+                .help("Size of the input and output buffers in bytes (K and M suffixes allowed) [default: 64K]")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .long("verbose")
+                .help("Print a ==> FILE <== header before each file")
+                .takes_value(false),
+        )
         .get_matches();
 
+    // デフォルト値があるのでunwrap可能
+    let number_format = NumberFormat {
+        width: parse_number(matches.value_of("number_width").unwrap(), "--number-width")?,
+        start: parse_number(matches.value_of("number_start").unwrap(), "--number-start")?,
+        separator: matches.value_of("number_separator").unwrap().to_string(),
+    };
+
+    let prefetch = matches
+        .value_of("prefetch")
+        .map(|val| {
+            parse_number::<usize>(val, "--prefetch")
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("Invalid --prefetch \"{}\"", val))
+        })
+        .transpose()?;
+
+    let buffer_size = matches
+        .value_of("buffer_size")
+        .map(parse_size)
+        .transpose()?
+        .unwrap_or(DEFAULT_BUFFER_SIZE);
+
     Ok(
         Config {
             files: matches.values_of_lossy("files").unwrap(), // value"s"_of_lossy() を使うこと: value_of_lossy() は単一Stringを返す
             number_lines: matches.is_present("number"),
             number_nonblank_lines: matches.is_present("number_nonblank"),
+            number_format,
+            out_file: matches.value_of_lossy("out_file").map(String::from),
+            tee_files: matches.values_of_lossy("tee").unwrap_or_default(),
+            append: matches.is_present("append"),
+            verbose: matches.is_present("verbose"),
+            selection: Selection {
+                ranges: matches
+                    .values_of("range")
+                    .into_iter()
+                    .flatten()
+                    .map(parse_range)
+                    .collect::<MyResult<_>>()?,
+            },
+            prefetch,
+            from_encoding: matches.value_of("from_encoding").map(str::parse).transpose()?,
+            unbuffered: matches.is_present("unbuffered"),
+            buffer_size,
         }
     )
 }
 
+// "START:END"の形式: 省略した開始は1行目、終了は最終行を表す。コロンがなければ1行のみ
+fn parse_range(val: &str) -> MyResult<(usize, usize)> {
+    let invalid = || format!("Invalid --range \"{}\"", val);
+    let parse = |num: &str, default: usize| match num {
+        "" => Ok(default),
+        _ => num.parse::<usize>().ok().filter(|&n| n > 0).ok_or_else(invalid),
+    };
+    let (start, end) = match val.split_once(':') {
+        Some((start, end)) => (parse(start, 1)?, parse(end, usize::MAX)?),
+        None if !val.is_empty() => {
+            let line_num = parse(val, 0)?;
+            (line_num, line_num)
+        }
+        None => return Err(invalid().into()),
+    };
+    if start > end {
+        return Err(invalid().into());
+    }
+    Ok((start, end))
+}
+
+// バイト数: Kは1024倍、Mは1024*1024倍
+fn parse_size(val: &str) -> MyResult<usize> {
+    let (num, unit) = match val.strip_suffix(['K', 'k']) {
+        Some(num) => (num, 1 << 10),
+        None => match val.strip_suffix(['M', 'm']) {
+            Some(num) => (num, 1 << 20),
+            None => (val, 1),
+        },
+    };
+    num.parse::<usize>()
+        .ok()
+        .and_then(|num| num.checked_mul(unit))
+        .filter(|&size| size > 0)
+        .ok_or_else(|| format!("Invalid --buffer-size \"{}\"", val).into())
+}
+
+fn parse_number<T: std::str::FromStr>(val: &str, name: &str) -> MyResult<T> {
+    val.parse()
+        .map_err(|_| format!("Invalid {} \"{}\"", name, val).into())
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    // dbg!(config);
-    for filename in config.files {
-        // println!("{}", filename);
-        match open(&filename) {
+    // 出力先を作成(切り詰め)する前に実体のパスを控えておく: 入力と同じファイルへの出力を検出するため
+    let out_paths: Vec<PathBuf> = config
+        .out_file
+        .iter()
+        .chain(&config.tee_files)
+        .filter_map(|name| fs::canonicalize(name).ok())
+        .collect();
+    let stdout = io::stdout();
+    let mut writers: Vec<Box<dyn Write>> = vec![match &config.out_file {
+        Some(out_filename) => buffered(
+            File::create(out_filename).map_err(|e| format!("{}: {}", out_filename, e))?,
+            &config,
+        ),
+        // 端末と-uでは標準出力の行単位のバッファのみを使う: 出力の度にロックを取得しないようにまとめてロックする
+        _ if config.unbuffered || stdout.is_terminal() => Box::new(stdout.lock()),
+        _ => Box::new(BufWriter::with_capacity(config.buffer_size, stdout.lock())),
+    }];
+    for tee_filename in &config.tee_files {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(config.append)
+            .truncate(!config.append)
+            .open(tee_filename)
+            .map_err(|e| format!("{}: {}", tee_filename, e))?;
+        writers.push(buffered(file, &config));
+    }
+    let mut out = Tee::new(writers);
+    let same_files: Vec<bool> = config.files.iter().map(|filename| is_same_file(filename, &out_paths)).collect();
+    // 先読みする場合は出力しないファイルを除いて、出力する順に読み込みスレッドへ渡す
+    let mut prefetcher = config.prefetch.map(|depth| {
+        let filenames = config
+            .files
+            .iter()
+            .zip(&same_files)
+            .filter(|(_, &same)| !same)
+            .map(|(filename, _)| filename.clone())
+            .collect();
+        Prefetcher::spawn(filenames, depth, config.buffer_size)
+    });
+    let mut printed_header = false; // 開けなかったファイルを除き、最初のヘッダーの前には空行を入れない
+    for (file_num, filename) in config.files.iter().enumerate() {
+        if same_files[file_num] {
+            eprintln!("{}: input file is output file", filename); // 自身への追記で無限に増え続けるのを防ぐ
+            continue;
+        }
+        let opened = match prefetcher.as_mut() {
+            Some(prefetcher) => match prefetcher.next() {
+                Some(input) => input.map(|file| file as Box<dyn BufRead>).map_err(From::from),
+                None => Err("prefetch thread stopped unexpectedly".into()),
+            },
+            None => open(filename, config.buffer_size),
+        };
+        match opened {
             Err(err) => eprintln!("Failed to open {}: {}", filename, err),
-            Ok(file) => {
-                // println!("Opened {}", filename)
-                let mut nonblank_line_num = 0;
-                for (line_num, line_result) in file.lines().enumerate() { // (index, 文字列) でループ処理
-                    let line = line_result?;
-                    // println!("{}", line);
-                    if config.number_lines {
-                        println!("{:>6}\t{}", line_num + 1, line); // 行数の桁が違っても表記がズレないように調整: 6桁表記で先頭空白埋め(数値は右寄せ)
-                    } else if config.number_nonblank_lines {
-                        if !line.is_empty() {
-                            nonblank_line_num += 1;
-                            println!("{:>6}\t{}", nonblank_line_num, line);
-                        } else {
-                            println!(); // 空白行は番号を付与せずにそのまま出力
-                        }
-                    } else {
-                        println!("{}", line);
-                    }
+            Ok(mut file) => {
+                if let Some(encoding) = config.from_encoding {
+                    file = decode::decoding_reader(file, encoding);
+                }
+                if config.verbose {
+                    print_header(&mut out, filename, !printed_header)?;
+                    printed_header = true;
+                }
+                let numbering = if config.number_nonblank_lines {
+                    Numbering::NonBlank
+                } else if config.number_lines {
+                    Numbering::All
+                } else {
+                    Numbering::None
+                };
+                if numbering != Numbering::None || !config.selection.ranges.is_empty() {
+                    print_lines(
+                        &mut file,
+                        &mut out,
+                        numbering,
+                        &config.number_format,
+                        &config.selection,
+                    )?;
+                } else {
+                    // 行番号が不要な場合はバイト列をそのままコピー: 非UTF-8のデータや末尾の改行の有無も保持される
+                    // 入力のバッファに読み込んだ分ずつ書き込むため、-uではパイプから読んだ行がすぐに出力される
+                    io::copy(&mut file, &mut out)?;
                 }
             },
         }
     }
+    out.flush()?; // BufWriterのdrop時の書き込みエラーを握りつぶさないように明示的にflushする
+    Ok(())
+}
+
+// ファイルへの出力: -uでは書き込みの度にそのまま書き出す
+fn buffered(file: File, config: &Config) -> Box<dyn Write> {
+    if config.unbuffered {
+        Box::new(file)
+    } else {
+        Box::new(BufWriter::with_capacity(config.buffer_size, file))
+    }
+}
+
+// headと同様のファイル名のヘッダー: 2ファイル目以降は空行で区切る
+fn print_header(out: &mut impl Write, filename: &str, first: bool) -> MyResult<()> {
+    writeln!(out, "{}==> {} <==", if first { "" } else { "\n" }, filename)?;
+    Ok(())
+}
+
+fn is_same_file(filename: &str, paths: &[PathBuf]) -> bool {
+    filename != "-" && fs::canonicalize(filename).is_ok_and(|path| paths.contains(&path))
+}
+
+// 選択された行に必要であれば行番号を付与して出力: 文字列ではなくバイト列として扱い、入力内容を変更しない
+// 行番号は範囲外の行も数えるため、元のファイルでの位置を表す
+fn print_lines(
+    file: &mut impl BufRead,
+    out: &mut impl Write,
+    numbering: Numbering,
+    number_format: &NumberFormat,
+    selection: &Selection,
+) -> MyResult<()> {
+    let mut number = number_format.start;
+    let mut line = Vec::new();
+    for line_num in 1.. {
+        if selection.is_done(line_num) {
+            break; // 残りは読み込まずに打ち切る
+        }
+        let bytes = file.read_until(b'\n', &mut line)?; // 改行コードも含めてバッファに読み込む
+        if bytes == 0 {
+            break; // EOF
+        }
+        let numbered = match numbering {
+            Numbering::None => false,
+            Numbering::All => true,
+            // 空白行は番号を付与せずにそのまま出力: CRLFの空行も空白行とする
+            Numbering::NonBlank => !matches!(line.as_slice(), b"\n" | b"\r\n" | b""),
+        };
+        if selection.contains(line_num) {
+            if numbered {
+                write!(out, "{}", number_format.format_line_number(number))?; // 行数の桁が違っても表記がズレないように調整: 指定桁数で先頭空白埋め(数値は右寄せ)
+            }
+            out.write_all(&line)?; // 改行コードも含まれるので末尾の改行の有無はそのまま保持される
+        }
+        if numbered {
+            number += 1;
+        }
+        line.clear();
+    }
     Ok(())
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> { // MyResult<dyn BufRead> だとサイズが固定できないため、Boxでヒープに格納する
+fn open(filename: &str, buffer_size: usize) -> MyResult<Box<dyn BufRead>> { // MyResult<dyn BufRead> だとサイズが固定できないため、Boxでヒープに格納する
     match filename {
-        "-" => Ok(Box::new(BufReader::new(stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+        "-" => Ok(Box::new(BufReader::with_capacity(buffer_size, stdin()))),
+        _ => Ok(Box::new(BufReader::with_capacity(buffer_size, File::open(filename)?))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_number, parse_range, parse_size, print_header, print_lines, NumberFormat, Numbering, Selection};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_print_lines_numbered() {
+        let mut out = vec![];
+        let res = print_lines(&mut Cursor::new(b"a\n\nb"), &mut out, Numbering::All, &NumberFormat::default(), &Selection::default());
+        assert!(res.is_ok());
+        assert_eq!(out, b"     1\ta\n     2\t\n     3\tb");
+
+        let mut out = vec![];
+        let res = print_lines(&mut Cursor::new(b"a\n\nb\n"), &mut out, Numbering::NonBlank, &NumberFormat::default(), &Selection::default());
+        assert!(res.is_ok());
+        assert_eq!(out, b"     1\ta\n\n     2\tb\n");
+
+        // Blank lines ending with CRLF are not numbered either
+        let mut out = vec![];
+        let res = print_lines(&mut Cursor::new(b"a\r\n\r\nb\r\n"), &mut out, Numbering::NonBlank, &NumberFormat::default(), &Selection::default());
+        assert!(res.is_ok());
+        assert_eq!(out, b"     1\ta\r\n\r\n     2\tb\r\n");
+    }
+
+    #[test]
+    fn test_format_line_number() {
+        let format = NumberFormat::default();
+        assert_eq!(format.format_line_number(1), "     1\t");
+        assert_eq!(format.format_line_number(1234567), "1234567\t");
+
+        let format = NumberFormat {
+            width: 3,
+            start: 1,
+            separator: ": ".to_string(),
+        };
+        assert_eq!(format.format_line_number(7), "  7: ");
+        assert_eq!(format.format_line_number(-2), " -2: ");
+
+        let format = NumberFormat {
+            width: 0,
+            start: 1,
+            separator: " ".to_string(),
+        };
+        assert_eq!(format.format_line_number(42), "42 ");
+    }
+
+    #[test]
+    fn test_print_lines_custom_format() {
+        let format = NumberFormat {
+            width: 2,
+            start: 9,
+            separator: "|".to_string(),
+        };
+        let mut out = vec![];
+        let res = print_lines(&mut Cursor::new(b"a\n\nb\n"), &mut out, Numbering::NonBlank, &format, &Selection::default());
+        assert!(res.is_ok());
+        assert_eq!(out, b" 9|a\n\n10|b\n");
+    }
+
+    #[test]
+    fn test_parse_number() {
+        let res = parse_number::<usize>("3", "--number-width");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 3);
+
+        let res = parse_number::<i64>("-5", "--number-start");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), -5);
+
+        let res = parse_number::<usize>("-1", "--number-width");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "Invalid --number-width \"-1\"");
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("64K").unwrap(), 64 * 1024);
+        assert_eq!(parse_size("1m").unwrap(), 1024 * 1024);
+
+        // Zero, missing numbers, unknown suffixes and overflow are rejected
+        for val in ["0", "K", "8G", "-1", &format!("{}M", usize::MAX)] {
+            let res = parse_size(val);
+            assert!(res.is_err());
+            assert_eq!(res.unwrap_err().to_string(), format!("Invalid --buffer-size \"{}\"", val));
+        }
+    }
+
+    #[test]
+    fn test_print_header() {
+        let mut out = vec![];
+        print_header(&mut out, "a.txt", true).unwrap();
+        print_header(&mut out, "-", false).unwrap();
+        assert_eq!(out, b"==> a.txt <==\n\n==> - <==\n");
+    }
+
+    #[test]
+    fn test_print_lines_non_utf8() {
+        let mut out = vec![];
+        let res = print_lines(&mut Cursor::new(b"\xff\xfe\r\n"), &mut out, Numbering::All, &NumberFormat::default(), &Selection::default());
+        assert!(res.is_ok());
+        assert_eq!(out, b"     1\t\xff\xfe\r\n");
+    }
+
+    #[test]
+    fn test_print_lines_selection() {
+        let text = b"1\n2\n\n4\n5\n6\n";
+        let selection = Selection {
+            ranges: vec![(5, 5), (2, 4)],
+        };
+        let mut out = vec![];
+        let res = print_lines(&mut Cursor::new(text), &mut out, Numbering::None, &NumberFormat::default(), &selection);
+        assert!(res.is_ok());
+        assert_eq!(out, b"2\n\n4\n5\n");
+
+        // Line numbers keep counting lines outside the ranges
+        let mut out = vec![];
+        let res = print_lines(&mut Cursor::new(text), &mut out, Numbering::NonBlank, &NumberFormat::default(), &selection);
+        assert!(res.is_ok());
+        assert_eq!(out, b"     2\t2\n\n     3\t4\n     4\t5\n");
+
+        // Reading stops after the last range
+        let mut file = Cursor::new(text);
+        let selection = Selection {
+            ranges: vec![(1, 2)],
+        };
+        let res = print_lines(&mut file, &mut vec![], Numbering::None, &NumberFormat::default(), &selection);
+        assert!(res.is_ok());
+        assert_eq!(file.position(), 4);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("100:200").unwrap(), (100, 200));
+        assert_eq!(parse_range("3:").unwrap(), (3, usize::MAX));
+        assert_eq!(parse_range(":3").unwrap(), (1, 3));
+        assert_eq!(parse_range("7").unwrap(), (7, 7));
+        assert_eq!(parse_range("7:7").unwrap(), (7, 7));
+
+        for bad in ["", "0:3", "5:3", "a:b", "1-3", "1:2:3"] {
+            let res = parse_range(bad);
+            assert!(res.is_err());
+            assert_eq!(res.unwrap_err().to_string(), format!("Invalid --range \"{}\"", bad));
+        }
     }
 }
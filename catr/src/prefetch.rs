@@ -0,0 +1,85 @@
+use std::{
+    fs::File,
+    io::{self, stdin, BufRead, BufReader, Cursor, Read},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+// この大きさ以下の通常のファイルは内容を全てメモリに読み込んでおく: それ以外は開くだけにする
+const MAX_PREFETCH_BYTES: u64 = 1 << 20;
+
+pub type Input = io::Result<Box<dyn BufRead + Send>>;
+
+/// Opens and reads input files on a background thread, at most `depth`
+/// files ahead of the consumer, yielding them in the given order.
+/// Inputs that are not read ahead are wrapped in a `buffer_size` reader.
+pub struct Prefetcher {
+    inputs: Receiver<Input>,
+}
+
+impl Prefetcher {
+    pub fn spawn(filenames: Vec<String>, depth: usize, buffer_size: usize) -> Self {
+        // 容量付きのチャネルで先読みするファイル数を制限する: 満杯の間は読み込みスレッドが待つ
+        let (tx, rx) = mpsc::sync_channel(depth);
+        thread::spawn(move || {
+            for filename in filenames {
+                if tx.send(read_ahead(&filename, buffer_size)).is_err() {
+                    break; // 受信側が途中で終了した場合は残りを読まない
+                }
+            }
+        });
+        Prefetcher { inputs: rx }
+    }
+}
+
+impl Iterator for Prefetcher {
+    type Item = Input;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inputs.recv().ok()
+    }
+}
+
+// 小さいファイルはopenとreadを済ませておく: 読み込みのエラーは逐次出力する場合と同様に出力時に返す
+fn read_ahead(filename: &str, buffer_size: usize) -> Input {
+    if filename == "-" {
+        return Ok(Box::new(BufReader::with_capacity(buffer_size, stdin())));
+    }
+    let mut file = File::open(filename)?;
+    let size = file.metadata().ok().filter(|meta| meta.is_file()).map(|meta| meta.len());
+    if let Some(size) = size.filter(|&size| size <= MAX_PREFETCH_BYTES) {
+        let mut buffer = Vec::with_capacity(size as usize);
+        if file.read_to_end(&mut buffer).is_ok() {
+            return Ok(Box::new(Cursor::new(buffer)));
+        }
+        file = File::open(filename)?; // 読み込めなかった場合は開き直して出力時にエラーにする
+    }
+    Ok(Box::new(BufReader::with_capacity(buffer_size, file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Prefetcher;
+    use std::io::Read;
+
+    #[test]
+    fn test_prefetcher_order() {
+        let filenames = ["tests/inputs/fox.txt", "missing.txt", "tests/inputs/empty.txt", "tests/inputs/fox.txt"];
+        let inputs: Vec<_> = Prefetcher::spawn(filenames.iter().map(|name| name.to_string()).collect(), 1, 8 * 1024)
+            .map(|input| {
+                input.map(|mut file| {
+                    let mut text = String::new();
+                    file.read_to_string(&mut text).unwrap();
+                    text
+                })
+            })
+            .collect();
+        assert_eq!(inputs.len(), 4);
+        // Files come back in the requested order, including open errors
+        let fox = std::fs::read_to_string("tests/inputs/fox.txt").unwrap();
+        assert_eq!(inputs[0].as_ref().unwrap(), &fox);
+        assert!(inputs[1].is_err());
+        assert_eq!(inputs[2].as_ref().unwrap(), "");
+        assert_eq!(inputs[3].as_ref().unwrap(), &fox);
+    }
+}
@@ -0,0 +1,93 @@
+use std::io::{self, Write};
+
+// 同じ内容を複数の出力先に書き込む: 標準出力と--teeのファイルへの同時出力に使う
+pub struct Tee<'a> {
+    writers: Vec<Box<dyn Write + 'a>>,
+}
+
+impl<'a> Tee<'a> {
+    pub fn new(writers: Vec<Box<dyn Write + 'a>>) -> Self {
+        Tee { writers }
+    }
+}
+
+impl Write for Tee<'_> {
+    // 一部の出力先だけに書き込まれた状態を残さないよう、全ての出力先に全体を書き込む
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for writer in &mut self.writers {
+            writer.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for writer in &mut self.writers {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tee;
+    use std::io::{self, Write};
+
+    // 1回の書き込みで最大2バイトしか受け付けない出力先
+    struct Short(Vec<u8>);
+
+    impl Write for Short {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let len = buf.len().min(2);
+            self.0.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tee() {
+        let mut first = vec![];
+        let mut second = Short(vec![]);
+        {
+            let mut tee = Tee::new(vec![Box::new(&mut first), Box::new(&mut second)]);
+            assert_eq!(tee.write(b"hello\n").unwrap(), 6);
+            tee.write_all(b"world").unwrap();
+            assert!(tee.flush().is_ok());
+        }
+        assert_eq!(first, b"hello\nworld");
+        assert_eq!(second.0, b"hello\nworld");
+
+        // No writers is a sink
+        let mut tee = Tee::new(vec![]);
+        assert_eq!(tee.write(b"abc").unwrap(), 3);
+    }
+
+    // 書き込みに失敗する出力先
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("broken"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::other("broken"))
+        }
+    }
+
+    #[test]
+    fn test_tee_error() {
+        let mut out = vec![];
+        {
+            let mut tee = Tee::new(vec![Box::new(&mut out), Box::new(Broken)]);
+            assert_eq!(tee.write(b"abc").unwrap_err().to_string(), "broken");
+            assert!(tee.flush().is_err());
+        }
+        // Writers before the failing one have already received the data
+        assert_eq!(out, b"abc");
+    }
+}
@@ -11,6 +11,7 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const BINARY: &str = "tests/inputs/binary.bin";
 
 // --------------------------------------------------
 #[test]
@@ -198,3 +199,401 @@ fn all_n() -> TestResult {
 fn all_b() -> TestResult {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
+
+// --------------------------------------------------
+fn run_bytes(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn binary() -> TestResult {
+    run_bytes(&[BINARY], BINARY)
+}
+
+#[test]
+fn binary_n() -> TestResult {
+    run_bytes(&["-n", BINARY], "tests/expected/binary.bin.n.out")
+}
+
+#[test]
+fn binary_stdin() -> TestResult {
+    let input = fs::read(BINARY)?;
+    Command::cargo_bin(PRG)?
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(input);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_n_custom_format() -> TestResult {
+    run(
+        &[
+            "-n",
+            "--number-width",
+            "3",
+            "--number-start",
+            "10",
+            "--number-separator",
+            ": ",
+            BUSTLE,
+        ],
+        "tests/expected/the-bustle.txt.n.custom.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_b_negative_start() -> TestResult {
+    run(
+        &["-b", "--number-width", "2", "--number-start", "-1", BUSTLE],
+        "tests/expected/the-bustle.txt.b.custom.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_number_width() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--number-width", "foo", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --number-width \"foo\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn all_verbose() -> TestResult {
+    run(&["--verbose", FOX, EMPTY, SPIDERS], "tests/expected/all.verbose.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn verbose_skips_bad_first_file() -> TestResult {
+    // 最初に出力するヘッダーの前には、開けなかったファイルがあっても空行を入れない
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/all.verbose.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--verbose", &bad, FOX, EMPTY, SPIDERS])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr(predicate::str::contains(format!("Failed to open {}", bad)));
+    Ok(())
+}
+
+// --------------------------------------------------
+fn gen_out_file() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("catr-{}", gen_bad_file()))
+}
+
+// --------------------------------------------------
+#[test]
+fn output_file() -> TestResult {
+    let out_file = gen_out_file();
+    let out_filename = out_file.to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args(["--verbose", FOX, EMPTY, SPIDERS, "-o", out_filename])
+        .assert()
+        .success()
+        .stdout("");
+    let expected = fs::read_to_string("tests/expected/all.verbose.out")?;
+    assert_eq!(fs::read_to_string(&out_file)?, expected);
+    fs::remove_file(&out_file)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_input_is_output() -> TestResult {
+    let out_file = gen_out_file();
+    let out_filename = out_file.to_str().unwrap();
+    fs::copy(SPIDERS, &out_file)?;
+    Command::cargo_bin(PRG)?
+        .args([FOX, out_filename, "--output", out_filename])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("input file is output file"));
+    let expected = fs::read_to_string("tests/expected/fox.txt.out")?;
+    assert_eq!(fs::read_to_string(&out_file)?, expected);
+    fs::remove_file(&out_file)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn tee_files() -> TestResult {
+    let tee1 = gen_out_file();
+    let tee2 = gen_out_file();
+    let expected = fs::read_to_string("tests/expected/all.verbose.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--verbose", FOX, EMPTY, SPIDERS])
+        .args(["--tee", tee1.to_str().unwrap(), "--tee", tee2.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(expected.clone());
+    assert_eq!(fs::read_to_string(&tee1)?, expected);
+    assert_eq!(fs::read_to_string(&tee2)?, expected);
+
+    // -oと併用した場合は出力ファイルと同じ内容を複製する
+    let out_file = gen_out_file();
+    Command::cargo_bin(PRG)?
+        .args([FOX, "-o", out_file.to_str().unwrap(), "--tee", tee1.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout("");
+    let expected = fs::read_to_string("tests/expected/fox.txt.out")?;
+    assert_eq!(fs::read_to_string(&out_file)?, expected);
+    assert_eq!(fs::read_to_string(&tee1)?, expected);
+
+    for file in [tee1, tee2, out_file] {
+        fs::remove_file(file)?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn tee_append() -> TestResult {
+    let tee = gen_out_file();
+    let tee_filename = tee.to_str().unwrap();
+    fs::copy(SPIDERS, &tee)?;
+    Command::cargo_bin(PRG)?
+        .args([FOX, "--tee", tee_filename, "--append"])
+        .assert()
+        .success();
+    let expected = fs::read_to_string(SPIDERS)? + &fs::read_to_string(FOX)?;
+    assert_eq!(fs::read_to_string(&tee)?, expected);
+
+    // 追記先を入力にすると無限に増え続けるため読み飛ばす
+    Command::cargo_bin(PRG)?
+        .args([FOX, tee_filename, "--tee", tee_filename, "-a"])
+        .assert()
+        .success()
+        .stdout(fs::read_to_string(FOX)?)
+        .stderr(predicate::str::contains("input file is output file"));
+    fs::remove_file(&tee)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_append_without_tee() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--append", FOX])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_n_range() -> TestResult {
+    // 行番号は元のファイルでの位置を表す
+    run(
+        &["-n", "--range", "2:3", "--range", "7:", BUSTLE],
+        "tests/expected/the-bustle.txt.n.range.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn range_per_file() -> TestResult {
+    // 範囲はファイルごとに適用される
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--range", ":1", BUSTLE, SPIDERS])
+        .assert()
+        .success();
+    let expected = format!(
+        "The bustle in a house\n{}",
+        fs::read_to_string(SPIDERS)?.lines().next().unwrap()
+    );
+    assert_eq!(String::from_utf8(cmd.get_output().stdout.clone())?.trim_end(), expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_range() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--range", "5:3", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --range \"5:3\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn prefetch() -> TestResult {
+    // 先読みしても出力の順序は変わらない
+    run(&["--prefetch", "1", FOX, SPIDERS, BUSTLE], "tests/expected/all.out")?;
+    run(&["--prefetch", "8", FOX, SPIDERS, BUSTLE, "-n"], "tests/expected/all.n.out")?;
+    run(&["--prefetch=2", "--verbose", FOX, EMPTY, SPIDERS], "tests/expected/all.verbose.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn prefetch_stdin() -> TestResult {
+    run_stdin(BUSTLE, &["--prefetch", "2", "-n", "-"], "tests/expected/the-bustle.txt.n.stdin.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn prefetch_skips_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/fox.txt.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--prefetch", "2", &bad, FOX])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr(predicate::str::is_match(format!("Failed to open {}: .* [(]os error 2[)]", bad))?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn prefetch_skips_input_is_output() -> TestResult {
+    let out_file = gen_out_file();
+    let out_filename = out_file.to_str().unwrap();
+    fs::copy(SPIDERS, &out_file)?;
+    Command::cargo_bin(PRG)?
+        .args(["--prefetch", "4", FOX, out_filename, "--output", out_filename])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("input file is output file"));
+    let expected = fs::read_to_string("tests/expected/fox.txt.out")?;
+    assert_eq!(fs::read_to_string(&out_file)?, expected);
+    fs::remove_file(&out_file)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_prefetch() -> TestResult {
+    for val in ["0", "x"] {
+        Command::cargo_bin(PRG)?
+            .args(["--prefetch", val, FOX])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!("Invalid --prefetch \"{}\"", val)));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+const SJIS: &str = "tests/inputs/sjis.txt";
+
+#[test]
+fn sjis_n() -> TestResult {
+    run(&["-n", "--from-encoding", "sjis", SJIS], "tests/expected/sjis.txt.n.out")
+}
+
+#[test]
+fn sjis_b_stdin() -> TestResult {
+    // 標準入力もバイト列のまま渡して変換する
+    let input = fs::read(SJIS)?;
+    let expected = fs::read_to_string("tests/expected/sjis.txt.b.stdin.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-b", "--from-encoding=shift_jis"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn latin1() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--from-encoding", "latin1", "tests/inputs/latin1.txt"])
+        .assert()
+        .success()
+        .stdout("Déjà vu\nCafé crème\n");
+    Ok(())
+}
+
+#[test]
+fn from_encoding_auto() -> TestResult {
+    // BOMのあるUTF-16は変換し、BOMのないファイルはそのまま出力する
+    run(
+        &["--from-encoding", "auto", "tests/inputs/utf16.txt", FOX],
+        "tests/expected/auto.out",
+    )
+}
+
+#[test]
+fn dies_bad_from_encoding() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--from-encoding", "klingon", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --from-encoding \"klingon\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn buffer_size() -> TestResult {
+    // バッファの大きさによらず出力は同じ
+    run(&["--buffer-size", "1", FOX, SPIDERS, BUSTLE], "tests/expected/all.out")?;
+    run(&["--buffer-size=3", "-n", FOX, SPIDERS, BUSTLE], "tests/expected/all.n.out")?;
+    run(&["--buffer-size", "1M", "--prefetch", "2", FOX, SPIDERS, BUSTLE], "tests/expected/all.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn unbuffered() -> TestResult {
+    run(&["-u", FOX, SPIDERS, BUSTLE], "tests/expected/all.out")?;
+    run(&["--unbuffered", "-n", FOX, SPIDERS, BUSTLE], "tests/expected/all.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn unbuffered_stdin_live() -> TestResult {
+    use std::{io::{BufRead, BufReader, Write}, process::Stdio};
+
+    // 入力が終わる前に、書き込んだ行がパイプの先へ出力される
+    for args in [&["-u"][..], &["-u", "-n"]] {
+        let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        for text in ["first\n", "second\n"] {
+            stdin.write_all(text.as_bytes())?;
+            line.clear();
+            stdout.read_line(&mut line)?;
+            assert!(line.ends_with(text));
+        }
+        drop(stdin);
+        assert!(child.wait()?.success());
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_buffer_size() -> TestResult {
+    for val in ["0", "x", "2G"] {
+        Command::cargo_bin(PRG)?
+            .args(["--buffer-size", val, FOX])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!("Invalid --buffer-size \"{}\"", val)));
+    }
+    Ok(())
+}
@@ -1,27 +1,151 @@
-use std::{error::Error, io::{BufReader, stdin, BufRead}, fs::File, cmp::Ordering::*};
+use std::{error::Error, io::{BufReader, stdin, BufRead}, fs::File, str::FromStr};
 
 use clap::{App, Arg};
 
-use crate::Column::*;
-
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
-    file1: String,
-    file2: String,
-    show_col1: bool,
-    show_col2: bool,
-    show_col3: bool,
+    files: Vec<String>, // 2つ、または3つ(実験的な3者比較モード)の入力ファイル
+    columns: ColumnMask,
     insensitive: bool,
     delimiter: String,
+    field: Option<usize>,
+    field_delimiter: String,
+    order: OrderCheck,
+    presort: bool,
+    color: ColorMode,
+    header: bool,
 }
 
-#[derive(Debug)]
-enum Column<'a> {
-    Col1(&'a str),
-    Col2(&'a str),
-    Col3(&'a str),
+// 色付き出力の方針
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum ColorMode {
+    Auto,   // 標準出力が端末の場合のみ色付け
+    Always, // 常に色付け
+    Never,  // 色付けしない
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!("Invalid --color \"{}\"", s)),
+        }
+    }
+}
+
+impl ColorMode {
+    fn enabled(&self) -> bool {
+        match self {
+            ColorMode::Auto => atty::is(atty::Stream::Stdout),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+// 入力が整列済みかの確認方法: 未整列の入力では正しく比較できないため、既定では警告する
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum OrderCheck {
+    Warn,   // ファイルごとに1回警告して処理を続ける
+    Fail,   // --check-order: エラーとして終了する
+    Ignore, // --nocheck-order: 確認しない
+}
+
+// 出力するカラムの集合: n番目のビットがカラムn+1に対応する
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct ColumnMask(u8);
+
+impl ColumnMask {
+    fn shows(&self, col: usize) -> bool {
+        self.0 & (1 << (col - 1)) != 0
+    }
+
+    // 指定カラムより前に表示されるカラム数: 出力時に先頭に付ける区切り文字の数
+    fn shown_before(&self, col: usize) -> usize {
+        (1..col).filter(|&c| self.shows(c)).count()
+    }
+}
+
+// 行を含むファイルの組み合わせ(n番目のビットがn番目のファイル)から出力先のカラムを求める
+// 2ファイル: 1のみ, 2のみ, 共通 / 3ファイル: 1のみ, 2のみ, 3のみ, 1と2, 1と3, 2と3, 共通
+fn column_of(members: u8, num_files: usize) -> usize {
+    match (num_files, members) {
+        (2, 0b011) => 3,
+        (3, 0b011) => 4,
+        (3, 0b101) => 5,
+        (3, 0b110) => 6,
+        (3, 0b111) => 7,
+        (_, 0b001) => 1,
+        (_, 0b010) => 2,
+        (_, 0b100) => 3,
+        _ => unreachable!("Invalid file combination"),
+    }
+}
+
+// column_ofの逆: カラムに出力される行を含むファイルの組み合わせ
+fn members_of(col: usize, num_files: usize) -> u8 {
+    (1..1u8 << num_files)
+        .find(|&members| column_of(members, num_files) == col)
+        .unwrap()
+}
+
+// カラムの色: 1つのファイルのみの行はファイルごとの色(赤, 緑, 青)、全ファイルに共通の行は白、一部に共通の行は黄
+fn color_of(col: usize, num_files: usize) -> &'static str {
+    let members = members_of(col, num_files);
+    match members.count_ones() {
+        1 => ["31", "32", "34"][members.trailing_zeros() as usize],
+        n if n as usize == num_files => "37",
+        _ => "33",
+    }
+}
+
+// 1行分の出力を組み立てる: 非表示のカラムを詰めて、前に表示されるカラムの分だけ区切り文字で字下げする
+struct Formatter<'a> {
+    columns: ColumnMask,
+    delimiter: &'a str,
+    num_files: usize,
+    color: bool,
+}
+
+impl Formatter<'_> {
+    fn paint(&self, col: usize, text: &str) -> String {
+        if !self.color || text.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[0m", color_of(col, self.num_files), text)
+    }
+
+    // 非表示のカラムはNone
+    fn line(&self, col: usize, val: &str) -> Option<String> {
+        self.columns.shows(col).then(|| {
+            format!("{}{}", self.delimiter.repeat(self.columns.shown_before(col)), self.paint(col, val))
+        })
+    }
+
+    // 表示する各カラムの見出し: 行を含むファイル名を" & "でつなぐ
+    fn header(&self, files: &[String]) -> String {
+        let num_cols = if self.num_files == 2 { 3 } else { 7 };
+        (1..=num_cols)
+            .filter(|&col| self.columns.shows(col))
+            .map(|col| {
+                let members = members_of(col, self.num_files);
+                let names: Vec<_> = files
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| members & (1 << i) != 0)
+                    .map(|(_, file)| file.as_str())
+                    .collect();
+                self.paint(col, &names.join(" & "))
+            })
+            .collect::<Vec<_>>()
+            .join(self.delimiter)
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -43,6 +167,12 @@ pub fn get_args() -> MyResult<Config> {
                 .takes_value(true)
                 .required(true),
         )
+        .arg(
+            Arg::with_name("file3")
+                .value_name("FILE3")
+                .help("Input file 3 (experimental three-way comparison)")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("suppress_col1")
                 .short("1")
@@ -61,6 +191,34 @@ pub fn get_args() -> MyResult<Config> {
                 .takes_value(false)
                 .help("Suppress printing of column 3"),
         )
+        .arg(
+            Arg::with_name("suppress_col4")
+                .short("4")
+                .takes_value(false)
+                .requires("file3")
+                .help("Suppress printing of column 4 (three-way only)"),
+        )
+        .arg(
+            Arg::with_name("suppress_col5")
+                .short("5")
+                .takes_value(false)
+                .requires("file3")
+                .help("Suppress printing of column 5 (three-way only)"),
+        )
+        .arg(
+            Arg::with_name("suppress_col6")
+                .short("6")
+                .takes_value(false)
+                .requires("file3")
+                .help("Suppress printing of column 6 (three-way only)"),
+        )
+        .arg(
+            Arg::with_name("suppress_col7")
+                .short("7")
+                .takes_value(false)
+                .requires("file3")
+                .help("Suppress printing of column 7 (three-way only)"),
+        )
         .arg(
             Arg::with_name("insensitive")
                 .short("i")
@@ -76,21 +234,109 @@ pub fn get_args() -> MyResult<Config> {
                 .default_value("\t")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("field")
+                .short("f")
+                .long("field")
+                .value_name("FIELD")
+                .help("Compare only this field (1-based) of each line")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("field_delimiter")
+                .short("t")
+                .long("delimiter")
+                .value_name("DELIM")
+                .help("Input field delimiter")
+                .default_value("\t")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("check_order")
+                .long("check-order")
+                .takes_value(false)
+                .help("Fail if the input is not correctly sorted"),
+        )
+        .arg(
+            Arg::with_name("nocheck_order")
+                .long("nocheck-order")
+                .takes_value(false)
+                .conflicts_with("check_order")
+                .help("Do not check that the input is correctly sorted"),
+        )
+        .arg(
+            Arg::with_name("presort")
+                .long("presort")
+                .takes_value(false)
+                .help("Sort each input in memory before comparing"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Color each column [auto|always|never]")
+                .takes_value(true)
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("header")
+                .long("header")
+                .takes_value(false)
+                .help("Print a header row naming the files of each column"),
+        )
         .get_matches();
 
+    let field = matches.value_of("field")
+        .map(parse_field)
+        .transpose()?;
+
+    // フラグ指定時は対象カラムを非表示にする: デフォルトは全カラムを表示させる
+    let columns = (1..=7)
+        .filter(|col| !matches.is_present(format!("suppress_col{}", col)))
+        .fold(0, |mask, col| mask | 1 << (col - 1));
+
     Ok(
         Config {
-            file1: matches.value_of("file1").unwrap().to_string(),
-            file2: matches.value_of("file2").unwrap().to_string(),
-            show_col1: !matches.is_present("suppress_col1"), // フラグ指定時は対象カラムを非表示にする: デフォルトは全カラムを表示させる
-            show_col2: !matches.is_present("suppress_col2"), // パラメータの名称に注意！
-            show_col3: !matches.is_present("suppress_col3"),
+            files: ["file1", "file2", "file3"]
+                .iter()
+                .filter_map(|name| matches.value_of(name))
+                .map(String::from)
+                .collect(),
+            columns: ColumnMask(columns),
             insensitive: matches.is_present("insensitive"),
             delimiter: matches.value_of("delimiter").unwrap().to_string(),
+            field,
+            field_delimiter: matches.value_of("field_delimiter").unwrap().to_string(),
+            order: if matches.is_present("check_order") {
+                OrderCheck::Fail
+            } else if matches.is_present("nocheck_order") {
+                OrderCheck::Ignore
+            } else {
+                OrderCheck::Warn
+            },
+            presort: matches.is_present("presort"),
+            color: matches.value_of("color").unwrap().parse()?, // デフォルト値があるのでunwrap可能
+            header: matches.is_present("header"),
         }
     )
 }
 
+// 1以上のフィールド番号のみを受け付ける
+fn parse_field(val: &str) -> MyResult<usize> {
+    match val.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("Invalid --field \"{}\"", val).into()),
+    }
+}
+
+// 比較に使うキーを行から取り出す: フィールド未指定の場合は行全体、該当フィールドがない場合は空文字
+fn extract_key<'a>(line: &'a str, field: Option<usize>, delimiter: &str) -> &'a str {
+    match field {
+        Some(num) => line.split(delimiter).nth(num - 1).unwrap_or(""),
+        None => line,
+    }
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(stdin()))),
@@ -102,11 +348,11 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let file1 = &config.file1;
-    let file2 = &config.file2;
-
-    if file1 == "-" && file2 == "-" {
-        return Err(From::from("Both input files cannot be STDIN (\"-\")"));
+    if config.files.iter().filter(|file| *file == "-").count() > 1 {
+        return Err(From::from(match config.files.len() {
+            2 => "Both input files cannot be STDIN (\"-\")",
+            _ => "Only one input file can be STDIN (\"-\")",
+        }));
     }
 
     let case = |line: String| {
@@ -117,79 +363,86 @@ pub fn run(config: Config) -> MyResult<()> {
         }
     };
 
-    let mut lines1 = open(&file1)?
-        .lines() // 各行データを抽出
-        .filter_map(Result::ok)
-        .map(case);
-    let mut lines2 = open(&file2)?
-        .lines()
-        .filter_map(Result::ok)
-        .map(case);
-
-    let print = |col: Column| {
-        let mut columns = vec![];
-        match col {
-            Col1(val) => {
-                if config.show_col1 {
-                    columns.push(val);
-                }
-            },
-            Col2(val) => {
-                if config.show_col2 {
-                    if config.show_col1 {
-                        columns.push("");
-                    }
-                    columns.push(val);
-                }
-            },
-            Col3(val) => {
-                if config.show_col3 {
-                    if config.show_col1 {
-                        columns.push("");
-                    }
-                    if config.show_col2 {
-                        columns.push("");
-                    }
-                    columns.push(val);
-                }
-            }
+    let key = |line: &str| extract_key(line, config.field, &config.field_delimiter).to_string();
+
+    let mut readers: Vec<Box<dyn Iterator<Item = String>>> = vec![];
+    for file in &config.files {
+        let lines = open(file)?
+            .lines() // 各行データを抽出
+            .map_while(Result::ok)
+            .map(case);
+        if config.presort {
+            // 全ての行を読み込んでキーの順に並べ替える: 同じキーの行は元の順序のまま
+            let mut lines: Vec<_> = lines.collect();
+            lines.sort_by_cached_key(|line| key(line));
+            readers.push(Box::new(lines.into_iter()));
+        } else {
+            readers.push(Box::new(lines));
         }
-        if !columns.is_empty() {
-            println!("{}", columns.join(&config.delimiter));
+    }
+
+    let formatter = Formatter {
+        columns: config.columns,
+        delimiter: &config.delimiter,
+        num_files: config.files.len(),
+        color: config.color.enabled(),
+    };
+    if config.header {
+        println!("{}", formatter.header(&config.files));
+    }
+    let print = |col: usize, val: &str| {
+        if let Some(line) = formatter.line(col, val) {
+            println!("{}", line);
         }
     };
 
-    let mut line1 = lines1.next(); // 最初の行データを取得: mutとして更新されていく
-    let mut line2 = lines2.next();
-
-    while line1.is_some() || line2.is_some() { // どちらかのファイルに行データがあるとき
-        match (&line1, &line2) {
-            (Some(val1), Some(val2)) => match val1.cmp(val2) { // 各行データの大小関係を比較
-                Equal => {
-                    print(Column::Col3(&val1));
-                    line1 = lines1.next();
-                    line2 = lines2.next();
-                },
-                Less => {
-                    // val1 < val2: ASCII文字列順序が小さい方を先に出力して次の行へ
-                    print(Column::Col1(&val1));
-                    line1 = lines1.next();
-                },
-                Greater => {
-                    // val1 > val2
-                    print(Column::Col2(&val2));
-                    line2 = lines2.next();
+    // 各ファイルの現在の行データ: 全て読み終えたファイルはNone
+    let mut lines: Vec<Option<String>> = readers.iter_mut().map(Iterator::next).collect();
+    let mut warned = vec![false; lines.len()]; // 未整列の警告を出力済みのファイル
+
+    // 全ファイルの現在の行のうちキーが最小の行を出力し、その行を含むファイルのみを次の行へ進める
+    // 比較はキーで行い、出力は行全体のまま行う
+    loop {
+        let keys: Vec<Option<&str>> = lines
+            .iter()
+            .map(|line| {
+                line.as_deref()
+                    .map(|val| extract_key(val, config.field, &config.field_delimiter))
+            })
+            .collect();
+        let Some(min) = keys.iter().flatten().min().copied() else {
+            break; // 全てのファイルを読み終えた
+        };
+        let members = keys
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| **key == Some(min))
+            .fold(0u8, |members, (i, _)| members | 1 << i);
+        let first = members.trailing_zeros() as usize; // 出力は最初に含むファイルの行を使う
+        print(column_of(members, lines.len()), lines[first].as_deref().unwrap());
+
+        for (i, reader) in readers.iter_mut().enumerate() {
+            if members & (1 << i) == 0 {
+                continue;
+            }
+            let next = reader.next();
+            // 直前の行よりキーが小さければ未整列
+            if let (Some(prev), Some(line)) = (&lines[i], &next) {
+                let unsorted = config.order != OrderCheck::Ignore
+                    && extract_key(line, config.field, &config.field_delimiter)
+                        < extract_key(prev, config.field, &config.field_delimiter);
+                if unsorted {
+                    let message = format!("file {} is not in sorted order", i + 1);
+                    if config.order == OrderCheck::Fail {
+                        return Err(From::from(message));
+                    }
+                    if !warned[i] {
+                        eprintln!("commr: {}", message);
+                        warned[i] = true;
+                    }
                 }
-            },
-            (Some(val1), None) => {
-                print(Column::Col1(&val1));
-                line1 = lines1.next();
-            },
-            (None, Some(val2)) => {
-                print(Column::Col2(&val2));
-                line2 = lines2.next();
-            },
-            _ => (),
+            }
+            lines[i] = next;
         }
     }
 
@@ -8,6 +8,10 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FILE1: &str = "tests/inputs/file1.txt";
 const FILE2: &str = "tests/inputs/file2.txt";
 const BLANK: &str = "tests/inputs/blank.txt";
+const FIELDS1: &str = "tests/inputs/fields1.csv";
+const FIELDS2: &str = "tests/inputs/fields2.csv";
+const CITIES1: &str = "tests/inputs/cities1.txt";
+const CITIES2: &str = "tests/inputs/cities2.txt";
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -42,7 +46,7 @@ fn dies_bad_file1() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&[&bad, FILE1])
+        .args([&bad, FILE1])
         .assert()
         .failure()
         .stderr(predicate::str::is_match(expected)?);
@@ -55,7 +59,7 @@ fn dies_bad_file2() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&[FILE1, &bad])
+        .args([FILE1, &bad])
         .assert()
         .failure()
         .stderr(predicate::str::is_match(expected)?);
@@ -67,7 +71,7 @@ fn dies_bad_file2() -> TestResult {
 fn dies_both_stdin() -> TestResult {
     let expected = "Both input files cannot be STDIN (\"-\")";
     Command::cargo_bin(PRG)?
-        .args(&["-", "-"])
+        .args(["-", "-"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -340,6 +344,184 @@ fn blank_file1() -> TestResult {
     run(&[BLANK, FILE1], "tests/expected/blank_file1.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_bad_field() -> TestResult {
+    for bad in ["0", "x"] {
+        Command::cargo_bin(PRG)?
+            .args(["-f", bad, FIELDS1, FIELDS2])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!("Invalid --field \"{}\"", bad)));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fields1_fields2_f2() -> TestResult {
+    run(
+        &["-f", "2", "-t", ",", FIELDS1, FIELDS2],
+        "tests/expected/fields1_fields2.f2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fields1_fields2_f2_3() -> TestResult {
+    run(
+        &["--field", "2", "--delimiter", ",", "-12", FIELDS1, FIELDS2],
+        "tests/expected/fields1_fields2.f2.3.out",
+    )
+}
+
+// --------------------------------------------------
+const FILE4: &str = "tests/inputs/file4.txt";
+
+#[test]
+fn file1_file2_file4() -> TestResult {
+    run(&[FILE1, FILE2, FILE4], "tests/expected/file1_file2_file4.out")
+}
+
+#[test]
+fn file1_file2_file4_2457() -> TestResult {
+    run(
+        &["-2", "-4", "-5", "-7", FILE1, FILE2, FILE4],
+        "tests/expected/file1_file2_file4.2457.out",
+    )
+}
+
+#[test]
+fn file1_file2_file4_7_i() -> TestResult {
+    // 3つのファイル全てに含まれる行のみ: 大文字小文字を区別しなければbも共通
+    Command::cargo_bin(PRG)?
+        .args(["-123456", "-i", FILE1, FILE2, FILE4])
+        .assert()
+        .success()
+        .stdout("c\n");
+    Command::cargo_bin(PRG)?
+        .args(["-123456", FILE1, "tests/inputs/file3.txt", FILE1])
+        .assert()
+        .success()
+        .stdout("a\nb\nc\nd\n");
+    Ok(())
+}
+
+#[test]
+fn dies_col4_without_file3() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-4", FILE1, FILE2])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn dies_two_stdin_three_way() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([FILE1, "-", "-"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Only one input file can be STDIN"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_warns() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/cities1_cities2.out")?;
+    Command::cargo_bin(PRG)?
+        .args([CITIES1, CITIES2])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr(
+            "commr: file 1 is not in sorted order\n\
+            commr: file 2 is not in sorted order\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_nocheck_order() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/cities1_cities2.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--nocheck-order", CITIES1, CITIES2])
+        .assert()
+        .success()
+        .stdout(expected)
+        .stderr("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unsorted_check_order() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", CITIES1, CITIES2])
+        .assert()
+        .failure()
+        .stdout("Jackson\n")
+        .stderr("file 1 is not in sorted order\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sorted_check_order() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", FILE1, FILE2])
+        .assert()
+        .success()
+        .stdout(fs::read_to_string("tests/expected/file1_file2.out")?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_presort() -> TestResult {
+    run(
+        &["--presort", "--check-order", CITIES1, CITIES2],
+        "tests/expected/cities1_cities2.presort.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_header() -> TestResult {
+    run(&["--header", FILE1, FILE2], "tests/expected/file1_file2.header.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_1_header_color() -> TestResult {
+    run(
+        &["--header", "--color", "always", "-1", FILE1, FILE2],
+        "tests/expected/file1_file2.1.header.color.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn file1_file2_file4_2457_header() -> TestResult {
+    run(
+        &["--header", "-2457", FILE1, FILE2, FILE4],
+        "tests/expected/file1_file2_file4.2457.header.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_color() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color", "yes", FILE1, FILE2])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --color \"yes\""));
+    Ok(())
+}
+
 //// --------------------------------------------------
 //#[test]
 //fn file1_blanks() -> TestResult {
@@ -0,0 +1,73 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use cutr::Extractor;
+use std::{io::Write, ops::Range};
+
+// 書き換え前の実装: 1行ごとにVecを確保してから文字列に変換する
+fn alloc_extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
+    let chars: Vec<_> = line.chars().collect();
+    char_pos.iter()
+        .cloned()
+        .flat_map(|range| range.filter_map(|i| chars.get(i)))
+        .collect()
+}
+
+fn alloc_extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
+    let bytes = line.as_bytes();
+    let selected: Vec<_> = byte_pos.iter()
+        .cloned()
+        .flat_map(|range| range.filter_map(|i| bytes.get(i)).copied())
+        .collect();
+    String::from_utf8_lossy(&selected).into_owned()
+}
+
+// 日本語を含む1万行の入力
+fn gen_lines() -> Vec<String> {
+    (0..10_000)
+        .map(|i| format!("{}\tRust入門\t{}\tThe quick brown fox jumps over the lazy dog", i, i * 7))
+        .collect()
+}
+
+fn bench_extract(c: &mut Criterion) {
+    let lines = gen_lines();
+    let pos = [0..3, 5..20, 30..40];
+    let mut out = Vec::with_capacity(1 << 20);
+
+    let mut group = c.benchmark_group("chars");
+    group.bench_function("alloc", |b| b.iter(|| {
+        out.clear();
+        for line in &lines {
+            writeln!(out, "{}", alloc_extract_chars(black_box(line), &pos)).unwrap();
+        }
+    }));
+    group.bench_function("extractor", |b| {
+        let mut extractor = Extractor::default();
+        b.iter(|| {
+            out.clear();
+            for line in &lines {
+                extractor.write_chars(black_box(line), &pos, &mut out).unwrap();
+            }
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("bytes");
+    group.bench_function("alloc", |b| b.iter(|| {
+        out.clear();
+        for line in &lines {
+            writeln!(out, "{}", alloc_extract_bytes(black_box(line), &pos)).unwrap();
+        }
+    }));
+    group.bench_function("extractor", |b| {
+        let mut extractor = Extractor::default();
+        b.iter(|| {
+            out.clear();
+            for line in &lines {
+                extractor.write_bytes(black_box(line), &pos, &mut out).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_extract);
+criterion_main!(benches);
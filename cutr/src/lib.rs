@@ -1,11 +1,15 @@
-use std::{error::Error, ops::Range, num::NonZeroUsize, io::{BufRead, BufReader, stdin, stdout}, fs::File};
+use std::{error::Error, ops::Range, num::NonZeroUsize, io::{self, BufRead, BufReader, BufWriter, Write, stdin, stdout}, fs::File};
 
 use clap::{App, Arg};
 use csv::{StringRecord, ReaderBuilder, WriterBuilder};
 use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::Extract::*;
 
+mod stats;
+use stats::Accumulator;
+
 type MyResult<T> = Result<T, Box<dyn Error>>;
 type PositionList = Vec<Range<usize>>; // 自然数で構成される範囲値のベクトル
 
@@ -13,7 +17,10 @@ type PositionList = Vec<Range<usize>>; // 自然数で構成される範囲値
 pub enum Extract {
     Fields(PositionList),
     Bytes(PositionList),
+    WholeBytes(PositionList), // 文字の途中で切れないよう範囲をUTF-8の境界に合わせたバイト単位の範囲指定
     Chars(PositionList),
+    Graphemes(PositionList), // 書記素クラスタ単位の範囲指定: 結合文字や絵文字を分割しない
+    Columns { pos: PositionList, trim: bool }, // 固定幅レコードの文字単位の桁範囲: 範囲ごとに1つのフィールドとして区切り文字で区切る
 }
 
 #[derive(Debug)]
@@ -21,6 +28,8 @@ pub struct Config {
     files: Vec<String>,
     delimiter: u8, // 区切り文字を単一バイトの値(0~255)として保持
     extract: Extract,
+    line_buffered: bool, // レコードごとに出力をフラッシュする: パイプラインの途中で使う場合向け
+    stats: bool, // 選択したフィールドを出力せず、カラムごとの集計を出力する
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -49,7 +58,7 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Selected fields")
                 .short("f")
                 .long("fields")
-                .conflicts_with_all(&["chars", "bytes"]),
+                .conflicts_with_all(&["chars", "bytes", "cols"]),
         )
         .arg(
             Arg::with_name("bytes") // バイト数で範囲指定
@@ -57,7 +66,7 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Selected bytes")
                 .short("b")
                 .long("bytes")
-                .conflicts_with_all(&["chars", "fields"]),
+                .conflicts_with_all(&["chars", "fields", "cols"]),
         )
         .arg(
             Arg::with_name("chars") // 文字数で範囲指定
@@ -65,7 +74,48 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Selected chars")
                 .short("c")
                 .long("chars")
-                .conflicts_with_all(&["fields", "bytes"]),
+                .conflicts_with_all(&["fields", "bytes", "cols"]),
+        )
+        .arg(
+            Arg::with_name("cols") // 固定幅レコードの桁位置で範囲指定
+                .value_name("COLS")
+                .help("Selected fixed-width columns, each printed as a field separated by DELIMITER")
+                .long("cols"),
+        )
+        .arg(
+            Arg::with_name("trim") // --colsで切り出したフィールドの前後の空白を除く
+                .help("Trim padding whitespace from --cols fields")
+                .long("trim")
+                .takes_value(false)
+                .requires("cols"),
+        )
+        .arg(
+            Arg::with_name("graphemes") // --charsの範囲を書記素クラスタ単位として扱う
+                .help("Treat --chars positions as grapheme clusters")
+                .long("graphemes")
+                .takes_value(false)
+                .requires("chars"),
+        )
+        .arg(
+            Arg::with_name("no_split_multibyte") // --bytesの範囲でマルチバイト文字を分割しない
+                .help("Do not split multibyte characters with --bytes")
+                .short("n")
+                .long("no-split-multibyte")
+                .takes_value(false)
+                .requires("bytes"),
+        )
+        .arg(
+            Arg::with_name("line_buffered")
+                .help("Flush output after every line")
+                .long("line-buffered")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("stats") // 全ファイルの選択したフィールドをカラムごとに集計する
+                .help("Print per-column statistics of the selected fields")
+                .long("stats")
+                .takes_value(false)
+                .requires("fields"),
         )
         .get_matches();
 
@@ -90,18 +140,31 @@ pub fn get_args() -> MyResult<Config> {
     let chars = matches.value_of("chars")
         .map(parse_pos)
         .transpose()?;
+    let cols = matches.value_of("cols")
+        .map(parse_pos)
+        .transpose()?;
 
     // 範囲指定方法で分岐
     let extract = if let Some(field_pos) = fields {
         Fields(field_pos)
     } else if let Some(byte_pos) = bytes {
-        Bytes(byte_pos)
+        if matches.is_present("no_split_multibyte") {
+            WholeBytes(byte_pos)
+        } else {
+            Bytes(byte_pos)
+        }
     } else if let Some(char_pos) = chars {
-        Chars(char_pos)
+        if matches.is_present("graphemes") {
+            Graphemes(char_pos)
+        } else {
+            Chars(char_pos)
+        }
+    } else if let Some(col_pos) = cols {
+        Columns { pos: col_pos, trim: matches.is_present("trim") }
     } else {
         // 範囲指定方法がフラグで渡されなかった場合: エラーを返す
         return Err(From::from(
-            "Must have --fields, --bytes, or --chars"
+            "Must have --fields, --bytes, --chars, or --cols"
         ));
     };
 
@@ -111,26 +174,27 @@ pub fn get_args() -> MyResult<Config> {
             files: matches.values_of_lossy("files").unwrap(),
             delimiter: *delim_bytes.first().unwrap(), // バイト配列の最初の参照値をデリファレンス: 所有権を取得するため
             extract,
+            line_buffered: matches.is_present("line_buffered"),
+            stats: matches.is_present("stats"),
         }
     )
 }
 
 fn parse_index(input: &str) -> Result<usize, String> { // 0から始まるindex値またはエラーメッセージを返す
     let value_error = || format!("illegal list value: \"{}\"", input);
-    input.starts_with("+")
-        .then(|| Err(value_error())) // Optionを返す: "+"で始まる場合はSomeにエラーメッセージを入れる
-        .unwrap_or_else(|| { // Noneの場合: エラーではない時
-            input.parse::<NonZeroUsize>() // str -> 非ゼロの値
+    if input.starts_with('+') { // "+"で始まる場合はエラーメッセージを返す
+        Err(value_error())
+    } else { // エラーではない時
+        input.parse::<NonZeroUsize>() // str -> 非ゼロの値
             .map(|n| usize::from(n) - 1) // 非ゼロの値 -> usizeに変換後、0から始まるindex値に修正
             .map_err(|_| value_error()) // parse時にエラーとなった場合
-        })
+    }
 }
 
 fn parse_pos(range: &str) -> MyResult<PositionList> { // カンマ区切りまたはダッシュ(-)範囲の数値を範囲値ベクトルとして返す
     // 正規表現を r"" で生の文字列として表現: \ エスケープ文字をRustに解釈させずにそのまま利用
     let range_re = Regex::new(r"^(\d+)-(\d+)$").unwrap(); // () 括弧で囲まれた範囲をキャプチャする
     range.split(',') // 区切り文字で分割
-        .into_iter()
         .map(|val| {
             // 単一の数値の場合: 0始まりのindex範囲に変換: 先頭の数値は範囲に含まれるが、後ろの数値は範囲に含まれない
             parse_index(val).map(|n| n..n+1)
@@ -171,72 +235,271 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
 }
 
 pub fn run(config: Config) -> MyResult<()> {
+    // 出力先のパイプが閉じられた場合(head等が先に終了した場合)は、それ以上出力する必要がないため正常終了とする
+    match cut(&config) {
+        Err(e) if is_broken_pipe(e.as_ref()) => Ok(()),
+        res => res,
+    }
+}
+
+// SIGPIPEは無視されているため、閉じられたパイプへの書き込みはBrokenPipeエラーになる
+fn is_broken_pipe(err: &(dyn Error + 'static)) -> bool {
+    if let Some(err) = err.downcast_ref::<io::Error>() {
+        return err.kind() == io::ErrorKind::BrokenPipe;
+    }
+    // csvクレート経由の書き込みエラーはcsv::Errorに包まれている
+    match err.downcast_ref::<csv::Error>().map(csv::Error::kind) {
+        Some(csv::ErrorKind::Io(err)) => err.kind() == io::ErrorKind::BrokenPipe,
+        _ => false,
+    }
+}
+
+fn cut(config: &Config) -> MyResult<()> {
+    // 行ごとのフラッシュを避けるため、ロックした標準出力をまとめてバッファリングする: --line-buffered指定時は行ごとにフラッシュする
+    let mut out = BufWriter::new(stdout().lock());
+    let line_buffered = config.line_buffered;
+    let mut extractor = Extractor::default();
+    let mut accumulator = config.stats.then(Accumulator::default);
     for filename in &config.files {
         match open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(reader) => match &config.extract {
+            Ok(mut reader) => match &config.extract {
                 Fields(field_pos) => {
                     // readerからカラム区切りレコードとして読み込む
                     let mut reader = ReaderBuilder::new()
                         .delimiter(config.delimiter)
                         .has_headers(false)
                         .from_reader(reader);
+                    let mut record = StringRecord::new();
+                    // --stats: 出力は全ファイルの集計後にまとめて行う
+                    if let Some(accumulator) = &mut accumulator {
+                        while reader.read_record(&mut record)? {
+                            accumulator.add_record(&record, field_pos);
+                        }
+                        continue;
+                    }
                     // 標準出力に書き込む
                     let mut wtr = WriterBuilder::new()
                         .delimiter(config.delimiter)
-                        .from_writer(stdout());
-                    for record in reader.records() {
-                        let record = record?;
+                        .from_writer(&mut out);
+                    while reader.read_record(&mut record)? { // レコードのバッファを使い回す
                         wtr.write_record(extract_fields(&record, field_pos))?;
+                        if line_buffered {
+                            wtr.flush()?; // csvのバッファと標準出力のバッファの両方をフラッシュする
+                        }
                     }
+                    wtr.flush()?; // drop時のフラッシュではエラーを検出できない
                 }
-                Bytes(byte_pos) => {
-                    for line in reader.lines() {
-                        println!("{}", extract_bytes(&line?, byte_pos))
-                    }
-                }
-                Chars(char_pos) => {
-                    for line in reader.lines() {
-                        println!("{}", extract_chars(&line?, char_pos))
-                    }
-                }
+                Bytes(byte_pos) => for_each_line(&mut reader, &mut out, line_buffered, |line, out| {
+                    extractor.write_bytes(line, byte_pos, out)
+                })?,
+                WholeBytes(byte_pos) => for_each_line(&mut reader, &mut out, line_buffered, |line, out| {
+                    extractor.write_whole_bytes(line, byte_pos, out)
+                })?,
+                Chars(char_pos) => for_each_line(&mut reader, &mut out, line_buffered, |line, out| {
+                    extractor.write_chars(line, char_pos, out)
+                })?,
+                Graphemes(char_pos) => for_each_line(&mut reader, &mut out, line_buffered, |line, out| {
+                    extractor.write_graphemes(line, char_pos, out)
+                })?,
+                Columns { pos, trim } => for_each_line(&mut reader, &mut out, line_buffered, |line, out| {
+                    extractor.write_columns(line, pos, config.delimiter, *trim, out)
+                })?,
             }
         }
     }
+    if let Some(accumulator) = &accumulator {
+        accumulator.write_table(&mut out)?;
+    }
+    out.flush()?;
     Ok(())
 }
 
-fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String { // &PositionListはwarningとなる: 不変サイズのリストを受け取れなくなるため
-    let chars: Vec<_> = line.chars().collect(); // 文字列をcharに分割後、ベクトルとして集約
-    // let mut selected: Vec<char> = vec![];
-
-    // for range in char_pos.iter().cloned() { // 範囲値リストをクローンしてイテレーション
-    //     // for i in range { // 範囲でイテレーション
-    //     //     if let Some(val) = chars.get(i) { // 指定位置にcharが存在すれば追加
-    //     //         selected.push(*val)
-    //     //     }
-    //     // }
-    //     selected.extend(range.filter_map(|i| chars.get(i))); // 値がSomeとして存在するもののみをフィルタリングして追加
-    // }
-    // selected.iter().collect() // charベクトルから文字列に変換
-    char_pos.iter()
-        .cloned()
-        // .map(|range| range.filter_map(|i| chars.get(i)))
-        // .flatten() // 多層イテレータを平坦化: 単一イテレータに変換する
-        .flat_map(|range| range.filter_map(|i| chars.get(i)))
-        .collect()
+// 行のバッファを使い回して1行ずつ処理する: lines()のように行ごとにStringを確保しない
+fn for_each_line<W: Write>(
+    reader: &mut impl BufRead,
+    out: &mut W,
+    line_buffered: bool,
+    mut f: impl FnMut(&str, &mut W) -> io::Result<()>,
+) -> MyResult<()> {
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        // lines()と同様に行末の改行(\n または \r\n)を取り除く
+        let trimmed = line.strip_suffix('\n').unwrap_or(&line);
+        let trimmed = trimmed.strip_suffix('\r').unwrap_or(trimmed);
+        f(trimmed, out)?;
+        if line_buffered {
+            out.flush()?;
+        }
+        line.clear();
+    }
+    Ok(())
 }
 
-fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
-    let bytes = line.as_bytes();
-    // 取得対象のバイト配列を変数に集約
-    let selected: Vec<_> = byte_pos.iter()
-        .cloned()
-        // 各バイトの参照値を複製して実体値として取得: String変換時の引数型に合わせるため
-        .flat_map(|range| range.filter_map(|i| bytes.get(i)).copied())
-        .collect();
-    // バイト配列から文字列に変換し、クローンして所有権を渡す
-    String::from_utf8_lossy(&selected).into_owned()
+/// Reusable buffers for extracting bytes, characters or grapheme clusters
+/// from many lines without allocating for every line
+#[derive(Debug, Default)]
+pub struct Extractor {
+    offsets: Vec<usize>, // 各文字(書記素)の開始バイト位置 + 行末
+    selected: Vec<u8>,   // --bytes で選択したバイト列
+}
+
+impl Extractor {
+    /// Write the characters at the given 0-based positions followed by a newline
+    pub fn write_chars(
+        &mut self,
+        line: &str,
+        char_pos: &[Range<usize>],
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        self.offsets.clear();
+        self.offsets.extend(line.char_indices().map(|(i, _)| i));
+        self.write_slices(line, char_pos, out)
+    }
+
+    /// Write the grapheme clusters at the given 0-based positions followed by a newline
+    pub fn write_graphemes(
+        &mut self,
+        line: &str,
+        grapheme_pos: &[Range<usize>],
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        self.offsets.clear();
+        self.offsets.extend(line.grapheme_indices(true).map(|(i, _)| i));
+        self.write_slices(line, grapheme_pos, out)
+    }
+
+    // 開始位置の一覧から範囲ごとの部分文字列を求め、連続した範囲は1回で書き込む
+    fn write_slices(
+        &mut self,
+        line: &str,
+        pos: &[Range<usize>],
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let count = self.offsets.len();
+        self.offsets.push(line.len()); // 最後の文字の終端
+        for range in pos {
+            let start = self.offsets[range.start.min(count)];
+            let end = self.offsets[range.end.min(count)];
+            out.write_all(&line.as_bytes()[start..end])?;
+        }
+        out.write_all(b"\n")
+    }
+
+    /// Write the characters at each 0-based column range of a fixed-width
+    /// record as a separate field, separated by `delimiter` and followed by a newline
+    ///
+    /// Every range yields a field, so a short line gives empty fields instead
+    /// of shifting the ones after it. With `trim`, the padding whitespace
+    /// around each field is removed.
+    pub fn write_columns(
+        &mut self,
+        line: &str,
+        col_pos: &[Range<usize>],
+        delimiter: u8,
+        trim: bool,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        self.offsets.clear();
+        self.offsets.extend(line.char_indices().map(|(i, _)| i));
+        let count = self.offsets.len();
+        self.offsets.push(line.len());
+        for (i, range) in col_pos.iter().enumerate() {
+            if i > 0 {
+                out.write_all(&[delimiter])?;
+            }
+            let field = &line[self.offsets[range.start.min(count)]..self.offsets[range.end.min(count)]];
+            out.write_all(if trim { field.trim() } else { field }.as_bytes())?;
+        }
+        out.write_all(b"\n")
+    }
+
+    /// Write the bytes at the given 0-based positions followed by a newline,
+    /// replacing incomplete UTF-8 sequences with U+FFFD
+    pub fn write_bytes(
+        &mut self,
+        line: &str,
+        byte_pos: &[Range<usize>],
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        self.write_selected(line, byte_pos.iter().cloned(), out)
+    }
+
+    /// Write the bytes at the given 0-based positions followed by a newline,
+    /// adjusting each range so that no multibyte character is split
+    ///
+    /// As with POSIX `cut -n`, a character is written when the range starts
+    /// inside it and is dropped when the range ends inside it.
+    pub fn write_whole_bytes(
+        &mut self,
+        line: &str,
+        byte_pos: &[Range<usize>],
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        self.write_selected(line, byte_pos.iter().map(|range| snap_to_chars(line, range)), out)
+    }
+
+    fn write_selected(
+        &mut self,
+        line: &str,
+        byte_pos: impl Iterator<Item = Range<usize>>,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let bytes = line.as_bytes();
+        self.selected.clear();
+        for range in byte_pos {
+            let start = range.start.min(bytes.len());
+            let end = range.end.min(bytes.len());
+            self.selected.extend_from_slice(&bytes[start..end]);
+        }
+        // 選択結果が有効なUTF-8であれば変換は発生しない(Cow::Borrowed)
+        out.write_all(String::from_utf8_lossy(&self.selected).as_bytes())?;
+        out.write_all(b"\n")
+    }
+}
+
+// バイト範囲の両端を文字の境界まで戻す: 開始位置は文字の先頭へ広げ、終了位置は文字の手前まで狭める
+fn snap_to_chars(line: &str, range: &Range<usize>) -> Range<usize> {
+    line.floor_char_boundary(range.start)..line.floor_char_boundary(range.end)
+}
+
+// 1行分の抽出結果を文字列として返す: 改行を除いてStringに変換
+fn extract_with(write: impl FnOnce(&mut Extractor, &mut Vec<u8>) -> io::Result<()>) -> String {
+    let mut out = vec![];
+    write(&mut Extractor::default(), &mut out).unwrap(); // Vecへの書き込みは失敗しない
+    out.pop(); // 末尾の改行
+    String::from_utf8(out).unwrap() // 書き込むのは有効なUTF-8のみ
+}
+
+/// Extract the characters (Unicode scalar values) at the given
+/// 0-based positions from a line, in the order of the positions
+pub fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String { // &PositionListはwarningとなる: 不変サイズのリストを受け取れなくなるため
+    extract_with(|extractor, out| extractor.write_chars(line, char_pos, out))
+}
+
+/// Extract the grapheme clusters at the given 0-based positions from a line,
+/// so that combining characters and emoji sequences are never split
+pub fn extract_graphemes(line: &str, grapheme_pos: &[Range<usize>]) -> String {
+    extract_with(|extractor, out| extractor.write_graphemes(line, grapheme_pos, out))
+}
+
+/// Extract the given 0-based column ranges of a fixed-width record as
+/// fields joined by `delimiter`, optionally trimming their padding
+pub fn extract_columns(line: &str, col_pos: &[Range<usize>], delimiter: u8, trim: bool) -> String {
+    extract_with(|extractor, out| extractor.write_columns(line, col_pos, delimiter, trim, out))
+}
+
+/// Extract the bytes at the given 0-based positions from a line,
+/// replacing incomplete UTF-8 sequences with U+FFFD
+pub fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
+    extract_with(|extractor, out| extractor.write_bytes(line, byte_pos, out))
+}
+
+/// Extract the bytes at the given 0-based positions from a line,
+/// adjusting the ranges to character boundaries instead of splitting
+/// multibyte characters
+pub fn extract_whole_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
+    extract_with(|extractor, out| extractor.write_whole_bytes(line, byte_pos, out))
 }
 
 // ライフタイム修飾子を付与: recordと同じライフタイムとして返り値の&strを定義
@@ -250,12 +513,41 @@ fn extract_fields<'a>(record: &'a StringRecord, field_pos: &[Range<usize>]) -> V
 
 // "cargo test unit" で実行されるUTを定義: モジュール名 "unit_tests" の接頭辞を認識して実行対象が絞り込まれるため
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)] // 単一の範囲も範囲リストとして渡すため
 mod unit_tests {
     use super::parse_pos;
     use super::extract_bytes;
+    use super::extract_whole_bytes;
     use super::extract_chars;
+    use super::extract_graphemes;
+    use super::extract_columns;
     use super::extract_fields;
+    use super::is_broken_pipe;
     use csv::StringRecord;
+    use std::io::{self, ErrorKind, Write};
+
+    #[test]
+    fn test_is_broken_pipe() {
+        let broken = || io::Error::from(ErrorKind::BrokenPipe);
+        assert!(is_broken_pipe(&broken()));
+        assert!(!is_broken_pipe(&io::Error::from(ErrorKind::NotFound)));
+
+        // csvの書き込みエラーに包まれている場合も判定できる
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::from(ErrorKind::BrokenPipe))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut wtr = csv::Writer::from_writer(Closed);
+        wtr.write_record(["a"]).unwrap(); // csvのバッファに書き込まれるだけ
+        let err = wtr.flush().map_err(csv::Error::from).unwrap_err();
+        assert!(is_broken_pipe(&err));
+        assert!(!is_broken_pipe(&csv::Error::from(io::Error::from(ErrorKind::Other))));
+    }
 
     #[test]
     fn test_parse_pos() {
@@ -397,6 +689,43 @@ mod unit_tests {
         );
     }
 
+    #[test]
+    fn test_extract_graphemes() {
+        assert_eq!(extract_graphemes("", &[0..1]), "".to_string());
+        // "e" followed by a combining acute accent is a single grapheme
+        let text = "e\u{301}bc";
+        assert_eq!(extract_chars(text, &[0..1]), "e".to_string());
+        assert_eq!(extract_graphemes(text, &[0..1]), "e\u{301}".to_string());
+        assert_eq!(extract_graphemes(text, &[2..3, 0..1]), "ce\u{301}".to_string());
+        // A family emoji joined with ZWJ is also kept together
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}x";
+        assert_eq!(
+            extract_graphemes(family, &[0..1]),
+            "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}".to_string()
+        );
+        assert_eq!(extract_graphemes(family, &[1..2, 5..6]), "x".to_string());
+    }
+
+    #[test]
+    fn test_extract_columns() {
+        let record = "0001  Alice Smith   Ōsaka   ";
+        assert_eq!(
+            extract_columns(record, &[0..4, 6..20, 20..28], b'\t', false),
+            "0001\tAlice Smith   \tŌsaka   ".to_string()
+        );
+        // Padding is removed from both ends of each field with trim
+        assert_eq!(
+            extract_columns(record, &[20..28, 0..4, 4..11], b',', true),
+            "Ōsaka,0001,Alice".to_string()
+        );
+        // Columns past the end of a short line are empty fields
+        assert_eq!(
+            extract_columns("0003  Chloé", &[6..11, 11..20, 0..4], b'|', true),
+            "Chloé||0003".to_string()
+        );
+        assert_eq!(extract_columns("", &[0..2, 2..4], b'\t', false), "\t".to_string());
+    }
+
     #[test]
     fn test_extract_bytes() {
         assert_eq!(extract_bytes("ábc", &[0..1]), "�".to_string());
@@ -407,6 +736,20 @@ mod unit_tests {
         assert_eq!(extract_bytes("ábc", &[0..2, 5..6]), "á".to_string());
     }
 
+    #[test]
+    fn test_extract_whole_bytes() {
+        // the start of a range extends back to the start of its character
+        assert_eq!(extract_whole_bytes("ábc", &[1..3]), "áb".to_string());
+        assert_eq!(extract_whole_bytes("ábc", &[1..2]), "á".to_string());
+        // the end of a range drops a character it would split
+        assert_eq!(extract_whole_bytes("ábc", &[0..1]), "".to_string());
+        assert_eq!(extract_whole_bytes("ábc", &[0..2]), "á".to_string());
+        assert_eq!(extract_whole_bytes("aé", &[0..2]), "a".to_string());
+        assert_eq!(extract_whole_bytes("ábc", &[3..4, 1..2]), "cá".to_string());
+        assert_eq!(extract_whole_bytes("ábc", &[0..usize::MAX]), "ábc".to_string());
+        assert_eq!(extract_whole_bytes("ábc", &[5..6]), "".to_string());
+    }
+
     #[test]
     fn test_extract_fields() {
         let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
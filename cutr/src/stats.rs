@@ -0,0 +1,178 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+    ops::Range,
+};
+
+use csv::StringRecord;
+
+// 1つのカラムの集計値: 空の値はcountとdistinctにのみ含め、min/maxとmeanの対象外とする
+#[derive(Debug, Default)]
+struct ColumnStats {
+    count: usize,
+    distinct: HashSet<String>,
+    text_min: Option<String>,
+    text_max: Option<String>,
+    non_empty: usize,
+    numeric: usize, // 数値として解釈できた値の数
+    sum: f64,
+    num_min: Option<(f64, String)>, // 比較用の数値と出力用の元の文字列
+    num_max: Option<(f64, String)>,
+}
+
+impl ColumnStats {
+    fn add(&mut self, value: &str) {
+        self.count += 1;
+        if !self.distinct.contains(value) {
+            self.distinct.insert(value.to_string());
+        }
+        if value.is_empty() {
+            return;
+        }
+        self.non_empty += 1;
+        if self.text_min.as_deref().is_none_or(|min| value < min) {
+            self.text_min = Some(value.to_string());
+        }
+        if self.text_max.as_deref().is_none_or(|max| value > max) {
+            self.text_max = Some(value.to_string());
+        }
+        // "inf"や"NaN"もparseできるが、集計を壊すため数値として扱わない
+        if let Some(num) = value.trim().parse::<f64>().ok().filter(|num| num.is_finite()) {
+            self.numeric += 1;
+            self.sum += num;
+            if self.num_min.as_ref().is_none_or(|(min, _)| num < *min) {
+                self.num_min = Some((num, value.to_string()));
+            }
+            if self.num_max.as_ref().is_none_or(|(max, _)| num > *max) {
+                self.num_max = Some((num, value.to_string()));
+            }
+        }
+    }
+
+    // 全ての値が数値の場合は数値として比べ、それ以外は文字列として比べる
+    fn min_max(&self) -> (Option<&str>, Option<&str>) {
+        if self.numeric > 0 && self.numeric == self.non_empty {
+            (
+                self.num_min.as_ref().map(|(_, text)| text.as_str()),
+                self.num_max.as_ref().map(|(_, text)| text.as_str()),
+            )
+        } else {
+            (self.text_min.as_deref(), self.text_max.as_deref())
+        }
+    }
+
+    fn mean(&self) -> Option<f64> {
+        (self.numeric > 0).then(|| self.sum / self.numeric as f64)
+    }
+}
+
+// 選択したフィールドの値をカラム番号ごとに集計する
+#[derive(Debug, Default)]
+pub struct Accumulator {
+    order: Vec<usize>, // 出力順: 最初に値が現れた順
+    columns: HashMap<usize, ColumnStats>,
+    selected: Vec<usize>, // レコードごとの選択カラムのバッファ
+}
+
+impl Accumulator {
+    pub fn add_record(&mut self, record: &StringRecord, field_pos: &[Range<usize>]) {
+        // 同じカラムが重複して選択されていても1レコードにつき1回だけ数える
+        self.selected.clear();
+        for i in field_pos.iter().flat_map(|range| range.start..range.end.min(record.len())) {
+            if !self.selected.contains(&i) {
+                self.selected.push(i);
+            }
+        }
+        for &i in &self.selected {
+            let stats = self.columns.entry(i).or_insert_with(|| {
+                self.order.push(i);
+                ColumnStats::default()
+            });
+            stats.add(&record[i]);
+        }
+    }
+
+    // カラムごとに1行の表を、各列の幅を揃えて出力する: 該当する値がない項目は"-"
+    pub fn write_table(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut rows = vec![["column", "count", "distinct", "min", "max", "mean"].map(String::from)];
+        for i in &self.order {
+            let stats = &self.columns[i];
+            let (min, max) = stats.min_max();
+            rows.push([
+                (i + 1).to_string(),
+                stats.count.to_string(),
+                stats.distinct.len().to_string(),
+                min.unwrap_or("-").to_string(),
+                max.unwrap_or("-").to_string(),
+                stats.mean().map_or("-".to_string(), |mean| format!("{:.2}", mean)),
+            ]);
+        }
+        let mut widths = [0; 6];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for row in &rows {
+            let mut line = String::new();
+            for (j, (cell, width)) in row.iter().zip(widths).enumerate() {
+                if j > 0 {
+                    line.push_str("  ");
+                }
+                line.push_str(cell);
+                line.extend(std::iter::repeat_n(' ', width - cell.chars().count()));
+            }
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod unit_tests {
+    use super::{Accumulator, ColumnStats};
+    use csv::StringRecord;
+
+    #[test]
+    fn test_column_stats() {
+        let mut stats = ColumnStats::default();
+        for value in ["10", "9", "", "10", "1.5"] {
+            stats.add(value);
+        }
+        assert_eq!(stats.count, 5);
+        assert_eq!(stats.distinct.len(), 4);
+        // Numeric columns are compared as numbers, not as text
+        assert_eq!(stats.min_max(), (Some("1.5"), Some("10")));
+        assert_eq!(stats.mean(), Some(7.625));
+
+        // A single non-numeric value makes min/max fall back to text
+        stats.add("abc");
+        assert_eq!(stats.min_max(), (Some("1.5"), Some("abc")));
+        assert_eq!(stats.mean(), Some(7.625));
+
+        let mut stats = ColumnStats::default();
+        stats.add("NaN");
+        stats.add("");
+        assert_eq!(stats.min_max(), (Some("NaN"), Some("NaN")));
+        assert_eq!(stats.mean(), None);
+    }
+
+    #[test]
+    fn test_accumulator() {
+        let mut acc = Accumulator::default();
+        // Columns are reported in selection order, each counted once per record
+        let field_pos = [2..3, 0..1, 0..2];
+        acc.add_record(&StringRecord::from(vec!["a", "1", "x"]), &field_pos);
+        acc.add_record(&StringRecord::from(vec!["b", "2"]), &field_pos);
+        let mut out = vec![];
+        acc.write_table(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "column  count  distinct  min  max  mean\n\
+             3       1      1         x    x    -\n\
+             1       2      2         a    b    -\n\
+             2       2      2         1    2    1.50\n"
+        );
+    }
+}
@@ -9,6 +9,8 @@ const PRG: &str = "cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
 const TSV: &str = "tests/inputs/movies1.tsv";
 const BOOKS: &str = "tests/inputs/books.tsv";
+const GRAPHEMES: &str = "tests/inputs/graphemes.txt";
+const REPORT: &str = "tests/inputs/report.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -35,7 +37,7 @@ fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&["-f", "1", CSV, &bad, TSV])
+        .args(["-f", "1", CSV, &bad, TSV])
         .assert()
         .success()
         .stderr(predicate::str::is_match(expected)?);
@@ -55,7 +57,7 @@ fn dies(args: &[&str], expected: &str) -> TestResult {
 // --------------------------------------------------
 #[test]
 fn dies_not_enough_args() -> TestResult {
-    dies(&[CSV], "Must have --fields, --bytes, or --chars")
+    dies(&[CSV], "Must have --fields, --bytes, --chars, or --cols")
 }
 
 // --------------------------------------------------
@@ -110,7 +112,7 @@ fn dies_bad_delimiter() -> TestResult {
 #[test]
 fn dies_chars_bytes_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-f", "1", "-b", "1"])
+        .args([CSV, "-c", "1", "-f", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -120,7 +122,7 @@ fn dies_chars_bytes_fields() -> TestResult {
 #[test]
 fn dies_bytes_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-f", "1", "-b", "1"])
+        .args([CSV, "-f", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -130,7 +132,7 @@ fn dies_bytes_fields() -> TestResult {
 #[test]
 fn dies_chars_fields() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-f", "1"])
+        .args([CSV, "-c", "1", "-f", "1"])
         .assert()
         .failure();
     Ok(())
@@ -140,7 +142,7 @@ fn dies_chars_fields() -> TestResult {
 #[test]
 fn dies_chars_bytes() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&[CSV, "-c", "1", "-b", "1"])
+        .args([CSV, "-c", "1", "-b", "1"])
         .assert()
         .failure();
     Ok(())
@@ -337,3 +339,192 @@ fn tsv_c1_8() -> TestResult {
 fn repeated_value() -> TestResult {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn dies_graphemes_without_chars() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([GRAPHEMES, "-f", "1", "--graphemes"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn graphemes_c1_2() -> TestResult {
+    run(
+        &[GRAPHEMES, "-c", "1-2", "--graphemes"],
+        "tests/expected/graphemes.txt.c1-2.g.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn graphemes_c1_2_without_flag() -> TestResult {
+    run(
+        &[GRAPHEMES, "-c", "1-2"],
+        "tests/expected/graphemes.txt.c1-2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_no_split_multibyte_without_bytes() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([GRAPHEMES, "-c", "1", "--no-split-multibyte"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn graphemes_b2_5_no_split() -> TestResult {
+    run(
+        &[GRAPHEMES, "-b", "2-5", "-n"],
+        "tests/expected/graphemes.txt.b2-5.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn report_cols() -> TestResult {
+    run(
+        &[REPORT, "--cols", "1-4,7-20,21-30,31-38"],
+        "tests/expected/report.txt.cols.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn report_cols_trim() -> TestResult {
+    run(
+        &[REPORT, "--cols", "7-20,31-38", "--trim", "-d", ","],
+        "tests/expected/report.txt.cols.trim.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_trim_without_cols() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([REPORT, "-c", "1-4", "--trim"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_cols_and_fields() -> TestResult {
+    dies(&[REPORT, "--cols", "1-4", "-f", "1"], "cannot be used with")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_cols() -> TestResult {
+    dies(&[REPORT, "--cols", "4-1"], "First number in range (4) must be lower than second number (1)")
+}
+
+// --------------------------------------------------
+#[test]
+fn line_buffered() -> TestResult {
+    // 出力内容はバッファリングの有無で変わらない
+    run(&[TSV, "-f", "1", "--line-buffered"], "tests/expected/movies1.tsv.f1.out")?;
+    run(&[TSV, "-c", "1", "--line-buffered"], "tests/expected/movies1.tsv.c1.out")
+}
+
+// --------------------------------------------------
+// 入力が続いている間も1行ごとに出力される
+#[test]
+fn line_buffered_flushes_each_line() -> TestResult {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        process::{Command, Stdio},
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+
+    for args in [["-f", "2"], ["-c", "3"]] {
+        let mut child = Command::new(assert_cmd::cargo::cargo_bin(PRG))
+            .args(args)
+            .arg("--line-buffered")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(b"a\tb\n")?;
+        stdin.flush()?;
+
+        let stdout = child.stdout.take().unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut line = String::new();
+            BufReader::new(stdout).read_line(&mut line).unwrap();
+            tx.send(line).unwrap();
+        });
+        let line = rx.recv_timeout(Duration::from_secs(5));
+        drop(stdin);
+        child.wait()?;
+        assert_eq!(line?, "b\n");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+// 出力先のパイプが閉じられた場合はエラーを出さずに正常終了する
+#[test]
+fn broken_pipe() -> TestResult {
+    use std::{
+        io::{Read, Write},
+        process::{Command, Stdio},
+        thread,
+    };
+
+    for args in [["-f", "1"], ["-b", "1"]] {
+        let mut child = Command::new(assert_cmd::cargo::cargo_bin(PRG))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = thread::spawn(move || {
+            // パイプのバッファより十分に大きな入力: 読み込み側が終了した後の書き込みエラーは無視する
+            let _ = stdin.write_all("a\tb\n".repeat(1 << 18).as_bytes());
+        });
+
+        let mut stdout = child.stdout.take().unwrap();
+        let mut buf = [0; 4];
+        stdout.read_exact(&mut buf)?;
+        drop(stdout); // 読み込み側を閉じる
+        let output = child.wait_with_output()?;
+        writer.join().unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stderr)?, "");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stats_tsv() -> TestResult {
+    run(&[TSV, "-f", "3,2", "--stats"], "tests/expected/movies1.tsv.f3,2.stats.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn stats_multiple_files() -> TestResult {
+    run(
+        &["-d", ",", "-f", "1-3", "--stats", "tests/inputs/books.csv", CSV],
+        "tests/expected/all.csv.f1-3.stats.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_stats_without_fields() -> TestResult {
+    dies(&[CSV, "-c", "1", "--stats"], "--fields <FIELDS>")
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::Metadata,
+    io::{self, Write},
+    path::PathBuf,
+};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use crate::{hash::{hash_file, HashAlgo}, walk::Entry};
+
+// 同じファイルを識別する値
+#[cfg(unix)]
+type FileId = (u64, u64); // (デバイス, inode)
+#[cfg(not(unix))]
+type FileId = PathBuf; // inodeを取得できないため正規化したパスで識別する: ハードリンクは区別できない
+
+#[cfg(unix)]
+fn file_id(_: &Entry, meta: &Metadata) -> FileId {
+    (meta.dev(), meta.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(entry: &Entry, _: &Metadata) -> FileId {
+    std::fs::canonicalize(entry.path()).unwrap_or_else(|_| entry.path().to_path_buf())
+}
+
+// 内容が同じファイルの組
+#[derive(Debug, PartialEq, Eq)]
+pub struct Group {
+    pub size: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+// 重複の候補となる通常のファイルを集める
+#[derive(Debug, Default)]
+pub struct Finder {
+    by_size: HashMap<u64, Vec<PathBuf>>,
+    seen: HashSet<FileId>, // ハードリンクや重なった起点のパスで同じファイルを二重に数えない
+}
+
+impl Finder {
+    // 通常のファイル以外と空のファイルは対象外: 空のファイルは--emptyで探せる
+    pub fn add(&mut self, entry: &Entry) {
+        let Ok(meta) = entry.metadata() else { return };
+        if !meta.is_file() || meta.len() == 0 || !self.seen.insert(file_id(entry, &meta)) {
+            return;
+        }
+        self.by_size.entry(meta.len()).or_default().push(entry.path().to_path_buf());
+    }
+
+    // サイズが同じファイルのみハッシュ値を求め、内容が一致する組を返す
+    // 読み込めなかったファイルはエラーを出力して除外する
+    pub fn groups(self) -> Vec<Group> {
+        let mut groups = vec![];
+        for (size, paths) in self.by_size {
+            if paths.len() < 2 {
+                continue;
+            }
+            let mut by_hash: HashMap<Vec<u8>, Vec<PathBuf>> = HashMap::new();
+            for path in paths {
+                match hash_file(&path, HashAlgo::Sha256) {
+                    Err(e) => eprintln!("{}: {}", path.display(), e),
+                    Ok(hash) => by_hash.entry(hash).or_default().push(path),
+                }
+            }
+            groups.extend(
+                by_hash
+                    .into_values()
+                    .filter(|paths| paths.len() > 1)
+                    .map(|mut paths| {
+                        paths.sort();
+                        Group { size, paths }
+                    }),
+            );
+        }
+        // 大きいファイルの組から出力し、同じサイズの組はパス順に並べる
+        groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.paths.cmp(&b.paths)));
+        groups
+    }
+}
+
+// 組ごとにパスを1行ずつ出力し、組の間は空行で区切る
+pub fn write_groups(out: &mut impl Write, groups: &[Group]) -> io::Result<()> {
+    for (i, group) in groups.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        for path in &group.paths {
+            writeln!(out, "{}", path.display())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_groups, Finder, Group};
+    use crate::walk::{WalkOptions, Walker};
+    use std::{fs, path::PathBuf};
+
+    #[test]
+    fn test_finder() {
+        let root = std::env::temp_dir().join(format!("findr-dupes-{}", std::process::id()));
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.txt"), "same\n").unwrap();
+        fs::write(root.join("sub/b.txt"), "same\n").unwrap();
+        fs::write(root.join("c.txt"), "diff\n").unwrap(); // 同じサイズで内容が異なる
+        fs::write(root.join("d.txt"), "longer text\n").unwrap();
+        fs::write(root.join("e.txt"), "longer text\n").unwrap();
+        fs::write(root.join("empty1"), "").unwrap();
+        fs::write(root.join("empty2"), "").unwrap();
+        #[cfg(unix)] // ハードリンクを同じファイルと判定できるのはUnixのみ
+        fs::hard_link(root.join("a.txt"), root.join("link.txt")).unwrap();
+
+        let mut finder = Finder::default();
+        for entry in Walker::new(root.to_str().unwrap(), &WalkOptions::default()) {
+            finder.add(&entry.unwrap());
+        }
+        let groups = finder.groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[0],
+            Group {
+                size: 12,
+                paths: vec![root.join("d.txt"), root.join("e.txt")],
+            }
+        );
+        // ハードリンクは同じファイルなので片方のみ含まれる
+        assert_eq!(groups[1].size, 5);
+        assert_eq!(groups[1].paths.len(), 2);
+        assert!(groups[1].paths.contains(&root.join("sub/b.txt")));
+
+        fs::remove_dir_all(&root).unwrap();
+
+        let mut out = vec![];
+        let groups = [
+            Group { size: 2, paths: vec![PathBuf::from("a"), PathBuf::from("b")] },
+            Group { size: 1, paths: vec![PathBuf::from("c"), PathBuf::from("d")] },
+        ];
+        write_groups(&mut out, &groups).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "a\nb\n\nc\nd\n");
+    }
+}
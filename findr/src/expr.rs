@@ -0,0 +1,213 @@
+use regex::Regex;
+use std::{fmt, iter::Peekable, vec::IntoIter};
+
+use crate::{size::SizeSpec, walk::Entry, EntryType};
+
+// 検索条件の式: 葉となる条件を論理演算で組み合わせる
+#[derive(Debug)]
+pub enum Expr {
+    All(Vec<Expr>), // 全ての条件を満たす: 空の場合は常にtrue
+    Any(Vec<Expr>), // いずれかの条件を満たす: 空の場合は常にfalse
+    Not(Box<Expr>),
+    Name(Regex),
+    Type(EntryType),
+    Size(SizeSpec),
+}
+
+impl Expr {
+    pub fn matches(&self, entry: &Entry) -> bool {
+        match self {
+            Expr::All(exprs) => exprs.iter().all(|expr| expr.matches(entry)),
+            Expr::Any(exprs) => exprs.iter().any(|expr| expr.matches(entry)),
+            Expr::Not(expr) => !expr.matches(entry),
+            Expr::Name(re) => re.is_match(&entry.file_name().to_string_lossy()),
+            Expr::Type(entry_type) => match entry_type {
+                EntryType::Link => entry.path_is_symlink(),
+                EntryType::Dir => entry.file_type().is_dir(),
+                EntryType::File => entry.file_type().is_file(),
+            },
+            Expr::Size(size) => entry.metadata().is_ok_and(|meta| size.matches(meta.len())),
+        }
+    }
+}
+
+// コマンドライン引数の出現順に並べた式の構成要素
+#[derive(Debug)]
+pub enum Token {
+    And,
+    Or,
+    Not,
+    Pred(Expr),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::And => write!(f, "--and"),
+            Token::Or => write!(f, "--or"),
+            Token::Not => write!(f, "--not"),
+            Token::Pred(_) => write!(f, "predicate"),
+        }
+    }
+}
+
+// 演算子を含まない場合は従来通り、同種の条件をOR、異なる種類の条件をANDで結合する
+pub fn parse(tokens: Vec<Token>) -> Result<Expr, String> {
+    if tokens.iter().all(|token| matches!(token, Token::Pred(_))) {
+        let (mut names, mut types, mut sizes) = (vec![], vec![], vec![]);
+        for token in tokens {
+            match token {
+                Token::Pred(expr @ Expr::Name(_)) => names.push(expr),
+                Token::Pred(expr @ Expr::Type(_)) => types.push(expr),
+                Token::Pred(expr) => sizes.push(expr),
+                _ => unreachable!(),
+            }
+        }
+        let groups = [names, types, sizes]
+            .into_iter()
+            .filter(|group| !group.is_empty())
+            .map(Expr::Any)
+            .collect();
+        return Ok(Expr::All(groups));
+    }
+
+    // 優先順位は --not > --and(省略可) > --or
+    let mut tokens = tokens.into_iter().peekable();
+    let expr = parse_or(&mut tokens)?;
+    match tokens.next() {
+        Some(token) => Err(format!("Invalid expression near \"{}\"", token)),
+        None => Ok(expr),
+    }
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+fn parse_or(tokens: &mut Tokens) -> Result<Expr, String> {
+    let mut exprs = vec![parse_and(tokens)?];
+    while tokens.next_if(|token| matches!(token, Token::Or)).is_some() {
+        exprs.push(parse_and(tokens)?);
+    }
+    Ok(if exprs.len() == 1 {
+        exprs.pop().unwrap()
+    } else {
+        Expr::Any(exprs)
+    })
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Expr, String> {
+    let mut exprs = vec![parse_not(tokens)?];
+    loop {
+        match tokens.peek() {
+            Some(Token::And) => {
+                tokens.next();
+            }
+            Some(Token::Not | Token::Pred(_)) => (), // 演算子の省略は --and とみなす
+            _ => break,
+        }
+        exprs.push(parse_not(tokens)?);
+    }
+    Ok(if exprs.len() == 1 {
+        exprs.pop().unwrap()
+    } else {
+        Expr::All(exprs)
+    })
+}
+
+fn parse_not(tokens: &mut Tokens) -> Result<Expr, String> {
+    match tokens.next() {
+        Some(Token::Not) => Ok(Expr::Not(Box::new(parse_not(tokens)?))),
+        Some(Token::Pred(expr)) => Ok(expr),
+        Some(token) => Err(format!("Invalid expression near \"{}\"", token)),
+        None => Err("Invalid expression: missing predicate".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Expr, Token};
+    use crate::EntryType;
+    use regex::Regex;
+
+    fn name(pattern: &str) -> Token {
+        Token::Pred(Expr::Name(Regex::new(pattern).unwrap()))
+    }
+
+    fn file_type(entry_type: EntryType) -> Token {
+        Token::Pred(Expr::Type(entry_type))
+    }
+
+    // 比較しやすいように式を文字列に変換する
+    fn show(expr: &Expr) -> String {
+        let join = |exprs: &[Expr]| exprs.iter().map(show).collect::<Vec<_>>().join(" ");
+        match expr {
+            Expr::All(exprs) => format!("(and {})", join(exprs)),
+            Expr::Any(exprs) => format!("(or {})", join(exprs)),
+            Expr::Not(expr) => format!("(not {})", show(expr)),
+            Expr::Name(re) => re.to_string(),
+            Expr::Type(entry_type) => format!("{:?}", entry_type),
+            Expr::Size(size) => format!("{:?}", size),
+        }
+    }
+
+    fn parse_show(tokens: Vec<Token>) -> Result<String, String> {
+        parse(tokens).map(|expr| show(&expr))
+    }
+
+    #[test]
+    fn test_parse_implicit() {
+        assert_eq!(parse_show(vec![]), Ok("(and )".to_string()));
+        assert_eq!(
+            parse_show(vec![name("a"), file_type(EntryType::File), name("b")]),
+            Ok("(and (or a b) (or File))".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        assert_eq!(
+            parse_show(vec![name("a"), Token::Or, name("b")]),
+            Ok("(or a b)".to_string())
+        );
+        assert_eq!(
+            parse_show(vec![Token::Not, name("a")]),
+            Ok("(not a)".to_string())
+        );
+        // --and は --or より優先され、省略もできる
+        assert_eq!(
+            parse_show(vec![
+                name("a"),
+                Token::And,
+                file_type(EntryType::Dir),
+                Token::Or,
+                Token::Not,
+                name("b"),
+                file_type(EntryType::File),
+            ]),
+            Ok("(or (and a Dir) (and (not b) File))".to_string())
+        );
+        assert_eq!(
+            parse_show(vec![Token::Not, Token::Not, name("a")]),
+            Ok("(not (not a))".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_show(vec![Token::Or, name("a")]),
+            Err("Invalid expression near \"--or\"".to_string())
+        );
+        assert_eq!(
+            parse_show(vec![name("a"), Token::And]),
+            Err("Invalid expression: missing predicate".to_string())
+        );
+        assert_eq!(
+            parse_show(vec![name("a"), Token::Or, Token::And, name("b")]),
+            Err("Invalid expression near \"--and\"".to_string())
+        );
+        assert_eq!(
+            parse_show(vec![Token::Not]),
+            Err("Invalid expression: missing predicate".to_string())
+        );
+    }
+}
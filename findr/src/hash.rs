@@ -0,0 +1,163 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use md5::Md5;
+use rayon::prelude::*;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+// --hashで指定するハッシュ関数
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum HashAlgo {
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+impl FromStr for HashAlgo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "md5" => Ok(HashAlgo::Md5),
+            "sha1" => Ok(HashAlgo::Sha1),
+            "sha256" => Ok(HashAlgo::Sha256),
+            "blake3" => Ok(HashAlgo::Blake3),
+            _ => Err(format!("Invalid --hash \"{}\"", s)),
+        }
+    }
+}
+
+// 計算途中のハッシュ関数の状態: blake3はDigestトレイトを実装しないため個別に扱う
+enum Hasher {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Md5 => Hasher::Md5(Md5::new()),
+            HashAlgo::Sha1 => Hasher::Sha1(Sha1::new()),
+            HashAlgo::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgo::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(hasher) => hasher.update(data),
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+// ファイル全体をメモリに読み込まず、バッファ単位で読み込んでハッシュ値を求める
+pub fn hash_file(path: &Path, algo: HashAlgo) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new(algo);
+    let mut buffer = vec![0; BUFFER_SIZE];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => hasher.update(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(hasher.finalize())
+}
+
+pub fn to_hex(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 複数のファイルのハッシュ値を並列に求め、探索順のまま「ハッシュ値  パス」の形式で出力する
+// sha256sum -c などでそのまま検証できる形式: 読み込めなかったファイルはエラーを出力して除外する
+pub fn write_hashes(out: &mut impl Write, paths: &[PathBuf], algo: HashAlgo) -> io::Result<usize> {
+    let hashes: Vec<_> = paths.par_iter().map(|path| hash_file(path, algo)).collect();
+    let mut failures = 0;
+    for (path, hash) in paths.iter().zip(hashes) {
+        match hash {
+            Ok(hash) => writeln!(out, "{}  {}", to_hex(&hash), path.display())?,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                failures += 1;
+            }
+        }
+    }
+    Ok(failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_file, to_hex, write_hashes, HashAlgo};
+    use std::{fs, path::PathBuf};
+
+    #[test]
+    fn test_parse_hash_algo() {
+        assert_eq!("md5".parse::<HashAlgo>(), Ok(HashAlgo::Md5));
+        assert_eq!("sha1".parse::<HashAlgo>(), Ok(HashAlgo::Sha1));
+        assert_eq!("sha256".parse::<HashAlgo>(), Ok(HashAlgo::Sha256));
+        assert_eq!("blake3".parse::<HashAlgo>(), Ok(HashAlgo::Blake3));
+        assert_eq!("crc32".parse::<HashAlgo>(), Err("Invalid --hash \"crc32\"".to_string()));
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let root = std::env::temp_dir().join(format!("findr-hash-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        // バッファの大きさを超えるファイルも全体のハッシュ値になる
+        let big = "x".repeat(super::BUFFER_SIZE * 2 + 1);
+        fs::write(root.join("big"), &big).unwrap();
+        fs::write(root.join("abc"), "abc").unwrap();
+
+        let abc = |algo| to_hex(&hash_file(&root.join("abc"), algo).unwrap());
+        assert_eq!(abc(HashAlgo::Md5), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(abc(HashAlgo::Sha1), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            abc(HashAlgo::Sha256),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            abc(HashAlgo::Blake3),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        assert_ne!(
+            to_hex(&hash_file(&root.join("big"), HashAlgo::Sha256).unwrap()),
+            abc(HashAlgo::Sha256)
+        );
+        assert!(hash_file(&root.join("missing"), HashAlgo::Md5).is_err());
+
+        // 並列に求めても入力の順に出力する
+        let paths: Vec<PathBuf> = ["abc", "missing", "abc"].iter().map(|name| root.join(name)).collect();
+        let mut out = vec![];
+        assert_eq!(write_hashes(&mut out, &paths, HashAlgo::Md5).unwrap(), 1);
+        let line = format!("900150983cd24fb0d6963f7d28e17f72  {}\n", root.join("abc").display());
+        assert_eq!(String::from_utf8(out).unwrap(), line.repeat(2));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,7 +1,35 @@
 use clap::{App, Arg};
 use regex::Regex;
-use walkdir::{WalkDir, DirEntry};
-use std::error::Error;
+use std::{error::Error, fs, io};
+#[cfg(unix)]
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+#[cfg(unix)]
+use users::get_user_by_name;
+
+mod dupes;
+mod expr;
+mod hash;
+#[cfg(unix)]
+mod perm;
+mod printer;
+mod progress;
+mod size;
+#[cfg(unix)]
+mod template;
+mod walk;
+use dupes::Finder;
+use expr::{Expr, Token};
+use hash::HashAlgo;
+#[cfg(unix)]
+use perm::PermSpec;
+use printer::{JsonPrinter, PlainPrinter, Print0Printer, Printer};
+#[cfg(unix)]
+use printer::PrintfPrinter;
+use progress::Progress;
+use size::SizeSpec;
+#[cfg(unix)]
+use template::Template;
+use walk::{Entry, WalkOptions, Walker};
 
 use crate::EntryType::*; // enumの各値を直接利用できるようにする
 
@@ -14,15 +42,43 @@ enum EntryType {
     Link,
 }
 
+// 出力形式
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum OutputFormat {
+    Plain,
+    Print0,
+    Json,
+    #[cfg(unix)]
+    Printf(Template),
+}
+
+// 条件に一致したエントリへの処理
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Action {
+    Print,
+    Delete,  // --delete --yes: 出力せずに削除する
+    Preview, // --delete --dry-run: 削除対象を出力するだけで削除しない
+    Duplicates, // --duplicates: 内容が同じファイルの組を出力する
+    Hash(HashAlgo), // --hash: 通常のファイルのハッシュ値とパスを出力する
+}
+
 #[derive(Debug)]
 pub struct Config {
     paths: Vec<String>,
-    names: Vec<Regex>,
-    entry_types: Vec<EntryType>,
+    expr: Expr,
+    walk: WalkOptions,
+    empty: bool,
+    #[cfg(unix)]
+    perm: Option<PermSpec>,
+    #[cfg(unix)]
+    user: Option<u32>,
+    format: OutputFormat,
+    action: Action,
+    progress: bool, // 標準エラーに進捗と集計を出力する
 }
 
 pub fn get_args() -> MyResult<Config> {
-    let matches = App::new("findr")
+    let app = App::new("findr")
         .version("0.1.0")
         .author("kazuki.ogiwara")
         .about("Rust find")
@@ -52,68 +108,336 @@ pub fn get_args() -> MyResult<Config> {
                 .takes_value(true)
                 .multiple(true)
         )
+        .arg(
+            Arg::with_name("sizes")
+                .value_name("SIZE")
+                .long("size")
+                .help("File size (10c, +1k, -2M)")
+                .allow_hyphen_values(true) // -SIZE形式を値として受け付ける
+                .number_of_values(1)
+                .multiple(true)
+        )
+        .arg(
+            Arg::with_name("and")
+                .long("and")
+                .help("Match both surrounding predicates")
+                .multiple(true)
+        )
+        .arg(
+            Arg::with_name("or")
+                .long("or")
+                .help("Match either surrounding predicate")
+                .multiple(true)
+        )
+        .arg(
+            Arg::with_name("not")
+                .long("not")
+                .help("Negate the following predicate")
+                .multiple(true)
+        )
+        .arg(
+            Arg::with_name("min_depth")
+                .value_name("DEPTH")
+                .long("min-depth")
+                .help("Minimum depth")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("max_depth")
+                .value_name("DEPTH")
+                .long("max-depth")
+                .help("Maximum depth")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("follow")
+                .short("L")
+                .long("follow")
+                .help("Follow symbolic links")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("respect_gitignore")
+                .long("respect-gitignore")
+                .help("Skip entries ignored by .gitignore files")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("empty")
+                .long("empty")
+                .help("Empty files and directories")
+                .takes_value(false)
+        );
+    // パーミッションと所有者はUnixのメタデータにのみ存在する
+    #[cfg(unix)]
+    let app = app
+        .arg(
+            Arg::with_name("perm")
+                .value_name("MODE")
+                .long("perm")
+                .help("Permission bits (644, -u+w, /111)")
+                .allow_hyphen_values(true) // -MODE形式を値として受け付ける
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("user")
+                .value_name("USER")
+                .long("user")
+                .help("Owner name or uid")
+                .takes_value(true)
+        );
+    let app = app
+        .arg(
+            Arg::with_name("print0")
+                .long("print0")
+                .help("Separate paths with NUL instead of newline")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print one JSON object per entry")
+                .takes_value(false)
+                .conflicts_with("print0")
+        );
+    // --printfの指示子は所有者やパーミッションなどUnixのメタデータを参照する
+    #[cfg(unix)]
+    let app = app
+        .arg(
+            Arg::with_name("printf")
+                .value_name("FORMAT")
+                .long("printf")
+                .help("Print entries using FORMAT (%p, %f, %s, %u, %m, %TY, ...)")
+                .allow_hyphen_values(true)
+                .takes_value(true)
+                .conflicts_with_all(&["print0", "json"])
+        );
+    let matches = app
+        .arg(
+            Arg::with_name("delete")
+                .long("delete")
+                .help("Delete matching files and directories, deepest first")
+                .takes_value(false)
+                .conflicts_with("follow") // リンク先の探索の起点の外にあるファイルを削除しないようにする
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .help("With --delete, print what would be deleted without deleting it")
+                .takes_value(false)
+                .requires("delete")
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Confirm --delete")
+                .takes_value(false)
+                .requires("delete")
+                .conflicts_with("dry_run")
+        )
+        .arg(
+            Arg::with_name("duplicates")
+                .long("duplicates")
+                .help("Print groups of regular files with identical contents")
+                .takes_value(false)
+                .conflicts_with_all(&["print0", "json", "printf", "delete"])
+        )
+        .arg(
+            Arg::with_name("hash")
+                .value_name("ALGO")
+                .long("hash")
+                .help("Print a digest of each regular file next to its path [md5|sha1|sha256|blake3]")
+                .takes_value(true)
+                .conflicts_with_all(&["print0", "json", "printf", "delete", "duplicates"])
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .help("Show entries scanned, matched and errors on STDERR, and a summary at the end")
+                .takes_value(false)
+        )
         .get_matches();
 
-    let names = matches
-        .values_of_lossy("names")
-        .map(|vals| { // Option<_>の中身を取り出す
-            vals.into_iter() // Vec<_>の中身として各要素をイテレーション
-                .map(|name| { // 正規表現の文字列またはエラーに変換
-                    Regex::new(&name)
-                        .map_err(|_| format!("Invalid --name \"{}\"", name))
-                })
-                .collect::<Result<Vec<_>, _>>() // 各要素をVec<_>またはエラーとして集約
-        }) // Option<Result>になる
-        .transpose()? // Option<Result>からResult<Option>に変換後、エラー有無を確認
-        .unwrap_or_default(); // Optionから中身のVec<_>を取り出す: Noneの場合にはデフォルト(空ベクトル: vec![])
-
-    let entry_types = matches
-        .values_of_lossy("types")
-        .map(|vals| {
-            vals.iter()
-                .map(|val| match val.as_str() { // 変数を文字列として条件分岐
-                    "d" => Dir,
-                    "f" => File,
-                    "l" => Link,
-                    _ => unreachable!("Invalid type"), // 異常処理としてpanic!を出力
-                })
-                .collect::<Vec<EntryType>>() // enumとして集約
-        })
-        .unwrap_or_default(); // OptionからVec<_>のみを取り出す
+    // 条件と演算子を引数の出現位置と組にして集める
+    let mut tokens: Vec<(usize, Token)> = vec![];
+    if let (Some(indices), Some(vals)) = (matches.indices_of("names"), matches.values_of("names")) {
+        for (index, name) in indices.zip(vals) {
+            let re = Regex::new(name).map_err(|_| format!("Invalid --name \"{}\"", name))?;
+            tokens.push((index, Token::Pred(Expr::Name(re))));
+        }
+    }
+    if let (Some(indices), Some(vals)) = (matches.indices_of("types"), matches.values_of("types")) {
+        for (index, val) in indices.zip(vals) {
+            let entry_type = match val { // 変数を文字列として条件分岐
+                "d" => Dir,
+                "f" => File,
+                "l" => Link,
+                _ => unreachable!("Invalid type"), // 異常処理としてpanic!を出力
+            };
+            tokens.push((index, Token::Pred(Expr::Type(entry_type))));
+        }
+    }
+    if let (Some(indices), Some(vals)) = (matches.indices_of("sizes"), matches.values_of("sizes")) {
+        for (index, val) in indices.zip(vals) {
+            tokens.push((index, Token::Pred(Expr::Size(val.parse::<SizeSpec>()?))));
+        }
+    }
+    for name in ["and", "or", "not"] {
+        for index in matches.indices_of(name).into_iter().flatten() {
+            let op = match name {
+                "and" => Token::And,
+                "or" => Token::Or,
+                _ => Token::Not,
+            };
+            tokens.push((index, op));
+        }
+    }
+    tokens.sort_by_key(|(index, _)| *index);
+    let expr = expr::parse(tokens.into_iter().map(|(_, token)| token).collect())?;
+
+    let min_depth = matches
+        .value_of("min_depth")
+        .map(|val| parse_depth(val, "--min-depth"))
+        .transpose()?;
+
+    let max_depth = matches
+        .value_of("max_depth")
+        .map(|val| parse_depth(val, "--max-depth"))
+        .transpose()?;
+
+    #[cfg(unix)]
+    let perm = matches
+        .value_of("perm")
+        .map(|val| val.parse::<PermSpec>())
+        .transpose()?;
+
+    #[cfg(unix)]
+    let user = matches.value_of("user").map(parse_user).transpose()?;
+
+    #[cfg(unix)]
+    let printf = matches
+        .value_of("printf")
+        .map(|val| val.parse::<Template>().map(OutputFormat::Printf))
+        .transpose()?;
+    #[cfg(not(unix))]
+    let printf = None;
+
+    let hash = matches
+        .value_of("hash")
+        .map(|val| val.parse::<HashAlgo>())
+        .transpose()?;
+
+    // 誤って削除しないよう、--deleteには--yesか--dry-runのどちらかを必須とする
+    let action = if matches.is_present("duplicates") {
+        Action::Duplicates
+    } else if let Some(algo) = hash {
+        Action::Hash(algo)
+    } else if !matches.is_present("delete") {
+        Action::Print
+    } else if matches.is_present("dry_run") {
+        Action::Preview
+    } else if matches.is_present("yes") {
+        Action::Delete
+    } else {
+        return Err(From::from("--delete requires --yes or --dry-run"));
+    };
 
     Ok(
         Config {
             paths: matches.values_of_lossy("paths").unwrap(),
-            names,
-            entry_types,
+            expr,
+            walk: WalkOptions {
+                min_depth,
+                max_depth,
+                follow_links: matches.is_present("follow"),
+                respect_gitignore: matches.is_present("respect_gitignore"),
+                contents_first: matches!(action, Action::Delete | Action::Preview), // ディレクトリを空にしてから削除するため
+            },
+            empty: matches.is_present("empty"),
+            #[cfg(unix)]
+            perm,
+            #[cfg(unix)]
+            user,
+            format: if let Some(format) = printf {
+                format
+            } else if matches.is_present("print0") {
+                OutputFormat::Print0
+            } else if matches.is_present("json") {
+                OutputFormat::Json
+            } else {
+                OutputFormat::Plain
+            },
+            action,
+            progress: matches.is_present("progress"),
         })
 }
 
+fn parse_depth(val: &str, name: &str) -> MyResult<usize> {
+    val.parse()
+        .map_err(|_| format!("Invalid {} \"{}\"", name, val).into())
+}
+
+// ユーザー名またはuidをuidに変換する
+#[cfg(unix)]
+fn parse_user(val: &str) -> MyResult<u32> {
+    val.parse()
+        .ok()
+        .or_else(|| get_user_by_name(val).map(|user| user.uid()))
+        .ok_or_else(|| format!("Invalid --user \"{}\"", val).into())
+}
+
 pub fn run(config: Config) -> MyResult<()> {
+    // フィルター関数として処理を定義: 名前・種類・サイズの条件式を評価する
+    let expr_filter = |entry: &Entry| config.expr.matches(entry);
+
     // フィルター関数として処理を定義: trueまたはfalseを返す
-    let type_filter = |entry: &DirEntry| {
-        config.entry_types.is_empty()
-            || config
-                .entry_types
-                .iter()
-                .any(|entry_type| match entry_type {
-                    // enum型の条件分岐: 全種類が網羅されていない場合、コンパイル時にエラーとなる
-                    Link => entry.path_is_symlink(),
-                    Dir => entry.file_type().is_dir(),
-                    File => entry.file_type().is_file(),
-                })
+    let empty_filter = |entry: &Entry| {
+        !config.empty
+            || match entry.metadata() {
+                Ok(meta) if meta.is_dir() => fs::read_dir(entry.path())
+                    .is_ok_and(|mut entries| entries.next().is_none()),
+                Ok(meta) => meta.is_file() && meta.len() == 0,
+                Err(_) => false,
+            }
     };
 
     // フィルター関数として処理を定義: trueまたはfalseを返す
-    let name_filter = |entry: &DirEntry| {
-        config.names.is_empty()
-            || config
-                .names
-                .iter()
-                .any(|re| re.is_match(&entry.file_name().to_string_lossy()))
+    #[cfg(unix)]
+    let perm_filter = |entry: &Entry| {
+        config.perm.is_none_or(|perm| {
+            entry
+                .metadata()
+                .is_ok_and(|meta| perm.matches(meta.permissions().mode()))
+        })
+    };
+
+    // フィルター関数として処理を定義: trueまたはfalseを返す
+    #[cfg(unix)]
+    let user_filter = |entry: &Entry| {
+        config
+            .user
+            .is_none_or(|uid| entry.metadata().is_ok_and(|meta| meta.uid() == uid))
+    };
+
+    // Unix以外では--perm, --userを指定できないため、全てのエントリを通す
+    #[cfg(not(unix))]
+    let (perm_filter, user_filter) = (|_: &Entry| true, |_: &Entry| true);
+
+    let out = io::stdout().lock();
+    let mut printer: Box<dyn Printer> = match &config.format {
+        OutputFormat::Plain => Box::new(PlainPrinter::new(out)),
+        OutputFormat::Print0 => Box::new(Print0Printer::new(out)),
+        OutputFormat::Json => Box::new(JsonPrinter::new(out)),
+        #[cfg(unix)]
+        OutputFormat::Printf(template) => Box::new(PrintfPrinter::new(out, template.clone())),
     };
 
-    for path in config.paths {
+    let progress = Progress::new(config.progress, atty::is(atty::Stream::Stderr));
+    let mut failures = 0; // 削除に失敗したエントリ数
+    let mut finder = Finder::default(); // --duplicates: 全ての起点のパスのファイルをまとめて比較する
+    let mut hashed = vec![]; // --hash: 全ての起点のパスのファイルをまとめて並列に読み込む
+    for path in &config.paths {
         // for entry in WalkDir::new(path) { // パスに含まれるディレクトリ, ファイル, リンクのパスを取得
         //     match entry {
         //         Err(e) => eprintln!("{}", e),
@@ -139,21 +463,79 @@ pub fn run(config: Config) -> MyResult<()> {
         //         }
         //     }
         // }
-        let entries = WalkDir::new(path)
-            .into_iter()
+        // Configの設定に従って探索範囲とシンボリックリンク・.gitignoreの扱いを指定
+        let entries = Walker::new(path, &config.walk)
             .filter_map(|entry| match entry { // イテレータの(Result型の)各要素を処理: (Option型の)返り値がNoneとなった要素をフィルタリングで除去
                 Err(e) => {
-                    eprintln!("{}", e);
+                    progress.error(e);
                     None // フィルタリングによってイレテータから除去される
                 }
-                Ok(entry) => Some(entry), // フィルタリングされず後続処理に渡される
+                Ok(entry) => {
+                    progress.scanned();
+                    Some(entry) // フィルタリングされず後続処理に渡される
+                }
             })
             // クロージャを組み合わせて絞り込みを実施
-            .filter(type_filter) // falseとなった要素は除去
-            .filter(name_filter)
-            .map(|entry| entry.path().display().to_string()) // 残った要素を文字列に変換
-            .collect::<Vec<_>>(); // ベクトルとして集約
-        println!("{}", entries.join("\n")); // 改行区切りで出力
+            .filter(expr_filter) // falseとなった要素は除去
+            .filter(empty_filter)
+            .filter(perm_filter)
+            .filter(user_filter);
+        for entry in entries {
+            progress.matched();
+            if config.action == Action::Duplicates {
+                finder.add(&entry);
+                continue;
+            }
+            if let Action::Hash(_) = config.action {
+                // ディレクトリやデバイスなど、内容を読めないエントリは出力しない
+                if entry.metadata().is_ok_and(|meta| meta.is_file()) {
+                    hashed.push(entry.path().to_path_buf());
+                }
+                continue;
+            }
+            if config.action == Action::Delete {
+                // 失敗しても残りのエントリの削除を続ける
+                if let Err(e) = remove(&entry) {
+                    progress.error(format!("{}: {}", entry.path().display(), e));
+                    failures += 1;
+                }
+                continue;
+            }
+            // 残った要素を指定の形式で出力
+            if let Err(e) = printer.print(&entry) {
+                progress.error(format!("{}: {}", entry.path().display(), e));
+            }
+        }
+    }
+    drop(printer); // 標準出力のロックを解放する
+    progress.clear(); // 探索後の出力やエラーが進捗の行に続かないようにする
+    let mut unreadable = 0; // --hash: 読み込めなかったファイル数
+    match config.action {
+        Action::Duplicates => dupes::write_groups(&mut io::stdout().lock(), &finder.groups())?,
+        Action::Hash(algo) => unreadable = hash::write_hashes(&mut io::stdout().lock(), &hashed, algo)?,
+        _ => {}
+    }
+    // 失敗による終了の前に集計を出力する
+    progress.add_errors(unreadable as u64);
+    progress.finish();
+    if unreadable > 0 {
+        return Err(From::from(format!("{} files could not be read", unreadable)));
+    }
+    if failures > 0 {
+        return Err(From::from(format!("{} entries could not be deleted", failures)));
     }
     Ok(())
 }
+
+// エントリを削除する: ディレクトリは空の場合のみ削除し、シンボリックリンクはリンク自体を削除する
+fn remove(entry: &Entry) -> io::Result<()> {
+    // "."や".."のような起点のパスは削除しない
+    if entry.path().file_name().is_none() {
+        return Ok(());
+    }
+    if entry.file_type().is_dir() && !entry.path_is_symlink() {
+        fs::remove_dir(entry.path())
+    } else {
+        fs::remove_file(entry.path())
+    }
+}
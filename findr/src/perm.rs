@@ -0,0 +1,152 @@
+use std::str::FromStr;
+
+// パーミッションの比較方法
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PermMatch {
+    Exact, // MODE: 完全一致
+    All,   // -MODE: 指定ビットを全て含む
+    Any,   // /MODE: 指定ビットのいずれかを含む
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PermSpec {
+    mode: u32,
+    kind: PermMatch,
+}
+
+impl PermSpec {
+    pub fn matches(&self, mode: u32) -> bool {
+        let mode = mode & 0o7777; // ファイル種別のビットを除外
+        match self.kind {
+            PermMatch::Exact => mode == self.mode,
+            PermMatch::All => mode & self.mode == self.mode,
+            PermMatch::Any => self.mode == 0 || mode & self.mode != 0,
+        }
+    }
+}
+
+impl FromStr for PermSpec {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid --perm \"{}\"", val);
+        let (kind, spec) = match val.chars().next() {
+            Some('-') => (PermMatch::All, &val[1..]),
+            Some('/') => (PermMatch::Any, &val[1..]),
+            _ => (PermMatch::Exact, val),
+        };
+        let mode = if spec.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+            parse_octal(spec)
+        } else {
+            parse_symbolic(spec)
+        }
+        .ok_or_else(err)?;
+        Ok(PermSpec { mode, kind })
+    }
+}
+
+// 8進数表記(例: 644)
+fn parse_octal(spec: &str) -> Option<u32> {
+    u32::from_str_radix(spec, 8).ok().filter(|&mode| mode <= 0o7777)
+}
+
+// 記号表記(例: u+w, u=rw,go=r)を0から順に適用してビットを求める
+fn parse_symbolic(spec: &str) -> Option<u32> {
+    let mut mode = 0;
+    for clause in spec.split(',') {
+        let op_pos = clause.find(['+', '-', '='])?;
+        let (who, mut rest) = clause.split_at(op_pos);
+        let mut mask = 0;
+        for c in who.chars() {
+            mask |= match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                'a' => 0o7777,
+                _ => return None,
+            };
+        }
+        if mask == 0 {
+            mask = 0o7777; // 対象の指定がない場合は全員
+        }
+        // u+r-x のように演算子が連続する場合に対応
+        while let Some(op) = rest.chars().next() {
+            rest = &rest[1..];
+            let end = rest.find(['+', '-', '=']).unwrap_or(rest.len());
+            let (perms, next) = rest.split_at(end);
+            let mut bits = 0;
+            for c in perms.chars() {
+                bits |= match c {
+                    'r' => 0o0444,
+                    'w' => 0o0222,
+                    'x' => 0o0111,
+                    's' => 0o6000,
+                    't' => 0o1000,
+                    _ => return None,
+                };
+            }
+            bits &= mask;
+            match op {
+                '+' => mode |= bits,
+                '-' => mode &= !bits,
+                '=' => mode = (mode & !mask) | bits,
+                _ => return None,
+            }
+            rest = next;
+        }
+    }
+    Some(mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PermMatch, PermSpec};
+
+    fn spec(mode: u32, kind: PermMatch) -> PermSpec {
+        PermSpec { mode, kind }
+    }
+
+    #[test]
+    fn test_parse_octal() {
+        assert_eq!("644".parse(), Ok(spec(0o644, PermMatch::Exact)));
+        assert_eq!("-220".parse(), Ok(spec(0o220, PermMatch::All)));
+        assert_eq!("/111".parse(), Ok(spec(0o111, PermMatch::Any)));
+        assert_eq!("4755".parse(), Ok(spec(0o4755, PermMatch::Exact)));
+
+        let res = "789".parse::<PermSpec>();
+        assert_eq!(res, Err("Invalid --perm \"789\"".to_string()));
+        assert!("17777".parse::<PermSpec>().is_err());
+        assert!("".parse::<PermSpec>().is_err());
+        assert!("-".parse::<PermSpec>().is_err());
+    }
+
+    #[test]
+    fn test_parse_symbolic() {
+        assert_eq!("-u+w".parse(), Ok(spec(0o200, PermMatch::All)));
+        assert_eq!("u=rw,go=r".parse(), Ok(spec(0o644, PermMatch::Exact)));
+        assert_eq!("/a+x".parse(), Ok(spec(0o111, PermMatch::Any)));
+        assert_eq!("+w".parse(), Ok(spec(0o222, PermMatch::Exact)));
+        assert_eq!("u+rwx-x".parse(), Ok(spec(0o600, PermMatch::Exact)));
+        assert_eq!("u+s,o+t".parse(), Ok(spec(0o5000, PermMatch::Exact)));
+
+        assert!("u+q".parse::<PermSpec>().is_err());
+        assert!("z+w".parse::<PermSpec>().is_err());
+        assert!("uw".parse::<PermSpec>().is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let exact = spec(0o644, PermMatch::Exact);
+        assert!(exact.matches(0o100644)); // 通常ファイルの種別ビット付き
+        assert!(!exact.matches(0o664));
+
+        let all = spec(0o220, PermMatch::All);
+        assert!(all.matches(0o664));
+        assert!(!all.matches(0o644));
+
+        let any = spec(0o022, PermMatch::Any);
+        assert!(any.matches(0o664));
+        assert!(!any.matches(0o644));
+        assert!(spec(0, PermMatch::Any).matches(0o600));
+    }
+}
@@ -0,0 +1,198 @@
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[cfg(unix)]
+use crate::template::Template;
+use crate::{walk::Entry, MyResult};
+
+// 検索結果の出力形式: 形式を追加する場合はPrinterを実装する
+pub trait Printer {
+    fn print(&mut self, entry: &Entry) -> MyResult<()>;
+}
+
+// 改行区切りでパスを出力する
+pub struct PlainPrinter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> PlainPrinter<W> {
+    pub fn new(out: W) -> Self {
+        PlainPrinter { out }
+    }
+}
+
+impl<W: Write> Printer for PlainPrinter<W> {
+    fn print(&mut self, entry: &Entry) -> MyResult<()> {
+        writeln!(self.out, "{}", entry.path().display())?;
+        Ok(())
+    }
+}
+
+// NUL区切りでパスを出力する: 改行を含むパスも xargs -0 に安全に渡せる
+pub struct Print0Printer<W: Write> {
+    out: W,
+}
+
+impl<W: Write> Print0Printer<W> {
+    pub fn new(out: W) -> Self {
+        Print0Printer { out }
+    }
+}
+
+impl<W: Write> Printer for Print0Printer<W> {
+    fn print(&mut self, entry: &Entry) -> MyResult<()> {
+        write!(self.out, "{}\0", entry.path().display())?;
+        Ok(())
+    }
+}
+
+// 1エントリを1行のJSONオブジェクトとして出力する(JSON Lines)
+pub struct JsonPrinter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> JsonPrinter<W> {
+    pub fn new(out: W) -> Self {
+        JsonPrinter { out }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Record {
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: &'static str,
+    size: u64,
+    mtime: i64, // UNIX時間(秒)
+}
+
+impl Record {
+    fn new(entry: &Entry) -> MyResult<Self> {
+        let metadata = entry.metadata()?;
+        let entry_type = if entry.path_is_symlink() {
+            "link"
+        } else if metadata.is_dir() {
+            "dir"
+        } else if metadata.is_file() {
+            "file"
+        } else {
+            "other"
+        };
+        Ok(Record {
+            path: entry.path().display().to_string(),
+            entry_type,
+            size: metadata.len(),
+            mtime: DateTime::<Utc>::from(metadata.modified()?).timestamp(), // Unix以外でも同じ値を得る
+        })
+    }
+}
+
+impl<W: Write> Printer for JsonPrinter<W> {
+    fn print(&mut self, entry: &Entry) -> MyResult<()> {
+        let record = Record::new(entry)?;
+        writeln!(self.out, "{}", serde_json::to_string(&record)?)?;
+        Ok(())
+    }
+}
+
+// --printfの書式に従って出力する: 改行は書式で指定する
+#[cfg(unix)]
+pub struct PrintfPrinter<W: Write> {
+    out: W,
+    template: Template,
+}
+
+#[cfg(unix)]
+impl<W: Write> PrintfPrinter<W> {
+    pub fn new(out: W, template: Template) -> Self {
+        PrintfPrinter { out, template }
+    }
+}
+
+#[cfg(unix)]
+impl<W: Write> Printer for PrintfPrinter<W> {
+    fn print(&mut self, entry: &Entry) -> MyResult<()> {
+        // 途中で失敗した場合に書きかけの出力を残さないよう、1エントリ分をまとめて書き込む
+        let buf = self.template.render(entry)?;
+        self.out.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Print0Printer, PlainPrinter, Printer};
+    #[cfg(unix)]
+    use super::{JsonPrinter, Record};
+    #[cfg(unix)]
+    use std::os::unix::fs::MetadataExt;
+    use crate::walk::Entry;
+    use walkdir::WalkDir;
+
+    fn entry(path: &str) -> Entry {
+        WalkDir::new(path).into_iter().next().unwrap().unwrap().into()
+    }
+
+    #[test]
+    fn test_plain_print0() {
+        let mut out = vec![];
+        let mut printer = PlainPrinter::new(&mut out);
+        printer.print(&entry("tests/inputs/g.csv")).unwrap();
+        printer.print(&entry("tests/inputs/a")).unwrap();
+        assert_eq!(out, b"tests/inputs/g.csv\ntests/inputs/a\n");
+
+        let mut out = vec![];
+        let mut printer = Print0Printer::new(&mut out);
+        printer.print(&entry("tests/inputs/g.csv")).unwrap();
+        printer.print(&entry("tests/inputs/a")).unwrap();
+        assert_eq!(out, b"tests/inputs/g.csv\0tests/inputs/a\0");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_record() {
+        let path = "tests/inputs/g.csv";
+        let metadata = std::fs::metadata(path).unwrap();
+        let record = Record::new(&entry(path)).unwrap();
+        assert_eq!(
+            record,
+            Record {
+                path: path.to_string(),
+                entry_type: "file",
+                size: metadata.len(),
+                mtime: metadata.mtime(),
+            }
+        );
+        assert_eq!(Record::new(&entry("tests/inputs/a")).unwrap().entry_type, "dir");
+
+        // リンク切れのシンボリックリンクは起点以外ではリンク自体の情報を使う
+        let link = WalkDir::new("tests/inputs/d")
+            .into_iter()
+            .filter_map(Result::ok)
+            .find(|entry| entry.file_name() == "b.csv")
+            .unwrap()
+            .into();
+        let record = Record::new(&link).unwrap();
+        assert_eq!(record.entry_type, "link");
+        assert_eq!(record.size, "../a/b.csv".len() as u64);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_json() {
+        let path = "tests/inputs/g.csv";
+        let mtime = std::fs::metadata(path).unwrap().mtime();
+        let mut out = vec![];
+        let mut printer = JsonPrinter::new(&mut out);
+        printer.print(&entry(path)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{{\"path\":\"tests/inputs/g.csv\",\"type\":\"file\",\"size\":2,\"mtime\":{}}}\n",
+                mtime
+            )
+        );
+    }
+}
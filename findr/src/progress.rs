@@ -0,0 +1,124 @@
+use std::{
+    cell::Cell,
+    fmt::Display,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+// 進捗の行を書き換える間隔: 端末への書き込みで探索が遅くならないようにする
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+// --progress: 探索したエントリ数、条件に一致した数、エラー数を数える
+// 探索のイテレータの各段階から更新するため、カウンタはCellで持ち&selfで更新する
+// 進捗と集計は標準エラーに出力し、標準出力の結果には混ぜない
+#[derive(Debug)]
+pub struct Progress {
+    enabled: bool,
+    live: bool, // 標準エラーが端末の場合のみ、進捗の行を書き換えて表示する
+    scanned: Cell<u64>,
+    matched: Cell<u64>,
+    errors: Cell<u64>,
+    drawn: Cell<bool>,             // 進捗の行が表示されたままか
+    last_draw: Cell<Option<Instant>>,
+}
+
+impl Progress {
+    pub fn new(enabled: bool, live: bool) -> Self {
+        Progress {
+            enabled,
+            live: enabled && live,
+            scanned: Cell::new(0),
+            matched: Cell::new(0),
+            errors: Cell::new(0),
+            drawn: Cell::new(false),
+            last_draw: Cell::new(None),
+        }
+    }
+
+    pub fn scanned(&self) {
+        self.scanned.set(self.scanned.get() + 1);
+        self.tick();
+    }
+
+    // 一致したエントリを出力する前に呼ぶ: 端末上で結果が進捗の行に続かないよう、進捗の行を消す
+    pub fn matched(&self) {
+        self.matched.set(self.matched.get() + 1);
+        self.clear();
+    }
+
+    // エラーを出力して数える: 進捗を表示しない場合も出力する
+    pub fn error(&self, message: impl Display) {
+        self.clear();
+        eprintln!("{}", message);
+        self.add_errors(1);
+    }
+
+    // エラーの出力を呼び出し側で済ませた場合に数だけ加える
+    pub fn add_errors(&self, count: u64) {
+        self.errors.set(self.errors.get() + count);
+    }
+
+    pub fn status(&self) -> String {
+        format!(
+            "{} scanned, {} matched, {} errors",
+            self.scanned.get(),
+            self.matched.get(),
+            self.errors.get()
+        )
+    }
+
+    // 進捗の行を消してから集計を出力する
+    pub fn finish(&self) {
+        if self.enabled {
+            self.clear();
+            eprintln!("{}", self.status());
+        }
+    }
+
+    // 前回の表示から一定時間が経った場合のみ書き換える
+    fn tick(&self) {
+        if !self.live {
+            return;
+        }
+        let now = Instant::now();
+        if self.last_draw.get().is_some_and(|last| now - last < REDRAW_INTERVAL) {
+            return;
+        }
+        self.last_draw.set(Some(now));
+        let mut err = io::stderr().lock();
+        let _ = write!(err, "\r\x1b[K{}", self.status()).and_then(|_| err.flush()); // 進捗の表示に失敗しても探索は続ける
+        self.drawn.set(true);
+    }
+
+    pub fn clear(&self) {
+        if self.drawn.replace(false) {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::Progress;
+
+    #[test]
+    fn test_status() {
+        let progress = Progress::new(true, false);
+        assert_eq!(progress.status(), "0 scanned, 0 matched, 0 errors");
+        for _ in 0..3 {
+            progress.scanned();
+        }
+        progress.matched();
+        progress.add_errors(2);
+        assert_eq!(progress.status(), "3 scanned, 1 matched, 2 errors");
+        // 端末でなければ進捗の行は表示しない
+        assert!(!progress.drawn.get());
+
+        // 無効な場合も数えるが、進捗の行は表示しない
+        let progress = Progress::new(false, true);
+        progress.scanned();
+        assert!(!progress.live && !progress.drawn.get());
+        assert_eq!(progress.status(), "1 scanned, 0 matched, 0 errors");
+    }
+}
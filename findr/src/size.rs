@@ -0,0 +1,86 @@
+use std::{cmp::Ordering, str::FromStr};
+
+// ファイルサイズの条件: find の -size と同様に単位で切り上げたサイズを比較する
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SizeSpec {
+    size: u64,
+    unit: u64,
+    cmp: Ordering, // Greater: +N, Less: -N, Equal: N
+}
+
+impl SizeSpec {
+    pub fn matches(&self, len: u64) -> bool {
+        len.div_ceil(self.unit).cmp(&self.size) == self.cmp
+    }
+}
+
+impl FromStr for SizeSpec {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid --size \"{}\"", val);
+        let (cmp, spec) = match val.chars().next() {
+            Some('+') => (Ordering::Greater, &val[1..]),
+            Some('-') => (Ordering::Less, &val[1..]),
+            _ => (Ordering::Equal, val),
+        };
+        // 末尾の単位: 省略時は512バイトのブロック単位
+        let (num, unit) = match spec.chars().last() {
+            Some('c') => (&spec[..spec.len() - 1], 1),
+            Some('b') => (&spec[..spec.len() - 1], 512),
+            Some('k') => (&spec[..spec.len() - 1], 1024),
+            Some('M') => (&spec[..spec.len() - 1], 1024 * 1024),
+            Some('G') => (&spec[..spec.len() - 1], 1024 * 1024 * 1024),
+            _ => (spec, 512),
+        };
+        if !num.chars().all(|c| c.is_ascii_digit()) {
+            return Err(err());
+        }
+        let size = num.parse().map_err(|_| err())?;
+        Ok(SizeSpec { size, unit, cmp })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SizeSpec;
+    use std::cmp::Ordering;
+
+    fn spec(size: u64, unit: u64, cmp: Ordering) -> SizeSpec {
+        SizeSpec { size, unit, cmp }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("10c".parse(), Ok(spec(10, 1, Ordering::Equal)));
+        assert_eq!("+1k".parse(), Ok(spec(1, 1024, Ordering::Greater)));
+        assert_eq!("-2M".parse(), Ok(spec(2, 1024 * 1024, Ordering::Less)));
+        assert_eq!("3".parse(), Ok(spec(3, 512, Ordering::Equal)));
+        assert_eq!("1G".parse(), Ok(spec(1, 1024 * 1024 * 1024, Ordering::Equal)));
+
+        let res = "10x".parse::<SizeSpec>();
+        assert_eq!(res, Err("Invalid --size \"10x\"".to_string()));
+        assert!("".parse::<SizeSpec>().is_err());
+        assert!("k".parse::<SizeSpec>().is_err());
+        assert!("+-1".parse::<SizeSpec>().is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let exact = spec(10, 1, Ordering::Equal);
+        assert!(exact.matches(10));
+        assert!(!exact.matches(11));
+
+        // 単位で切り上げて比較する: 1バイトでも1kとみなす
+        let kib = spec(1, 1024, Ordering::Equal);
+        assert!(kib.matches(1));
+        assert!(kib.matches(1024));
+        assert!(!kib.matches(1025));
+        assert!(!kib.matches(0));
+
+        assert!(spec(1, 1024, Ordering::Greater).matches(1025));
+        assert!(!spec(1, 1024, Ordering::Greater).matches(1024));
+        assert!(spec(1, 1024, Ordering::Less).matches(0));
+        assert!(!spec(1, 1024, Ordering::Less).matches(1));
+    }
+}
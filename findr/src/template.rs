@@ -0,0 +1,302 @@
+use std::{
+    fs::Metadata,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{FileTypeExt, MetadataExt},
+    },
+    str::FromStr,
+};
+
+use chrono::{DateTime, Local, TimeZone};
+use users::{get_group_by_gid, get_user_by_uid};
+
+use crate::{walk::Entry, MyResult};
+
+// 時刻の指示子で参照するタイムスタンプ
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum TimeField {
+    Accessed, // %A
+    Changed,  // %C
+    Modified, // %T
+}
+
+// 1エントリ分の出力に埋め込む値
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Directive {
+    Path,                  // %p: パス
+    Name,                  // %f: 先頭のディレクトリを除いた名前
+    Dir,                   // %h: 先頭のディレクトリ
+    Size,                  // %s: バイト数
+    User,                  // %u: 所有者名(不明な場合はuid)
+    Uid,                   // %U
+    Group,                 // %g: グループ名(不明な場合はgid)
+    Gid,                   // %G
+    Mode,                  // %m: パーミッションの8進数表記
+    Type,                  // %y: 種類を表す1文字
+    Time(TimeField, char), // %Tk, %Ak, %Ck: kはタイムスタンプの表示部分
+}
+
+impl Directive {
+    // パス以外の情報はメタデータから取得する
+    fn needs_metadata(&self) -> bool {
+        !matches!(self, Directive::Path | Directive::Name | Directive::Dir | Directive::Type)
+    }
+
+    fn render(&self, entry: &Entry, metadata: Option<&Metadata>, buf: &mut Vec<u8>) {
+        let text = match (self, metadata) {
+            (Directive::Path, _) => return buf.extend(entry.path().as_os_str().as_bytes()),
+            (Directive::Name, _) => return buf.extend(entry.file_name().as_bytes()),
+            (Directive::Dir, _) => {
+                // 起点のパスなどディレクトリを含まない場合は"."
+                match entry.path().parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    Some(dir) => return buf.extend(dir.as_os_str().as_bytes()),
+                    None => ".".to_string(),
+                }
+            },
+            (Directive::Type, _) => file_type_char(entry).to_string(),
+            (Directive::Size, Some(meta)) => meta.len().to_string(),
+            (Directive::User, Some(meta)) => get_user_by_uid(meta.uid())
+                .map_or_else(|| meta.uid().to_string(), |user| user.name().to_string_lossy().into_owned()),
+            (Directive::Uid, Some(meta)) => meta.uid().to_string(),
+            (Directive::Group, Some(meta)) => get_group_by_gid(meta.gid())
+                .map_or_else(|| meta.gid().to_string(), |group| group.name().to_string_lossy().into_owned()),
+            (Directive::Gid, Some(meta)) => meta.gid().to_string(),
+            (Directive::Mode, Some(meta)) => format!("{:o}", meta.mode() & 0o7777),
+            (Directive::Time(field, part), Some(meta)) => {
+                let (secs, nsecs) = match field {
+                    TimeField::Accessed => (meta.atime(), meta.atime_nsec()),
+                    TimeField::Changed => (meta.ctime(), meta.ctime_nsec()),
+                    TimeField::Modified => (meta.mtime(), meta.mtime_nsec()),
+                };
+                format_time(secs, nsecs, *part)
+            },
+            (_, None) => unreachable!("metadata is required"),
+        };
+        buf.extend(text.as_bytes());
+    }
+}
+
+// --printfの書式: 文字列と指示子の並び
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Piece {
+    Literal(Vec<u8>),
+    Directive(Directive),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    // 1エントリ分の出力: メタデータは必要な指示子がある場合のみ1回だけ取得する
+    pub fn render(&self, entry: &Entry) -> MyResult<Vec<u8>> {
+        let mut metadata = None;
+        let mut buf = vec![];
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(bytes) => buf.extend(bytes),
+                Piece::Directive(directive) => {
+                    if directive.needs_metadata() && metadata.is_none() {
+                        metadata = Some(entry.metadata()?);
+                    }
+                    directive.render(entry, metadata.as_ref(), &mut buf);
+                },
+            }
+        }
+        Ok(buf)
+    }
+}
+
+// タイムスタンプの表示部分: %の後に続く文字としてstrftimeの書式にそのまま渡す
+const TIME_PARTS: &str = "aAbBcdDHIjklmMprSTUwWxXyYZ";
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut chars = val.chars();
+        while let Some(c) = chars.next() {
+            let directive = match c {
+                '\\' => {
+                    literal.push(match chars.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('0') => '\0',
+                        Some('\\') => '\\',
+                        Some(c) => return Err(format!("Invalid --printf escape \"\\{}\"", c)),
+                        None => return Err(format!("Invalid --printf \"{}\"", val)),
+                    });
+                    continue;
+                },
+                '%' => match chars.next() {
+                    Some('%') => {
+                        literal.push('%');
+                        continue;
+                    },
+                    Some('p') => Directive::Path,
+                    Some('f') => Directive::Name,
+                    Some('h') => Directive::Dir,
+                    Some('s') => Directive::Size,
+                    Some('u') => Directive::User,
+                    Some('U') => Directive::Uid,
+                    Some('g') => Directive::Group,
+                    Some('G') => Directive::Gid,
+                    Some('m') => Directive::Mode,
+                    Some('y') => Directive::Type,
+                    Some(c @ ('A' | 'C' | 'T')) => {
+                        let field = match c {
+                            'A' => TimeField::Accessed,
+                            'C' => TimeField::Changed,
+                            _ => TimeField::Modified,
+                        };
+                        match chars.next() {
+                            Some(part) if part == '@' || part == '+' || TIME_PARTS.contains(part) => {
+                                Directive::Time(field, part)
+                            },
+                            Some(part) => {
+                                return Err(format!("Invalid --printf directive \"%{}{}\"", c, part))
+                            },
+                            None => return Err(format!("Invalid --printf \"{}\"", val)),
+                        }
+                    },
+                    Some(c) => return Err(format!("Invalid --printf directive \"%{}\"", c)),
+                    None => return Err(format!("Invalid --printf \"{}\"", val)),
+                },
+                c => {
+                    literal.push(c);
+                    continue;
+                },
+            };
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(std::mem::take(&mut literal).into_bytes()));
+            }
+            pieces.push(Piece::Directive(directive));
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal.into_bytes()));
+        }
+        Ok(Template { pieces })
+    }
+}
+
+// findの-printfと同じ1文字の種類: リンクを辿った場合はリンク先の種類
+fn file_type_char(entry: &Entry) -> char {
+    let file_type = entry.file_type();
+    if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_dir() {
+        'd'
+    } else if file_type.is_file() {
+        'f'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_block_device() {
+        'b'
+    } else {
+        'U'
+    }
+}
+
+// @はUNIX時間(秒)、+は日付と時刻を+で繋いだ形式、それ以外はローカル時刻の該当部分
+fn format_time(secs: i64, nsecs: i64, part: char) -> String {
+    if part == '@' {
+        return secs.to_string();
+    }
+    let time = Local
+        .timestamp_opt(secs, nsecs as u32)
+        .single()
+        .unwrap_or_else(|| DateTime::from(DateTime::UNIX_EPOCH));
+    match part {
+        '+' => time.format("%Y-%m-%d+%H:%M:%S").to_string(),
+        _ => time.format(&format!("%{}", part)).to_string(),
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{Directive, Piece, Template, TimeField};
+    use crate::walk::Entry;
+    use std::os::unix::fs::MetadataExt;
+    use walkdir::WalkDir;
+
+    fn entry(path: &str) -> Entry {
+        WalkDir::new(path).into_iter().next().unwrap().unwrap().into()
+    }
+
+    fn render(template: &str, path: &str) -> String {
+        let template: Template = template.parse().unwrap();
+        String::from_utf8(template.render(&entry(path)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "%p %TY-%Tm\\n".parse(),
+            Ok(Template {
+                pieces: vec![
+                    Piece::Directive(Directive::Path),
+                    Piece::Literal(b" ".to_vec()),
+                    Piece::Directive(Directive::Time(TimeField::Modified, 'Y')),
+                    Piece::Literal(b"-".to_vec()),
+                    Piece::Directive(Directive::Time(TimeField::Modified, 'm')),
+                    Piece::Literal(b"\n".to_vec()),
+                ]
+            })
+        );
+        assert_eq!(
+            "100%%\\t\\0".parse(),
+            Ok(Template { pieces: vec![Piece::Literal(b"100%\t\0".to_vec())] })
+        );
+        assert_eq!("".parse(), Ok(Template { pieces: vec![] }));
+        assert_eq!(
+            "%z".parse::<Template>(),
+            Err("Invalid --printf directive \"%z\"".to_string())
+        );
+        assert_eq!(
+            "%Tq".parse::<Template>(),
+            Err("Invalid --printf directive \"%Tq\"".to_string())
+        );
+        assert_eq!(
+            "\\q".parse::<Template>(),
+            Err("Invalid --printf escape \"\\q\"".to_string())
+        );
+        assert_eq!("%p%".parse::<Template>(), Err("Invalid --printf \"%p%\"".to_string()));
+        assert_eq!("%T".parse::<Template>(), Err("Invalid --printf \"%T\"".to_string()));
+    }
+
+    #[test]
+    fn test_render() {
+        let path = "tests/inputs/a/a.txt";
+        let metadata = std::fs::metadata(path).unwrap();
+        assert_eq!(render("%p|%f|%h|%s|%y\\n", path), "tests/inputs/a/a.txt|a.txt|tests/inputs/a|2|f\n");
+        assert_eq!(render("%f %h %y", "tests/inputs"), "inputs tests d");
+        assert_eq!(render("%h", "."), ".");
+        assert_eq!(
+            render("%m %U %G %T@", path),
+            format!(
+                "{:o} {} {} {}",
+                metadata.mode() & 0o7777,
+                metadata.uid(),
+                metadata.gid(),
+                metadata.mtime()
+            )
+        );
+        // リンクを辿らない場合はリンク自体の情報
+        let link = WalkDir::new("tests/inputs/d")
+            .into_iter()
+            .filter_map(Result::ok)
+            .find(|entry| entry.file_name() == "b.csv")
+            .unwrap()
+            .into();
+        let template: Template = "%y %s".parse().unwrap();
+        assert_eq!(template.render(&link).unwrap(), b"l 10");
+    }
+}
@@ -0,0 +1,244 @@
+use std::{
+    ffi::OsStr,
+    fs::{self, FileType, Metadata},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use ignore::WalkBuilder;
+use walkdir::WalkDir;
+
+use crate::MyResult;
+
+// 探索で見つかったエントリ: walkdirとignoreのどちらで探索しても同じように扱う
+#[derive(Debug, Clone)]
+pub struct Entry {
+    path: PathBuf,
+    file_type: FileType,
+    path_is_symlink: bool,
+    follow: bool, // シンボリックリンクを辿ってメタデータを取得するか
+}
+
+impl Entry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // 起点のパスが"."などの場合はパス全体を名前とする
+    pub fn file_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    pub fn path_is_symlink(&self) -> bool {
+        self.path_is_symlink
+    }
+
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        if self.follow {
+            fs::metadata(&self.path)
+        } else {
+            fs::symlink_metadata(&self.path)
+        }
+    }
+
+    fn from_ignore(entry: ignore::DirEntry) -> MyResult<Self> {
+        // 標準入力以外のエントリは必ず種類を持つ
+        let file_type = entry
+            .file_type()
+            .ok_or_else(|| format!("{}: unknown file type", entry.path().display()))?;
+        Ok(Entry {
+            file_type,
+            path_is_symlink: entry.path_is_symlink(),
+            follow: entry.path_is_symlink() && !file_type.is_symlink(),
+            path: entry.into_path(),
+        })
+    }
+}
+
+impl From<walkdir::DirEntry> for Entry {
+    fn from(entry: walkdir::DirEntry) -> Self {
+        // walkdirはリンクを辿る設定の場合のみfile_typeがリンク先の種類になる
+        let follow = entry.path_is_symlink() && !entry.file_type().is_symlink();
+        Entry {
+            file_type: entry.file_type(),
+            path_is_symlink: entry.path_is_symlink(),
+            follow,
+            path: entry.into_path(),
+        }
+    }
+}
+
+// 探索方法の設定
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WalkOptions {
+    pub min_depth: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub follow_links: bool,
+    pub respect_gitignore: bool, // .gitignoreに記載されたエントリを除外する
+    pub contents_first: bool,    // ディレクトリの中身をディレクトリ自体より先に返す: 削除する場合に使う
+}
+
+// 探索の実装: --respect-gitignore指定時のみignoreクレートを使う
+pub enum Walker {
+    Dir(walkdir::IntoIter, bool),
+    Ignore(ignore::Walk, Option<usize>),
+    Buffered(std::vec::IntoIter<MyResult<Entry>>),
+}
+
+impl Walker {
+    pub fn new(path: &str, options: &WalkOptions) -> Self {
+        if options.respect_gitignore {
+            let walk = WalkBuilder::new(path)
+                .standard_filters(false) // 隠しファイルや.ignoreは対象のまま
+                .git_ignore(true)
+                .git_exclude(true)
+                .parents(true)
+                .require_git(false) // gitリポジトリ外でも.gitignoreを読む
+                .follow_links(options.follow_links)
+                .max_depth(options.max_depth)
+                .filter_entry(|entry| entry.file_name() != ".git") // リポジトリの管理情報は探索しない
+                .build();
+            let walker = Walker::Ignore(walk, options.min_depth);
+            if options.contents_first {
+                // ignoreは中身を先に返せないため、全体を読んでから逆順にする: 親より子が先になる
+                let mut entries: Vec<_> = walker.collect();
+                entries.reverse();
+                return Walker::Buffered(entries.into_iter());
+            }
+            return walker;
+        }
+        let mut walker = WalkDir::new(path)
+            .follow_links(options.follow_links)
+            .contents_first(options.contents_first);
+        if let Some(depth) = options.min_depth {
+            walker = walker.min_depth(depth);
+        }
+        if let Some(depth) = options.max_depth {
+            walker = walker.max_depth(depth);
+        }
+        Walker::Dir(walker.into_iter(), options.follow_links)
+    }
+}
+
+impl Iterator for Walker {
+    type Item = MyResult<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Walker::Dir(iter, follow_links) => Some(match iter.next()? {
+                Ok(entry) => Ok(entry.into()),
+                // --follow指定時のリンク切れはエラーではなくリンク自体のエントリとして扱う
+                Err(e) => match dangling_link_entry(&e, *follow_links) {
+                    Some(entry) => Ok(entry.into()),
+                    None => Err(e.into()),
+                },
+            }),
+            Walker::Ignore(iter, min_depth) => loop {
+                // ignoreは最小の深さを指定できないため、浅いエントリを読み飛ばす
+                match iter.next()? {
+                    Ok(entry) if entry.depth() < min_depth.unwrap_or(0) => continue,
+                    Ok(entry) => return Some(Entry::from_ignore(entry)),
+                    Err(e) => return Some(Err(e.into())),
+                }
+            },
+            Walker::Buffered(iter) => iter.next(),
+        }
+    }
+}
+
+// リンク先が存在しないシンボリックリンクの場合、リンクを辿らずにリンク自体のエントリを取得する
+fn dangling_link_entry(err: &walkdir::Error, follow_links: bool) -> Option<walkdir::DirEntry> {
+    if !follow_links || err.io_error()?.kind() != ErrorKind::NotFound {
+        return None;
+    }
+    let path = err.path()?;
+    if !path.symlink_metadata().ok()?.file_type().is_symlink() {
+        return None;
+    }
+    WalkDir::new(path)
+        .follow_root_links(false)
+        .max_depth(0)
+        .into_iter()
+        .next()?
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{WalkOptions, Walker};
+    use std::{fs, path::PathBuf};
+
+    // 探索結果のパスを起点からの相対パスで並べて返す
+    fn walk(root: &PathBuf, options: &WalkOptions) -> Vec<String> {
+        let mut paths: Vec<_> = Walker::new(root.to_str().unwrap(), options)
+            .map(|entry| {
+                let entry = entry.unwrap();
+                entry.path().strip_prefix(root).unwrap().display().to_string()
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_walker() {
+        let root = std::env::temp_dir().join(format!("findr-walk-{}", std::process::id()));
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".gitignore"), "build/\n*.log\n").unwrap();
+        fs::write(root.join("main.rs"), "").unwrap();
+        fs::write(root.join("debug.log"), "").unwrap();
+        fs::write(root.join("build/out.o"), "").unwrap();
+        fs::write(root.join(".git/HEAD"), "").unwrap();
+
+        let options = WalkOptions::default();
+        assert_eq!(
+            walk(&root, &options),
+            ["", ".git", ".git/HEAD", ".gitignore", "build", "build/out.o", "debug.log", "main.rs"]
+        );
+
+        // 無視されたエントリと.gitは除外し、隠しファイルは残す
+        let options = WalkOptions {
+            respect_gitignore: true,
+            ..Default::default()
+        };
+        assert_eq!(walk(&root, &options), ["", ".gitignore", "main.rs"]);
+
+        // 深さの指定はどちらの探索でも同じ
+        let options = WalkOptions {
+            min_depth: Some(1),
+            max_depth: Some(1),
+            respect_gitignore: true,
+            ..Default::default()
+        };
+        assert_eq!(walk(&root, &options), [".gitignore", "main.rs"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_walker_contents_first() {
+        let root = std::env::temp_dir().join(format!("findr-contents-first-{}", std::process::id()));
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/c"), "").unwrap();
+
+        // どちらの探索でもディレクトリは中身より後に現れる
+        for respect_gitignore in [false, true] {
+            let options = WalkOptions {
+                respect_gitignore,
+                contents_first: true,
+                ..Default::default()
+            };
+            let paths: Vec<_> = Walker::new(root.to_str().unwrap(), &options)
+                .map(|entry| entry.unwrap().path().strip_prefix(&root).unwrap().display().to_string())
+                .collect();
+            assert_eq!(paths, ["a/b/c", "a/b", "a", ""]);
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -3,7 +3,7 @@ use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
 use std::{borrow::Cow, fs, path::Path};
 
-type TestResult = Result<(), Box<dyn std::error::Error>>;
+type TestResult<T = ()> = Result<T, Box<dyn std::error::Error>>;
 
 const PRG: &str = "findr";
 
@@ -39,7 +39,7 @@ fn skips_bad_dir() -> TestResult {
 #[test]
 fn dies_bad_name() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["--name", "*.csv"])
+        .args(["--name", "*.csv"])
         .assert()
         .failure()
         .stderr(predicate::str::contains("Invalid --name \"*.csv\""));
@@ -51,7 +51,7 @@ fn dies_bad_name() -> TestResult {
 fn dies_bad_type() -> TestResult {
     let expected = "error: 'x' isn't a valid value for '--type <TYPE>...'";
     Command::cargo_bin(PRG)?
-        .args(&["--type", "x"])
+        .args(["--type", "x"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -67,7 +67,7 @@ fn format_file_name(expected_file: &str) -> Cow<str> {
 
 // --------------------------------------------------
 #[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Borrowed(expected_file)
     expected_file.into()
 }
@@ -295,7 +295,7 @@ fn unreadable_dir() -> TestResult {
     //permissions.set_mode(0o000);
 
     std::process::Command::new("chmod")
-        .args(&["000", dirname])
+        .args(["000", dirname])
         .status()
         .expect("failed");
 
@@ -316,3 +316,614 @@ fn unreadable_dir() -> TestResult {
     assert!(stderr.contains("cant-touch-this: Permission denied"));
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_max_depth() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--max-depth", "foo"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --max-depth \"foo\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_depth_1() -> TestResult {
+    run(
+        &["tests/inputs", "--max-depth", "1"],
+        "tests/expected/max_depth_1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn min_depth_3() -> TestResult {
+    run(
+        &["tests/inputs", "--min-depth", "3"],
+        "tests/expected/min_depth_3.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn min_max_depth_2() -> TestResult {
+    run(
+        &["tests/inputs", "--min-depth", "2", "--max-depth", "2"],
+        "tests/expected/min_max_depth_2.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn type_l_follow() -> TestResult {
+    run(
+        &["tests/inputs", "-t", "l", "--follow"],
+        "tests/expected/type_l_follow.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn type_f_follow() -> TestResult {
+    run(
+        &["tests/inputs", "-t", "f", "-L"],
+        "tests/expected/type_f_follow.txt",
+    )
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn gen_tmp_dir(prefix: &str) -> TestResult<std::path::PathBuf> {
+    let dir = std::env::temp_dir().join(format!("{}-{}", prefix, gen_bad_file()));
+    fs::create_dir(&dir)?;
+    Ok(dir)
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+fn run_sorted(args: &[&str]) -> TestResult<Vec<String>> {
+    let cmd = Command::cargo_bin(PRG)?.args(args).assert().success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut lines: Vec<String> = stdout
+        .split("\n")
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect();
+    lines.sort();
+    Ok(lines)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn empty() -> TestResult {
+    let dir = gen_tmp_dir("findr-empty")?;
+    fs::create_dir(dir.join("empty_dir"))?;
+    fs::create_dir(dir.join("full_dir"))?;
+    fs::write(dir.join("full_dir/full.txt"), "a\n")?;
+    fs::write(dir.join("empty.txt"), "")?;
+
+    let root = dir.to_string_lossy().to_string();
+    let lines = run_sorted(&[&root, "--empty"]);
+    fs::remove_dir_all(&dir)?;
+
+    assert_eq!(
+        lines?,
+        vec![format!("{}/empty.txt", root), format!("{}/empty_dir", root)]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn dies_bad_perm() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--perm", "u+q"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --perm \"u+q\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn perm() -> TestResult {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = gen_tmp_dir("findr-perm")?;
+    for (name, mode) in [("a.txt", 0o644), ("b.txt", 0o664), ("c.sh", 0o755)] {
+        let path = dir.join(name);
+        fs::write(&path, "a\n")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode))?;
+    }
+
+    let root = dir.to_string_lossy().to_string();
+    let file = |name: &str| format!("{}/{}", root, name);
+    let exact = run_sorted(&[&root, "-t", "f", "--perm", "644"]);
+    let symbolic = run_sorted(&[&root, "-t", "f", "--perm", "u=rw,go=r"]);
+    let all = run_sorted(&[&root, "-t", "f", "--perm", "-g+w"]);
+    let any = run_sorted(&[&root, "-t", "f", "--perm", "/111"]);
+    fs::remove_dir_all(&dir)?;
+
+    assert_eq!(exact?, vec![file("a.txt")]);
+    assert_eq!(symbolic?, vec![file("a.txt")]);
+    assert_eq!(all?, vec![file("b.txt")]);
+    assert_eq!(any?, vec![file("c.sh")]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn dies_bad_user() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--user", &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!("Invalid --user \"{}\"", bad)));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn user() -> TestResult {
+    use std::os::unix::fs::MetadataExt;
+
+    // 入力ファイルは全て同じユーザーが所有している
+    let uid = fs::metadata("tests/inputs")?.uid();
+    run(
+        &["tests/inputs", "--user", &uid.to_string()],
+        "tests/expected/path1.txt",
+    )?;
+
+    let lines = run_sorted(&["tests/inputs", "--user", &(uid + 1).to_string()])?;
+    assert!(lines.is_empty());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_size() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--size", "1x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --size \"1x\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_expression() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--or", "-n", "a"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid expression near \"--or\""));
+
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-n", "a", "--not"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid expression: missing predicate"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn name_csv_or_mp3() -> TestResult {
+    run(
+        &["tests/inputs", "-n", "csv", "--or", "-n", "mp3"],
+        "tests/expected/name_csv_or_mp3.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn type_f_not_name_csv() -> TestResult {
+    run(
+        &["tests/inputs", "-t", "f", "--not", "-n", "csv"],
+        "tests/expected/type_f_not_name_csv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn type_f_size_or_type_d() -> TestResult {
+    run(
+        &[
+            "tests/inputs", "-t", "f", "--and", "--size", "2c", "--or", "-t",
+            "d", "--not", "-n", "a",
+        ],
+        "tests/expected/type_f_size_or_type_d.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_print0_json() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--print0", "--json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn print0() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-n", "csv", "--print0"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert!(stdout.ends_with('\0'));
+    assert!(!stdout.contains('\n'));
+    let mut paths: Vec<&str> = stdout.split_terminator('\0').collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        ["tests/inputs/a/b/b.csv", "tests/inputs/d/b.csv", "tests/inputs/g.csv"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn json() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a", "-n", "csv|^b$", "--json"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut records = stdout
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    records.sort_by_key(|record| record["path"].to_string());
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["path"], "tests/inputs/a/b");
+    assert_eq!(records[0]["type"], "dir");
+    assert_eq!(records[1]["path"], "tests/inputs/a/b/b.csv");
+    assert_eq!(records[1]["type"], "file");
+    assert_eq!(records[1]["size"], 2);
+    assert!(records[1]["mtime"].is_i64());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn respect_gitignore() -> TestResult {
+    // 無視パターンに一致するファイルはリポジトリに含められないため、一時ディレクトリに作成する
+    let root = std::env::temp_dir().join(format!("findr-{}", gen_bad_file()));
+    fs::create_dir_all(root.join("target/debug"))?;
+    fs::create_dir_all(root.join("src"))?;
+    fs::write(root.join(".gitignore"), "/target\n*.swp\n")?;
+    fs::write(root.join("src/main.rs"), "")?;
+    fs::write(root.join("src/.main.rs.swp"), "")?;
+    fs::write(root.join("target/debug/findr"), "")?;
+    let dir = root.to_string_lossy().to_string();
+
+    let cmd = Command::cargo_bin(PRG)?
+        .args([&dir, "-t", "f", "--respect-gitignore"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut lines: Vec<&str> = stdout.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [format!("{}/.gitignore", dir), format!("{}/src/main.rs", dir)]
+    );
+
+    // 指定しなければ全て出力する
+    let cmd = Command::cargo_bin(PRG)?.args([&dir, "-t", "f"]).assert().success();
+    assert_eq!(cmd.get_output().stdout.iter().filter(|&&b| b == b'\n').count(), 4);
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn printf() -> TestResult {
+    let lines = run_sorted(&["tests/inputs/a", "--printf", "%y %f %h %s\\n", "-n", "csv|mp3|^b$", "-t", "f"])?;
+    assert_eq!(
+        lines,
+        [
+            "f b.csv tests/inputs/a/b 2",
+            "f c.mp3 tests/inputs/a/b/c 2",
+        ]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn printf_time() -> TestResult {
+    let dir = gen_tmp_dir("findr-printf")?;
+    let path = dir.join("old.txt");
+    fs::write(&path, "")?;
+    // 2001-02-03 04:05:06 UTC
+    let mtime = std::time::UNIX_EPOCH + std::time::Duration::from_secs(981173106);
+    fs::File::options().write(true).open(&path)?.set_modified(mtime)?;
+
+    let cmd = Command::cargo_bin(PRG)?
+        .env("TZ", "UTC")
+        .args([
+            path.to_str().unwrap(),
+            "--printf",
+            "%TY-%Tm-%Td %TH:%TM:%TS|%T+|%T@|%%\\0",
+        ])
+        .assert()
+        .success();
+    fs::remove_dir_all(&dir)?;
+    cmd.stdout("2001-02-03 04:05:06|2001-02-03+04:05:06|981173106|%\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn dies_bad_printf() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--printf", "%p %z"])
+        .assert()
+        .failure()
+        .stderr("Invalid --printf directive \"%z\"\n");
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--printf", "%p", "--print0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_delete_without_confirmation() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--delete requires --yes or --dry-run"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn delete() -> TestResult {
+    let dir = gen_tmp_dir("findr-delete")?;
+    fs::create_dir_all(dir.join("a/b/c"))?;
+    fs::create_dir(dir.join("keep"))?;
+    fs::write(dir.join("keep/keep.txt"), "a\n")?;
+    fs::write(dir.join("a/b/empty.txt"), "")?;
+    let root = dir.to_string_lossy().to_string();
+
+    // 削除対象は中身が先に並び、何も削除しない
+    let cmd = Command::cargo_bin(PRG)?
+        .args([&root, "--empty", "--delete", "--dry-run"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<_> = stdout.lines().collect();
+    assert!(lines.contains(&format!("{}/a/b/c", root).as_str()));
+    assert!(lines.contains(&format!("{}/a/b/empty.txt", root).as_str()));
+    assert!(dir.join("a/b/c").exists());
+
+    // 中身を削除した後のディレクトリも空として削除される
+    Command::cargo_bin(PRG)?
+        .args([&root, "--min-depth", "1", "--empty", "--delete", "--yes"])
+        .assert()
+        .success()
+        .stdout("");
+    let remaining = run_sorted(&[&root]);
+    fs::remove_dir_all(&dir)?;
+
+    assert_eq!(
+        remaining?,
+        vec![root.clone(), format!("{}/keep", root), format!("{}/keep/keep.txt", root)]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn delete_reports_errors() -> TestResult {
+    let dir = gen_tmp_dir("findr-delete-errors")?;
+    fs::create_dir(dir.join("full"))?;
+    fs::write(dir.join("full/full.txt"), "a\n")?;
+    fs::write(dir.join("gone.txt"), "")?;
+    let root = dir.to_string_lossy().to_string();
+
+    // 空でないディレクトリの削除に失敗しても残りの削除を続ける
+    let cmd = Command::cargo_bin(PRG)?
+        .args([&root, "-t", "d", "--name", "full", "--or", "--name", "gone", "--delete", "-y"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(cmd.get_output().stderr.clone())?;
+    let gone = dir.join("gone.txt").exists();
+    let full = dir.join("full").exists();
+    fs::remove_dir_all(&dir)?;
+
+    assert!(stderr.contains(&format!("{}/full: ", root)));
+    assert!(stderr.contains("1 entries could not be deleted"));
+    assert!(!gone);
+    assert!(full);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn delete_does_not_follow_links() -> TestResult {
+    let dir = gen_tmp_dir("findr-delete-links")?;
+    fs::create_dir_all(dir.join("walk"))?;
+    fs::create_dir(dir.join("target"))?;
+    fs::write(dir.join("target/precious.txt"), "a\n")?;
+    fs::write(dir.join("walk/old.txt"), "")?;
+    std::os::unix::fs::symlink("../target", dir.join("walk/link"))?;
+    let root = dir.join("walk").to_string_lossy().to_string();
+
+    // -Lとは併用できない
+    let follow = Command::cargo_bin(PRG)?
+        .args([&root, "-L", "-t", "f", "--delete", "--yes"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(follow.get_output().stderr.clone())?;
+    let kept_with_follow = dir.join("target/precious.txt").exists() && dir.join("walk/old.txt").exists();
+
+    // リンク先のディレクトリの中身は削除しない
+    Command::cargo_bin(PRG)?
+        .args([&root, "-t", "f", "--delete", "--yes"])
+        .assert()
+        .success();
+    let precious = dir.join("target/precious.txt").exists();
+    let old = dir.join("walk/old.txt").exists();
+    let link = dir.join("walk/link").exists();
+    fs::remove_dir_all(&dir)?;
+
+    assert!(stderr.contains("cannot be used with"));
+    assert!(kept_with_follow);
+    assert!(precious);
+    assert!(!old);
+    assert!(link);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn duplicates_inputs() -> TestResult {
+    // テスト用の入力ファイルは全て同じ内容
+    run(&["tests/inputs", "--duplicates"], "tests/expected/duplicates.txt")
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn duplicates() -> TestResult {
+    let dir = gen_tmp_dir("findr-duplicates")?;
+    fs::create_dir(dir.join("sub"))?;
+    fs::write(dir.join("a.txt"), "same\n")?;
+    fs::write(dir.join("sub/a.txt"), "same\n")?;
+    fs::write(dir.join("b.txt"), "diff\n")?;
+    fs::write(dir.join("c.log"), "a longer line\n")?;
+    fs::write(dir.join("sub/c.txt"), "a longer line\n")?;
+    fs::write(dir.join("empty1"), "")?;
+    fs::write(dir.join("empty2"), "")?;
+    let root = dir.to_string_lossy().to_string();
+
+    let all = Command::cargo_bin(PRG)?.args([&root, "--duplicates"]).assert().success();
+    let all = String::from_utf8(all.get_output().stdout.clone())?;
+    // 名前の条件で絞り込んだファイルの中から重複を探す
+    let txt = Command::cargo_bin(PRG)?
+        .args([&root, "--name", "[.]txt$", "--duplicates"])
+        .assert()
+        .success();
+    let txt = String::from_utf8(txt.get_output().stdout.clone())?;
+    fs::remove_dir_all(&dir)?;
+
+    assert_eq!(
+        all,
+        format!(
+            "{0}/c.log\n{0}/sub/c.txt\n\n{0}/a.txt\n{0}/sub/a.txt\n",
+            root
+        )
+    );
+    assert_eq!(txt, format!("{0}/a.txt\n{0}/sub/a.txt\n", root));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_duplicates_with_delete() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--duplicates", "--delete", "--yes"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn hash() -> TestResult {
+    let dir = gen_tmp_dir("findr-hash")?;
+    fs::create_dir(dir.join("sub"))?;
+    fs::write(dir.join("a.txt"), "abc")?;
+    fs::write(dir.join("sub/b.txt"), "abc")?;
+    let root = dir.to_string_lossy().to_string();
+
+    // ディレクトリは出力しない
+    let sha256 = run_sorted(&[&root, "--hash", "sha256"])?;
+    let md5 = run_sorted(&[&root, "--name", "^a", "--hash", "md5"])?;
+    fs::remove_dir_all(&dir)?;
+
+    let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    assert_eq!(
+        sha256,
+        [format!("{}  {}/a.txt", digest, root), format!("{}  {}/sub/b.txt", digest, root)]
+    );
+    assert_eq!(md5, [format!("900150983cd24fb0d6963f7d28e17f72  {}/a.txt", root)]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_hash() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--hash", "crc32"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --hash \"crc32\""));
+    Command::cargo_bin(PRG)?
+        .args(["--hash", "md5", "--json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn progress() -> TestResult {
+    let dir = gen_tmp_dir("findr-progress")?;
+    fs::create_dir(dir.join("sub"))?;
+    fs::write(dir.join("a.txt"), "")?;
+    fs::write(dir.join("sub/b.txt"), "")?;
+    let root = dir.to_string_lossy().to_string();
+    let bad = gen_bad_file();
+
+    // 端末でなければ集計のみを標準エラーに出力し、標準出力の結果は変わらない
+    let cmd = Command::cargo_bin(PRG)?
+        .args([&root, &bad, "-t", "f", "--progress"])
+        .assert()
+        .success();
+    let output = cmd.get_output();
+    let stdout = String::from_utf8(output.stdout.clone())?;
+    let stderr = String::from_utf8(output.stderr.clone())?;
+    fs::remove_dir_all(&dir)?;
+
+    let mut lines: Vec<_> = stdout.lines().collect();
+    lines.sort();
+    assert_eq!(lines, [format!("{}/a.txt", root), format!("{}/sub/b.txt", root)]);
+    assert!(stderr.contains(&format!("{}: ", bad)));
+    assert!(stderr.ends_with("4 scanned, 2 matched, 1 errors\n"));
+    assert!(!stderr.contains('\r'));
+    Ok(())
+}
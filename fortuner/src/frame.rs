@@ -0,0 +1,118 @@
+use std::str::FromStr;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::wrap::wrap;
+
+// Fortuneを囲む枠の種類
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BoxStyle {
+    Ascii,   // cowsayのような吹き出し
+    Unicode, // 罫線素片の枠
+    None,    // 枠を付けずにそのまま出力する
+}
+
+impl FromStr for BoxStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(BoxStyle::Ascii),
+            "unicode" => Ok(BoxStyle::Unicode),
+            "none" => Ok(BoxStyle::None),
+            _ => Err(format!("Invalid --box \"{}\"", s)),
+        }
+    }
+}
+
+// 枠の種類と、枠の内側の最大の表示幅
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub style: BoxStyle,
+    pub width: usize,
+}
+
+impl Frame {
+    // 本文を折り返して枠で囲む: 枠の幅は最も長い行に合わせる
+    pub fn decorate(&self, text: &str) -> String {
+        if self.style == BoxStyle::None {
+            return text.to_string();
+        }
+        let lines = wrap(text, self.width);
+        let width = lines.iter().map(|line| line.width()).max().unwrap_or(0);
+        let pad = |line: &str| format!("{}{}", line, " ".repeat(width - line.width()));
+        let mut framed = vec![];
+        match self.style {
+            BoxStyle::Ascii => {
+                framed.push(format!(" {}", "_".repeat(width + 2)));
+                let last = lines.len().saturating_sub(1);
+                for (i, line) in lines.iter().enumerate() {
+                    // 1行のみの場合は<>、複数行の場合は上下の角を丸めた吹き出しにする
+                    let (left, right) = match i {
+                        _ if lines.len() == 1 => ('<', '>'),
+                        0 => ('/', '\\'),
+                        _ if i == last => ('\\', '/'),
+                        _ => ('|', '|'),
+                    };
+                    framed.push(format!("{} {} {}", left, pad(line), right));
+                }
+                framed.push(format!(" {}", "-".repeat(width + 2)));
+            }
+            BoxStyle::Unicode => {
+                framed.push(format!("┌{}┐", "─".repeat(width + 2)));
+                for line in &lines {
+                    framed.push(format!("│ {} │", pad(line)));
+                }
+                framed.push(format!("└{}┘", "─".repeat(width + 2)));
+            }
+            BoxStyle::None => unreachable!(),
+        }
+        framed.join("\n")
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{BoxStyle, Frame};
+
+    fn frame(style: BoxStyle, width: usize) -> Frame {
+        Frame { style, width }
+    }
+
+    #[test]
+    fn test_parse_box_style() {
+        assert_eq!("ascii".parse::<BoxStyle>(), Ok(BoxStyle::Ascii));
+        assert_eq!("unicode".parse::<BoxStyle>(), Ok(BoxStyle::Unicode));
+        assert_eq!("none".parse::<BoxStyle>(), Ok(BoxStyle::None));
+        assert_eq!("round".parse::<BoxStyle>(), Err("Invalid --box \"round\"".to_string()));
+    }
+
+    #[test]
+    fn test_decorate_ascii() {
+        assert_eq!(frame(BoxStyle::Ascii, 40).decorate("Hello"), " _______\n< Hello >\n -------");
+        assert_eq!(
+            frame(BoxStyle::Ascii, 8).decorate("one two three four"),
+            " _________\n/ one two \\\n| three   |\n\\ four    /\n ---------"
+        );
+    }
+
+    #[test]
+    fn test_decorate_unicode() {
+        assert_eq!(
+            frame(BoxStyle::Unicode, 40).decorate("猫\nneko"),
+            "┌──────┐\n│ 猫   │\n│ neko │\n└──────┘"
+        );
+        // 長い単語は幅で区切る
+        assert_eq!(
+            frame(BoxStyle::Unicode, 4).decorate("abcdefgh"),
+            "┌──────┐\n│ abcd │\n│ efgh │\n└──────┘"
+        );
+    }
+
+    #[test]
+    fn test_decorate_none() {
+        let text = "a very long line that is not wrapped";
+        assert_eq!(frame(BoxStyle::None, 4).decorate(text), text);
+    }
+}
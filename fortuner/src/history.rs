@@ -0,0 +1,121 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::MyResult;
+
+// 最近表示したFortuneの履歴: 本文のハッシュ値を古い順に1行ずつ16進数で保存する
+// 開いている間はファイルを排他ロックし、同時に起動した別のシェルからの読み書きを待たせる
+#[derive(Debug)]
+pub struct History {
+    file: File,
+    hashes: Vec<u64>,
+}
+
+impl History {
+    pub fn open(path: &Path) -> MyResult<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false) // ロックを取得してから読み込むため、開く時点では切り詰めない
+            .open(path)?;
+        file.lock()?; // ファイルを閉じると解放される
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        // 壊れた行は読み飛ばす
+        let hashes = contents
+            .lines()
+            .filter_map(|line| u64::from_str_radix(line.trim(), 16).ok())
+            .collect();
+        Ok(History { file, hashes })
+    }
+
+    // 直近num件のうちに表示したFortuneか
+    pub fn is_recent(&self, text: &str, num: usize) -> bool {
+        let hash = hash(text);
+        self.hashes.iter().rev().take(num).any(|&recent| recent == hash)
+    }
+
+    // 表示したFortuneを追加し、直近keep件だけを残して書き戻す
+    pub fn record(mut self, text: &str, keep: usize) -> MyResult<()> {
+        self.hashes.push(hash(text));
+        let skip = self.hashes.len().saturating_sub(keep);
+        let contents: String = self.hashes[skip..]
+            .iter()
+            .map(|hash| format!("{:016x}\n", hash))
+            .collect();
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+}
+
+// 実行ごとに変わらないハッシュ値が必要なため、DefaultHasherではなくFNV-1aを使う
+fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// 履歴の保存先: XDG Base Directory仕様に従い、未設定の場合は~/.local/state
+pub fn default_path() -> MyResult<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME").filter(|dir| Path::new(dir).is_absolute()) {
+        Some(dir) => PathBuf::from(dir),
+        None => env::var_os("HOME")
+            .map(|home| Path::new(&home).join(".local/state"))
+            .ok_or("Cannot determine the history file: HOME is not set")?,
+    };
+    Ok(base.join("fortuner").join("history"))
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{hash, History};
+    use std::fs;
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(hash("ab"), hash("ba"));
+    }
+
+    #[test]
+    fn test_history() {
+        let path = std::env::temp_dir()
+            .join(format!("fortuner-history-{}", std::process::id()))
+            .join("history");
+
+        let history = History::open(&path).unwrap();
+        assert!(!history.is_recent("a", 2));
+        history.record("a", 2).unwrap();
+        History::open(&path).unwrap().record("b", 2).unwrap();
+
+        let history = History::open(&path).unwrap();
+        assert!(history.is_recent("a", 2));
+        assert!(history.is_recent("b", 1));
+        assert!(!history.is_recent("a", 1)); // 直近1件はbのみ
+        history.record("c", 2).unwrap();
+
+        // 古いものから捨てる
+        let history = History::open(&path).unwrap();
+        assert!(!history.is_recent("a", 2));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+        drop(history);
+
+        // 壊れた行は無視する
+        fs::write(&path, "xyz\n").unwrap();
+        assert!(History::open(&path).unwrap().hashes.is_empty());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
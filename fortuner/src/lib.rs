@@ -1,16 +1,58 @@
-use std::{error::Error, path::PathBuf, ffi::OsStr, fs::{metadata, File}, io::{BufReader, BufRead}};
+use std::{borrow::Borrow, collections::{BTreeMap, HashSet}, error::Error, fmt, path::{Path, PathBuf}, ffi::OsStr, fs::{self, canonicalize, metadata, File}, io::{self, BufReader, BufRead, Write}};
 
 use clap::{App, Arg};
-use rand::{rngs::StdRng, SeedableRng, seq::SliceRandom};
+use rand::{distributions::{Distribution, WeightedIndex}, rngs::StdRng, Rng, SeedableRng, seq::SliceRandom};
 use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
 use walkdir::WalkDir;
 
+mod frame;
+mod history;
+mod source;
+mod wrap;
+use frame::{BoxStyle, Frame};
+use history::History;
+use source::{split_weight, Source};
+
 type MyResult<T> = Result<T, Box<dyn Error>>; // エラートレイトを実装するオブジェクトは必ずBoxに格納: サイズ不明のため格納先のみを指定する
 
 #[derive(Debug)]
 struct Fortune {
     source: String,
     text: String,
+    author: Option<String>, // JSON/TOML形式のファイルのみ: 本文の後に出力する
+    tags: Vec<String>,      // --tagでの絞り込みに使う
+    bucket: usize,          // 読み込み元を指定した引数の番号: 重み付きの抽出で同じ引数のFortuneをまとめる
+    weight: Option<u32>,    // 読み込み元にSOURCE:WEIGHTで指定した重み
+}
+
+// 読み込み元を指定した引数の番号と、SOURCE:WEIGHTで指定した重み
+type Bucket = (usize, Option<u32>);
+
+impl fmt::Display for Fortune {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)?;
+        if let Some(author) = &self.author {
+            write!(f, "\n-- {}", author)?;
+        }
+        Ok(())
+    }
+}
+
+// JSON/TOML形式のファイルの1エントリ
+#[derive(Debug, Deserialize)]
+struct Entry {
+    text: String,
+    author: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+// TOML形式では[[fortune]]の配列として記載する
+#[derive(Debug, Deserialize)]
+struct TomlFile {
+    #[serde(default)]
+    fortune: Vec<Entry>,
 }
 
 #[derive(Debug)]
@@ -18,6 +60,65 @@ pub struct Config {
     sources: Vec<String>,
     pattern: Option<Regex>,
     seed: Option<u64>,
+    output: OutputPolicy,
+    find: FindOptions,
+    interactive: bool,
+    delimiter: String,
+    tags: Vec<String>,
+    no_repeat: Option<usize>, // 直近N回に表示したFortuneを避ける
+    history: Option<PathBuf>, // 履歴ファイル: 未指定の場合は既定の場所
+}
+
+// ソースファイルの探索方法
+#[derive(Debug, Default)]
+struct FindOptions {
+    include_hidden: bool,      // '.'で始まるファイルやディレクトリも対象にするか
+    follow_links: bool,        // シンボリックリンク先のディレクトリも探索するか
+    exclude_exts: Vec<String>, // .dat以外に除外する拡張子
+}
+
+// 対話モードの状態: 未表示のFortuneだけを保持して、同じセッション内での重複を避ける
+struct Session<'a> {
+    remaining: Vec<&'a Fortune>,
+    rng: StdRng,
+}
+
+impl<'a> Session<'a> {
+    fn new(fortunes: Vec<&'a Fortune>, seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(val) => StdRng::seed_from_u64(val),
+            None => StdRng::from_entropy(),
+        };
+        Session {
+            remaining: fortunes,
+            rng,
+        }
+    }
+
+    // 未表示のFortuneからpick_fortuneで1つ選んで取り除く: 全て表示済みならNone
+    fn next(&mut self) -> Option<&'a Fortune> {
+        let picked = *pick_fortune(&self.remaining, Some(self.rng.gen()))?;
+        self.remaining.retain(|fortune| !std::ptr::eq(*fortune, picked));
+        Some(picked)
+    }
+}
+
+// ソース名(ファイル名)の出力方針
+#[derive(Debug, Clone, Copy)]
+struct OutputPolicy {
+    show_source: bool,       // ランダム抽出時にもソース名を出力するか
+    sources_to_stdout: bool, // ソース名を標準エラーではなく標準出力に出力するか
+    frame: Frame,            // Fortuneの本文を囲む枠
+}
+
+impl OutputPolicy {
+    fn print_source(&self, source: &str) {
+        if self.sources_to_stdout {
+            println!("({})\n%", source);
+        } else {
+            eprintln!("({})\n%", source);
+        }
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -30,7 +131,7 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("FILE")
                 .multiple(true)
                 .required(true)
-                .help("Input files or directories"),
+                .help("Input files, directories or http(s) URLs, optionally weighted as SOURCE:WEIGHT"),
         )
         .arg(
             Arg::with_name("pattern")
@@ -53,6 +154,90 @@ pub fn get_args() -> MyResult<Config> {
                 .long("seed")
                 .help("Random seed"),
         )
+        .arg(
+            Arg::with_name("show_source")
+                .short("c")
+                .takes_value(false)
+                .help("Show the source file of the fortune"),
+        )
+        .arg(
+            Arg::with_name("sources_to_stdout")
+                .long("sources-to-stdout")
+                .takes_value(false)
+                .help("Print source file names to STDOUT instead of STDERR"),
+        )
+        .arg(
+            Arg::with_name("all")
+                .long("all")
+                .takes_value(false)
+                .help("Include hidden files and directories"),
+        )
+        .arg(
+            Arg::with_name("follow")
+                .short("L")
+                .long("follow")
+                .takes_value(false)
+                .help("Follow symbolic links to directories"),
+        )
+        .arg(
+            Arg::with_name("exclude_ext")
+                .value_name("EXT")
+                .long("exclude-ext")
+                .multiple(true)
+                .number_of_values(1) // 位置引数を値として消費しないように1つずつ指定
+                .help("Exclude files with the extension"),
+        )
+        .arg(
+            Arg::with_name("interactive")
+                .short("I") // -iは--insensitiveで使用済み
+                .long("interactive")
+                .takes_value(false)
+                .help("Browse fortunes interactively (Enter: next, q: quit)"),
+        )
+        .arg(
+            Arg::with_name("delimiter")
+                .value_name("DELIM")
+                .short("d")
+                .long("delimiter")
+                .default_value("%")
+                .allow_hyphen_values(true)
+                .help("Line separating fortunes in plain text files"),
+        )
+        .arg(
+            Arg::with_name("tag")
+                .value_name("TAG")
+                .long("tag")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Only use fortunes with the tag (JSON/TOML files)"),
+        )
+        .arg(
+            Arg::with_name("no_repeat")
+                .value_name("N")
+                .long("no-repeat")
+                .help("Do not pick any of the last N fortunes shown"),
+        )
+        .arg(
+            Arg::with_name("history")
+                .value_name("FILE")
+                .long("history")
+                .requires("no_repeat")
+                .help("History file for --no-repeat [default: $XDG_STATE_HOME/fortuner/history]"),
+        )
+        .arg(
+            Arg::with_name("box")
+                .value_name("STYLE")
+                .long("box")
+                .default_value("none")
+                .help("Frame the fortune in a box [ascii|unicode|none]"),
+        )
+        .arg(
+            Arg::with_name("width")
+                .value_name("COLUMNS")
+                .long("width")
+                .default_value("40")
+                .help("Wrap framed fortunes to at most COLUMNS columns"),
+        )
         .get_matches();
 
     let pattern = matches.value_of("pattern")
@@ -67,11 +252,43 @@ pub fn get_args() -> MyResult<Config> {
         .map(parse_u64)
         .transpose()?;
 
+    // デフォルト値があるのでunwrap可能
+    let style = matches.value_of("box").unwrap().parse::<BoxStyle>()?;
+    let width = matches.value_of("width").unwrap();
+    let width = width.parse::<usize>()
+        .ok()
+        .filter(|&width| width > 0)
+        .ok_or_else(|| format!("Invalid --width \"{}\"", width))?;
+
+    let no_repeat = matches.value_of("no_repeat")
+        .map(|val| parse_u64(val).map(|num| num as usize))
+        .transpose()?;
+
     Ok(
         Config {
             sources: matches.values_of_lossy("sources").unwrap(),
             pattern,
             seed,
+            output: OutputPolicy {
+                show_source: matches.is_present("show_source"),
+                sources_to_stdout: matches.is_present("sources_to_stdout"),
+                frame: Frame { style, width },
+            },
+            find: FindOptions {
+                include_hidden: matches.is_present("all"),
+                follow_links: matches.is_present("follow"),
+                exclude_exts: matches
+                    .values_of_lossy("exclude_ext")
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|ext| ext.trim_start_matches('.').to_string()) // ".bak"と"bak"を同じ扱いにする
+                    .collect(),
+            },
+            interactive: matches.is_present("interactive"),
+            delimiter: matches.value_of("delimiter").unwrap().to_string(), // デフォルト値があるのでunwrap可能
+            tags: matches.values_of_lossy("tag").unwrap_or_default(),
+            no_repeat,
+            history: matches.value_of("history").map(PathBuf::from),
         }
     )
 }
@@ -83,112 +300,285 @@ fn parse_u64(val: &str) -> MyResult<u64> {
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let files = find_files(&config.sources)?;
-    let fortunes = read_fortunes(&files)?;
+    let files = find_files(&config.sources, &config.find)?;
+    let mut fortunes = read_fortunes(&files, &config.delimiter)?;
+    // タグが指定されている場合はいずれかのタグを持つFortuneのみを対象にする
+    if !config.tags.is_empty() {
+        fortunes.retain(|fortune| fortune.tags.iter().any(|tag| config.tags.contains(tag)));
+    }
 
+    if config.interactive {
+        // 正規表現が指定されている場合は一致するFortuneのみを順に表示する
+        let candidates = fortunes
+            .iter()
+            .filter(|fortune| config.pattern.as_ref().is_none_or(|p| p.is_match(&fortune.text)))
+            .collect();
+        let mut session = Session::new(candidates, config.seed);
+        browse(&mut session, io::stdin().lock(), &mut io::stdout(), config.output)?;
     // 正規表現が指定されている場合は一致する全てのFortuneを出力
-    if let Some(pattern) = config.pattern {
+    } else if let Some(pattern) = config.pattern {
         // 直前のソース名(ファイルパス)の保存先を定義
         let mut prev_source = None;
         // Fortuneのうち、テキスト内容が正規表現と合致するもののみをフィルタリングしてループ処理
         for fortune in fortunes.iter().filter(|fortune| pattern.is_match(&fortune.text)) {
             // (Optionの中身を参照して)直前のソース名と不一致の場合はファイル名を出力: 初回は(Noneなので)デフォルトで(ファイル名を)出力
-            if prev_source.as_ref().map_or(true, |s| s != &fortune.source) {
-                eprintln!("({})\n%", fortune.source);
+            if prev_source.as_ref().is_none_or(|s| s != &fortune.source) {
+                config.output.print_source(&fortune.source);
                 prev_source = Some(fortune.source.clone()); // 所有権の関係から複製して保存
             }
-            println!("{}\n%", fortune.text);
+            println!("{}\n%", config.output.frame.decorate(&fortune.to_string()));
         }
     } else {
         // 正規表現未指定時はシード値を元にランダムに1つFortuneを抽出して出力
-        let text = pick_fortune(&fortunes, config.seed)
-            .or_else(|| Some("No fortunes found".to_string())).unwrap(); // エラーの場合は文字列を返す
-        println!("{}", text);
+        // 履歴は出力して記録し終えるまでロックしたままにする
+        let history = match config.no_repeat {
+            Some(num) => {
+                let path = match &config.history {
+                    Some(path) => path.clone(),
+                    None => history::default_path()?,
+                };
+                Some((History::open(&path)?, num))
+            }
+            None => None,
+        };
+        let picked = match &history {
+            Some((history, num)) => pick_unrepeated(&fortunes, history, *num, config.seed),
+            None => pick_fortune(&fortunes, config.seed),
+        };
+        match picked {
+            Some(fortune) => {
+                if config.output.show_source {
+                    config.output.print_source(&fortune.source);
+                }
+                println!("{}", config.output.frame.decorate(&fortune.to_string()));
+                if let Some((history, num)) = history {
+                    history.record(&fortune.text, num)?;
+                }
+            }
+            None => println!("No fortunes found"),
+        }
     }
     Ok(())
 }
 
 // PathBufを利用することで所有権が直接得られる: Pathは不定サイズのためBox<Path>等のように利用しなければならない
-fn find_files(paths: &[String]) -> MyResult<Vec<PathBuf>> {
+// URLは探索せずにそのまま、ファイルの後に指定順で並べる
+// SOURCE:WEIGHTで指定した重みは、ディレクトリであれば配下のファイル全体で1つのバケットとして扱う
+fn find_files(paths: &[String], options: &FindOptions) -> MyResult<Vec<(Source, Bucket)>> {
     let dat = OsStr::new("dat");
-    let mut files = vec![];
-
-    for path in paths {
+    let mut files: Vec<(PathBuf, Bucket)> = vec![];
+    let mut urls = vec![];
+
+    // 除外対象の拡張子か否かを判定
+    let is_excluded = |path: &std::path::Path| {
+        path.extension().is_some_and(|ext| {
+            ext == dat || options.exclude_exts.iter().any(|excluded| ext == excluded.as_str())
+        })
+    };
+
+    for (index, arg) in paths.iter().enumerate() {
+        let (path, weight) = split_weight(arg)?;
+        if let Source::Url(url) = Source::parse(path) {
+            if !urls.iter().any(|(seen, _)| seen == &url) {
+                urls.push((url, (index, weight)));
+            }
+            continue;
+        }
         match metadata(path) {
             Err(e) => return Err(format!("{}: {}", path, e).into()),
             Ok(_) => files.extend(
                 WalkDir::new(path)
+                    .follow_links(options.follow_links)
                     .into_iter() // パスを再起的に探索
-                    .filter_map(Result::ok) // 読み込めないファイルやディレクトリを除去
-                    .filter(|entry| {
-                        entry.file_type().is_file()
-                            && entry.path().extension() != Some(dat) // .datではないファイルのみをフィルタリング
+                    // 隠しファイルやディレクトリはディレクトリごと探索対象から外す: 引数で指定されたパス自体は対象とする
+                    .filter_entry(|entry| {
+                        options.include_hidden
+                            || entry.depth() == 0
+                            || !entry.file_name().to_string_lossy().starts_with('.')
                     })
-                    .map(|entry| entry.path().into()), // ファイルパスを集約
+                    .filter_map(Result::ok) // 読み込めないファイルやディレクトリを除去
+                    .filter(|entry| entry.file_type().is_file() && !is_excluded(entry.path())) // 除外対象ではないファイルのみをフィルタリング
+                    .map(|entry| (entry.path().into(), (index, weight))), // ファイルパスを集約
             )
         }
     }
     files.sort();
-    files.dedup(); // ファイルパスの重複を除去: 連続する同じファイルパスは1つにする
-    Ok(files)
+    // ファイルパスの重複を除去: 異なるシンボリックリンク経由で到達した同じファイルも実体のパスで比較して1つにする
+    let mut seen = HashSet::new();
+    files.retain(|(file, _)| seen.insert(canonicalize(file).unwrap_or_else(|_| file.clone())));
+    Ok(files
+        .into_iter()
+        .map(|(file, bucket)| (Source::File(file), bucket))
+        .chain(urls.into_iter().map(|(url, bucket)| (Source::Url(url), bucket)))
+        .collect())
 }
 
-// ファイル名と記載内容の構造体をベクトルで返す
-fn read_fortunes(paths: &[PathBuf]) -> MyResult<Vec<Fortune>> {
+// ファイル名と記載内容の構造体をベクトルで返す: 拡張子に応じて読み込み方法を切り替える
+// 重み付きの抽出のため、各Fortuneには読み込み元を指定した引数ごとのバケットの番号と重みを持たせる
+fn read_fortunes(sources: &[(Source, Bucket)], delimiter: &str) -> MyResult<Vec<Fortune>> {
     let mut fortunes = vec![];
+
+    for (source, (bucket, weight)) in sources {
+        // ソース名を文字列として所有
+        let basename = source.name();
+        let entries = source
+            .local_path()
+            .and_then(|path| match path.extension().and_then(OsStr::to_str) {
+                Some("json") => read_json(&path),
+                Some("toml") => read_toml(&path),
+                _ => read_text(&path, delimiter),
+            })
+            .map_err(|e| format!("{}: {}", source, e))?;
+
+        fortunes.extend(entries.into_iter().map(|entry| Fortune {
+            source: basename.clone(), // 所有権ごと複製
+            text: entry.text,
+            author: entry.author,
+            tags: entry.tags,
+            bucket: *bucket,
+            weight: *weight,
+        }));
+    }
+    Ok(fortunes)
+}
+
+// 区切り行で区切られたテキスト形式
+fn read_text(path: &Path, delimiter: &str) -> MyResult<Vec<Entry>> {
+    let file = File::open(path)?;
+    let mut entries = vec![];
     let mut buffer = vec![];
 
-    for path in paths {
-        // パスを文字列として所有
-        let basename = path.file_name().unwrap().to_string_lossy().into_owned();
-        // パスをファイルとして開く
-        let file = File::open(path).map_err(|e| {
-            format!("{}: {}", path.to_string_lossy().into_owned(), e)
-        })?;
-
-        // ファイルをバッファで1行ずつ(読み込み可能な行のみを)読み込む
-        for line in BufReader::new(file).lines().filter_map(Result::ok) {
-            // 区切り文字が見つかった場合: 記載内容が空でなければパス情報と共にstructに詰め込んでベクトルに追加
-            if line == "%" {
-                if !buffer.is_empty() {
-                    fortunes.push(Fortune {
-                        source: basename.clone(), // 所有権ごと複製
-                        text: buffer.join("\n"), // 改行を含む内容を格納
-                    });
-                    buffer.clear();
-                }
-            } else {
-                // 区切り文字未到達段階: ベクトルに各行の文字列を格納
-                buffer.push(line.to_string());
+    // ファイルをバッファで1行ずつ(読み込みエラーが発生するまでの行を)読み込む
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        // 区切り文字が見つかった場合: 記載内容が空でなければエントリとして追加
+        if line == delimiter {
+            if !buffer.is_empty() {
+                entries.push(Entry {
+                    text: buffer.join("\n"), // 改行を含む内容を格納
+                    author: None,
+                    tags: vec![],
+                });
+                buffer.clear();
             }
+        } else {
+            // 区切り文字未到達段階: ベクトルに各行の文字列を格納
+            buffer.push(line);
         }
     }
-    Ok(fortunes)
+    Ok(entries)
+}
+
+// エントリの配列を記載したJSON形式
+fn read_json(path: &Path) -> MyResult<Vec<Entry>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
 }
 
-// ベクトルの中からシード値を元にランダムに1つ抽出した構造体の記載内容を返す
-fn pick_fortune(fortunes: &[Fortune], seed: Option<u64>) -> Option<String> {
+fn read_toml(path: &Path) -> MyResult<Vec<Entry>> {
+    let file: TomlFile = toml::from_str(&fs::read_to_string(path)?)?;
+    Ok(file.fortune)
+}
+
+// ベクトルの中からシード値を元にランダムに1つ抽出した構造体を返す: ソース名も参照できるように構造体の参照のまま返す
+fn pick_fortune<T: Borrow<Fortune>>(fortunes: &[T], seed: Option<u64>) -> Option<&T> {
     if let Some(val) = seed {
         // seed値から乱数(ランダムな数値生成)器を作成
         let mut rng = StdRng::seed_from_u64(val);
-        // ベクトルから乱数器で要素を抽出し、Stringに変換: 可変引数として渡す
-        fortunes.choose(&mut rng).map(|f| f.text.to_string())
+        // ベクトルから乱数器で要素を抽出: 可変引数として渡す
+        choose_weighted(fortunes, &mut rng)
     } else {
         // seedが無い場合はスレッド依存の乱数生成器を利用: 可変引数として渡す
         let mut rng = rand::thread_rng();
-        fortunes.choose(&mut rng).map(|f| f.text.to_string())
+        choose_weighted(fortunes, &mut rng)
     }
 }
 
+// 重みが指定されていなければ全てのFortuneから等確率で選ぶ
+// 指定されていれば、まず読み込み元のバケットを重みに比例して選び(未指定のものは重み1)、その中から等確率で選ぶ
+fn choose_weighted<'a, T: Borrow<Fortune>>(fortunes: &'a [T], rng: &mut impl Rng) -> Option<&'a T> {
+    if fortunes.iter().all(|fortune| fortune.borrow().weight.is_none()) {
+        return fortunes.choose(rng);
+    }
+    let mut buckets: BTreeMap<usize, (u32, Vec<&T>)> = BTreeMap::new();
+    for fortune in fortunes {
+        let (bucket, weight) = (fortune.borrow().bucket, fortune.borrow().weight.unwrap_or(1));
+        buckets.entry(bucket).or_insert_with(|| (weight, vec![])).1.push(fortune);
+    }
+    let buckets: Vec<_> = buckets.into_values().collect();
+    let index = WeightedIndex::new(buckets.iter().map(|(weight, _)| *weight)).ok()?;
+    buckets[index.sample(rng)].1.choose(rng).copied()
+}
+
+// 直近num件に表示したもの以外から1つ選ぶ: 全て表示済みの場合は全体から選ぶ
+fn pick_unrepeated<'a>(fortunes: &'a [Fortune], history: &History, num: usize, seed: Option<u64>) -> Option<&'a Fortune> {
+    let candidates: Vec<_> = fortunes
+        .iter()
+        .filter(|fortune| !history.is_recent(&fortune.text, num))
+        .collect();
+    if candidates.is_empty() {
+        pick_fortune(fortunes, seed)
+    } else {
+        pick_fortune(&candidates, seed).copied()
+    }
+}
+
+// Fortuneを1つ表示して入力を待つ: Enterで次へ、qまたは入力終了で終了
+fn browse(
+    session: &mut Session,
+    mut input: impl BufRead,
+    out: &mut impl Write,
+    output: OutputPolicy,
+) -> MyResult<()> {
+    let mut line = String::new();
+    loop {
+        match session.next() {
+            None => {
+                writeln!(out, "No more fortunes")?;
+                break;
+            }
+            Some(fortune) => {
+                if output.show_source {
+                    output.print_source(&fortune.source);
+                }
+                writeln!(out, "{}", output.frame.decorate(&fortune.to_string()))?;
+            }
+        }
+        write!(out, "[Enter: next, q: quit] ")?;
+        out.flush()?; // 改行なしのプロンプトを入力前に表示する
+
+        line.clear();
+        if input.read_line(&mut line)? == 0 || line.trim() == "q" {
+            break;
+        }
+    }
+    Ok(())
+}
+
 // --------------------------------------------------
 #[cfg(test)]
 mod tests {
+    use super::browse;
     use super::find_files;
+    use super::FindOptions;
     use super::parse_u64;
     use super::pick_fortune;
     use super::read_fortunes;
     use super::Fortune;
-    use std::path::PathBuf;
+    use super::{BoxStyle, Frame};
+    use super::OutputPolicy;
+    use super::Session;
+    use super::Source;
+    use std::{collections::HashSet, io::Cursor};
+
+    fn fortune(text: &str) -> Fortune {
+        Fortune {
+            source: "fortunes".to_string(),
+            text: text.to_string(),
+            author: None,
+            tags: vec![],
+            bucket: 0,
+            weight: None,
+        }
+    }
 
     #[test]
     fn test_parse_u64() {
@@ -208,30 +598,30 @@ mod tests {
     #[test]
     fn test_find_files() {
         // Verify that the function finds a file known to exist
-        let res = find_files(&["./tests/inputs/jokes".to_string()]);
+        let res = find_files(&["./tests/inputs/jokes".to_string()], &FindOptions::default());
         assert!(res.is_ok());
 
         let files = res.unwrap();
         assert_eq!(files.len(), 1);
         assert_eq!(
-            files.get(0).unwrap().to_string_lossy(),
+            files.first().unwrap().0.to_string(),
             "./tests/inputs/jokes"
         );
 
         // Fails to find a bad file
-        let res = find_files(&["/path/does/not/exist".to_string()]);
+        let res = find_files(&["/path/does/not/exist".to_string()], &FindOptions::default());
         assert!(res.is_err());
 
-        // Finds all the input files, excludes ".dat"
-        let res = find_files(&["./tests/inputs".to_string()]);
+        // Finds all the input files, excludes ".dat" and hidden files
+        let res = find_files(&["./tests/inputs".to_string()], &FindOptions::default());
         assert!(res.is_ok());
 
         // Check number and order of files
         let files = res.unwrap();
-        assert_eq!(files.len(), 5);
-        let first = files.get(0).unwrap().display().to_string();
+        assert_eq!(files.len(), 4);
+        let first = files.first().unwrap().0.to_string();
         assert!(first.contains("ascii-art"));
-        let last = files.last().unwrap().display().to_string();
+        let last = files.last().unwrap().0.to_string();
         assert!(last.contains("quotes"));
 
         // Test for multiple sources, path must be unique and sorted
@@ -239,22 +629,85 @@ mod tests {
             "./tests/inputs/jokes".to_string(),
             "./tests/inputs/ascii-art".to_string(),
             "./tests/inputs/jokes".to_string(),
-        ]);
+        ], &FindOptions::default());
         assert!(res.is_ok());
         let files = res.unwrap();
         assert_eq!(files.len(), 2);
-        if let Some(filename) = files.first().unwrap().file_name() {
-            assert_eq!(filename.to_string_lossy(), "ascii-art".to_string())
-        }
-        if let Some(filename) = files.last().unwrap().file_name() {
-            assert_eq!(filename.to_string_lossy(), "jokes".to_string())
-        }
+        assert_eq!(files.first().unwrap().0.name(), "ascii-art");
+        assert_eq!(files.last().unwrap().0.name(), "jokes");
+
+        // URLs are not looked up and follow the files in the given order
+        let res = find_files(&[
+            "https://example.com/b.txt".to_string(),
+            "./tests/inputs/jokes".to_string(),
+            "https://example.com/a.txt".to_string(),
+            "https://example.com/b.txt".to_string(),
+        ], &FindOptions::default());
+        assert!(res.is_ok());
+        let files: Vec<_> = res.unwrap().iter().map(|(file, _)| file.to_string()).collect();
+        assert_eq!(
+            files,
+            ["./tests/inputs/jokes", "https://example.com/b.txt", "https://example.com/a.txt"]
+        );
+    }
+
+    #[test]
+    fn test_find_files_options() {
+        let names = |options: &FindOptions| -> Vec<String> {
+            find_files(&["./tests/sources".to_string()], options)
+                .unwrap()
+                .iter()
+                .map(|(path, _)| path.to_string())
+                .collect()
+        };
+
+        // Hidden files and symlinked directories are skipped by default
+        assert_eq!(
+            names(&FindOptions::default()),
+            [
+                "./tests/sources/notes.bak",
+                "./tests/sources/sub/wisdom",
+                "./tests/sources/visible",
+            ]
+        );
+
+        // Include hidden files
+        let options = FindOptions {
+            include_hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(names(&options).len(), 4);
+        assert_eq!(names(&options)[0], "./tests/sources/.hidden");
+
+        // The same file reached through a symlink is listed only once
+        let options = FindOptions {
+            follow_links: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            names(&options),
+            [
+                "./tests/sources/linked/wisdom",
+                "./tests/sources/notes.bak",
+                "./tests/sources/visible",
+            ]
+        );
+
+        // Exclude additional extensions
+        let options = FindOptions {
+            exclude_exts: vec!["bak".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            names(&options),
+            ["./tests/sources/sub/wisdom", "./tests/sources/visible"]
+        );
     }
 
     #[test]
     fn test_read_fortunes() {
         // Parses all the fortunes without a filter
-        let res = read_fortunes(&[PathBuf::from("./tests/inputs/jokes")]);
+        let res = read_fortunes(&[(Source::parse("./tests/inputs/jokes"), (0, None))], "%");
         assert!(res.is_ok());
 
         if let Ok(fortunes) = res {
@@ -274,13 +727,66 @@ mod tests {
 
         // Filters for matching text
         let res = read_fortunes(&[
-            PathBuf::from("./tests/inputs/jokes"),
-            PathBuf::from("./tests/inputs/quotes"),
-        ]);
+            (Source::parse("./tests/inputs/jokes"), (0, None)),
+            (Source::parse("./tests/inputs/quotes"), (1, None)),
+        ], "%");
         assert!(res.is_ok());
         assert_eq!(res.unwrap().len(), 11);
     }
 
+    #[test]
+    fn test_read_fortunes_delimiter() {
+        let fortunes = read_fortunes(&[(Source::parse("./tests/formats/dashes"), (0, None))], "---").unwrap();
+        assert_eq!(fortunes.len(), 2);
+        assert_eq!(fortunes[1].text, "Measure twice,\ncut once.");
+
+        // 区切り行が見つからなければFortuneとして扱わない
+        let fortunes = read_fortunes(&[(Source::parse("./tests/formats/dashes"), (0, None))], "%").unwrap();
+        assert!(fortunes.is_empty());
+    }
+
+    #[test]
+    fn test_read_fortunes_structured() {
+        let fortunes = read_fortunes(&[
+            (Source::parse("./tests/formats/quotes.json"), (0, None)),
+            (Source::parse("./tests/formats/quotes.toml"), (1, None)),
+        ], "%").unwrap();
+        assert_eq!(fortunes.len(), 5);
+        assert_eq!(fortunes[0].source, "quotes.json");
+        assert_eq!(fortunes[0].author.as_deref(), Some("Edsger W. Dijkstra"));
+        assert_eq!(fortunes[1].tags, ["programming", "future"]);
+        // 作者やタグは省略可能
+        assert_eq!(fortunes[2].author, None);
+        assert!(fortunes[2].tags.is_empty());
+        assert_eq!(fortunes[4].source, "quotes.toml");
+        assert_eq!(
+            fortunes[4].to_string(),
+            "Life is what happens\nwhile you are busy making other plans.\n-- John Lennon"
+        );
+
+        // 不正な形式はファイル名付きのエラー
+        let res = read_fortunes(&[(Source::parse("./tests/formats/broken.json"), (0, None))], "%");
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_read_fortunes_weighted_dir() {
+        // ディレクトリの重みは配下のファイルごとではなく、引数全体で1つのバケットに付く
+        let files = find_files(&[
+            "./tests/inputs:1".to_string(),
+            "./tests/formats/quotes.json:1".to_string(),
+        ], &FindOptions::default()).unwrap();
+        let fortunes = read_fortunes(&files, "%").unwrap();
+        let buckets: HashSet<_> = fortunes.iter().map(|fortune| (fortune.bucket, fortune.weight)).collect();
+        assert_eq!(buckets, HashSet::from([(0, Some(1)), (1, Some(1))]));
+
+        // 配下のファイルの数によらず、同じ重みのファイルと同じ割合で選ばれる
+        let picked = (0..200)
+            .filter(|seed| pick_fortune(&fortunes, Some(*seed)).unwrap().bucket == 1)
+            .count();
+        assert!((70..=130).contains(&picked), "picked {} of 200", picked);
+    }
+
     #[test]
     fn test_pick_fortune() {
         // Create a slice of fortunes
@@ -289,22 +795,113 @@ mod tests {
                 source: "fortunes".to_string(),
                 text: "You cannot achieve the impossible without attempting the absurd."
                     .to_string(),
+                author: None,
+                tags: vec![],
+                bucket: 0,
+                weight: None,
             },
             Fortune {
                 source: "fortunes".to_string(),
                 text: "Assumption is the mother of all screw-ups."
                     .to_string(),
+                author: None,
+                tags: vec![],
+                bucket: 0,
+                weight: None,
             },
             Fortune {
                 source: "fortunes".to_string(),
                 text: "Neckties strangle clear thinking.".to_string(),
+                author: None,
+                tags: vec![],
+                bucket: 0,
+                weight: None,
             },
         ];
 
         // Pick a fortune with a seed
         assert_eq!(
-            pick_fortune(fortunes, Some(1)).unwrap(),
+            pick_fortune(fortunes, Some(1)).unwrap().text,
             "Neckties strangle clear thinking.".to_string()
         );
     }
+
+    #[test]
+    fn test_pick_fortune_weighted() {
+        let weighted = |text: &str, bucket, weight| Fortune {
+            bucket,
+            weight,
+            ..fortune(text)
+        };
+        // 件数によらず、重みの大きいバケットから選ばれる
+        let mut fortunes: Vec<_> = (0..100).map(|_| weighted("a", 0, Some(1))).collect();
+        fortunes.push(weighted("b", 1, Some(1_000_000)));
+        for seed in 0..20 {
+            assert_eq!(pick_fortune(&fortunes, Some(seed)).unwrap().text, "b");
+        }
+
+        // 重みを指定しないバケットは重み1として扱う
+        let fortunes = [weighted("a", 0, None), weighted("b", 1, Some(1_000_000))];
+        assert_eq!(pick_fortune(&fortunes, Some(1)).unwrap().text, "b");
+
+        // 参照のベクトルからも選べる
+        let refs: Vec<_> = fortunes.iter().collect();
+        assert_eq!(pick_fortune(&refs, Some(1)).unwrap().text, "b");
+    }
+
+    #[test]
+    fn test_session() {
+        let fortunes = [fortune("a"), fortune("b"), fortune("c")];
+
+        // 全て表示し終えるまで同じFortuneは選ばれない
+        let mut session = Session::new(fortunes.iter().collect(), Some(1));
+        let mut seen = HashSet::new();
+        while let Some(fortune) = session.next() {
+            assert!(seen.insert(fortune.text.clone()));
+        }
+        assert_eq!(seen.len(), 3);
+        assert!(session.next().is_none());
+
+        // 同じseedなら同じ順序になる
+        let order = |seed| {
+            let mut session = Session::new(fortunes.iter().collect(), Some(seed));
+            std::iter::from_fn(|| session.next().map(|f| f.text.clone())).collect::<Vec<_>>()
+        };
+        assert_eq!(order(5), order(5));
+    }
+
+    #[test]
+    fn test_browse() {
+        let fortunes = [fortune("a"), fortune("b")];
+        let output = OutputPolicy {
+            show_source: false,
+            sources_to_stdout: false,
+            frame: Frame {
+                style: BoxStyle::None,
+                width: 40,
+            },
+        };
+        let prompt = "[Enter: next, q: quit] ";
+
+        // qで終了する
+        let mut session = Session::new(fortunes.iter().collect(), Some(1));
+        let mut out = vec![];
+        browse(&mut session, Cursor::new("q\n"), &mut out, output).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches(prompt).count(), 1);
+
+        // Enterで次へ進み、全て表示したら終了する
+        let mut session = Session::new(fortunes.iter().collect(), Some(1));
+        let mut out = vec![];
+        browse(&mut session, Cursor::new("\n\n\n"), &mut out, output).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("a\n") && out.contains("b\n"));
+        assert!(out.ends_with(&format!("{}No more fortunes\n", prompt)));
+
+        // 入力が終了した場合も終了する
+        let mut session = Session::new(fortunes.iter().collect(), Some(1));
+        let mut out = vec![];
+        browse(&mut session, Cursor::new(""), &mut out, output).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().matches(prompt).count(), 1);
+    }
 }
@@ -0,0 +1,235 @@
+use std::{env, fmt, fs, io::Read, path::{Path, PathBuf}, time::Duration};
+
+use sha2::{Digest, Sha256};
+
+use crate::MyResult;
+
+// 応答しないサーバーで止まらないよう、接続と受信の待ち時間を制限する
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Fortuneの読み込み元: ローカルのファイルか、HTTP(S)で取得するURL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    File(PathBuf),
+    Url(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Url(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+impl Source {
+    // 引数がURLの形式であればUrl、それ以外はFileとして扱う
+    pub fn parse(arg: &str) -> Self {
+        if is_url(arg) {
+            Source::Url(arg.to_string())
+        } else {
+            Source::File(PathBuf::from(arg))
+        }
+    }
+
+    // 出力に使うソース名: ファイル名またはURLの最後の要素
+    pub fn name(&self) -> String {
+        match self {
+            Source::File(path) => path.file_name().unwrap().to_string_lossy().into_owned(),
+            Source::Url(url) => url_path(url)
+                .rsplit('/')
+                .find(|segment| !segment.is_empty())
+                .unwrap_or(url)
+                .to_string(),
+        }
+    }
+
+    // 内容を読み込むローカルのパス: URLは取得してキャッシュに保存し、取得できない場合は前回のキャッシュを使う
+    pub fn local_path(&self) -> MyResult<PathBuf> {
+        match self {
+            Source::File(path) => Ok(path.clone()),
+            Source::Url(url) => {
+                let cached = cache_dir()?.join(cache_name(url));
+                match fetch(url) {
+                    Ok(body) => {
+                        fs::create_dir_all(cached.parent().unwrap())?;
+                        fs::write(&cached, body)?;
+                    }
+                    Err(e) if cached.is_file() => {
+                        eprintln!("{}: {} (using cached copy)", url, e);
+                    }
+                    Err(e) => return Err(e),
+                }
+                Ok(cached)
+            }
+        }
+    }
+}
+
+// SOURCE:WEIGHTの形式の引数を読み込み元と重みに分ける: 重みは正の整数
+// 末尾が数字でない場合や、その名前のファイルが存在する場合は引数全体を読み込み元とする
+// URLはパスを含む場合のみ重みとみなす: http://localhost:8080のポート番号は重みではない
+pub fn split_weight(arg: &str) -> MyResult<(&str, Option<u32>)> {
+    if fs::metadata(arg).is_ok() {
+        return Ok((arg, None));
+    }
+    match arg.rsplit_once(':') {
+        Some((source, weight))
+            if !source.is_empty()
+                && !weight.is_empty()
+                && weight.bytes().all(|b| b.is_ascii_digit())
+                && (!is_url(source) || url_path(source).contains('/')) =>
+        {
+            match weight.parse() {
+                Ok(weight) if weight > 0 => Ok((source, Some(weight))),
+                _ => Err(format!("Invalid weight \"{}\"", arg).into()),
+            }
+        }
+        _ => Ok((arg, None)),
+    }
+}
+
+fn is_url(arg: &str) -> bool {
+    arg.starts_with("http://") || arg.starts_with("https://")
+}
+
+// スキームとクエリ、フラグメントを除いたURLのパス部分
+fn url_path(url: &str) -> &str {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+// エラーメッセージには呼び出し側でURLを付けるため、ureqのエラーからはURLを除く
+fn fetch(url: &str) -> MyResult<Vec<u8>> {
+    let mut body = vec![];
+    ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .build()
+        .get(url)
+        .call()
+        .map_err(|e| match e {
+            ureq::Error::Status(code, _) => format!("HTTP status {}", code),
+            ureq::Error::Transport(t) => match t.message() {
+                Some(message) => format!("{}: {}", t.kind(), message),
+                None => t.kind().to_string(),
+            },
+        })?
+        .into_reader()
+        .read_to_end(&mut body)?;
+    Ok(body)
+}
+
+// キャッシュの保存先: XDG Base Directory仕様に従い、未設定の場合は~/.cache
+fn cache_dir() -> MyResult<PathBuf> {
+    let base = match env::var_os("XDG_CACHE_HOME").filter(|dir| Path::new(dir).is_absolute()) {
+        Some(dir) => PathBuf::from(dir),
+        None => env::var_os("HOME")
+            .map(|home| Path::new(&home).join(".cache"))
+            .ok_or("Cannot determine the cache directory: HOME is not set")?,
+    };
+    Ok(base.join("fortuner"))
+}
+
+// URLごとのキャッシュファイル名: スキームやクエリのみが異なるURLも区別するため、URL全体のハッシュ値とする
+// 拡張子で読み込み方法を判定するため、パスの末尾の拡張子のみを残す
+fn cache_name(url: &str) -> String {
+    let hash: String = Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let ext = url_path(url)
+        .rsplit('/')
+        .next()
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()));
+    match ext {
+        Some(ext) => format!("{}.{}", hash, ext),
+        None => hash,
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{cache_name, split_weight, Source};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Source::parse("https://example.com/quotes.txt"),
+            Source::Url("https://example.com/quotes.txt".to_string())
+        );
+        assert_eq!(
+            Source::parse("./tests/inputs/jokes"),
+            Source::File(PathBuf::from("./tests/inputs/jokes"))
+        );
+        // スキームのないホスト名はファイルとして扱う
+        assert_eq!(
+            Source::parse("example.com/quotes.txt"),
+            Source::File(PathBuf::from("example.com/quotes.txt"))
+        );
+    }
+
+    #[test]
+    fn test_split_weight() {
+        assert_eq!(split_weight("quotes.txt:3").unwrap(), ("quotes.txt", Some(3)));
+        assert_eq!(split_weight("quotes.txt").unwrap(), ("quotes.txt", None));
+        // 数字以外の接尾辞は読み込み元の一部とみなす
+        assert_eq!(split_weight("a:b").unwrap(), ("a:b", None));
+        assert_eq!(split_weight(":3").unwrap(), (":3", None));
+        // 存在するファイルの名前はそのまま使う
+        assert_eq!(split_weight("./tests/inputs/jokes").unwrap(), ("./tests/inputs/jokes", None));
+
+        // URLのポート番号は重みとみなさない
+        assert_eq!(
+            split_weight("http://localhost:8080").unwrap(),
+            ("http://localhost:8080", None)
+        );
+        assert_eq!(
+            split_weight("http://localhost:8080/q.json:2").unwrap(),
+            ("http://localhost:8080/q.json", Some(2))
+        );
+
+        // 重みは正の整数
+        for arg in ["quotes.txt:0", "quotes.txt:99999999999"] {
+            let res = split_weight(arg);
+            assert!(res.is_err());
+            assert_eq!(res.unwrap_err().to_string(), format!("Invalid weight \"{}\"", arg));
+        }
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(Source::parse("./tests/inputs/jokes").name(), "jokes");
+        assert_eq!(Source::parse("https://example.com/a/quotes.json?v=2").name(), "quotes.json");
+        assert_eq!(Source::parse("https://example.com/").name(), "example.com");
+    }
+
+    #[test]
+    fn test_cache_name() {
+        let name = cache_name("https://example.com/a/quotes.json");
+        assert_eq!(name.len(), 64 + ".json".len());
+        assert!(name.ends_with(".json"));
+        assert_eq!(name, cache_name("https://example.com/a/quotes.json"));
+        // 拡張子のないURLはハッシュ値のみ
+        assert_eq!(cache_name("http://localhost:8080/q?x=1#top").len(), 64);
+
+        // スキーム、クエリ、フラグメントのみが異なるURLも別のファイルにする
+        let names: std::collections::HashSet<_> = [
+            "https://example.com/a/quotes.json",
+            "http://example.com/a/quotes.json",
+            "https://example.com/a/quotes.json?lang=ja",
+            "https://example.com/a/quotes.json#top",
+            "https://example.com/a_quotes.json",
+        ]
+        .iter()
+        .map(|url| cache_name(url))
+        .collect();
+        assert_eq!(names.len(), 5);
+    }
+}
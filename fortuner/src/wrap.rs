@@ -0,0 +1,117 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const TAB_WIDTH: usize = 8;
+
+// 表示幅がwidth以下になるように行を折り返す: 全角文字は2マスとして数える
+// 元の改行は保ち、幅に収まる行はアスキーアートなどの空白を崩さないようそのまま残す
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(2); // 全角文字を1文字ずつは置けるようにする
+    let mut lines = vec![];
+    for line in text.lines() {
+        let line = expand_tabs(line);
+        let line = line.trim_end();
+        if line.width() <= width {
+            lines.push(line.to_string());
+        } else {
+            wrap_line(line, width, &mut lines);
+        }
+    }
+    lines
+}
+
+// 単語単位で詰めて折り返す: 先頭の字下げは最初の行にのみ残す
+fn wrap_line(line: &str, width: usize, lines: &mut Vec<String>) {
+    let body = line.trim_start();
+    let mut current = line[..line.len() - body.len()].to_string();
+    for word in body.split_whitespace() {
+        let current_width = current.width();
+        let separator = usize::from(!current.trim_start().is_empty()); // 行頭以外は空白で区切る
+        if current_width + separator + word.width() <= width {
+            if separator == 1 {
+                current.push(' ');
+            }
+            current.push_str(word);
+            continue;
+        }
+        if !current.trim_start().is_empty() {
+            lines.push(std::mem::take(&mut current));
+        } else if current_width + word.width() > width {
+            current.clear(); // 字下げだけの行は作らない
+        }
+        // 1行に収まらない長い単語は文字の途中で区切る
+        for c in word.chars() {
+            if current.width() + c.width().unwrap_or(0) > width {
+                lines.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+}
+
+// タブは表示幅が環境によって異なるため、8桁ごとのタブ位置まで空白に置き換える
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::new();
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - column % TAB_WIDTH;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += c.width().unwrap_or(0);
+        }
+    }
+    expanded
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{expand_tabs, wrap};
+
+    #[test]
+    fn test_wrap_words() {
+        assert_eq!(wrap("The quick brown fox jumps over the lazy dog.", 15), [
+            "The quick brown",
+            "fox jumps over",
+            "the lazy dog.",
+        ]);
+        // 元の改行と空行は保つ
+        assert_eq!(wrap("a b\n\nc d", 3), ["a b", "", "c d"]);
+        // 幅に収まる行の空白はそのまま
+        assert_eq!(wrap("  /\\_/\\\n ( o.o )", 10), ["  /\\_/\\", " ( o.o )"]);
+        assert_eq!(wrap("    indented words here", 12), ["    indented", "words here"]);
+        assert!(wrap("", 10).is_empty());
+    }
+
+    #[test]
+    fn test_wrap_long_word() {
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("see https://example.com/a/long/path ok", 10), [
+            "see",
+            "https://ex",
+            "ample.com/",
+            "a/long/pat",
+            "h ok",
+        ]);
+        assert_eq!(wrap("        abcdefghij", 6), ["abcdef", "ghij"]);
+    }
+
+    #[test]
+    fn test_wrap_wide_chars() {
+        // 全角文字は2マス: 幅の途中で区切らない
+        assert_eq!(wrap("日本語の文章", 5), ["日本", "語の", "文章"]);
+        assert_eq!(wrap("春 夏 秋 冬", 5), ["春 夏", "秋 冬"]);
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("a\tb"), "a       b");
+        assert_eq!(expand_tabs("\t\tx"), " ".repeat(16) + "x");
+        assert_eq!(expand_tabs("日本\tx"), "日本    x");
+    }
+}
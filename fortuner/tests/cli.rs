@@ -11,6 +11,9 @@ const EMPTY_DIR: &str = "./tests/inputs/empty";
 const JOKES: &str = "./tests/inputs/jokes";
 const LITERATURE: &str = "./tests/inputs/literature";
 const QUOTES: &str = "./tests/inputs/quotes";
+const DASHES: &str = "./tests/formats/dashes";
+const JSON: &str = "./tests/formats/quotes.json";
+const TOML: &str = "./tests/formats/quotes.toml";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -37,7 +40,7 @@ fn dies_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&[LITERATURE, &bad])
+        .args([LITERATURE, &bad])
         .assert()
         .failure()
         .stderr(predicate::str::is_match(expected)?);
@@ -50,7 +53,7 @@ fn dies_bad_seed() -> TestResult {
     let bad = random_string();
     let expected = format!("\"{}\" not a valid integer", &bad);
     Command::cargo_bin(PRG)?
-        .args(&[LITERATURE, "--seed", &bad])
+        .args([LITERATURE, "--seed", &bad])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -101,6 +104,80 @@ fn dir_seed_10() -> TestResult {
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn weighted_sources() -> TestResult {
+    // 重みの大きい読み込み元からほぼ必ず選ばれる
+    let jokes = format!("{}:1000", JOKES);
+    run(
+        &[&jokes, QUOTES, "-s", "1"],
+        "Q: What happens when frogs park illegally?\nA: They get toad.\n",
+    )?;
+    let quotes = format!("{}:1000", QUOTES);
+    for seed in ["1", "2", "3"] {
+        Command::cargo_bin(PRG)?
+            .args([JOKES, &quotes, "-c", "--sources-to-stdout", "-s", seed])
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("(quotes)\n%\n"));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_weight() -> TestResult {
+    let bad = format!("{}:0", JOKES);
+    Command::cargo_bin(PRG)?
+        .args([QUOTES, &bad])
+        .assert()
+        .failure()
+        .stderr(format!("Invalid weight \"{}\"\n", bad));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn delimiter() -> TestResult {
+    run(&[DASHES, "-s", "1", "--delimiter", "---"], "Measure twice,\ncut once.\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn tag_json() -> TestResult {
+    run(
+        &[JSON, "--tag", "future"],
+        "The best way to predict the future is to invent it.\n-- Alan Kay\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tag_multiple() -> TestResult {
+    run(
+        &[JSON, TOML, "--tag", "life", "--tag", "nothing"],
+        "Life is what happens\nwhile you are busy making other plans.\n-- John Lennon\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn tag_not_found() -> TestResult {
+    // タグを持たないテキスト形式のFortuneは選ばれない
+    run(&[QUOTES, TOML, "--tag", "future"], "No fortunes found\n")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_json() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["./tests/formats/broken.json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("./tests/formats/broken.json: "));
+    Ok(())
+}
+
 // --------------------------------------------------
 fn run_outfiles(args: &[&str], out_file: &str, err_file: &str) -> TestResult {
     let out = fs::read_to_string(out_file)?;
@@ -173,3 +250,262 @@ fn mark_twain_lower_i() -> TestResult {
         "tests/expected/twain_lower_i.err",
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn quotes_seed_1_show_source() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([QUOTES, "-s", "1", "-c"])
+        .assert()
+        .success()
+        .stderr("(quotes)\n%\n")
+        .stdout("You can observe a lot just by watching.\n-- Yogi Berra\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quotes_seed_1_show_source_to_stdout() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([QUOTES, "-s", "1", "-c", "--sources-to-stdout"])
+        .assert()
+        .success()
+        .stderr("")
+        .stdout(
+            "(quotes)\n%\nYou can observe a lot just by watching.\n-- Yogi Berra\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn yogi_berra_cap_sources_to_stdout() -> TestResult {
+    let err = fs::read_to_string("tests/expected/berra_cap.err")?;
+    let out = fs::read_to_string("tests/expected/berra_cap.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--pattern", "Yogi Berra", "--sources-to-stdout", FORTUNE_DIR])
+        .assert()
+        .success()
+        .stderr("")
+        .stdout(err + &out);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sources_all_exclude_ext() -> TestResult {
+    run(
+        &[
+            "tests/sources",
+            "-m",
+            "fortune",
+            "--all",
+            "--exclude-ext",
+            ".bak",
+            "--sources-to-stdout",
+        ],
+        "(.hidden)\n%\nHidden fortune.\n%\n(visible)\n%\nVisible fortune.\n%\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn sources_follow_dedup() -> TestResult {
+    run(
+        &["tests/sources", "-L", "-m", "wisdom", "--sources-to-stdout"],
+        "(wisdom)\n%\nNested wisdom.\n%\n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn interactive_pattern() -> TestResult {
+    // 一致するFortuneが1つだけなので、表示後は次へ進むと終了する
+    Command::cargo_bin(PRG)?
+        .args([JOKES, "-I", "-m", "deer"])
+        .write_stdin("\n")
+        .assert()
+        .success()
+        .stdout(
+            "Q: What do you call a deer wearing an eye patch?\n\
+            A: A bad idea (bad-eye deer).\n\
+            [Enter: next, q: quit] No more fortunes\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn interactive_quit() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args([JOKES, "--interactive", "-s", "1"])
+        .write_stdin("\nq\n")
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout.matches("[Enter: next, q: quit] ").count(), 2);
+    assert!(!stdout.contains("No more fortunes"));
+    Ok(())
+}
+
+// --------------------------------------------------
+// 1回だけ応答するHTTPサーバーを起動し、ファイルの内容を返すURLを返す
+fn serve_once(path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    let body = fs::read(path)?;
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    std::thread::spawn(move || {
+        if let Ok((mut stream, _)) = listener.accept() {
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(&body);
+        }
+    });
+    Ok(format!("http://{}/shared/quotes.json", addr))
+}
+
+#[test]
+fn url_source_cached() -> TestResult {
+    let cache = std::env::temp_dir().join(format!("fortuner-cache-{}", random_string()));
+    let url = serve_once(JSON)?;
+
+    // 拡張子に応じて読み込み、ソース名はURLの最後の要素
+    Command::cargo_bin(PRG)?
+        .env("XDG_CACHE_HOME", &cache)
+        .args([&url, "-m", "Simplicity", "--sources-to-stdout"])
+        .assert()
+        .success()
+        .stdout(
+            "(quotes.json)\n%\n\
+            Simplicity is prerequisite for reliability.\n\
+            -- Edsger W. Dijkstra\n%\n",
+        );
+
+    // サーバーが応答しなくなってもキャッシュから読み込む
+    Command::cargo_bin(PRG)?
+        .env("XDG_CACHE_HOME", &cache)
+        .args([&url, "-m", "Simplicity", "--sources-to-stdout"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("(quotes.json)\n%\nSimplicity"))
+        .stderr(predicate::str::contains("(using cached copy)"));
+
+    fs::remove_dir_all(&cache)?;
+    Ok(())
+}
+
+#[test]
+fn dies_url_unreachable() -> TestResult {
+    let cache = std::env::temp_dir().join(format!("fortuner-cache-{}", random_string()));
+    Command::cargo_bin(PRG)?
+        .env("XDG_CACHE_HOME", &cache)
+        .arg("http://127.0.0.1:1/quotes")
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with("http://127.0.0.1:1/quotes: "));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn no_repeat_history() -> TestResult {
+    let dir = std::env::temp_dir().join(format!("fortuner-history-{}", random_string()));
+    let history = dir.join("history");
+    let history = history.to_str().unwrap();
+
+    // 同じseedでも直近に表示したFortuneは選ばれない
+    let mut seen = vec![];
+    for _ in 0..6 {
+        let output = Command::cargo_bin(PRG)?
+            .args([JOKES, "--seed", "1", "--no-repeat", "5", "--history", history])
+            .output()?;
+        assert!(output.status.success());
+        let fortune = String::from_utf8(output.stdout)?;
+        assert!(!seen.contains(&fortune));
+        seen.push(fortune);
+    }
+    assert_eq!(fs::read_to_string(history)?.lines().count(), 5);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn no_repeat_default_history() -> TestResult {
+    let state = std::env::temp_dir().join(format!("fortuner-state-{}", random_string()));
+    for _ in 0..2 {
+        Command::cargo_bin(PRG)?
+            .env("XDG_STATE_HOME", &state)
+            .args([QUOTES, "--no-repeat", "1"])
+            .assert()
+            .success();
+    }
+    assert_eq!(fs::read_to_string(state.join("fortuner/history"))?.lines().count(), 1);
+    fs::remove_dir_all(&state)?;
+    Ok(())
+}
+
+#[test]
+fn dies_history_without_no_repeat() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args([JOKES, "--history", "history"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn jokes_seed_1_box_ascii() -> TestResult {
+    run(
+        &[JOKES, "-s", "1", "--box", "ascii"],
+        " _________________________________
+/ Q: What happens when frogs park \\
+| illegally?                      |
+\\ A: They get toad.               /
+ ---------------------------------
+",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn quotes_seed_1_box_unicode_width() -> TestResult {
+    run(
+        &[QUOTES, "-s", "1", "--box", "unicode", "--width", "20"],
+        "┌───────────────────┐
+│ You can observe a │
+│ lot just by       │
+│ watching.         │
+│ -- Yogi Berra     │
+└───────────────────┘
+",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_box() -> TestResult {
+    for (args, expected) in [
+        (["--box", "round"], "Invalid --box \"round\""),
+        (["--width", "0"], "Invalid --width \"0\""),
+    ] {
+        Command::cargo_bin(PRG)?
+            .arg(JOKES)
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+    Ok(())
+}
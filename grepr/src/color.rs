@@ -0,0 +1,160 @@
+use std::{env, ops::Range, str::FromStr};
+
+// 色付き出力の方針
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColorMode {
+    Auto,   // 標準出力が端末の場合のみ色付け
+    Always, // 常に色付け
+    Never,  // 色付けしない
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!("Invalid --color \"{}\"", s)),
+        }
+    }
+}
+
+impl ColorMode {
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorMode::Auto => atty::is(atty::Stream::Stdout),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+// GREP_COLORSの設定: "ms=01;31:fn=35:ln=32" のような形式
+// 一致部分(ms, mtは同じ扱い)、ファイル名(fn)、行番号(ln)、バイト位置(bn)、区切り文字(se)の色を指定する
+#[derive(Debug, PartialEq, Clone)]
+pub struct Colors {
+    matched: String,
+    filename: String,
+    line_number: String,
+    byte_offset: String,
+    separator: String,
+}
+
+impl Default for Colors {
+    // GREP_COLORS未設定時はGNU grepと同じ既定色を使う
+    fn default() -> Self {
+        Colors {
+            matched: "01;31".to_string(),
+            filename: "35".to_string(),
+            line_number: "32".to_string(),
+            byte_offset: "32".to_string(),
+            separator: "36".to_string(),
+        }
+    }
+}
+
+impl Colors {
+    pub fn from_env() -> Self {
+        match env::var("GREP_COLORS") {
+            Ok(val) => Colors::parse(&val),
+            _ => Colors::default(),
+        }
+    }
+
+    // 指定のない項目や不明な項目は既定色のまま: 値を空にすると色付けしない
+    pub fn parse(val: &str) -> Self {
+        let mut colors = Colors::default();
+        for (key, code) in val.split(':').filter_map(|entry| entry.split_once('=')) {
+            let field = match key {
+                "ms" | "mt" => &mut colors.matched,
+                "fn" => &mut colors.filename,
+                "ln" => &mut colors.line_number,
+                "bn" => &mut colors.byte_offset,
+                "se" => &mut colors.separator,
+                _ => continue,
+            };
+            *field = code.to_string();
+        }
+        colors
+    }
+
+    pub fn matched(&self, text: &str) -> String {
+        paint(&self.matched, text)
+    }
+
+    pub fn filename(&self, text: &str) -> String {
+        paint(&self.filename, text)
+    }
+
+    pub fn line_number(&self, text: &str) -> String {
+        paint(&self.line_number, text)
+    }
+
+    pub fn byte_offset(&self, text: &str) -> String {
+        paint(&self.byte_offset, text)
+    }
+
+    pub fn separator(&self, text: &str) -> String {
+        paint(&self.separator, text)
+    }
+
+    // 一致した範囲のみを色付けする: 範囲は昇順で重複していないこと
+    pub fn highlight(&self, line: &str, ranges: &[Range<usize>]) -> String {
+        let mut result = String::new();
+        let mut last = 0;
+        for range in ranges {
+            result.push_str(&line[last..range.start]);
+            result.push_str(&paint(&self.matched, &line[range.clone()]));
+            last = range.end;
+        }
+        result.push_str(&line[last..]);
+        result
+    }
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if code.is_empty() || text.is_empty() {
+        return text.to_string();
+    }
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorMode, Colors};
+
+    #[test]
+    fn test_parse_color_mode() {
+        assert_eq!("always".parse::<ColorMode>(), Ok(ColorMode::Always));
+        assert_eq!("never".parse::<ColorMode>(), Ok(ColorMode::Never));
+        assert_eq!(
+            "yes".parse::<ColorMode>(),
+            Err("Invalid --color \"yes\"".to_string())
+        );
+        assert!(!ColorMode::Never.enabled());
+    }
+
+    #[test]
+    fn test_parse_colors() {
+        let colors = Colors::parse("mt=01;32:fn=:xx=1:ln");
+        assert_eq!(
+            colors.highlight("abc", &[0..1, 2..3]),
+            "\x1b[01;32ma\x1b[0mb\x1b[01;32mc\x1b[0m"
+        );
+        assert_eq!(colors.filename("a.txt"), "a.txt"); // 空の値は色付けしない
+        assert_eq!(colors.line_number("3"), "\x1b[32m3\x1b[0m");
+        assert_eq!(Colors::parse(""), Colors::default());
+    }
+
+    #[test]
+    fn test_highlight() {
+        let colors = Colors::default();
+        assert_eq!(
+            colors.highlight("a fox and a fox\n", &[2..5, 12..15]),
+            "a \x1b[01;31mfox\x1b[0m and a \x1b[01;31mfox\x1b[0m\n"
+        );
+        assert_eq!(colors.highlight("fox\n", &[]), "fox\n");
+    }
+}
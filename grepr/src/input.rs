@@ -0,0 +1,121 @@
+use std::{
+    fs::File,
+    io::{self, stdin, BufRead, BufReader},
+    path::Path,
+};
+
+use flate2::bufread::MultiGzDecoder;
+
+// 入力の圧縮形式
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    // 拡張子から判定する
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    // 先頭のマジックナンバーから判定する
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+// 入力を開く: 圧縮されたファイルは展開しながら読み込むため、展開後のファイルを作らずに検索できる
+// detectがtrueの場合は拡張子によらず先頭のバイト列から判定する: 標準入力も判定の対象になる
+pub fn open(filename: &str, detect: bool) -> io::Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn BufRead> = match filename {
+        "-" => Box::new(BufReader::new(stdin())),
+        _ => Box::new(BufReader::new(File::open(filename)?)),
+    };
+    let compression = if detect {
+        Compression::detect(reader.fill_buf()?) // 読み込み位置は進めない
+    } else {
+        Compression::from_path(filename)
+    };
+    decode(reader, compression)
+}
+
+fn decode(reader: Box<dyn BufRead>, compression: Compression) -> io::Result<Box<dyn BufRead>> {
+    Ok(match compression {
+        Compression::None => reader,
+        // ログのローテーションなどで連結されたgzipも最後まで展開する
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+    })
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{decode, open, Compression};
+    use std::io::{BufRead, Cursor, Read, Write};
+
+    fn read_all(mut reader: Box<dyn BufRead>) -> String {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Compression::from_path("app.log.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("dir/app.log.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("app.log"), Compression::None);
+        assert_eq!(Compression::from_path("gz"), Compression::None);
+        assert_eq!(Compression::from_path("-"), Compression::None);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Compression::detect(&[0x1f, 0x8b, 0x08]), Compression::Gzip);
+        assert_eq!(Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd, 0x00]), Compression::Zstd);
+        assert_eq!(Compression::detect(b"plain text"), Compression::None);
+        assert_eq!(Compression::detect(b""), Compression::None);
+    }
+
+    #[test]
+    fn test_decode() {
+        // 連結された複数のgzipメンバーは1つの入力として展開する
+        let mut gzip = vec![];
+        for text in ["foo\n", "bar\n"] {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(text.as_bytes()).unwrap();
+            gzip.extend(encoder.finish().unwrap());
+        }
+        assert_eq!(read_all(decode(Box::new(Cursor::new(gzip)), Compression::Gzip).unwrap()), "foo\nbar\n");
+
+        let zstd = zstd::encode_all("baz\n".as_bytes(), 0).unwrap();
+        assert_eq!(read_all(decode(Box::new(Cursor::new(zstd)), Compression::Zstd).unwrap()), "baz\n");
+
+        // 圧縮されていない入力をgzipとして読むとエラーになる
+        let mut text = String::new();
+        let mut reader = decode(Box::new(Cursor::new("plain\n")), Compression::Gzip).unwrap();
+        assert!(reader.read_to_string(&mut text).is_err());
+    }
+
+    #[test]
+    fn test_open() {
+        let fox = read_all(open("tests/inputs/fox.txt", false).unwrap());
+        assert_eq!(read_all(open("tests/compressed/fox.txt.gz", false).unwrap()), fox);
+        // 拡張子がなくても先頭のバイト列から判定できる
+        let bustle = read_all(open("tests/inputs/bustle.txt", true).unwrap());
+        assert_eq!(read_all(open("tests/compressed/bustle.bin", true).unwrap()), bustle);
+        assert!(open("tests/compressed/missing.txt.gz", false).is_err());
+    }
+}
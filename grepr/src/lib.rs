@@ -1,17 +1,158 @@
-use std::{error::Error, io::{BufRead, BufReader, stdin}, fs::{File, metadata}, mem};
+use std::{collections::VecDeque, error::Error, ffi::OsStr, io::{self, BufRead, Write}, fs::metadata, thread};
 
 use clap::{App, Arg};
-use regex::{Regex, RegexBuilder};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use walkdir::WalkDir;
 
+mod color;
+mod input;
+mod matcher;
+mod parallel;
+mod printer;
+use color::{ColorMode, Colors};
+use matcher::{Engine, Matcher};
+use printer::Printer;
+
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 pub struct Config {
-    pattern: Regex,
+    patterns: Matcher,
     files: Vec<String>,
     recursive: bool,
-    count: bool,
+    mode: OutputMode,
     invert_match: bool,
+    only_matching: bool,
+    replace: Option<String>, // 一致した部分を置き換えて出力するテンプレート
+    json: bool,
+    line_number: bool,
+    byte_offset: bool,
+    quiet: bool,
+    search_zip: bool, // 拡張子によらず内容から圧縮形式を判定する
+    filter: GlobFilter,
+    color: ColorMode,
+    max_count: Option<usize>,
+    before_context: usize,
+    after_context: usize,
+    threads: usize, // 複数のファイルを並列に検索するスレッド数
+}
+
+// 検索結果の終了ステータス: grep と同様に一致あり0、一致なし1、エラー2
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Status {
+    Match,
+    NoMatch,
+    Error,
+}
+
+impl Status {
+    pub fn code(&self) -> i32 {
+        match self {
+            Status::Match => 0,
+            Status::NoMatch => 1,
+            Status::Error => 2,
+        }
+    }
+}
+
+// 再帰探索時にファイル名で絞り込むglobパターン: excludeはincludeより優先される
+#[derive(Debug, Default)]
+struct GlobFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl GlobFilter {
+    fn new(include: &[String], exclude: &[String]) -> MyResult<Self> {
+        Ok(GlobFilter {
+            include: build_globset(include, "--include")?,
+            exclude: build_globset(exclude, "--exclude")?,
+        })
+    }
+
+    // 除外パターンに一致する場合は、ディレクトリであれば配下ごと探索しない
+    fn is_excluded(&self, name: &OsStr) -> bool {
+        self.exclude.as_ref().is_some_and(|set| set.is_match(name))
+    }
+
+    // 対象パターンが未指定の場合は全てのファイルが対象
+    fn is_included(&self, name: &OsStr) -> bool {
+        self.include.as_ref().is_none_or(|set| set.is_match(name))
+    }
+}
+
+// パターンが未指定の場合はNoneを返す
+fn build_globset(patterns: &[String], name: &str) -> MyResult<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|_| format!("Invalid {} \"{}\"", name, pattern))?;
+        builder.add(glob);
+    }
+    Ok(Some(builder.build()?))
+}
+
+// 出力モード: 行単位の出力以外では結果が確定した時点で読み込みを打ち切る
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum OutputMode {
+    Lines,             // 一致した各行を出力
+    Count,             // 一致した行数を出力
+    FilesWithMatches,  // 一致したファイル名のみ出力
+    FilesWithoutMatch, // 一致しなかったファイル名のみ出力
+}
+
+// 出力する行の種類: 文脈として出力する行は区切り文字を変える
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum LineKind {
+    Selected, // 一致した行(-vでは一致しなかった行)
+    Context,  // -A, -Bで一致した行の前後に出力する行
+}
+
+// 各行の分類結果: 出力モードに関わらず、行の選択はこの判定のみで決まる
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum MatchDecision {
+    Selected,  // 一致した行(-vでは一致しなかった行)
+    Rejected,  // 選択されなかった行: 文脈として出力される場合がある
+    Exhausted, // -mの上限に達した後の行: 一致するかどうかは判定しない
+}
+
+// パターンの一致、-vによる反転、-mの上限をまとめて行を分類する
+struct Selector<'a> {
+    patterns: &'a Matcher,
+    invert_match: bool,
+    max_count: Option<usize>,
+    selected: usize, // これまでに選択した行数
+}
+
+impl<'a> Selector<'a> {
+    fn new(patterns: &'a Matcher, invert_match: bool, max_count: Option<usize>) -> Self {
+        Selector { patterns, invert_match, max_count, selected: 0 }
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.max_count == Some(self.selected)
+    }
+
+    fn decide(&mut self, line: &str) -> MatchDecision {
+        if self.is_exhausted() {
+            MatchDecision::Exhausted
+        } else if self.patterns.is_match(line) ^ self.invert_match { // XORで条件分岐: true/false または false/true
+            self.selected += 1;
+            MatchDecision::Selected
+        } else {
+            MatchDecision::Rejected
+        }
+    }
+}
+
+// 一致した行と、ファイル内での位置
+#[derive(Debug, PartialEq)]
+struct Match<'a> {
+    line_num: usize,  // 1始まりの行番号
+    byte_offset: u64, // 行頭のバイト位置(0始まり)
+    line: &'a str,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -23,7 +164,7 @@ pub fn get_args() -> MyResult<Config> {
             Arg::with_name("pattern")
                 .value_name("PATTERN")
                 .help("Search pattern")
-                .required(true),
+                .required_unless_one(&["regexp", "pattern_file"]), // -e, -f指定時は全ての位置引数がファイル
         )
         .arg(
             Arg::with_name("files")
@@ -32,6 +173,25 @@ pub fn get_args() -> MyResult<Config> {
                 .multiple(true)
                 .default_value("-"),
         )
+        .arg(
+            Arg::with_name("regexp")
+                .short("e")
+                .long("regexp")
+                .value_name("PATTERN")
+                .help("Search pattern (may be repeated)")
+                .multiple(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true), // -で始まるパターンも指定できる
+        )
+        .arg(
+            Arg::with_name("pattern_file")
+                .short("f")
+                .long("file")
+                .value_name("FILE")
+                .help("Read newline-separated patterns from FILE")
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::with_name("insensitive")
                 .short("i")
@@ -39,6 +199,14 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Case-insensitive")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("engine")
+                .long("engine")
+                .value_name("ENGINE")
+                .help("Regex engine [default|pcre]; pcre supports lookaround and backreferences")
+                .takes_value(true)
+                .default_value("default"),
+        )
         .arg(
             Arg::with_name("recursive")
                 .short("r")
@@ -53,6 +221,22 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Count occurrences")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("files_with_matches")
+                .short("l")
+                .long("files-with-matches")
+                .help("Print only names of files with matches")
+                .takes_value(false)
+                .conflicts_with_all(&["count", "files_without_match"]),
+        )
+        .arg(
+            Arg::with_name("files_without_match")
+                .short("L")
+                .long("files-without-match")
+                .help("Print only names of files without matches")
+                .takes_value(false)
+                .conflicts_with("count"),
+        )
         .arg(
             Arg::with_name("invert")
                 .short("v")
@@ -60,74 +244,264 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Invert match")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("only_matching")
+                .short("o")
+                .long("only-matching")
+                .help("Print only the matched parts of selected lines")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("replace")
+                .long("replace")
+                .value_name("TEMPLATE")
+                .help("Print selected lines with each match replaced by TEMPLATE; $1 or ${name} refers to a capture group")
+                .takes_value(true)
+                .conflicts_with_all(&["count", "files_with_matches", "files_without_match"]),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print selected and context lines as JSON objects, one per line, in ripgrep's format")
+                .takes_value(false)
+                .conflicts_with_all(&["count", "files_with_matches", "files_without_match", "only_matching", "replace"]),
+        )
+        .arg(
+            Arg::with_name("line_number")
+                .short("n")
+                .long("line-number")
+                .help("Print line numbers")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("byte_offset")
+                .short("b")
+                .long("byte-offset")
+                .help("Print byte offsets")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .help("Suppress output and exit on first match")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("search_zip")
+                .short("z")
+                .long("search-zip")
+                .help("Detect gzip/zstd input by its contents, including STDIN")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("include")
+                .long("include")
+                .value_name("GLOB")
+                .help("Search only files whose name matches GLOB")
+                .multiple(true)
+                .number_of_values(1), // 後続の位置引数を値として消費しないように1つずつ指定
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .long("exclude")
+                .value_name("GLOB")
+                .help("Skip files and directories whose name matches GLOB")
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Highlight matches [auto|always|never]; colors are read from GREP_COLORS")
+                .takes_value(true)
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("max_count")
+                .short("m")
+                .long("max-count")
+                .value_name("NUM")
+                .help("Stop reading a file after NUM selected lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("after_context")
+                .short("A")
+                .long("after-context")
+                .value_name("NUM")
+                .help("Print NUM lines of trailing context after selected lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("before_context")
+                .short("B")
+                .long("before-context")
+                .value_name("NUM")
+                .help("Print NUM lines of leading context before selected lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("context")
+                .short("C")
+                .long("context")
+                .value_name("NUM")
+                .help("Print NUM lines of leading and trailing context")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("threads")
+                .short("j")
+                .long("threads")
+                .value_name("NUM")
+                .help("Search files with NUM threads [default: number of CPUs with --recursive, otherwise 1]")
+                .takes_value(true),
+        )
         .get_matches();
 
-    let pattern_str = matches.value_of("pattern").unwrap();
-    let pattern = RegexBuilder::new(pattern_str) // ビルダーを利用
-        .case_insensitive(matches.is_present("insensitive")) // 大文字小文字の区別ありなしを設定
-        .build() // 正規表現をビルド
-        .map_err(|_| format!("Invalid pattern \"{}\"", pattern_str))?;
+    let mut files = matches.values_of_lossy("files").unwrap();
+    let patterns = if matches.is_present("regexp") || matches.is_present("pattern_file") {
+        let mut patterns = matches.values_of_lossy("regexp").unwrap_or_default();
+        for filename in matches.values_of_lossy("pattern_file").unwrap_or_default() {
+            patterns.extend(read_patterns(&filename)?);
+        }
+        // 最初の位置引数はパターンではなくファイルとして扱う
+        if let Some(first) = matches.value_of("pattern") {
+            if matches.occurrences_of("files") == 0 {
+                files.clear(); // デフォルトのstdinを置き換える
+            }
+            files.insert(0, first.to_string());
+        }
+        patterns
+    } else {
+        vec![matches.value_of("pattern").unwrap().to_string()]
+    };
+    let engine = matches.value_of("engine").unwrap().parse::<Engine>()?; // デフォルト値があるのでunwrap可能
+    let patterns = Matcher::new(&patterns, matches.is_present("insensitive"), engine)?;
+
+    let mode = if matches.is_present("count") {
+        OutputMode::Count
+    } else if matches.is_present("files_with_matches") {
+        OutputMode::FilesWithMatches
+    } else if matches.is_present("files_without_match") {
+        OutputMode::FilesWithoutMatch
+    } else {
+        OutputMode::Lines
+    };
+
+    let filter = GlobFilter::new(
+        &matches.values_of_lossy("include").unwrap_or_default(),
+        &matches.values_of_lossy("exclude").unwrap_or_default(),
+    )?;
+
+    let max_count = matches.value_of("max_count")
+        .map(|val| val.parse().map_err(|_| format!("Invalid --max-count \"{}\"", val)))
+        .transpose()?;
+    // -A, -Bの指定がない場合は-Cの値を使う
+    let context = parse_context(matches.value_of("context"), "--context")?;
+    let after_context = parse_context(matches.value_of("after_context"), "--after-context")?
+        .or(context)
+        .unwrap_or(0);
+    let before_context = parse_context(matches.value_of("before_context"), "--before-context")?
+        .or(context)
+        .unwrap_or(0);
+
+    let recursive = matches.is_present("recursive");
+    let threads = match matches.value_of("threads") {
+        Some(val) => val.parse().ok()
+            .filter(|&num| num > 0)
+            .ok_or_else(|| format!("Invalid --threads \"{}\"", val))?,
+        // 再帰探索ではCPU数だけ並列に検索する
+        None if recursive => thread::available_parallelism().map_or(1, |num| num.get()),
+        None => 1,
+    };
 
     Ok(
         Config {
-            pattern: pattern,
-            files: matches.values_of_lossy("files").unwrap(),
-            recursive: matches.is_present("recursive"),
-            count: matches.is_present("count"),
-            invert_match: matches.is_present("invert")
+            patterns,
+            files,
+            recursive,
+            mode,
+            invert_match: matches.is_present("invert"),
+            only_matching: matches.is_present("only_matching"),
+            replace: matches.value_of("replace").map(str::to_string),
+            json: matches.is_present("json"),
+            line_number: matches.is_present("line_number"),
+            byte_offset: matches.is_present("byte_offset"),
+            quiet: matches.is_present("quiet"),
+            search_zip: matches.is_present("search_zip"),
+            filter,
+            color: matches.value_of("color").unwrap().parse()?, // デフォルト値があるのでunwrap可能
+            max_count,
+            before_context,
+            after_context,
+            threads,
         }
     )
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
-    }
+fn parse_context(val: Option<&str>, name: &str) -> MyResult<Option<usize>> {
+    val.map(|val| val.parse().map_err(|_| format!("Invalid {} \"{}\"", name, val).into()))
+        .transpose()
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let entries = find_files(&config.files, config.recursive);
-    let num_files = entries.len();
-    let print = |fname: &str, val: &str| {
-        if num_files > 1 {
-            print!("{}:{}", fname, val);
-        } else {
-            print!("{}", val);
+// 1行1パターンとして読み込む
+fn read_patterns(filename: &str) -> MyResult<Vec<String>> {
+    let file = input::open(filename, false).map_err(|e| format!("{}: {}", filename, e))?;
+    Ok(file.lines().collect::<Result<_, _>>()?)
+}
+
+pub fn run(config: Config) -> MyResult<Status> {
+    let entries = find_files(&config.files, config.recursive, &config.filter);
+    let mut printer = Printer::new(
+        io::stdout().lock(),
+        // 再帰探索ではglobで絞り込まれて1ファイルになってもファイル名を出力する
+        config.recursive || entries.len() > 1,
+        config.line_number,
+        config.byte_offset,
+        // 色付けが有効な場合のみGREP_COLORSを読み込む: JSONでは色付けしない
+        (config.color.enabled() && !config.json).then(Colors::from_env),
+        // 文脈を出力する場合は連続しない行の間に"--"を出力: -oでは文脈を出力せず、JSONでは区切らない
+        (config.before_context > 0 || config.after_context > 0) && !config.only_matching && !config.json,
+        config.json,
+    );
+    let mut matched = false;
+    let mut has_error = false;
+    // 入力の順に各ファイルの結果を受け取る: 残りのファイルを読まずに終了する場合はtrue
+    let mut on_result = |result: MyResult<bool>| match result {
+        Err(e) => {
+            eprintln!("{}", e);
+            has_error = true;
+            false
+        }
+        Ok(found) => {
+            matched |= found;
+            // 静かなモードでは最初に一致した時点で残りのファイルを読まずに終了
+            found && config.quiet
         }
     };
-    for entry in entries {
-        match entry {
-            Err(e) => eprintln!("{}", e),
-            Ok(filename) => match open(&filename) {
-                Err(e) => eprintln!("{}: {}", filename, e),
-                Ok(file) => {
-                    match find_lines(
-                        file,
-                        &config.pattern,
-                        config.invert_match,
-                    ) {
-                        Err(e) => eprintln!("{}", e),
-                        Ok(matches) => {
-                            if config.count {
-                                // 検索にヒットした行数カウントを出力
-                                print(&filename, &format!("{}\n", matches.len()));
-                            } else {
-                                // 検索にヒットした各行をそれぞれ出力
-                                for line in matches {
-                                    print(&filename, &line);
-                                }
-                            }
-                        }
-                    }
-                }
+    if config.threads > 1 && entries.len() > 1 {
+        parallel::search_files(&entries, &config, &mut printer, &mut on_result)?;
+    } else {
+        for entry in entries {
+            if on_result(entry.and_then(|filename| search_file(&filename, &config, &mut printer))) {
+                break;
             }
         }
     }
-    Ok(())
+    Ok(if matched && config.quiet {
+        Status::Match
+    } else if has_error {
+        Status::Error
+    } else if matched {
+        Status::Match
+    } else {
+        Status::NoMatch
+    })
 }
 
-fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
+fn find_files(paths: &[String], recursive: bool, filter: &GlobFilter) -> Vec<MyResult<String>> {
     let mut results = vec![];
     for path in paths {
         match path.as_str() {
@@ -143,8 +517,15 @@ fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
                         if recursive {
                             for entry in WalkDir::new(path) // ディレクトリ内を再帰的に探索
                                 .into_iter()
+                                // 除外パターンに一致したエントリは読み込む前に枝刈りする: 指定されたパス自体は対象とする
+                                .filter_entry(|dir_entry| {
+                                    dir_entry.depth() == 0 || !filter.is_excluded(dir_entry.file_name())
+                                })
                                 .flatten()
-                                .filter(|dir_entry| dir_entry.file_type().is_file()) { // ファイルのみをフィルタリング
+                                .filter(|dir_entry| {
+                                    dir_entry.file_type().is_file() // ファイルのみをフィルタリング
+                                        && filter.is_included(dir_entry.file_name())
+                                }) {
                                 results.push(
                                     Ok(
                                         entry.path()
@@ -165,79 +546,388 @@ fn find_files(paths: &[String], recursive: bool) -> Vec<MyResult<String>> {
     results
 }
 
-fn find_lines<T: BufRead>(mut file: T, pattern: &Regex, invert_match: bool) -> MyResult<Vec<String>> {
-    let mut matches = vec![];
+// 1つのファイルを開いて検索する: エラーにはファイル名を付ける
+fn search_file<W: Write>(filename: &str, config: &Config, printer: &mut Printer<W>) -> MyResult<bool> {
+    // .gzや.zstのファイルは展開しながら検索する
+    let file = input::open(filename, config.search_zip).map_err(|e| format!("{}: {}", filename, e))?;
+    if config.quiet {
+        has_match(file, &config.patterns, config.invert_match, config.max_count)
+    } else {
+        search(file, filename, config, printer)
+    }
+    .map_err(|e| format!("{}: {}", filename, e).into()) // 展開に失敗した場合もファイル名を付ける
+}
+
+// 検索しながら結果を順次出力する: 一致した行は保持しない
+// 返り値は選択された結果があったかどうか: -Lでは出力したファイルがあった場合にtrue
+fn search<T: BufRead, W: Write>(
+    file: T,
+    filename: &str,
+    config: &Config,
+    printer: &mut Printer<W>,
+) -> MyResult<bool> {
+    match config.mode {
+        OutputMode::Lines => {
+            // 検索にヒットした各行をそれぞれ出力
+            let mut found = false;
+            printer.start_file();
+            let limits = if config.only_matching {
+                Limits { max_count: config.max_count, ..Default::default() }
+            } else {
+                Limits {
+                    max_count: config.max_count,
+                    before_context: config.before_context,
+                    after_context: config.after_context,
+                }
+            };
+            find_lines(file, &config.patterns, config.invert_match, limits, |m, kind| {
+                if kind == LineKind::Context {
+                    return printer.context(filename, &m);
+                }
+                found = true;
+                if config.only_matching {
+                    // 反転時の行には一致部分がないため何も出力しない
+                    if !config.invert_match {
+                        let line = m.line.strip_suffix('\n').unwrap_or(m.line);
+                        match &config.replace {
+                            Some(template) => {
+                                for (range, text) in config.patterns.replacements(line, template) {
+                                    printer.only_replaced(filename, &m, range.start, &text)?;
+                                }
+                            }
+                            None => {
+                                for range in config.patterns.find_ranges(line) {
+                                    printer.only_matching(filename, &m, range)?;
+                                }
+                            }
+                        }
+                    }
+                    return Ok(());
+                }
+                // 置き換えた部分を色付けする: 反転時の行は置き換えずにそのまま出力する
+                if let (Some(template), false) = (&config.replace, config.invert_match) {
+                    let line = m.line.strip_suffix('\n').unwrap_or(m.line);
+                    let (mut replaced, ranges) = config.patterns.replace(line, template);
+                    replaced.push_str(&m.line[line.len()..]); // 改行を戻す
+                    return printer.line(filename, &Match { line: &replaced, ..m }, &ranges);
+                }
+                // 反転時の行には一致部分がないため色付けしない
+                let highlights = if printer.wants_ranges() && !config.invert_match {
+                    config.patterns.find_ranges(m.line)
+                } else {
+                    vec![]
+                };
+                printer.line(filename, &m, &highlights)
+            })?;
+            Ok(found)
+        }
+        OutputMode::Count => {
+            // 検索にヒットした行数カウントを出力
+            let count = count_lines(file, &config.patterns, config.invert_match, config.max_count)?;
+            printer.count(filename, count)?;
+            Ok(count > 0)
+        }
+        OutputMode::FilesWithMatches | OutputMode::FilesWithoutMatch => {
+            // -lでは一致したファイル、-Lでは一致しなかったファイルの名前を出力
+            let matched = has_match(file, &config.patterns, config.invert_match, config.max_count)?;
+            let selected = matched == (config.mode == OutputMode::FilesWithMatches);
+            if selected {
+                printer.filename(filename)?;
+            }
+            Ok(selected)
+        }
+    }
+}
+
+// 1行ずつ分類してon_lineに渡す: 行バッファを使い回すのでメモリ使用量は一定
+// on_lineは-mの上限に達した後も続きの行が必要かを返し、不要になった時点で読み込みを打ち切る
+fn scan<T, F>(mut file: T, selector: &mut Selector, mut on_line: F) -> MyResult<()>
+where
+    T: BufRead,
+    F: FnMut(Match, MatchDecision) -> MyResult<bool>,
+{
     let mut line = String::new();
+    let mut line_num = 0;
+    let mut byte_offset = 0;
+    let mut pending = false;
 
-    loop {
+    while !selector.is_exhausted() || pending {
         let bytes = file.read_line(&mut line)?;
         if bytes == 0 {
             break; // EOF
         }
-        if pattern.is_match(&line) ^ invert_match { // XORで条件分岐: true/false または false/true
-            matches.push(mem::take(&mut line)); // 不必要なコピーを避けつつ必要であれば複製: cloneとほぼ同じ
-        }
+        line_num += 1;
+        let decision = selector.decide(&line);
+        pending = on_line(Match { line_num, byte_offset, line: &line }, decision)?;
+        byte_offset += bytes as u64;
         line.clear();
     }
-    Ok(matches)
+    Ok(())
+}
+
+// 一致した行数のみを数える: 行の内容は保持しない
+// max_countに達した時点で読み込みを打ち切る
+fn count_lines<T: BufRead>(
+    file: T,
+    patterns: &Matcher,
+    invert_match: bool,
+    max_count: Option<usize>,
+) -> MyResult<usize> {
+    let mut selector = Selector::new(patterns, invert_match, max_count);
+    scan(file, &mut selector, |_, _| Ok(false))?;
+    Ok(selector.selected)
+}
+
+// 最初に一致した行が見つかった時点で読み込みを打ち切る: -m 0では何も読まない
+fn has_match<T: BufRead>(
+    file: T,
+    patterns: &Matcher,
+    invert_match: bool,
+    max_count: Option<usize>,
+) -> MyResult<bool> {
+    let max_count = max_count.map_or(1, |max| max.min(1));
+    Ok(count_lines(file, patterns, invert_match, Some(max_count))? > 0)
+}
+
+// 行単位の出力での読み込みの制限
+#[derive(Debug, Default, Clone, Copy)]
+struct Limits {
+    max_count: Option<usize>, // 一致した行がこの数に達したら、後続の文脈を出力して打ち切る
+    before_context: usize,    // 一致した行の前に出力する行数
+    after_context: usize,     // 一致した行の後に出力する行数
+}
+
+// 一致した行と前後の文脈の行ごとにon_lineを呼び出す: 前の文脈は最大before_context行のみ保持する
+fn find_lines<T, F>(file: T, patterns: &Matcher, invert_match: bool, limits: Limits, mut on_line: F) -> MyResult<()>
+where
+    T: BufRead,
+    F: FnMut(Match, LineKind) -> MyResult<()>,
+{
+    let mut selector = Selector::new(patterns, invert_match, limits.max_count);
+    let mut before: VecDeque<(usize, u64, String)> = VecDeque::with_capacity(limits.before_context);
+    let mut remaining = 0; // 出力が残っている後の文脈の行数
+
+    scan(file, &mut selector, |m, decision| {
+        match decision {
+            MatchDecision::Selected => {
+                for (line_num, byte_offset, line) in before.drain(..) {
+                    on_line(Match { line_num, byte_offset, line: &line }, LineKind::Context)?;
+                }
+                on_line(m, LineKind::Selected)?;
+                remaining = limits.after_context;
+            }
+            // 上限に達した後の行は一致するかどうかに関わらず文脈として出力する
+            MatchDecision::Rejected | MatchDecision::Exhausted if remaining > 0 => {
+                on_line(m, LineKind::Context)?;
+                remaining -= 1;
+            }
+            // 出力していない行は次に一致した行の前の文脈の候補として保持する
+            MatchDecision::Rejected if limits.before_context > 0 => {
+                if before.len() == limits.before_context {
+                    before.pop_front();
+                }
+                before.push_back((m.line_num, m.byte_offset, m.line.to_string()));
+            }
+            MatchDecision::Rejected | MatchDecision::Exhausted => {}
+        }
+        Ok(remaining > 0)
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{find_files, find_lines};
+    use super::{
+        count_lines, find_files, find_lines, has_match, Engine, GlobFilter, LineKind, Limits, Match, MatchDecision, Matcher,
+        MyResult, Selector,
+    };
     use rand::{distributions::Alphanumeric, Rng};
-    use regex::{Regex, RegexBuilder};
     use std::io::Cursor;
 
+    // 一致した行を(行番号, バイト位置, 行)として集める
+    fn collect_lines(text: &[u8], patterns: &Matcher, invert_match: bool) -> MyResult<Vec<(usize, u64, String)>> {
+        let mut matches = vec![];
+        find_lines(Cursor::new(text), patterns, invert_match, Limits::default(), |m: Match, _| {
+            matches.push((m.line_num, m.byte_offset, m.line.to_string()));
+            Ok(())
+        })?;
+        Ok(matches)
+    }
+
+    // 制限付きで出力する行を(行番号, 種類)として集める
+    fn collect_limited(text: &[u8], patterns: &Matcher, limits: Limits) -> Vec<(usize, LineKind)> {
+        let mut lines = vec![];
+        find_lines(Cursor::new(text), patterns, false, limits, |m: Match, kind| {
+            lines.push((m.line_num, kind));
+            Ok(())
+        })
+        .unwrap();
+        lines
+    }
+
+    #[test]
+    fn test_find_lines_limits() {
+        use LineKind::*;
+        let text = b"a\na\nb\nc\na\nd\n";
+        let re = Matcher::new(&["a"], false, Engine::Default).unwrap();
+
+        let limits = Limits { max_count: Some(2), ..Default::default() };
+        assert_eq!(collect_limited(text, &re, limits), [(1, Selected), (2, Selected)]);
+
+        // 上限に達した後の文脈は一致する行も含めて出力する
+        let limits = Limits { max_count: Some(1), after_context: 2, ..Default::default() };
+        assert_eq!(collect_limited(text, &re, limits), [(1, Selected), (2, Context), (3, Context)]);
+
+        // 一致した行が続く場合は文脈の残りが延長される
+        let limits = Limits { after_context: 1, ..Default::default() };
+        assert_eq!(
+            collect_limited(text, &re, limits),
+            [(1, Selected), (2, Selected), (3, Context), (5, Selected), (6, Context)]
+        );
+
+        let limits = Limits { max_count: Some(0), after_context: 1, ..Default::default() };
+        assert_eq!(collect_limited(text, &re, limits), []);
+
+        // 前の文脈は出力済みの行と重複しない
+        let limits = Limits { before_context: 2, ..Default::default() };
+        assert_eq!(
+            collect_limited(text, &re, limits),
+            [(1, Selected), (2, Selected), (3, Context), (4, Context), (5, Selected)]
+        );
+        let limits = Limits { max_count: Some(1), before_context: 1, after_context: 1 };
+        assert_eq!(collect_limited(b"b\nc\na\nd\na\n", &re, limits), [(2, Context), (3, Selected), (4, Context)]);
+    }
+
+    #[test]
+    fn test_selector() {
+        use MatchDecision::*;
+        let re = Matcher::new(&["a"], false, Engine::Default).unwrap();
+        let mut selector = Selector::new(&re, false, Some(2));
+        let decisions: Vec<_> = ["a", "b", "a", "a"].iter().map(|line| selector.decide(line)).collect();
+        assert_eq!(decisions, [Selected, Rejected, Selected, Exhausted]);
+
+        // 反転しても上限は選択された行に対して数える
+        let mut selector = Selector::new(&re, true, Some(1));
+        let decisions: Vec<_> = ["a", "b", "c"].iter().map(|line| selector.decide(line)).collect();
+        assert_eq!(decisions, [Rejected, Selected, Exhausted]);
+        assert!(selector.is_exhausted());
+    }
+
+    #[test]
+    fn test_find_lines_inverted_context() {
+        use LineKind::*;
+        let re = Matcher::new(&["a"], false, Engine::Default).unwrap();
+        let mut lines = vec![];
+        let limits = Limits { before_context: 1, after_context: 1, ..Default::default() };
+        find_lines(Cursor::new(b"a\na\nb\na\na\na\nc\n"), &re, true, limits, |m: Match, kind| {
+            lines.push((m.line_num, kind));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(lines, [(2, Context), (3, Selected), (4, Context), (6, Context), (7, Selected)]);
+    }
+
     #[test]
     fn test_find_lines() {
         let text = b"Lorem\nIpsum\r\nDOLOR";
 
         // The pattern _or_ should match the one line, "Lorem"
-        let re1 = Regex::new("or").unwrap();
-        let matches = find_lines(Cursor::new(&text), &re1, false);
+        let re1 = Matcher::new(&["or"], false, Engine::Default).unwrap();
+        let matches = collect_lines(text, &re1, false);
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 1);
 
         // When inverted, the function should match the other two lines
-        let matches = find_lines(Cursor::new(&text), &re1, true);
+        let matches = collect_lines(text, &re1, true);
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 2);
 
         // This regex will be case-insensitive
-        let re2 = RegexBuilder::new("or")
-            .case_insensitive(true)
-            .build()
-            .unwrap();
+        let re2 = Matcher::new(&["or"], true, Engine::Default).unwrap();
 
         // The two lines "Lorem" and "DOLOR" should match
-        let matches = find_lines(Cursor::new(&text), &re2, false);
+        let matches = collect_lines(text, &re2, false);
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 2);
 
         // When inverted, the one remaining line should match
-        let matches = find_lines(Cursor::new(&text), &re2, true);
+        let matches = collect_lines(text, &re2, true);
         assert!(matches.is_ok());
         assert_eq!(matches.unwrap().len(), 1);
+
+        // Line numbers and byte offsets refer to the start of each line
+        let matches = collect_lines(text, &re2, false).unwrap();
+        assert_eq!(
+            matches,
+            vec![
+                (1, 0, "Lorem\n".to_string()),
+                (3, 13, "DOLOR".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_lines() {
+        let text = b"Lorem\nIpsum\r\nDOLOR";
+        let re = Matcher::new(&["or"], false, Engine::Default).unwrap();
+
+        let count = count_lines(Cursor::new(&text), &re, false, None);
+        assert!(count.is_ok());
+        assert_eq!(count.unwrap(), 1);
+
+        let count = count_lines(Cursor::new(&text), &re, true, None);
+        assert!(count.is_ok());
+        assert_eq!(count.unwrap(), 2);
+
+        // The count never exceeds the maximum
+        let count = count_lines(Cursor::new(&text), &re, true, Some(1));
+        assert!(count.is_ok());
+        assert_eq!(count.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_has_match() {
+        let text = b"Lorem\nIpsum\r\nDOLOR";
+
+        let re1 = Matcher::new(&["or"], false, Engine::Default).unwrap();
+        let matched = has_match(Cursor::new(&text), &re1, false, None);
+        assert!(matched.is_ok());
+        assert!(matched.unwrap());
+
+        // "DOLOR" has no lowercase letters, so it matches when inverted
+        let re2 = Matcher::new(&["[a-z]"], false, Engine::Default).unwrap();
+        let matched = has_match(Cursor::new(&text), &re2, true, None);
+        assert!(matched.is_ok());
+        assert!(matched.unwrap());
+
+        // Every line matches, so nothing is left when inverted
+        let re3 = Matcher::new(&["."], false, Engine::Default).unwrap();
+        let matched = has_match(Cursor::new(&text), &re3, true, None);
+        assert!(matched.is_ok());
+        assert!(!matched.unwrap());
+
+        // Nothing is read with a maximum of zero
+        let matched = has_match(Cursor::new(&text), &re1, false, Some(0));
+        assert!(matched.is_ok());
+        assert!(!matched.unwrap());
     }
 
     #[test]
     fn test_find_files() {
         // Verify that the function finds a file known to exist
         let files =
-            find_files(&["./tests/inputs/fox.txt".to_string()], false);
+            find_files(&["./tests/inputs/fox.txt".to_string()], false, &GlobFilter::default());
         assert_eq!(files.len(), 1);
         assert_eq!(files[0].as_ref().unwrap(), "./tests/inputs/fox.txt");
 
         // The function should reject a directory without the recursive option
-        let files = find_files(&["./tests/inputs".to_string()], false);
+        let files = find_files(&["./tests/inputs".to_string()], false, &GlobFilter::default());
         assert_eq!(files.len(), 1);
         if let Err(e) = &files[0] {
             assert_eq!(e.to_string(), "./tests/inputs is a directory");
         }
 
         // Verify the function recurses to find four files in the directory
-        let res = find_files(&["./tests/inputs".to_string()], true);
+        let res = find_files(&["./tests/inputs".to_string()], true, &GlobFilter::default());
         let mut files: Vec<String> = res
             .iter()
             .map(|r| r.as_ref().unwrap().replace("\\", "/"))
@@ -262,8 +952,53 @@ mod tests {
             .collect();
 
         // Verify that the function returns the bad file as an error
-        let files = find_files(&[bad], false);
+        let files = find_files(&[bad], false, &GlobFilter::default());
         assert_eq!(files.len(), 1);
         assert!(files[0].is_err());
     }
+
+    // 再帰探索の結果をソートしたパスの一覧として返す
+    fn find_sorted(path: &str, include: &[&str], exclude: &[&str]) -> Vec<String> {
+        let to_vec = |globs: &[&str]| globs.iter().map(|g| g.to_string()).collect::<Vec<_>>();
+        let filter = GlobFilter::new(&to_vec(include), &to_vec(exclude)).unwrap();
+        let mut files: Vec<String> = find_files(&[path.to_string()], true, &filter)
+            .iter()
+            .map(|r| r.as_ref().unwrap().replace("\\", "/"))
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_find_files_globs() {
+        // Only files matching an include pattern are kept
+        assert_eq!(
+            find_sorted("./tests/inputs", &["b*", "n*"], &[]),
+            ["./tests/inputs/bustle.txt", "./tests/inputs/nobody.txt"]
+        );
+
+        // Exclude takes precedence over include
+        assert_eq!(
+            find_sorted("./tests/inputs", &["*.txt"], &["nobody.txt", "e*"]),
+            ["./tests/inputs/bustle.txt", "./tests/inputs/fox.txt"]
+        );
+
+        // Excluded directories are pruned, but the starting path is not
+        assert_eq!(
+            find_sorted("./tests", &[], &["expected", "compressed"]),
+            [
+                "./tests/cli.rs",
+                "./tests/inputs/bustle.txt",
+                "./tests/inputs/empty.txt",
+                "./tests/inputs/fox.txt",
+                "./tests/inputs/nobody.txt",
+            ]
+        );
+        assert_eq!(find_sorted("./tests/inputs", &[], &["inputs"]).len(), 4);
+
+        // Invalid patterns are rejected
+        let res = GlobFilter::new(&["[".to_string()], &[]);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "Invalid --include \"[\"");
+    }
 }
@@ -1,8 +1,11 @@
 use std::process::exit;
 
 fn main() {
-    if let Err(e) = grepr::get_args().and_then(grepr::run) {
-        eprintln!("{}", e);
-        exit(1);
+    match grepr::get_args().and_then(grepr::run) {
+        Ok(status) => exit(status.code()), // 一致の有無を終了ステータスで返す
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
     }
 }
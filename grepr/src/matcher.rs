@@ -0,0 +1,270 @@
+use std::{ops::Range, str::FromStr};
+
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+
+use crate::MyResult;
+
+// --engineで指定する正規表現エンジン
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Engine {
+    Default, // regexクレート: 線形時間で検索できるが、後方参照や先読み・後読みは使えない
+    Pcre,    // fancy-regexクレート: 後方参照や先読み・後読みを使えるが、バックトラックにより遅くなる場合がある
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Engine::Default),
+            "pcre" => Ok(Engine::Pcre),
+            _ => Err(format!("Invalid --engine \"{}\"", s)),
+        }
+    }
+}
+
+// 1つの検索パターン: エンジンの違いはここで吸収する
+// PCRE風のエンジンでバックトラックの上限を超えるなどして検索に失敗した場合は、一致しなかったものとみなす
+#[derive(Debug)]
+enum Pattern {
+    Default(Regex),
+    Pcre(fancy_regex::Regex),
+}
+
+impl Pattern {
+    fn new(pattern: &str, insensitive: bool, engine: Engine) -> Option<Self> {
+        match engine {
+            Engine::Default => RegexBuilder::new(pattern)
+                .case_insensitive(insensitive)
+                .build()
+                .ok()
+                .map(Pattern::Default),
+            // ビルダーの大文字小文字の指定は後方参照などの部分に効かないため、インラインフラグで指定する
+            Engine::Pcre => {
+                let pattern = if insensitive { format!("(?i){}", pattern) } else { pattern.to_string() };
+                fancy_regex::Regex::new(&pattern).ok().map(Pattern::Pcre)
+            }
+        }
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            Pattern::Default(re) => re.is_match(line),
+            Pattern::Pcre(re) => re.is_match(line).unwrap_or(false),
+        }
+    }
+
+    fn find_iter(&self, line: &str) -> Vec<Range<usize>> {
+        match self {
+            Pattern::Default(re) => re.find_iter(line).map(|m| m.range()).collect(),
+            Pattern::Pcre(re) => re.find_iter(line).map_while(Result::ok).map(|m| m.range()).collect(),
+        }
+    }
+
+    // 一致した範囲と、そこをテンプレートで置き換えた文字列
+    fn expand_iter(&self, line: &str, template: &str) -> Vec<(Range<usize>, String)> {
+        let mut expanded = vec![];
+        match self {
+            Pattern::Default(re) => {
+                for caps in re.captures_iter(line) {
+                    let mut text = String::new();
+                    caps.expand(template, &mut text);
+                    expanded.push((caps.get(0).unwrap().range(), text));
+                }
+            }
+            Pattern::Pcre(re) => {
+                for caps in re.captures_iter(line).map_while(Result::ok) {
+                    let mut text = String::new();
+                    caps.expand(template, &mut text);
+                    expanded.push((caps.get(0).unwrap().range(), text));
+                }
+            }
+        }
+        expanded
+    }
+}
+
+// 複数の検索パターン: いずれかに一致する行を一致とみなす
+#[derive(Debug)]
+pub struct Matcher {
+    set: Option<RegexSet>, // 行が一致するかの判定用: 既定のエンジンのみ
+    patterns: Vec<Pattern>, // 一致した範囲の取得用: 色付けと-o、--replaceでのみ使う
+}
+
+impl Matcher {
+    // パターンが空の場合はどの行にも一致しない
+    pub fn new<S: AsRef<str>>(patterns: &[S], insensitive: bool, engine: Engine) -> MyResult<Self> {
+        // どのパターンが不正かを示すため、個別に検証してからまとめる
+        let compiled = patterns
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern.as_ref(), insensitive, engine)
+                    .ok_or_else(|| format!("Invalid pattern \"{}\"", pattern.as_ref()))
+            })
+            .collect::<Result<_, _>>()?;
+        // PCRE風のパターンはRegexSetにまとめられないため、パターンごとに判定する
+        let set = match engine {
+            Engine::Default => Some(
+                RegexSetBuilder::new(patterns)
+                    .case_insensitive(insensitive) // 大文字小文字の区別ありなしを設定
+                    .build()?,
+            ),
+            Engine::Pcre => None,
+        };
+        Ok(Matcher { set, patterns: compiled })
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        match &self.set {
+            Some(set) => set.is_match(line),
+            None => self.patterns.iter().any(|pattern| pattern.is_match(line)),
+        }
+    }
+
+    // いずれかのパターンに一致した範囲を昇順で返す: 重なる範囲や隣接する範囲はまとめる
+    pub fn find_ranges(&self, line: &str) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = self
+            .patterns
+            .iter()
+            .flat_map(|pattern| pattern.find_iter(line))
+            .filter(|range| !range.is_empty()) // 空文字列への一致は色付けできない
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = vec![];
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
+    // 一致した範囲と、そこをテンプレートで置き換えた文字列を昇順で返す
+    // テンプレートの$1や${name}は一致したパターンのキャプチャグループを参照する
+    // 重なる一致は最も左のものを、同じ位置では先に指定したパターンのものを使う
+    pub fn replacements(&self, line: &str, template: &str) -> Vec<(Range<usize>, String)> {
+        let mut candidates: Vec<_> = self
+            .patterns
+            .iter()
+            .flat_map(|pattern| pattern.expand_iter(line, template))
+            .filter(|(range, _)| !range.is_empty()) // 空文字列への一致は置き換えない
+            .collect();
+        candidates.sort_by_key(|(range, _)| range.start); // 安定ソートでパターンの順序を保つ
+        let mut replacements = vec![];
+        let mut end = 0;
+        for (range, text) in candidates {
+            if range.start < end {
+                continue;
+            }
+            end = range.end;
+            replacements.push((range, text));
+        }
+        replacements
+    }
+
+    // 一致した部分を置き換えた行と、出力した行の中で置き換えた部分の範囲を返す
+    pub fn replace(&self, line: &str, template: &str) -> (String, Vec<Range<usize>>) {
+        let mut replaced = String::new();
+        let mut ranges = vec![];
+        let mut pos = 0;
+        for (range, text) in self.replacements(line, template) {
+            replaced.push_str(&line[pos..range.start]);
+            ranges.push(replaced.len()..replaced.len() + text.len());
+            replaced.push_str(&text);
+            pos = range.end;
+        }
+        replaced.push_str(&line[pos..]);
+        ranges.retain(|range| !range.is_empty()); // 空文字列への置き換えは色付けできない
+        (replaced, ranges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Engine, Matcher};
+
+    #[test]
+    fn test_is_match() {
+        let matcher = Matcher::new(&["fox", "^The"], false, Engine::Default).unwrap();
+        assert!(matcher.is_match("quick fox"));
+        assert!(matcher.is_match("The dog"));
+        assert!(!matcher.is_match("the dog"));
+
+        let matcher = Matcher::new(&["fox", "^The"], true, Engine::Default).unwrap();
+        assert!(matcher.is_match("the dog"));
+        assert!(matcher.is_match("FOX"));
+
+        let none: [&str; 0] = [];
+        assert!(!Matcher::new(&none, false, Engine::Default).unwrap().is_match("anything"));
+    }
+
+    #[test]
+    fn test_find_ranges() {
+        let matcher = Matcher::new(&["fox", "o"], false, Engine::Default).unwrap();
+        assert_eq!(matcher.find_ranges("a fox, two foxes"), [2..5, 9..10, 11..14]);
+
+        let matcher = Matcher::new(&["ab", "bc", "x*"], true, Engine::Default).unwrap();
+        assert_eq!(matcher.find_ranges("ABC abc"), [0..3, 4..7]);
+        assert!(matcher.find_ranges("zzz").is_empty());
+    }
+
+    #[test]
+    fn test_replace() {
+        let matcher = Matcher::new(&[r"(\w+)@(\w+)"], false, Engine::Default).unwrap();
+        assert_eq!(
+            matcher.replace("mail bob@example or amy@test", "$2:$1"),
+            ("mail example:bob or test:amy".to_string(), vec![5..16, 20..28])
+        );
+        // 存在しないグループは空文字列になり、$$は$になる
+        assert_eq!(matcher.replace("a@b", "${3}$$").0, "$");
+
+        // 複数のパターンでは最も左の一致を使い、同じ位置では先のパターンを優先する
+        let matcher = Matcher::new(&["(?P<w>fox)", "fo", "x*"], false, Engine::Default).unwrap();
+        assert_eq!(
+            matcher.replacements("a fox, foe", "[${w}]"),
+            [(2..5, "[fox]".to_string()), (7..9, "[]".to_string())]
+        );
+        assert_eq!(matcher.replace("no match", "x"), ("no match".to_string(), vec![]));
+        assert_eq!(matcher.replace("fox", ""), ("".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_new_invalid() {
+        let res = Matcher::new(&["ok", "*foo"], false, Engine::Default);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "Invalid pattern \"*foo\"");
+    }
+
+    #[test]
+    fn test_parse_engine() {
+        assert_eq!("default".parse::<Engine>(), Ok(Engine::Default));
+        assert_eq!("pcre".parse::<Engine>(), Ok(Engine::Pcre));
+        assert_eq!("pcre2".parse::<Engine>(), Err("Invalid --engine \"pcre2\"".to_string()));
+    }
+
+    #[test]
+    fn test_pcre() {
+        // 既定のエンジンでは後読みや後方参照は不正なパターン
+        assert!(Matcher::new(&[r"(?<=\$)\d+"], false, Engine::Default).is_err());
+        assert!(Matcher::new(&[r"(\w)\1"], false, Engine::Default).is_err());
+
+        let matcher = Matcher::new(&[r"(?<=\$)\d+", r"\b(\w+) \1\b"], false, Engine::Pcre).unwrap();
+        assert!(matcher.is_match("costs $42"));
+        assert!(matcher.is_match("the the end"));
+        assert!(!matcher.is_match("costs 42 yen"));
+        assert_eq!(matcher.find_ranges("$5 or $10, the the"), [1..2, 7..9, 11..18]);
+        assert_eq!(
+            matcher.replace("$5 or $10", "<$0>"),
+            ("$<5> or $<10>".to_string(), vec![1..4, 9..13])
+        );
+
+        let matcher = Matcher::new(&["fox(?!es)"], true, Engine::Pcre).unwrap();
+        assert!(matcher.is_match("one Fox"));
+        assert!(!matcher.is_match("two foxes"));
+
+        let res = Matcher::new(&["ok", "(?<=a+"], false, Engine::Pcre);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "Invalid pattern \"(?<=a+\"");
+    }
+}
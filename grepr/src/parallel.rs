@@ -0,0 +1,126 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    sync::{mpsc, Condvar, Mutex},
+    thread,
+};
+
+use crate::{printer::Printer, search_file, Config, MyResult};
+
+// 1ファイル分の検索結果: 出力はメモリに溜め、エラーはスレッド間で受け渡せるよう文字列にする
+type FileResult = Result<(bool, Printer<Vec<u8>>), String>;
+
+// 検索の進み具合: 位置はいずれもjobsの位置
+#[derive(Debug, Default)]
+struct Progress {
+    head: usize, // 出力の順番が来ているファイル
+    next: usize, // 次に検索を始めるファイル
+    stop: bool,  // まだ始めていないファイルを検索しない
+}
+
+// 複数のファイルをconfig.threads個のスレッドで並列に検索する
+// 呼び出し元のスレッドが出力の順番が来たファイルを検索して直接出力し、他のスレッドはその先のファイルを先読みして出力をメモリに溜める
+// 先読みは順番が来たファイルからconfig.threads個先までに限り、溜める出力の数を抑える
+// ファイルごとの出力を入力の順に書き出すため、結果は逐次検索した場合と同じになる
+// on_resultがtrueを返した時点で、まだ始めていないファイルの検索を打ち切る
+pub fn search_files<W: Write>(
+    entries: &[MyResult<String>],
+    config: &Config,
+    printer: &mut Printer<W>,
+    on_result: &mut impl FnMut(MyResult<bool>) -> bool,
+) -> MyResult<()> {
+    // 開けなかったパスは検索せず、出力の順番が来た時点でエラーにする
+    let jobs: Vec<&str> = entries
+        .iter()
+        .filter_map(|entry| entry.as_ref().ok().map(String::as_str))
+        .collect();
+    let window = config.threads;
+    let template = printer.fork();
+    let progress = Mutex::new(Progress::default());
+    let changed = Condvar::new(); // headの移動と打ち切りを知らせる
+    let (tx, rx) = mpsc::channel::<(usize, FileResult)>();
+
+    // 先読みするスレッドが次に検索するファイルを決める: 全て検索し終えたか打ち切った場合はNone
+    let claim = || {
+        let mut state = progress.lock().unwrap();
+        loop {
+            if state.stop || state.next >= jobs.len() {
+                return None;
+            }
+            // 順番が来たファイルは呼び出し元のスレッドが検索する
+            if state.next > state.head && state.next <= state.head + window {
+                state.next += 1;
+                return Some(state.next - 1);
+            }
+            state = changed.wait(state).unwrap();
+        }
+    };
+
+    thread::scope(|scope| {
+        // 呼び出し元のスレッドも検索するため、残りのスレッドを起動する
+        for _ in 0..(config.threads - 1).min(jobs.len()) {
+            let tx = tx.clone();
+            let (jobs, template, claim) = (&jobs, &template, &claim);
+            scope.spawn(move || {
+                while let Some(pos) = claim() {
+                    let mut part = template.fork();
+                    let result = search_file(jobs[pos], config, &mut part)
+                        .map(|found| (found, part))
+                        .map_err(|e| e.to_string());
+                    if tx.send((pos, result)).is_err() {
+                        break; // 出力側が途中で終了した場合は残りを検索しない
+                    }
+                }
+            });
+        }
+        drop(tx); // 全てのスレッドが終了した時点で受信を終える
+
+        // 順番が来るまで、先に終わったファイルの結果を保持する: 先読みの範囲に限られる
+        let mut done: BTreeMap<usize, FileResult> = BTreeMap::new();
+        let mut pos = 0;
+        for entry in entries {
+            let result = match entry {
+                Err(e) => Err(e.to_string().into()),
+                Ok(filename) => {
+                    // 順番が来たことを知らせ、まだ誰も始めていなければ自分で検索する
+                    let claimed = {
+                        let mut state = progress.lock().unwrap();
+                        state.head = pos;
+                        let claimed = state.next > pos;
+                        if !claimed {
+                            state.next = pos + 1;
+                        }
+                        changed.notify_all();
+                        claimed
+                    };
+                    pos += 1;
+                    if claimed {
+                        let result = loop {
+                            if let Some(result) = done.remove(&(pos - 1)) {
+                                break result;
+                            }
+                            let Ok((pos, result)) = rx.recv() else {
+                                return Err("search thread stopped unexpectedly".into());
+                            };
+                            done.insert(pos, result);
+                        };
+                        result.map_err(From::from).and_then(|(found, part)| {
+                            printer.append(part)?;
+                            Ok(found)
+                        })
+                    } else {
+                        // 大きなファイルでも溜めずに一致した行から順に出力する
+                        search_file(filename, config, printer)
+                    }
+                }
+            };
+            if on_result(result) {
+                break;
+            }
+        }
+        // 待機中のスレッドを終了させる
+        progress.lock().unwrap().stop = true;
+        changed.notify_all();
+        Ok(())
+    })
+}
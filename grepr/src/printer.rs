@@ -0,0 +1,356 @@
+use std::{io::Write, ops::Range};
+
+use serde::Serialize;
+
+use crate::{color::Colors, Match, MyResult};
+
+// --jsonで出力する1行分のメッセージ: ripgrepの--jsonのmatchとcontextの形式に合わせる
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+enum JsonMessage<'a> {
+    Match(JsonLine<'a>),
+    Context(JsonLine<'a>),
+}
+
+#[derive(Debug, Serialize)]
+struct JsonLine<'a> {
+    path: JsonText<'a>,
+    lines: JsonText<'a>, // 末尾の改行を含む
+    line_number: usize,
+    absolute_offset: u64,
+    submatches: Vec<JsonSubmatch<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonText<'a> {
+    text: &'a str,
+}
+
+// 行内で一致した部分: startとendは行頭からのバイト位置
+#[derive(Debug, Serialize)]
+struct JsonSubmatch<'a> {
+    #[serde(rename = "match")]
+    matched: JsonText<'a>,
+    start: usize,
+    end: usize,
+}
+
+// 検索結果の出力形式を管理する: 出力先はWriteを実装する任意の型
+pub struct Printer<W: Write> {
+    out: W,
+    with_filename: bool,
+    line_number: bool,
+    byte_offset: bool,
+    colors: Option<Colors>,   // 色付けしない場合はNone
+    json: bool,               // 各行をJSONで出力するか: ファイル名などの指定と色付けは使わない
+    group_separator: bool,    // 連続しない行の間に"--"を出力するか
+    last_line: Option<usize>, // 現在のファイルで最後に出力した行番号
+    printed: bool,            // いずれかの行を出力したか
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(
+        out: W,
+        with_filename: bool,
+        line_number: bool,
+        byte_offset: bool,
+        colors: Option<Colors>,
+        group_separator: bool,
+        json: bool,
+    ) -> Self {
+        Printer {
+            out,
+            with_filename,
+            line_number,
+            byte_offset,
+            colors,
+            group_separator,
+            json,
+            last_line: None,
+            printed: false,
+        }
+    }
+
+    // 同じ設定で、出力をメモリに書き込むPrinterを作る: 並列検索でファイルごとの出力を溜める
+    pub fn fork(&self) -> Printer<Vec<u8>> {
+        Printer {
+            out: vec![],
+            with_filename: self.with_filename,
+            line_number: self.line_number,
+            byte_offset: self.byte_offset,
+            colors: self.colors.clone(),
+            group_separator: self.group_separator,
+            json: self.json,
+            last_line: None,
+            printed: false,
+        }
+    }
+
+    // forkしたPrinterの出力を書き出す: ファイルの間の区切り行はここで出力する
+    pub fn append(&mut self, part: Printer<Vec<u8>>) -> MyResult<()> {
+        if self.group_separator && self.printed && part.printed {
+            writeln!(self.out, "{}", self.paint("--", Colors::separator))?;
+        }
+        self.out.write_all(&part.out)?;
+        self.printed |= part.printed;
+        Ok(())
+    }
+
+    // ファイルが変わると行番号が連続しなくなる
+    pub fn start_file(&mut self) {
+        self.last_line = None;
+    }
+
+    // 色付けする場合とJSONで出力する場合のみ一致した範囲を求めれば良い
+    pub fn wants_ranges(&self) -> bool {
+        self.colors.is_some() || self.json
+    }
+
+    // 一致した行を「ファイル名:行番号:バイト位置:行」の形式で出力: highlightsの範囲を色付けする
+    pub fn line(&mut self, filename: &str, m: &Match, highlights: &[Range<usize>]) -> MyResult<()> {
+        if self.json {
+            return self.json_line(filename, m, highlights, JsonMessage::Match);
+        }
+        self.prefixes(filename, m, ":")?;
+        match &self.colors {
+            Some(colors) => write!(self.out, "{}", colors.highlight(m.line, highlights))?,
+            None => write!(self.out, "{}", m.line)?,
+        }
+        Ok(())
+    }
+
+    // 文脈の行を「ファイル名-行番号-バイト位置-行」の形式で出力: 色付けはしない
+    pub fn context(&mut self, filename: &str, m: &Match) -> MyResult<()> {
+        if self.json {
+            return self.json_line(filename, m, &[], JsonMessage::Context);
+        }
+        self.prefixes(filename, m, "-")?;
+        write!(self.out, "{}", m.line)?;
+        Ok(())
+    }
+
+    // -oで一致した部分のみを1行ずつ出力: バイト位置は一致した部分の先頭
+    pub fn only_matching(&mut self, filename: &str, m: &Match, range: Range<usize>) -> MyResult<()> {
+        self.only_replaced(filename, m, range.start, &m.line[range])
+    }
+
+    // --replaceと-oで、一致した部分を置き換えた文字列を1行ずつ出力: バイト位置は置き換える前の一致した部分の先頭
+    pub fn only_replaced(&mut self, filename: &str, m: &Match, start: usize, text: &str) -> MyResult<()> {
+        let part = Match {
+            line_num: m.line_num,
+            byte_offset: m.byte_offset + start as u64,
+            line: text,
+        };
+        self.prefixes(filename, &part, ":")?;
+        writeln!(self.out, "{}", self.paint(part.line, Colors::matched))?;
+        Ok(())
+    }
+
+    pub fn count(&mut self, filename: &str, count: usize) -> MyResult<()> {
+        if self.with_filename {
+            self.prefix(filename, Colors::filename, ":")?;
+        }
+        writeln!(self.out, "{}", count)?;
+        Ok(())
+    }
+
+    pub fn filename(&mut self, filename: &str) -> MyResult<()> {
+        writeln!(self.out, "{}", self.paint(filename, Colors::filename))?;
+        Ok(())
+    }
+
+    // 1行を1つのJSONオブジェクトとして出力する
+    fn json_line<'a>(
+        &mut self,
+        filename: &'a str,
+        m: &Match<'a>,
+        ranges: &[Range<usize>],
+        message: impl FnOnce(JsonLine<'a>) -> JsonMessage<'a>,
+    ) -> MyResult<()> {
+        let line = JsonLine {
+            path: JsonText { text: filename },
+            lines: JsonText { text: m.line },
+            line_number: m.line_num,
+            absolute_offset: m.byte_offset,
+            submatches: ranges
+                .iter()
+                .map(|range| JsonSubmatch {
+                    matched: JsonText { text: &m.line[range.clone()] },
+                    start: range.start,
+                    end: range.end,
+                })
+                .collect(),
+        };
+        writeln!(self.out, "{}", serde_json::to_string(&message(line))?)?;
+        Ok(())
+    }
+
+    // 行の前に区切り行と各値を出力する
+    fn prefixes(&mut self, filename: &str, m: &Match, separator: &str) -> MyResult<()> {
+        if self.group_separator && self.printed && self.last_line.is_none_or(|n| n + 1 != m.line_num) {
+            writeln!(self.out, "{}", self.paint("--", Colors::separator))?;
+        }
+        self.last_line = Some(m.line_num);
+        self.printed = true;
+        if self.with_filename {
+            self.prefix(filename, Colors::filename, separator)?;
+        }
+        if self.line_number {
+            self.prefix(&m.line_num.to_string(), Colors::line_number, separator)?;
+        }
+        if self.byte_offset {
+            self.prefix(&m.byte_offset.to_string(), Colors::byte_offset, separator)?;
+        }
+        Ok(())
+    }
+
+    // 「値:」の形式で出力する
+    fn prefix(&mut self, text: &str, paint: fn(&Colors, &str) -> String, separator: &str) -> MyResult<()> {
+        let text = self.paint(text, paint);
+        let separator = self.paint(separator, Colors::separator);
+        write!(self.out, "{}{}", text, separator)?;
+        Ok(())
+    }
+
+    fn paint(&self, text: &str, paint: fn(&Colors, &str) -> String) -> String {
+        match &self.colors {
+            Some(colors) => paint(colors, text),
+            None => text.to_string(),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::Printer;
+    use crate::{color::Colors, Match};
+
+    const MATCH: Match = Match {
+        line_num: 3,
+        byte_offset: 42,
+        line: "foo\n",
+    };
+
+    fn print_line(with_filename: bool, line_number: bool, byte_offset: bool) -> String {
+        let mut printer = Printer::new(vec![], with_filename, line_number, byte_offset, None, false, false);
+        printer.line("a.txt", &MATCH, &[]).unwrap();
+        String::from_utf8(printer.out).unwrap()
+    }
+
+    #[test]
+    fn test_line() {
+        assert_eq!(print_line(false, false, false), "foo\n");
+        assert_eq!(print_line(true, false, false), "a.txt:foo\n");
+        assert_eq!(print_line(false, true, false), "3:foo\n");
+        assert_eq!(print_line(false, false, true), "42:foo\n");
+        assert_eq!(print_line(true, true, true), "a.txt:3:42:foo\n");
+    }
+
+    #[test]
+    fn test_only_matching() {
+        let m = Match {
+            line_num: 2,
+            byte_offset: 10,
+            line: "a fox and a fox\n",
+        };
+        let mut printer = Printer::new(vec![], true, true, true, None, false, false);
+        printer.only_matching("a.txt", &m, 2..5).unwrap();
+        printer.only_matching("a.txt", &m, 12..15).unwrap();
+        assert_eq!(String::from_utf8(printer.out).unwrap(), "a.txt:2:12:fox\na.txt:2:22:fox\n");
+    }
+
+    #[test]
+    fn test_only_replaced() {
+        let mut printer = Printer::new(vec![], false, false, true, None, false, false);
+        printer.only_replaced("a.txt", &MATCH, 1, "OO").unwrap();
+        assert_eq!(String::from_utf8(printer.out).unwrap(), "43:OO\n");
+    }
+
+    #[test]
+    fn test_count() {
+        let mut printer = Printer::new(vec![], true, true, false, None, false, false);
+        printer.count("a.txt", 2).unwrap();
+        printer.filename("b.txt").unwrap();
+        assert_eq!(String::from_utf8(printer.out).unwrap(), "a.txt:2\nb.txt\n");
+    }
+
+    #[test]
+    fn test_context() {
+        let line = |line_num| Match {
+            line_num,
+            byte_offset: 0,
+            line: "foo\n",
+        };
+        let mut printer = Printer::new(vec![], true, true, false, None, true, false);
+        printer.start_file();
+        printer.line("a.txt", &line(1), &[]).unwrap();
+        printer.context("a.txt", &line(2)).unwrap();
+        printer.line("a.txt", &line(5), &[]).unwrap();
+        // ファイルが変わった場合も区切る
+        printer.start_file();
+        printer.line("b.txt", &line(6), &[]).unwrap();
+        assert_eq!(
+            String::from_utf8(printer.out).unwrap(),
+            "a.txt:1:foo\na.txt-2-foo\n--\na.txt:5:foo\n--\nb.txt:6:foo\n"
+        );
+    }
+
+    #[test]
+    fn test_append() {
+        let line = |line_num| Match {
+            line_num,
+            byte_offset: 0,
+            line: "foo\n",
+        };
+        let mut printer = Printer::new(vec![], true, true, false, None, true, false);
+        let mut a = printer.fork();
+        a.line("a.txt", &line(1), &[]).unwrap();
+        a.line("a.txt", &line(3), &[]).unwrap();
+        let b = printer.fork();
+        let mut c = printer.fork();
+        c.context("c.txt", &line(2)).unwrap();
+        // 出力のないファイルの前後では区切らず、ファイルの間のみ区切る
+        printer.append(a).unwrap();
+        printer.append(b).unwrap();
+        printer.append(c).unwrap();
+        assert_eq!(
+            String::from_utf8(printer.out).unwrap(),
+            "a.txt:1:foo\n--\na.txt:3:foo\n--\nc.txt-2-foo\n"
+        );
+    }
+
+    #[test]
+    fn test_colored() {
+        let mut printer = Printer::new(vec![], true, true, false, Some(Colors::default()), false, false);
+        assert!(printer.wants_ranges());
+        printer.line("a.txt", &MATCH, &[0..1, 2..3]).unwrap();
+        printer.filename("b.txt").unwrap();
+        assert_eq!(
+            String::from_utf8(printer.out).unwrap(),
+            "\x1b[35ma.txt\x1b[0m\x1b[36m:\x1b[0m\x1b[32m3\x1b[0m\x1b[36m:\x1b[0m\
+            \x1b[01;31mf\x1b[0mo\x1b[01;31mo\x1b[0m\n\x1b[35mb.txt\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let m = Match {
+            line_num: 2,
+            byte_offset: 10,
+            line: "say \"hi\"\tto C:\\ café\n",
+        };
+        let mut printer = Printer::new(vec![], false, false, false, None, true, true);
+        assert!(printer.wants_ranges());
+        printer.line("dir/a \"b\".txt", &m, &[4..8, 12..13]).unwrap();
+        printer.context("dir/a \"b\".txt", &MATCH).unwrap();
+        // JSONでは文脈が連続しなくても区切り行を出力しない
+        assert_eq!(
+            String::from_utf8(printer.out).unwrap(),
+            "{\"type\":\"match\",\"data\":{\"path\":{\"text\":\"dir/a \\\"b\\\".txt\"},\
+            \"lines\":{\"text\":\"say \\\"hi\\\"\\tto C:\\\\ café\\n\"},\"line_number\":2,\"absolute_offset\":10,\
+            \"submatches\":[{\"match\":{\"text\":\"\\\"hi\\\"\"},\"start\":4,\"end\":8},\
+            {\"match\":{\"text\":\"C\"},\"start\":12,\"end\":13}]}}\n\
+            {\"type\":\"context\",\"data\":{\"path\":{\"text\":\"dir/a \\\"b\\\".txt\"},\
+            \"lines\":{\"text\":\"foo\\n\"},\"line_number\":3,\"absolute_offset\":42,\"submatches\":[]}}\n"
+        );
+    }
+}
@@ -42,7 +42,7 @@ fn dies_no_args() -> TestResult {
 #[test]
 fn dies_bad_pattern() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["*foo", FOX])
+        .args(["*foo", FOX])
         .assert()
         .failure()
         .stderr(predicate::str::contains("Invalid pattern \"*foo\""));
@@ -55,7 +55,7 @@ fn warns_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&["foo", &bad])
+        .args(["foo", &bad])
         .assert()
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
@@ -72,7 +72,7 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
         expected_file
     };
 
-    let expected = fs::read_to_string(&expected_file)?;
+    let expected = fs::read_to_string(expected_file)?;
 
     Command::cargo_bin(PRG)?
         .args(args)
@@ -234,7 +234,7 @@ fn warns_dir_not_recursive() -> TestResult {
     let stdout = "tests/inputs/fox.txt:\
         The quick brown fox jumps over the lazy dog.";
     Command::cargo_bin(PRG)?
-        .args(&["fox", INPUTS_DIR, FOX])
+        .args(["fox", INPUTS_DIR, FOX])
         .assert()
         .stderr(predicate::str::contains("tests/inputs is a directory"))
         .stdout(predicate::str::contains(stdout));
@@ -271,9 +271,645 @@ fn stdin_insensitive_count() -> TestResult {
     let expected = fs::read_to_string(expected_file)?;
 
     Command::cargo_bin(PRG)?
-        .args(&["-ci", "the", "-"])
+        .args(["-ci", "the", "-"])
         .write_stdin(input)
         .assert()
         .stdout(expected);
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn dies_count_and_files_with_matches() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-l", "the", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The argument '--count' cannot be used with '--files-with-matches'",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files_with_matches() -> TestResult {
+    run(
+        &["-l", "the", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/all.the.files_with_matches",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files_without_match() -> TestResult {
+    run(
+        &["-L", "the", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/all.the.files_without_match",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files_without_match_insensitive() -> TestResult {
+    run(
+        &["--files-without-match", "-i", "the", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/all.the.insensitive.files_without_match",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn files_with_matches_single_file() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--files-with-matches", "fox", FOX])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", FOX));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn line_number() -> TestResult {
+    run(
+        &["-n", "-i", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.lowercase.insensitive.n",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn byte_offset() -> TestResult {
+    run(
+        &["--byte-offset", "The", BUSTLE],
+        "tests/expected/bustle.txt.the.capitalized.b",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn line_number_byte_offset_multiple() -> TestResult {
+    run(
+        &["-nbi", "the", BUSTLE, EMPTY, FOX, NOBODY],
+        "tests/expected/all.the.lowercase.insensitive.nb",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_include() -> TestResult {
+    run(
+        &["-ri", "--include", "n*.txt", "then", INPUTS_DIR],
+        "tests/expected/the.recursive.insensitive",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_exclude_over_include() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-ri", "--include", "*.txt", "--exclude", "nobody.txt", "then", INPUTS_DIR])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_exclude() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-r", "--exclude", "[", "foo", INPUTS_DIR])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --exclude \"[\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn exit_status() -> TestResult {
+    Command::cargo_bin(PRG)?.args(["fox", FOX]).assert().code(0);
+    Command::cargo_bin(PRG)?.args(["nobody", FOX]).assert().code(1);
+    Command::cargo_bin(PRG)?.args(["-c", "nobody", FOX]).assert().code(1);
+
+    // 読み込みエラーがあれば一致があっても2
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["fox", FOX, &bad])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("fox"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-q", "the", BUSTLE, FOX])
+        .assert()
+        .code(0)
+        .stdout("");
+    Command::cargo_bin(PRG)?
+        .args(["--quiet", "nobody", FOX])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_exits_on_first_match() -> TestResult {
+    // 一致した時点で終了するので、後続の存在しないファイルは読まれない
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["-q", "fox", FOX, &bad])
+        .assert()
+        .code(0)
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin(PRG)?
+        .args(["-q", "fox", &bad, FOX])
+        .assert()
+        .code(0)
+        .stderr(predicate::str::contains(bad));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_patterns() -> TestResult {
+    // -e指定時は最初の位置引数もファイルとして扱う
+    Command::cargo_bin(PRG)?
+        .args(["-e", "fox", "-e", "^Is", FOX, BUSTLE])
+        .assert()
+        .code(0)
+        .stdout(format!(
+            "{}:The quick brown fox jumps over the lazy dog.\n{}:Is solemnest of industries\n",
+            FOX, BUSTLE
+        ));
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-i", "-e", "THE", "--regexp", "-?love", BUSTLE])
+        .assert()
+        .stdout("4\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_file() -> TestResult {
+    let pattern_file = std::env::temp_dir().join(format!("grepr-{}", gen_bad_file()));
+    fs::write(&pattern_file, "^And\nearth\n")?;
+    let pattern_filename = pattern_file.to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args(["-n", "-f", pattern_filename, "-e", "Until", BUSTLE])
+        .assert()
+        .code(0)
+        .stdout("4:Enacted upon earth,—\n7:And putting love away\n9:Until eternity.\n");
+
+    // 空のパターンファイルはどの行にも一致しない
+    fs::write(&pattern_file, "")?;
+    Command::cargo_bin(PRG)?
+        .args(["-f", pattern_filename, BUSTLE])
+        .assert()
+        .code(1)
+        .stdout("");
+    fs::remove_file(&pattern_file)?;
+
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["-f", &bad, BUSTLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::is_match(format!("{}: .* [(]os error 2[)]", bad))?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color_always() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color", "always", "-e", "o[gx]", "-e", "^The", FOX])
+        .env_remove("GREP_COLORS")
+        .assert()
+        .code(0)
+        .stdout(
+            "\x1b[01;31mThe\x1b[0m quick brown f\x1b[01;31mox\x1b[0m \
+            jumps over the lazy d\x1b[01;31mog\x1b[0m.\n",
+        );
+
+    // GREP_COLORSで色を変更できる: 空の値は色付けしない
+    Command::cargo_bin(PRG)?
+        .args(["--color", "always", "-n", "fox", FOX, EMPTY])
+        .env("GREP_COLORS", "ms=4:fn=:se=")
+        .assert()
+        .code(0)
+        .stdout(format!(
+            "{}:\x1b[32m1\x1b[0m:The quick brown \x1b[4mfox\x1b[0m jumps over the lazy dog.\n",
+            FOX
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color_never() -> TestResult {
+    // 端末以外への出力(auto)と--color never では色付けしない
+    for args in [vec!["fox", FOX], vec!["--color", "never", "fox", FOX]] {
+        Command::cargo_bin(PRG)?
+            .args(&args)
+            .assert()
+            .code(0)
+            .stdout("The quick brown fox jumps over the lazy dog.\n");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_color() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color", "yes", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --color \"yes\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn engine_pcre() -> TestResult {
+    // 後読み・先読みと後方参照は--engine pcreでのみ使える
+    Command::cargo_bin(PRG)?
+        .args(["(?<=—)\\w+(?=—)", NOBODY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid pattern \"(?<=—)\\w+(?=—)\""));
+    Command::cargo_bin(PRG)?
+        .args(["--engine", "pcre", "-n", "-o", "(?<=—)\\w+(?=—)", NOBODY])
+        .assert()
+        .code(0)
+        .stdout("2:Nobody\n");
+    Command::cargo_bin(PRG)?
+        .args(["--engine", "pcre", "-c", "-i", "(L)\\1", NOBODY])
+        .assert()
+        .code(0)
+        .stdout("2\n");
+    Command::cargo_bin(PRG)?
+        .args(["--engine", "pcre", "--color", "always", "\\w+(?= fox)", FOX])
+        .env_remove("GREP_COLORS")
+        .assert()
+        .code(0)
+        .stdout("The quick \x1b[01;31mbrown\x1b[0m fox jumps over the lazy dog.\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_engine() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--engine", "pcre2", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --engine \"pcre2\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_count_after_context() -> TestResult {
+    run(
+        &["-m1", "-A2", "-n", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.m1.A2.n",
+    )
+}
+
+#[test]
+fn max_count_after_context_multiple() -> TestResult {
+    run(
+        &["-m", "1", "-A", "1", "-i", "the", BUSTLE, NOBODY],
+        "tests/expected/all.the.insensitive.m1.A1",
+    )
+}
+
+#[test]
+fn max_count_count() -> TestResult {
+    run(
+        &["-c", "-m2", "-i", "the", BUSTLE, FOX],
+        "tests/expected/all.the.insensitive.m2.count",
+    )
+}
+
+#[test]
+fn max_count_zero() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-m", "0", "the", BUSTLE])
+        .assert()
+        .code(1)
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn dies_bad_max_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--max-count", "x", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --max-count \"x\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn invert_after_context() -> TestResult {
+    run(
+        &["-v", "-A2", "-n", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.v.A2.n",
+    )
+}
+
+#[test]
+fn invert_count() -> TestResult {
+    run(&["-v", "-c", "the", BUSTLE, FOX], "tests/expected/all.the.v.count")
+}
+
+#[test]
+fn invert_max_count_context() -> TestResult {
+    run(
+        &["-v", "-m2", "-B1", "-A1", "-n", "the", BUSTLE],
+        "tests/expected/bustle.txt.the.v.m2.B1.A1.n",
+    )
+}
+
+#[test]
+fn context() -> TestResult {
+    run(
+        &["-C", "1", "-i", "the", BUSTLE, NOBODY],
+        "tests/expected/all.the.insensitive.C1",
+    )
+}
+
+#[test]
+fn only_matching() -> TestResult {
+    run(
+        &["-o", "-b", "-n", "-i", "the", BUSTLE, FOX],
+        "tests/expected/all.the.insensitive.o.n.b",
+    )
+}
+
+#[test]
+fn invert_only_matching() -> TestResult {
+    // 反転した行には一致部分がないため何も出力しないが、選択された行があれば成功とする
+    Command::cargo_bin(PRG)?
+        .args(["-v", "-o", "the", BUSTLE])
+        .assert()
+        .code(0)
+        .stdout("");
+    Ok(())
+}
+
+#[test]
+fn replace() -> TestResult {
+    // 文脈の行は置き換えない
+    run(
+        &["-n", "-A1", "-i", "(t)he", "--replace", "${1}HE", BUSTLE, FOX],
+        "tests/expected/all.the.insensitive.replace.n.A1",
+    )
+}
+
+#[test]
+fn replace_only_matching() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-o", "-b", r"(\w)o(\w)", "--replace", "${2}o${1}", FOX])
+        .assert()
+        .success()
+        .stdout("11:wor\n16:xof\n40:god\n");
+    Ok(())
+}
+
+#[test]
+fn replace_color() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color", "always", "dog", "--replace", "cat", FOX])
+        .assert()
+        .success()
+        .stdout("The quick brown fox jumps over the lazy \x1b[01;31mcat\x1b[0m.\n");
+    Ok(())
+}
+
+#[test]
+fn invert_replace() -> TestResult {
+    // 反転した行には一致部分がないため置き換えずに出力する
+    Command::cargo_bin(PRG)?
+        .args(["-v", "dog", "--replace", "cat", FOX, NOBODY])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("cat").not());
+    Ok(())
+}
+
+#[test]
+fn dies_replace_with_json() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--json", "--replace", "x", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_context() -> TestResult {
+    for (flag, name) in [("-B", "--before-context"), ("-C", "--context")] {
+        Command::cargo_bin(PRG)?
+            .args([flag, "x", "fox", FOX])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!("Invalid {} \"x\"", name)));
+    }
+    Ok(())
+}
+
+#[test]
+fn json() -> TestResult {
+    run(
+        &["--json", "-i", "-A1", "the", BUSTLE, FOX],
+        "tests/expected/all.the.insensitive.json.A1",
+    )
+}
+
+#[test]
+fn json_escapes_stdin() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--json", "x"])
+        .write_stdin("a\t\"x\"\\\n")
+        .assert()
+        .success()
+        .stdout(
+            "{\"type\":\"match\",\"data\":{\"path\":{\"text\":\"-\"},\"lines\":{\"text\":\"a\\t\\\"x\\\"\\\\\\n\"},\
+            \"line_number\":1,\"absolute_offset\":0,\"submatches\":[{\"match\":{\"text\":\"x\"},\"start\":3,\"end\":4}]}}\n",
+        );
+    Ok(())
+}
+
+#[test]
+fn dies_json_with_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--json", "-c", "fox", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn compressed_by_extension() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-i", "the", "tests/compressed/fox.txt.gz", "tests/compressed/bustle.txt.zst"])
+        .assert()
+        .code(0)
+        .stdout(
+            "tests/compressed/fox.txt.gz:The quick brown fox jumps over the lazy dog.\n\
+             tests/compressed/bustle.txt.zst:The bustle in a house\n\
+             tests/compressed/bustle.txt.zst:The morning after death\n\
+             tests/compressed/bustle.txt.zst:The sweeping up the heart,\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn search_zip_recursive() -> TestResult {
+    // -zでは拡張子のないファイルも内容から判定する
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-r", "-l", "-i", "bustle", "tests/compressed"])
+        .assert()
+        .code(0)
+        .stdout("tests/compressed/bustle.bin\ntests/compressed/bustle.txt.zst\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn search_zip_stdin() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "fox"])
+        .pipe_stdin("tests/compressed/fox.txt.gz")?
+        .assert()
+        .code(0)
+        .stdout("The quick brown fox jumps over the lazy dog.\n");
+
+    // -zがない場合は圧縮されていない入力として読む
+    Command::cargo_bin(PRG)?
+        .args(["fox"])
+        .pipe_stdin(FOX)?
+        .assert()
+        .code(0);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_compressed_file() -> TestResult {
+    let filename = format!("{}.gz", gen_bad_file());
+    fs::write(&filename, "The quick brown fox\n")?;
+    let cmd = Command::cargo_bin(PRG)?.args(["fox", &filename]).assert();
+    fs::remove_file(&filename)?;
+    cmd.code(2).stderr(format!("{}: invalid gzip header\n", filename));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_threads() -> TestResult {
+    for threads in ["1", "2", "8"] {
+        run(
+            &["-r", "--threads", threads, "dog", INPUTS_DIR],
+            "tests/expected/dog.recursive",
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_same_order() -> TestResult {
+    // 並列に検索しても、文脈の区切り行やエラーを含めて逐次検索と同じ順に出力する
+    let bad = gen_bad_file();
+    let output = |threads| -> Result<_, Box<dyn std::error::Error>> {
+        let output = Command::cargo_bin(PRG)?
+            .args(["-in", "-C", "1", "-j", threads, "the", BUSTLE, &bad, FOX, NOBODY, BUSTLE])
+            .output()?;
+        Ok((output.status.code(), output.stdout, output.stderr))
+    };
+    let expected = output("1")?;
+    assert_eq!(expected.0, Some(2));
+    assert!(String::from_utf8_lossy(&expected.1).contains("--\n"));
+    for threads in ["2", "4"] {
+        assert_eq!(output(threads)?, expected);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_stream_first_file() -> TestResult {
+    use std::{io::{BufRead, BufReader, Write}, process::Stdio};
+
+    // 順番が来たファイルは溜めずに出力するため、入力が終わる前に一致した行が出力される
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(["-j", "4", "first", "-", FOX, BUSTLE])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    stdin.write_all(b"the first line\n")?;
+    let mut line = String::new();
+    stdout.read_line(&mut line)?;
+    assert!(line.ends_with(":the first line\n"));
+    drop(stdin);
+    assert!(child.wait()?.success());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_many_files() -> TestResult {
+    // 先読みの範囲を超える数のファイルも入力の順に出力する
+    let files: Vec<_> = [FOX, BUSTLE, EMPTY, NOBODY].iter().cycle().take(40).copied().collect();
+    let output = |threads| -> Result<_, Box<dyn std::error::Error>> {
+        Ok(Command::cargo_bin(PRG)?.args(["-j", threads, "-n", "the"]).args(&files).output()?.stdout)
+    };
+    let expected = output("1")?;
+    assert!(!expected.is_empty());
+    assert_eq!(output("3")?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_quiet() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-q", "-j", "4", "dog", EMPTY, NOBODY, FOX, BUSTLE])
+        .assert()
+        .code(0)
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_threads() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-j", "0", "dog", FOX])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Invalid --threads \"0\""));
+    Ok(())
+}
@@ -0,0 +1,6 @@
+tests/inputs/bustle.txt:1:0:The
+tests/inputs/bustle.txt:2:22:The
+tests/inputs/bustle.txt:6:97:The
+tests/inputs/bustle.txt:6:113:the
+tests/inputs/fox.txt:1:0:The
+tests/inputs/fox.txt:1:31:the
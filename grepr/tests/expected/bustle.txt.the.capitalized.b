@@ -0,0 +1,3 @@
+0:The bustle in a house
+22:The morning after death
+97:The sweeping up the heart,
@@ -0,0 +1,121 @@
+use std::io::{self, Write};
+
+// 1行に出力するバイト数
+const ROW_SIZE: usize = 16;
+
+/// Writer that renders the bytes written to it as a hex and ASCII dump,
+/// 16 bytes per row prefixed with the offset of the row, in the format of
+/// `hexdump -C`. Call [`HexDump::finish`] to print the last partial row and
+/// the final offset.
+pub struct HexDump<W: Write> {
+    out: W,
+    offset: u64,  // 次の行の先頭のバイト位置
+    row: Vec<u8>, // 1行分に満たないため出力していないバイト
+    dumped: bool, // 1バイトでも受け取ったか
+}
+
+impl<W: Write> HexDump<W> {
+    /// Create a dump whose first row is labeled with `offset`, e.g. the
+    /// number of bytes skipped before the dumped part of a file
+    pub fn new(out: W, offset: u64) -> Self {
+        HexDump {
+            out,
+            offset,
+            row: Vec::with_capacity(ROW_SIZE),
+            dumped: false,
+        }
+    }
+
+    /// Flush the last partial row and print the offset just past the last
+    /// byte; nothing is printed if no bytes were written
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.row.is_empty() {
+            self.write_row()?;
+        }
+        if self.dumped {
+            writeln!(self.out, "{:08x}", self.offset)?;
+        }
+        Ok(self.out)
+    }
+
+    // 「位置  16進数(8バイトごとに区切る)  |ASCII|」の形式: 表示できない文字は"."にする
+    fn write_row(&mut self) -> io::Result<()> {
+        let mut hex = String::with_capacity(ROW_SIZE * 3 + 1);
+        for i in 0..ROW_SIZE {
+            if i == ROW_SIZE / 2 {
+                hex.push(' ');
+            }
+            match self.row.get(i) {
+                Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+                None => hex.push_str("   "), // 最終行の足りない部分も桁を揃える
+            }
+        }
+        let ascii: String = self
+            .row
+            .iter()
+            .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+            .collect();
+        writeln!(self.out, "{:08x}  {} |{}|", self.offset, hex, ascii)?;
+        self.offset += self.row.len() as u64;
+        self.row.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for HexDump<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.row.push(byte);
+            if self.row.len() == ROW_SIZE {
+                self.write_row()?;
+            }
+        }
+        self.dumped |= !buf.is_empty();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::HexDump;
+    use std::io::Write;
+
+    fn dump(chunks: &[&[u8]], offset: u64) -> String {
+        let mut dump = HexDump::new(vec![], offset);
+        for chunk in chunks {
+            dump.write_all(chunk).unwrap();
+        }
+        String::from_utf8(dump.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_hexdump() {
+        assert_eq!(
+            dump(&["Öne line, four words.\n".as_bytes()], 0),
+            "00000000  c3 96 6e 65 20 6c 69 6e  65 2c 20 66 6f 75 72 20  |..ne line, four |\n\
+             00000010  77 6f 72 64 73 2e 0a                              |words..|\n\
+             00000017\n"
+        );
+
+        // Rows do not depend on how the input is split into writes
+        let bytes: Vec<u8> = (0..32).collect();
+        let whole = dump(&[&bytes], 0);
+        assert_eq!(dump(&[&bytes[..5], &bytes[5..20], &bytes[20..]], 0), whole);
+        assert_eq!(
+            whole,
+            "00000000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  |................|\n\
+             00000010  10 11 12 13 14 15 16 17  18 19 1a 1b 1c 1d 1e 1f  |................|\n\
+             00000020\n"
+        );
+
+        // Offsets start from the given position, and empty input prints nothing
+        assert_eq!(dump(&[b"~\x7f"], 0x1f8), "000001f8  7e 7f                                             |~.|\n000001fa\n");
+        assert_eq!(dump(&[], 0), "");
+        assert_eq!(dump(&[b""], 0), "");
+    }
+}
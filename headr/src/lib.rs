@@ -1,7 +1,10 @@
-use std::{error::Error, io::{Read, BufRead, stdin, BufReader}, fs::File};
+use std::{collections::HashSet, error::Error, io::{self, Read, BufRead, Write, stdin, stdout, BufReader, Cursor}, fs::{self, File}, path::{Path, PathBuf}};
 
 use clap::{App, Arg};
 
+mod hexdump;
+pub use hexdump::HexDump;
+
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
@@ -9,6 +12,53 @@ pub struct Config {
     files: Vec<String>,
     lines: usize,
     bytes: Option<usize>,
+    both: bool, // --both: 行単位の先頭とバイト単位の先頭を両方出力する
+    skip: Skip,
+    percent: Option<Percent>, // -n, -cの代わりに全体に対する割合で出力する
+    header_always: bool, // CSV/TSVの先頭行を常に出力し、-nはデータ行のみを数える
+    headers: HeaderMode,
+    output_dir: Option<PathBuf>,
+    force: bool,
+    hex: bool, // --hex: バイト単位の出力を16進数とASCIIのダンプで表示する
+}
+
+// 先頭から読み飛ばす範囲: 残りの部分に対して-nや-cを適用する
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Skip {
+    Lines(usize), // --skip-lines
+    Bytes(usize), // --skip-bytes
+}
+
+// 全体の行数またはバイト数に対する割合: 0より大きく100以下
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Percent {
+    Lines(f64), // --percent
+    Bytes(f64), // --percent-bytes
+}
+
+impl Percent {
+    // 全体の大きさから出力する数を求める: 端数は切り捨てる
+    fn of(&self, total: u64) -> usize {
+        let (Percent::Lines(percent) | Percent::Bytes(percent)) = *self;
+        (total as f64 * percent / 100.0).floor() as usize
+    }
+}
+
+// ファイル名のヘッダー(==> FILE <==)の出力方針
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HeaderMode {
+    Auto,   // 複数ファイルの場合のみ出力
+    Always, // -v: 常に出力
+    Never,  // -q: 出力しない
+}
+
+// ヘッダーを出力するかどうかを判定する
+pub fn show_headers(mode: HeaderMode, num_files: usize) -> bool {
+    match mode {
+        HeaderMode::Auto => num_files > 1,
+        HeaderMode::Always => true,
+        HeaderMode::Never => false,
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -39,7 +89,88 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("BYTES")
                 .help("Number of bytes")
                 .takes_value(true)
-                .conflicts_with("lines")
+        )
+        .arg(
+            Arg::with_name("both")
+                .long("both")
+                .help("Print the first LINES lines followed by the first BYTES bytes")
+                .takes_value(false)
+                .requires("bytes"),
+        )
+        .arg(
+            Arg::with_name("skip_lines")
+                .long("skip-lines")
+                .value_name("N")
+                .help("Skip the first N lines before printing")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("skip_bytes")
+                .long("skip-bytes")
+                .value_name("N")
+                .help("Skip the first N bytes before printing")
+                .takes_value(true)
+                .conflicts_with("skip_lines"),
+        )
+        .arg(
+            Arg::with_name("percent")
+                .long("percent")
+                .value_name("P")
+                .help("Print the first P% of the lines")
+                .takes_value(true)
+                .conflicts_with_all(&["bytes", "both", "skip_lines", "skip_bytes"]),
+        )
+        .arg(
+            Arg::with_name("percent_bytes")
+                .long("percent-bytes")
+                .value_name("P")
+                .help("Print the first P% of the bytes")
+                .takes_value(true)
+                .conflicts_with_all(&["percent", "bytes", "both", "skip_lines", "skip_bytes"]),
+        )
+        .arg(
+            Arg::with_name("header_always")
+                .long("header-always")
+                .help("For CSV/TSV input, always print the header line and count only data lines with -n and --skip-lines")
+                .takes_value(false)
+                .conflicts_with_all(&["bytes", "both", "skip_bytes", "percent", "percent_bytes"]),
+        )
+        .arg(
+            Arg::with_name("hex")
+                .long("hex")
+                .help("Print the selected bytes as a hex and ASCII dump with offsets")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .visible_alias("silent")
+                .help("Never print headers giving file names")
+                .takes_value(false)
+                .overrides_with("verbose"), // 後に指定されたオプションを優先する
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Always print headers giving file names")
+                .takes_value(false)
+                .overrides_with("quiet"),
+        )
+        .arg(
+            Arg::with_name("output_dir")
+                .long("output-dir")
+                .value_name("DIR")
+                .help("Write each file's head to DIR/<basename> instead of STDOUT")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("force")
+                .long("force")
+                .help("Overwrite existing files in the output directory")
+                .takes_value(false)
+                .requires("output_dir"),
         )
         .get_matches();
 
@@ -53,10 +184,59 @@ pub fn get_args() -> MyResult<Config> {
         .transpose()
         .map_err(|e| format!("illegal byte count -- {}", e))?;
 
+    // --bothなしでの-nと-cの同時指定は、どちらを優先するか曖昧なためエラーとする
+    let both = matches.is_present("both");
+    if !both && matches.occurrences_of("lines") > 0 && bytes.is_some() {
+        return Err("--lines and --bytes can only be combined with --both".into());
+    }
+
+    // 0は読み飛ばさない指定として受け付ける
+    let skip = if let Some(val) = matches.value_of("skip_lines") {
+        Skip::Lines(val.parse().map_err(|_| format!("illegal skip count -- {}", val))?)
+    } else if let Some(val) = matches.value_of("skip_bytes") {
+        Skip::Bytes(val.parse().map_err(|_| format!("illegal skip count -- {}", val))?)
+    } else {
+        Skip::Lines(0)
+    };
+
+    let percent = if let Some(val) = matches.value_of("percent") {
+        Some(Percent::Lines(parse_percent(val)?))
+    } else if let Some(val) = matches.value_of("percent_bytes") {
+        Some(Percent::Bytes(parse_percent(val)?))
+    } else {
+        None
+    };
+    // -nはデフォルト値があるため、明示的に指定された場合のみエラーとする
+    if percent.is_some() && matches.occurrences_of("lines") > 0 {
+        return Err("--lines cannot be combined with --percent or --percent-bytes".into());
+    }
+
+    // 行単位の出力はダンプしない
+    let hex = matches.is_present("hex");
+    if hex && bytes.is_none() && !matches!(percent, Some(Percent::Bytes(_))) {
+        return Err("--hex requires --bytes or --percent-bytes".into());
+    }
+
+    let headers = if matches.is_present("quiet") {
+        HeaderMode::Never
+    } else if matches.is_present("verbose") {
+        HeaderMode::Always
+    } else {
+        HeaderMode::Auto
+    };
+
     Ok(Config {
         files: matches.values_of_lossy("files").unwrap(), // Optionをunwrap()
         lines: lines.unwrap(), // Optionをunwrap()
         bytes, // Optionのまま渡す
+        both,
+        skip,
+        percent,
+        header_always: matches.is_present("header_always"),
+        headers,
+        output_dir: matches.value_of("output_dir").map(PathBuf::from),
+        force: matches.is_present("force"),
+        hex,
     })
 }
 
@@ -67,14 +247,210 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
+/// Iterator over the first lines of a reader, created by [`head_lines`].
+pub struct HeadLines<R> {
+    reader: R,
+    remaining: usize,
+}
+
+impl<R: BufRead> Iterator for HeadLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut line = String::new();
+        // 改行コードも含めて1行ずつ読み込む: EOFの時は0バイトが読み込まれる
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                self.remaining -= 1;
+                Some(Ok(line))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Return an iterator over the first `n` lines of `reader`,
+/// each including its line ending
+pub fn head_lines<R: BufRead>(reader: R, n: usize) -> HeadLines<R> {
+    HeadLines { reader, remaining: n }
+}
+
+/// Return a reader limited to the first `n` bytes of `reader`
+pub fn head_bytes<R: Read>(reader: R, n: usize) -> io::Take<R> {
+    reader.take(n as u64) // usizeはu64に変換して使用する
+}
+
+// 先頭行が区切り形式のヘッダーであれば取り出す
+// そうでない場合は読み込んだ行を戻し、ヘッダーのない通常のファイルとして扱う
+fn split_header(file: &mut Box<dyn BufRead>) -> io::Result<Option<Vec<u8>>> {
+    let mut line = vec![];
+    file.read_until(b'\n', &mut line)?;
+    if is_delimited(&line) {
+        return Ok(Some(line));
+    }
+    let rest = std::mem::replace(file, Box::new(io::empty()));
+    *file = Box::new(Cursor::new(line).chain(rest));
+    Ok(None)
+}
+
+// カンマ、タブ、セミコロンのいずれかを含む行を区切り形式とみなす
+fn is_delimited(line: &[u8]) -> bool {
+    line.iter().any(|b| matches!(b, b',' | b'\t' | b';'))
+}
+
+// 先頭の指定範囲を読み捨て、読み捨てたバイト数を返す: ファイルが短い場合は末尾までで止まり、残りは空になる
+fn discard(file: &mut impl BufRead, skip: Skip) -> io::Result<u64> {
+    match skip {
+        Skip::Lines(num) => {
+            let mut skipped = 0;
+            let mut buf = vec![];
+            for _ in 0..num {
+                buf.clear();
+                // UTF-8として不正な行も読み飛ばせるようにバイト列として読み込む
+                match file.read_until(b'\n', &mut buf)? {
+                    0 => break,
+                    bytes => skipped += bytes as u64,
+                }
+            }
+            Ok(skipped)
+        }
+        Skip::Bytes(num) => io::copy(&mut file.take(num as u64), &mut io::sink()),
+    }
+}
+
+// 行単位の先頭部分を出力し、読み込んだ内容をconsumedに保存する
+fn write_lines_section(
+    file: &mut impl BufRead,
+    num_lines: usize,
+    consumed: &mut Vec<u8>,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    for line in head_lines(file, num_lines) {
+        let line = line?;
+        out.write_all(line.as_bytes())?; // 改行コードも含まれるのでそのまま出力
+        consumed.extend_from_slice(line.as_bytes());
+    }
+    Ok(())
+}
+
+// バイト単位の先頭部分を出力する: 読み込み済みの内容(consumed)が先頭にあり、不足分のみファイルから読み込む
+// hexはダンプの先頭の行に表示するファイル内の位置: 指定された場合はバイト列を変換せずにダンプする
+fn write_bytes_section(
+    file: &mut impl Read,
+    num_bytes: usize,
+    consumed: &[u8],
+    hex: Option<u64>,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let mut buffer = consumed[..num_bytes.min(consumed.len())].to_vec();
+    if num_bytes > buffer.len() {
+        head_bytes(file, num_bytes - buffer.len()).read_to_end(&mut buffer)?; // 指定のバイト数の分だけ読み込む
+    }
+    match hex {
+        Some(offset) => {
+            let mut dump = HexDump::new(out, offset);
+            dump.write_all(&buffer)?;
+            dump.finish()?;
+        }
+        None => write!(out, "{}", String::from_utf8_lossy(&buffer))?, // マルチバイト文字の途中で切れた場合も出力できるように変換
+    }
+    Ok(())
+}
+
+// 出力する数を決めるために全体の大きさを先に求める
+// 通常のファイルはバイト数をstatで、行数を別に開いて数える: 読み込み中のfileはそのまま出力に使う
+// 標準入力などは読み直せないため、全体をメモリに読み込んでから数える
+fn count_first(
+    mut file: Box<dyn BufRead>,
+    filename: &str,
+    percent: Percent,
+) -> MyResult<(Box<dyn BufRead>, usize)> {
+    let metadata = match filename {
+        "-" => None,
+        _ => Some(fs::metadata(filename)?),
+    };
+    if let Some(metadata) = metadata.filter(|metadata| metadata.is_file()) {
+        let total = match percent {
+            Percent::Lines(_) => count_lines(BufReader::new(File::open(filename)?))?,
+            Percent::Bytes(_) => metadata.len(),
+        };
+        return Ok((file, percent.of(total)));
+    }
+    let mut buffer = vec![];
+    file.read_to_end(&mut buffer)?;
+    let total = match percent {
+        Percent::Lines(_) => count_lines(buffer.as_slice())?,
+        Percent::Bytes(_) => buffer.len() as u64,
+    };
+    Ok((Box::new(Cursor::new(buffer)), percent.of(total)))
+}
+
+// 行数を数える: 末尾の行に改行がない場合も1行とする
+fn count_lines(mut file: impl BufRead) -> io::Result<u64> {
+    let mut num_lines = 0;
+    let mut buf = vec![];
+    while file.read_until(b'\n', &mut buf)? > 0 {
+        num_lines += 1;
+        buf.clear();
+    }
+    Ok(num_lines)
+}
+
+// 出力ディレクトリ内に入力ファイルと同じ名前のファイルを作成する
+// 既存ファイルは--force指定時のみ上書きし、同じ実行内での名前の重複はエラーとする
+fn create_output(
+    dir: &Path,
+    filename: &str,
+    force: bool,
+    created: &mut HashSet<PathBuf>,
+) -> MyResult<File> {
+    let basename = match filename {
+        "-" => "stdin".into(), // 標準入力はファイル名がないため固定の名前にする
+        _ => Path::new(filename)
+            .file_name()
+            .ok_or_else(|| format!("{}: invalid file name", filename))?
+            .to_os_string(),
+    };
+    let path = dir.join(basename);
+    if created.contains(&path) {
+        return Err(format!("{}: duplicate output file name", path.display()).into());
+    }
+    if path.exists() && !force {
+        return Err(format!("{}: already exists (use --force to overwrite)", path.display()).into());
+    }
+    let file = File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    created.insert(path);
+    Ok(file)
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    let num_files = config.files.len();
+    // ファイルごとに出力する場合はヘッダーを付けない
+    let headers = config.output_dir.is_none() && show_headers(config.headers, config.files.len());
+    if let Some(dir) = &config.output_dir {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let mut created = HashSet::new();
 
     for (file_num, filename) in config.files.iter().enumerate() {
-        match open(&filename) {
+        match open(filename) {
             Err(e) => eprintln!("{}: {}", filename, e),
             Ok(mut file) => {
-                if num_files > 1 { // 対象ファイル数が複数の場合
+                // 出力先をファイルごとに切り替える
+                let mut out: Box<dyn Write> = match &config.output_dir {
+                    Some(dir) => match create_output(dir, filename, config.force, &mut created) {
+                        Ok(out_file) => Box::new(out_file),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    },
+                    None => Box::new(stdout()),
+                };
+                if headers { // 対象ファイル数が複数の場合または-v指定時
                     println!(
                         "{}==> {} <==",
                         if file_num > 0 { "\n" } else { "" }, // 2ファイル目以降は改行を追加
@@ -84,24 +460,50 @@ pub fn run(config: Config) -> MyResult<()> {
                 // for line in file.lines().take(config.lines) { // take(n)でイテレータの回数を制限
                 //     println!("{}", line?); // lines()は各行の文字列を取得し、改行コード無しで返す
                 // }
-                if let Some(num_bytes) = config.bytes {
-                    // let mut handle = file.take(num_bytes as u64); // 指定のバイト数で対象範囲指定: usizeはu64に変換して使用する
-                    // let mut buffer = vec![0; num_bytes]; // 読み込み先となる固定サイズの空バイト配列を作成
-                    // let bytes_read = handle.read(&mut buffer)?; // 指定のバイト数の分だけ読み込む: 実際の読み込みサイズを返り値で取得
-                    // print!("{}", String::from_utf8_lossy(&buffer[..bytes_read])); // 実際に読み込まれたサイズ分だけバイト配列を文字列に変換して出力
-
-                    let bytes = file.bytes().take(num_bytes).collect::<Result<Vec<_>, _>>(); // turbofishで型情報を明示
-                    print!("{}", String::from_utf8_lossy(&bytes?));
-                } else {
-                    let mut line = String::new();
-                    for _ in 0..config.lines { // 行数の指定
-                        let bytes = file.read_line(&mut line)?; // ファイルから各行のバイト配列を読み込み、文字列の変数に代入(返り値は読み込みバイト数): バイト配列なので改行コードもそのまま代入される
-                        if bytes == 0 {
-                            break; // EOFの時は0バイトが読み込まれる
+                // ヘッダー行は読み飛ばす範囲と出力する行数に含めない
+                if config.header_always {
+                    match split_header(&mut file) {
+                        Ok(Some(header)) => out.write_all(&header)?,
+                        Ok(None) => {}
+                        Err(e) => {
+                            eprintln!("{}: {}", filename, e);
+                            continue;
+                        }
+                    }
+                }
+                let skipped = match discard(&mut file, config.skip) {
+                    Ok(skipped) => skipped,
+                    Err(e) => {
+                        eprintln!("{}: {}", filename, e);
+                        continue;
+                    }
+                };
+                let hex = config.hex.then_some(skipped); // ダンプの位置は読み飛ばした分から数える
+                if let Some(percent) = config.percent {
+                    let (mut file, num) = match count_first(file, filename, percent) {
+                        Ok(counted) => counted,
+                        Err(e) => {
+                            eprintln!("{}: {}", filename, e);
+                            continue;
                         }
-                        print!("{}", line); // 改行コードも含まれるのでln不要
-                        line.clear(); // 文字列をリセット
+                    };
+                    match percent {
+                        Percent::Lines(_) => write_lines_section(&mut file, num, &mut vec![], &mut out)?,
+                        Percent::Bytes(_) => write_bytes_section(&mut file, num, &[], hex, &mut out)?,
                     }
+                    continue;
+                }
+                // 標準入力は読み直せないため、行の出力で読み込んだ内容をバイトの出力でも使う
+                let mut consumed = vec![];
+                if config.bytes.is_none() || config.both {
+                    write_lines_section(&mut file, config.lines, &mut consumed, &mut out)?;
+                }
+                if let Some(num_bytes) = config.bytes {
+                    // 行の出力が改行で終わらない場合も、バイトの出力は新しい行から始める
+                    if !consumed.is_empty() && !consumed.ends_with(b"\n") {
+                        writeln!(out)?;
+                    }
+                    write_bytes_section(&mut file, num_bytes, &consumed, hex, &mut out)?;
                 }
             },
         };
@@ -116,17 +518,225 @@ fn parse_positive_int(val: &str) -> MyResult<usize> {
     }
 }
 
-#[test]
-fn test_parse_positive_int() {
-    let res = parse_positive_int("3");
-    assert!(res.is_ok());
-    assert_eq!(res.unwrap(), 3);
+// 0より大きく100以下の割合: 小数も受け付ける
+fn parse_percent(val: &str) -> MyResult<f64> {
+    match val.parse::<f64>() {
+        Ok(n) if n > 0.0 && n <= 100.0 => Ok(n),
+        _ => Err(format!("illegal percentage -- {}", val).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_first, count_lines, create_output, discard, head_bytes, head_lines, is_delimited, parse_percent, parse_positive_int, show_headers, split_header, write_bytes_section, write_lines_section, HeaderMode, Percent, Skip};
+    use std::{collections::HashSet, fs::{self, File}, io::{BufRead, BufReader, Cursor, Read}};
+
+    #[test]
+    fn test_show_headers() {
+        assert!(!show_headers(HeaderMode::Auto, 1));
+        assert!(show_headers(HeaderMode::Auto, 2));
+        assert!(show_headers(HeaderMode::Always, 1));
+        assert!(!show_headers(HeaderMode::Never, 2));
+    }
+
+    #[test]
+    fn test_parse_positive_int() {
+        let res = parse_positive_int("3");
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 3);
+
+        let res = parse_positive_int("foo");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "foo".to_string());
+
+        let res = parse_positive_int("0");
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "0".to_string());
+    }
+
+    #[test]
+    fn test_head_lines() {
+        let text = "one\r\ntwo\nthree";
+
+        // Line endings are kept as-is
+        let lines: Result<Vec<_>, _> = head_lines(Cursor::new(text), 2).collect();
+        assert!(lines.is_ok());
+        assert_eq!(lines.unwrap(), ["one\r\n", "two\n"]);
+
+        // Asking for more lines than available returns them all
+        let lines: Result<Vec<_>, _> = head_lines(Cursor::new(text), 10).collect();
+        assert!(lines.is_ok());
+        assert_eq!(lines.unwrap(), ["one\r\n", "two\n", "three"]);
 
-    let res = parse_positive_int("foo");
-    assert!(res.is_err());
-    assert_eq!(res.unwrap_err().to_string(), "foo".to_string());
+        // Zero lines and empty input yield nothing
+        assert_eq!(head_lines(Cursor::new(text), 0).count(), 0);
+        assert_eq!(head_lines(Cursor::new(""), 3).count(), 0);
+    }
+
+    #[test]
+    fn test_head_bytes() {
+        let mut buffer = vec![];
+        let res = head_bytes(Cursor::new("héllo"), 2).read_to_end(&mut buffer);
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 2);
+        assert_eq!(buffer, b"h\xc3");
+
+        let mut buffer = vec![];
+        let res = head_bytes(Cursor::new("hi"), 10).read_to_end(&mut buffer);
+        assert!(res.is_ok());
+        assert_eq!(buffer, b"hi");
+    }
+
+    #[test]
+    fn test_discard() {
+        let rest = |text: &[u8], skip: Skip| {
+            let mut file = Cursor::new(text);
+            let skipped = discard(&mut file, skip).unwrap();
+            assert_eq!(skipped, file.position());
+            let mut buffer = vec![];
+            file.read_to_end(&mut buffer).unwrap();
+            buffer
+        };
+        assert_eq!(rest(b"one\r\ntwo\nthree", Skip::Lines(1)), b"two\nthree");
+        assert_eq!(rest(b"one\r\ntwo\nthree", Skip::Lines(0)), b"one\r\ntwo\nthree");
+        assert_eq!(rest(b"h\xffi\nthere\n", Skip::Lines(1)), b"there\n");
+        assert_eq!(rest("héllo".as_bytes(), Skip::Bytes(2)), b"\xa9llo");
+
+        // Skipping past the end leaves nothing to print
+        assert_eq!(rest(b"one\ntwo", Skip::Lines(5)), b"");
+        assert_eq!(rest(b"one", Skip::Bytes(10)), b"");
+    }
+
+    #[test]
+    fn test_split_header() {
+        let split = |text: &'static str| {
+            let mut file: Box<dyn BufRead> = Box::new(Cursor::new(text));
+            let header = split_header(&mut file).unwrap();
+            let mut rest = String::new();
+            file.read_to_string(&mut rest).unwrap();
+            (header, rest)
+        };
+
+        // A delimited first line is taken out as the header
+        assert_eq!(split("id,name\n1,a\n"), (Some(b"id,name\n".to_vec()), "1,a\n".to_string()));
+
+        // Otherwise the first line is put back as data
+        assert_eq!(split("one\ntwo\n"), (None, "one\ntwo\n".to_string()));
+        assert_eq!(split(""), (None, "".to_string()));
+    }
+
+    #[test]
+    fn test_is_delimited() {
+        assert!(is_delimited(b"id,name\n"));
+        assert!(is_delimited(b"id\tname\n"));
+        assert!(is_delimited(b"id;name"));
+        assert!(!is_delimited(b"just some words\n"));
+        assert!(!is_delimited(b""));
+    }
+
+    #[test]
+    fn test_sections() {
+        let mut file = Cursor::new("one\ntwo\nthree\n");
+        let mut consumed = vec![];
+        let mut out = vec![];
+        assert!(write_lines_section(&mut file, 1, &mut consumed, &mut out).is_ok());
+        assert_eq!(consumed, b"one\n");
+
+        // Bytes already read for the lines are reused as the raw prefix
+        assert!(write_bytes_section(&mut file, 2, &consumed, None, &mut out).is_ok());
+        assert!(write_bytes_section(&mut file, 6, &consumed, None, &mut out).is_ok());
+        assert_eq!(out, b"one\nonone\ntw");
+
+        // Without consumed bytes the section reads straight from the file
+        let mut out = vec![];
+        let res = write_bytes_section(&mut Cursor::new("héllo"), 2, &[], None, &mut out);
+        assert!(res.is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "h\u{FFFD}");
 
-    let res = parse_positive_int("0");
-    assert!(res.is_err());
-    assert_eq!(res.unwrap_err().to_string(), "0".to_string());
+        // A hex dump keeps the raw bytes and labels rows from the given offset
+        let mut out = vec![];
+        let res = write_bytes_section(&mut Cursor::new("héllo"), 3, &[], Some(16), &mut out);
+        assert!(res.is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "00000010  68 c3 a9                                          |h..|\n00000013\n"
+        );
+    }
+
+    #[test]
+    fn test_create_output() {
+        let dir = std::env::temp_dir().join(format!("headr-create-output-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut created = HashSet::new();
+
+        // The output file is named after the input's basename
+        assert!(create_output(&dir, "tests/inputs/one.txt", false, &mut created).is_ok());
+        assert!(dir.join("one.txt").is_file());
+        assert!(create_output(&dir, "-", false, &mut created).is_ok());
+        assert!(dir.join("stdin").is_file());
+
+        // The same basename twice in one run is a collision, even with force
+        let res = create_output(&dir, "other/one.txt", true, &mut created);
+        assert!(res.is_err());
+        assert!(res.unwrap_err().to_string().ends_with("one.txt: duplicate output file name"));
+
+        // Existing files are only overwritten with force
+        let mut created = HashSet::new();
+        let res = create_output(&dir, "tests/inputs/one.txt", false, &mut created);
+        assert!(res.is_err());
+        assert!(res.unwrap_err().to_string().contains("already exists"));
+        assert!(create_output(&dir, "tests/inputs/one.txt", true, &mut created).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_percent() {
+        assert_eq!(parse_percent("50").unwrap(), 50.0);
+        assert_eq!(parse_percent("12.5").unwrap(), 12.5);
+        assert_eq!(parse_percent("100").unwrap(), 100.0);
+        for val in ["0", "100.1", "-5", "NaN", "foo"] {
+            let res = parse_percent(val);
+            assert!(res.is_err());
+            assert_eq!(res.unwrap_err().to_string(), format!("illegal percentage -- {}", val));
+        }
+    }
+
+    #[test]
+    fn test_percent_of() {
+        assert_eq!(Percent::Lines(50.0).of(10), 5);
+        assert_eq!(Percent::Lines(25.0).of(10), 2); // Fractions are rounded down
+        assert_eq!(Percent::Bytes(100.0).of(49), 49);
+        assert_eq!(Percent::Bytes(1.0).of(0), 0);
+    }
+
+    #[test]
+    fn test_count_lines() {
+        assert_eq!(count_lines(Cursor::new("")).unwrap(), 0);
+        assert_eq!(count_lines(Cursor::new("one\ntwo\n")).unwrap(), 2);
+        assert_eq!(count_lines(Cursor::new("one\ntwo")).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_count_first() {
+        let open = |filename| -> Box<dyn BufRead> {
+            Box::new(BufReader::new(File::open(filename).unwrap()))
+        };
+
+        // Regular files are counted without consuming the reader
+        let (mut file, num) = count_first(open("tests/inputs/ten.txt"), "tests/inputs/ten.txt", Percent::Lines(50.0)).unwrap();
+        assert_eq!(num, 5);
+        let mut rest = String::new();
+        file.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, fs::read_to_string("tests/inputs/ten.txt").unwrap());
+        let (_, num) = count_first(open("tests/inputs/ten.txt"), "tests/inputs/ten.txt", Percent::Bytes(50.0)).unwrap();
+        assert_eq!(num, 24);
+
+        // Other inputs are buffered in memory first
+        let (mut file, num) = count_first(Box::new(Cursor::new("a\nb\nc\nd")), "-", Percent::Lines(75.0)).unwrap();
+        assert_eq!(num, 3);
+        let mut rest = String::new();
+        file.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "a\nb\nc\nd");
+    }
 }
@@ -15,6 +15,7 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TEN: &str = "./tests/inputs/ten.txt";
+const BOOKS: &str = "./tests/inputs/books.csv";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -41,7 +42,7 @@ fn dies_bad_bytes() -> TestResult {
     let bad = random_string();
     let expected = format!("illegal byte count -- {}", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-c", &bad, EMPTY])
+        .args(["-c", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -55,7 +56,7 @@ fn dies_bad_lines() -> TestResult {
     let bad = random_string();
     let expected = format!("illegal line count -- {}", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-n", &bad, EMPTY])
+        .args(["-n", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -66,11 +67,10 @@ fn dies_bad_lines() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn dies_bytes_and_lines() -> TestResult {
-    let msg = "The argument '--lines <LINES>' cannot be \
-               used with '--bytes <BYTES>'";
+    let msg = "--lines and --bytes can only be combined with --both";
 
     Command::cargo_bin(PRG)?
-        .args(&["-n", "1", "-c", "2"])
+        .args(["-n", "1", "-c", "2"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(msg));
@@ -103,7 +103,7 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
         .args(args)
         .assert()
         .success()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(expected.as_bytes() as &[u8]));
 
     Ok(())
 }
@@ -125,7 +125,7 @@ fn run_stdin(
         .write_stdin(input)
         .args(args)
         .assert()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(expected.as_bytes() as &[u8]));
 
     Ok(())
 }
@@ -419,3 +419,287 @@ fn multiple_files_c4() -> TestResult {
         "tests/expected/all.c4.out",
     )
 }
+
+#[test]
+fn multiple_files_n4_quiet() -> TestResult {
+    run(
+        &["-n", "4", "-q", EMPTY, ONE, TWO, THREE, TEN],
+        "tests/expected/all.n4.q.out",
+    )
+}
+
+#[test]
+fn multiple_files_n4_silent() -> TestResult {
+    run(
+        &["-n", "4", "--silent", EMPTY, ONE, TWO, THREE, TEN],
+        "tests/expected/all.n4.q.out",
+    )
+}
+
+#[test]
+fn multiple_files_verbose_then_quiet() -> TestResult {
+    run(
+        &["-n", "4", "-v", "-q", EMPTY, ONE, TWO, THREE, TEN],
+        "tests/expected/all.n4.q.out",
+    )
+}
+
+#[test]
+fn one_verbose() -> TestResult {
+    run(&["--verbose", ONE], "tests/expected/one.txt.v.out")
+}
+
+#[test]
+fn ten_c4_verbose() -> TestResult {
+    run(&["-v", "-c", "4", TEN], "tests/expected/ten.txt.c4.v.out")
+}
+
+#[test]
+fn both_n2_c6() -> TestResult {
+    run(&["-n", "2", "-c", "6", "--both", TEN], "tests/expected/ten.txt.n2.c6.both.out")
+}
+
+#[test]
+fn both_n1_c20_stdin() -> TestResult {
+    // 標準入力でも行の出力で読み込んだ内容をバイトの出力に使う
+    run_stdin(
+        &["-n", "1", "-c", "20", "--both"],
+        TWO,
+        "tests/expected/two.txt.n1.c20.both.out",
+    )
+}
+
+#[test]
+fn multiple_files_both() -> TestResult {
+    run(
+        &["-n", "1", "-c", "1", "--both", ONE, TWO],
+        "tests/expected/one_two.n1.c1.both.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_both_without_bytes() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1", "--both", ONE])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+fn gen_out_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("headr-{}", random_string()))
+}
+
+// --------------------------------------------------
+#[test]
+fn output_dir() -> TestResult {
+    let dir = gen_out_dir();
+    let dir_name = dir.to_string_lossy().to_string();
+    Command::cargo_bin(PRG)?
+        .args(["-n", "2", "--output-dir", &dir_name, ONE, TEN])
+        .assert()
+        .success()
+        .stdout("");
+
+    // ヘッダーは付けずに各ファイルへ出力される
+    let expected = fs::read_to_string("tests/expected/one.txt.n2.out")?;
+    assert_eq!(fs::read_to_string(dir.join("one.txt"))?, expected);
+    let expected = fs::read_to_string("tests/expected/ten.txt.n2.out")?;
+    assert_eq!(fs::read_to_string(dir.join("ten.txt"))?, expected);
+
+    // 既存のファイルは--forceがなければ上書きしない
+    Command::cargo_bin(PRG)?
+        .args(["-c", "2", "--output-dir", &dir_name, ONE])
+        .assert()
+        .stderr(predicate::str::contains("already exists"));
+    let expected = fs::read_to_string("tests/expected/one.txt.n2.out")?;
+    assert_eq!(fs::read_to_string(dir.join("one.txt"))?, expected);
+
+    Command::cargo_bin(PRG)?
+        .args(["-c", "2", "--output-dir", &dir_name, "--force", ONE])
+        .assert()
+        .success();
+    let expected = fs::read_to_string("tests/expected/one.txt.c2.out")?;
+    assert_eq!(fs::read_to_string(dir.join("one.txt"))?, expected);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_force_without_output_dir() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--force", ONE])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_skip() -> TestResult {
+    for opt in ["--skip-lines", "--skip-bytes"] {
+        Command::cargo_bin(PRG)?
+            .args([opt, "foo", TEN])
+            .assert()
+            .failure()
+            .stderr("illegal skip count -- foo\n");
+    }
+    Command::cargo_bin(PRG)?
+        .args(["--skip-lines", "1", "--skip-bytes", "1", TEN])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_skip_lines3_n2() -> TestResult {
+    run(&[TEN, "--skip-lines", "3", "-n", "2"], "tests/expected/ten.txt.skip_lines3.n2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_skip_lines3_n2_stdin() -> TestResult {
+    run_stdin(&["--skip-lines", "3", "-n", "2"], TEN, "tests/expected/ten.txt.skip_lines3.n2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_skip_bytes5_c6() -> TestResult {
+    run(&[TEN, "--skip-bytes", "5", "-c", "6"], "tests/expected/ten.txt.skip_bytes5.c6.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_skip_lines2() -> TestResult {
+    // 1行しかないファイルは読み飛ばした後に何も出力しない
+    run(&["--skip-lines", "2", ONE, THREE], "tests/expected/all.skip_lines2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_percent50() -> TestResult {
+    run(&[TEN, "--percent", "50"], "tests/expected/ten.txt.percent50.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_percent50_stdin() -> TestResult {
+    // 標準入力は全体を読み込んでから行数を数える
+    run_stdin(&["--percent", "50"], TEN, "tests/expected/ten.txt.percent50.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn ten_percent_bytes50() -> TestResult {
+    run(&[TEN, "--percent-bytes", "50"], "tests/expected/ten.txt.percent_bytes50.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_percent50() -> TestResult {
+    // 端数は切り捨てるため、1行のファイルは何も出力しない
+    run(&["--percent", "50", EMPTY, ONE, TEN, THREE, TWO], "tests/expected/all.percent50.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_percent() -> TestResult {
+    for val in ["0", "101", "foo"] {
+        Command::cargo_bin(PRG)?
+            .args(["--percent", val, TEN])
+            .assert()
+            .failure()
+            .stderr(format!("illegal percentage -- {}\n", val));
+    }
+    Command::cargo_bin(PRG)?
+        .args(["--percent", "50", "-n", "2", TEN])
+        .assert()
+        .failure()
+        .stderr("--lines cannot be combined with --percent or --percent-bytes\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn header_always_n2() -> TestResult {
+    // 区切り形式でないファイルは先頭行もデータ行として数える
+    run(&["--header-always", "-n", "2", BOOKS, TEN], "tests/expected/all.header_always.n2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn books_header_always_skip_lines1_n2_stdin() -> TestResult {
+    run_stdin(
+        &["--header-always", "--skip-lines", "1", "-n", "2"],
+        BOOKS,
+        "tests/expected/books.csv.header_always.skip_lines1.n2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_header_always_with_bytes() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--header-always", "-c", "2", BOOKS])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_c30() -> TestResult {
+    run(&["--hex", "-c", "30", ONE], "tests/expected/one.txt.hex.c30.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_skip_bytes_stdin() -> TestResult {
+    // 読み飛ばした分から位置を数える
+    run_stdin(
+        &["--hex", "--skip-bytes", "4", "-c", "8"],
+        TEN,
+        "tests/expected/ten.txt.hex.skip_bytes4.c8.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_binary() -> TestResult {
+    // 不正なUTF-8のバイト列も置換せずにダンプする
+    let input: Vec<u8> = (0..=255).rev().collect();
+    Command::cargo_bin(PRG)?
+        .args(["--hex", "-c", "20", "--skip-lines", "1"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(
+            "000000f6  09 08 07 06 05 04 03 02  01 00                    |..........|\n\
+             00000100\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_all() -> TestResult {
+    run(&["--hex", "--percent-bytes", "50", EMPTY, ONE, TWO], "tests/expected/all.hex.percent_bytes50.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_hex_without_bytes() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--hex", "-n", "2", ONE])
+        .assert()
+        .failure()
+        .stderr("--hex requires --bytes or --percent-bytes\n");
+    Ok(())
+}
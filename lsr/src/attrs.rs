@@ -0,0 +1,189 @@
+use std::path::Path;
+
+// パーミッション文字列の後に付ける印: 拡張属性があれば'@'、ACLがあれば'+'
+// 両方ある場合はmacOSのlsと同様に'@'を優先する。対応していないプラットフォームでは常にNone
+pub fn indicator(path: &Path) -> Option<char> {
+    let names = sys::xattr_names(path).ok()?; // 拡張属性に対応していないファイルシステムなどは印なし
+    if names.iter().any(|name| !sys::is_hidden(name)) {
+        Some('@')
+    } else if sys::has_acl(path, &names) {
+        Some('+')
+    } else {
+        None
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod sys {
+    use std::{
+        ffi::CString,
+        io,
+        os::{raw::c_char, unix::ffi::OsStrExt},
+        path::Path,
+        ptr,
+    };
+
+    #[cfg(target_os = "linux")]
+    unsafe fn listxattr(path: *const c_char, list: *mut c_char, size: usize) -> isize {
+        libc::listxattr(path, list, size)
+    }
+
+    #[cfg(target_os = "macos")]
+    unsafe fn listxattr(path: *const c_char, list: *mut c_char, size: usize) -> isize {
+        libc::listxattr(path, list, size, 0)
+    }
+
+    // 拡張属性の名前の一覧: lsの他の列と同様にシンボリックリンクは辿る
+    pub fn xattr_names(path: &Path) -> io::Result<Vec<Vec<u8>>> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        loop {
+            // 1回目で必要なサイズを調べてから読み込む
+            let size = unsafe { listxattr(path.as_ptr(), ptr::null_mut(), 0) };
+            if size < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut buf = vec![0u8; size as usize];
+            let len = unsafe { listxattr(path.as_ptr(), buf.as_mut_ptr() as *mut c_char, buf.len()) };
+            if len < 0 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(libc::ERANGE) {
+                    continue; // 2回の呼び出しの間に属性が増えた場合は読み直す
+                }
+                return Err(err);
+            }
+            buf.truncate(len as usize);
+            // NUL終端の名前が連続して並んでいる
+            return Ok(buf
+                .split(|&b| b == 0)
+                .filter(|name| !name.is_empty())
+                .map(<[u8]>::to_vec)
+                .collect());
+        }
+    }
+
+    // Linuxではシステムが管理する属性(ACLとSELinuxのラベル)を'@'の対象にしない
+    #[cfg(target_os = "linux")]
+    pub fn is_hidden(name: &[u8]) -> bool {
+        name.starts_with(b"system.posix_acl_") || name.starts_with(b"security.")
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn is_hidden(_name: &[u8]) -> bool {
+        false
+    }
+
+    // POSIX ACLは拡張属性として保存される: ディレクトリの既定のACLも含める
+    #[cfg(target_os = "linux")]
+    pub fn has_acl(_path: &Path, names: &[Vec<u8>]) -> bool {
+        names.iter().any(|name| {
+            name == b"system.posix_acl_access" || name == b"system.posix_acl_default"
+        })
+    }
+
+    // macOSのACLは拡張属性の一覧に現れないため、ACLのエントリを直接調べる
+    #[cfg(target_os = "macos")]
+    pub fn has_acl(path: &Path, _names: &[Vec<u8>]) -> bool {
+        use std::os::raw::{c_int, c_void};
+
+        const ACL_TYPE_EXTENDED: c_int = 0x0000_0100;
+        const ACL_FIRST_ENTRY: c_int = 0;
+
+        extern "C" {
+            fn acl_get_file(path: *const c_char, acl_type: c_int) -> *mut c_void;
+            fn acl_get_entry(acl: *mut c_void, entry_id: c_int, entry: *mut *mut c_void) -> c_int;
+            fn acl_free(obj: *mut c_void) -> c_int;
+        }
+
+        let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+            return false;
+        };
+        unsafe {
+            let acl = acl_get_file(path.as_ptr(), ACL_TYPE_EXTENDED);
+            if acl.is_null() {
+                return false;
+            }
+            let mut entry = ptr::null_mut();
+            let found = acl_get_entry(acl, ACL_FIRST_ENTRY, &mut entry) == 0;
+            acl_free(acl);
+            found
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod sys {
+    use std::{io, path::Path};
+
+    pub fn xattr_names(_path: &Path) -> io::Result<Vec<Vec<u8>>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn is_hidden(_name: &[u8]) -> bool {
+        false
+    }
+
+    pub fn has_acl(_path: &Path, _names: &[Vec<u8>]) -> bool {
+        false
+    }
+}
+
+// --------------------------------------------------
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::indicator;
+    use std::{ffi::CString, fs, os::unix::ffi::OsStrExt, path::Path};
+
+    // 拡張属性を設定する: ファイルシステムが対応していない場合はfalse
+    fn set_xattr(path: &Path, name: &str, value: &[u8]) -> bool {
+        let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let name = CString::new(name).unwrap();
+        let res = unsafe {
+            libc::setxattr(path.as_ptr(), name.as_ptr(), value.as_ptr().cast(), value.len(), 0)
+        };
+        res == 0
+    }
+
+    // ユーザー1000に読み取りを許可するACL: ヘッダー(バージョン2)と(タグ, 権限, ID)のエントリの並び
+    fn acl_value() -> Vec<u8> {
+        let mut value = 2u32.to_le_bytes().to_vec();
+        for (tag, perm, id) in [
+            (0x01u16, 6u16, u32::MAX), // 所有者
+            (0x02, 4, 1000),           // 指定したユーザー
+            (0x04, 4, u32::MAX),       // グループ
+            (0x10, 4, u32::MAX),       // マスク
+            (0x20, 4, u32::MAX),       // その他
+        ] {
+            value.extend(tag.to_le_bytes());
+            value.extend(perm.to_le_bytes());
+            value.extend(id.to_le_bytes());
+        }
+        value
+    }
+
+    #[test]
+    fn test_indicator() {
+        let dir = std::env::temp_dir().join(format!("lsr-attrs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let plain = dir.join("plain");
+        let xattr = dir.join("xattr");
+        let acl = dir.join("acl");
+        for path in [&plain, &xattr, &acl] {
+            fs::write(path, "").unwrap();
+        }
+
+        assert_eq!(indicator(&plain), None);
+        if set_xattr(&xattr, "user.lsr", b"1") {
+            assert_eq!(indicator(&xattr), Some('@'));
+        }
+        if set_xattr(&acl, "system.posix_acl_access", &acl_value()) {
+            assert_eq!(indicator(&acl), Some('+'));
+            // 拡張属性とACLの両方がある場合は'@'
+            if set_xattr(&acl, "user.lsr", b"1") {
+                assert_eq!(indicator(&acl), Some('@'));
+            }
+        }
+        assert_eq!(indicator(&dir.join("missing")), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
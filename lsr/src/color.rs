@@ -0,0 +1,138 @@
+use std::{collections::HashMap, env, fs, os::unix::fs::PermissionsExt, path::Path, str::FromStr};
+
+// 色付き出力の方針
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColorMode {
+    Auto,   // 標準出力が端末の場合のみ色付け
+    Always, // 常に色付け
+    Never,  // 色付けしない
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(format!("Invalid --color \"{}\"", s)),
+        }
+    }
+}
+
+impl ColorMode {
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorMode::Auto => atty::is(atty::Stream::Stdout),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+// LS_COLORSの設定: "di=01;34:ln=01;36:*.tar=01;31" のような形式
+#[derive(Debug, PartialEq)]
+pub struct LsColors {
+    codes: HashMap<String, String>, // 種別(di, ln, ex)または拡張子(*.tar)をキーとしたSGRコード
+}
+
+impl Default for LsColors {
+    // LS_COLORS未設定時はGNU lsと同じ既定色を使う
+    fn default() -> Self {
+        LsColors::parse("di=01;34:ln=01;36:ex=01;32")
+    }
+}
+
+impl LsColors {
+    pub fn from_env() -> Self {
+        match env::var("LS_COLORS") {
+            Ok(val) if !val.is_empty() => LsColors::parse(&val),
+            _ => LsColors::default(),
+        }
+    }
+
+    pub fn parse(val: &str) -> Self {
+        let codes = val
+            .split(':')
+            .filter_map(|entry| entry.split_once('=')) // "key=code" の形式のみを対象とする
+            .filter(|(key, code)| !key.is_empty() && !code.is_empty())
+            .map(|(key, code)| (key.to_string(), code.to_string()))
+            .collect();
+        LsColors { codes }
+    }
+
+    // パスの種別に応じたSGRコードを返す: 該当なしの場合はNone
+    fn code_for(&self, path: &Path) -> Option<&str> {
+        let metadata = fs::symlink_metadata(path).ok()?; // シンボリックリンクを辿らずに取得
+        let key = if metadata.file_type().is_symlink() {
+            "ln"
+        } else if metadata.is_dir() {
+            "di"
+        } else if metadata.permissions().mode() & 0o111 != 0 {
+            "ex"
+        } else {
+            return path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .and_then(|name| {
+                    self.codes
+                        .iter()
+                        .filter(|(key, _)| key.starts_with('*'))
+                        .find(|(key, _)| name.ends_with(&key[1..]))
+                        .map(|(_, code)| code.as_str())
+                });
+        };
+        self.codes.get(key).map(String::as_str)
+    }
+
+    pub fn paint(&self, path: &Path, text: &str) -> String {
+        match self.code_for(path) {
+            Some(code) => format!("\x1b[{}m{}\x1b[0m", code, text),
+            None => text.to_string(),
+        }
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod test {
+    use super::{ColorMode, LsColors};
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_color_mode() {
+        assert_eq!("auto".parse::<ColorMode>(), Ok(ColorMode::Auto));
+        assert_eq!("always".parse::<ColorMode>(), Ok(ColorMode::Always));
+        assert_eq!("never".parse::<ColorMode>(), Ok(ColorMode::Never));
+        assert!("foo".parse::<ColorMode>().is_err());
+    }
+
+    #[test]
+    fn test_parse_ls_colors() {
+        let colors = LsColors::parse("di=01;34:ln=:bad:*.txt=00;33");
+        assert_eq!(colors.codes.len(), 2);
+        assert_eq!(colors.codes.get("di").unwrap(), "01;34");
+        assert_eq!(colors.codes.get("*.txt").unwrap(), "00;33");
+    }
+
+    #[test]
+    fn test_paint() {
+        let colors = LsColors::parse("di=01;34:*.txt=00;33");
+        assert_eq!(
+            colors.paint(Path::new("tests/inputs/dir"), "dir"),
+            "\x1b[01;34mdir\x1b[0m"
+        );
+        assert_eq!(
+            colors.paint(Path::new("tests/inputs/fox.txt"), "fox.txt"),
+            "\x1b[00;33mfox.txt\x1b[0m"
+        );
+
+        // Entries without a matching code are left untouched
+        let colors = LsColors::parse("di=01;34");
+        assert_eq!(
+            colors.paint(Path::new("tests/inputs/fox.txt"), "fox.txt"),
+            "fox.txt"
+        );
+    }
+}
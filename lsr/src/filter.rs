@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use globset::{Glob, GlobMatcher};
+
+use crate::MyResult;
+
+// --only-dirs, --only-filesで絞り込む項目の種類
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EntryKind {
+    Dirs,
+    Files, // ディレクトリ以外: シンボリックリンクはリンク先の種類で判定する
+}
+
+// 一覧に表示する項目の絞り込み: 条件を指定しない場合は全て表示する
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub kind: Option<EntryKind>,
+    pub pattern: Option<GlobMatcher>, // --match: パスではなくファイル名に対して判定する
+}
+
+impl Filter {
+    pub fn new(kind: Option<EntryKind>, pattern: Option<&str>) -> MyResult<Self> {
+        let pattern = pattern
+            .map(|val| {
+                Glob::new(val)
+                    .map(|glob| glob.compile_matcher())
+                    .map_err(|_| format!("Invalid --match \"{}\"", val))
+            })
+            .transpose()?;
+        Ok(Filter { kind, pattern })
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let kind_ok = match self.kind {
+            None => true,
+            Some(EntryKind::Dirs) => path.is_dir(),
+            Some(EntryKind::Files) => !path.is_dir(),
+        };
+        // ファイル名のない".."などは引数そのものと比べる
+        kind_ok
+            && self.pattern.as_ref().is_none_or(|pattern| {
+                pattern.is_match(path.file_name().map_or(path.as_os_str(), |name| name))
+            })
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{EntryKind, Filter};
+    use std::path::Path;
+
+    #[test]
+    fn test_matches() {
+        let filter = Filter::default();
+        assert!(filter.matches(Path::new("tests/inputs/fox.txt")));
+        assert!(filter.matches(Path::new("tests/inputs/dir")));
+
+        let filter = Filter::new(Some(EntryKind::Dirs), None).unwrap();
+        assert!(!filter.matches(Path::new("tests/inputs/fox.txt")));
+        assert!(filter.matches(Path::new("tests/inputs/dir")));
+
+        let filter = Filter::new(Some(EntryKind::Files), Some("*.txt")).unwrap();
+        assert!(filter.matches(Path::new("tests/inputs/fox.txt")));
+        assert!(!filter.matches(Path::new("tests/inputs/dir")));
+        assert!(!filter.matches(Path::new("tests/inputs/.hidden")));
+
+        // ディレクトリ部分は判定に使わない
+        let filter = Filter::new(None, Some("in*")).unwrap();
+        assert!(filter.matches(Path::new("tests/inputs")));
+        assert!(!filter.matches(Path::new("tests/inputs/dir")));
+
+        let res = Filter::new(None, Some("a[b"));
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().to_string(), "Invalid --match \"a[b\"");
+    }
+}
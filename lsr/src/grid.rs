@@ -0,0 +1,145 @@
+// 複数列表示での並べ方
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Layout {
+    OneLine, // -1: 1行に1つ
+    Columns, // -C: 上から下へ並べてから次の列へ
+    Across,  // -x: 左から右へ並べてから次の行へ
+}
+
+// 表示する文字列と、エスケープシーケンスを除いた表示幅
+#[derive(Debug, PartialEq)]
+pub struct Cell {
+    pub text: String,
+    pub width: usize,
+}
+
+impl Cell {
+    pub fn new(text: String, plain: &str) -> Self {
+        Cell {
+            text,
+            width: plain.chars().count(),
+        }
+    }
+}
+
+// 列間の空白
+const GAP: usize = 2;
+
+// 端末の幅に収まる最大の列数でセルを並べる: 末尾の改行は含まない
+pub fn format_grid(cells: &[Cell], term_width: usize, layout: Layout) -> String {
+    let (num_rows, col_widths) = fit(cells, term_width, layout);
+    let num_cols = col_widths.len();
+    let mut lines = vec![];
+    for row in 0..num_rows {
+        let mut line = String::new();
+        for (col, col_width) in col_widths.iter().enumerate() {
+            let index = match layout {
+                Layout::Across => row * num_cols + col,
+                _ => col * num_rows + row,
+            };
+            let Some(cell) = cells.get(index) else {
+                break;
+            };
+            if col > 0 {
+                line.push_str(&" ".repeat(GAP));
+            }
+            line.push_str(&cell.text);
+            // 次のセルがある場合のみ列幅まで空白で埋める: 行末に空白を残さない
+            let next = match layout {
+                Layout::Across => index + 1,
+                _ => index + num_rows,
+            };
+            if col + 1 < num_cols && next < cells.len() {
+                line.push_str(&" ".repeat(col_width - cell.width));
+            }
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+// 行数と各列の幅を求める: 列数の多い方から順に試す
+fn fit(cells: &[Cell], term_width: usize, layout: Layout) -> (usize, Vec<usize>) {
+    if layout == Layout::OneLine || cells.is_empty() {
+        let width = cells.iter().map(|cell| cell.width).max().unwrap_or(0);
+        return (cells.len(), vec![width]);
+    }
+    for num_cols in (1..=cells.len()).rev() {
+        let num_rows = cells.len().div_ceil(num_cols);
+        // 縦に並べる場合は行数から実際に使う列数が決まる
+        let num_cols = match layout {
+            Layout::Across => num_cols,
+            _ => cells.len().div_ceil(num_rows),
+        };
+        let mut col_widths = vec![0; num_cols];
+        for (i, cell) in cells.iter().enumerate() {
+            let col = match layout {
+                Layout::Across => i % num_cols,
+                _ => i / num_rows,
+            };
+            col_widths[col] = col_widths[col].max(cell.width);
+        }
+        let total: usize = col_widths.iter().sum::<usize>() + GAP * (num_cols - 1);
+        if total <= term_width || num_cols == 1 {
+            return (num_rows, col_widths);
+        }
+    }
+    unreachable!() // 1列の場合は必ず返る
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_grid, Cell, Layout};
+
+    fn cells(names: &[&str]) -> Vec<Cell> {
+        names
+            .iter()
+            .map(|name| Cell::new(name.to_string(), name))
+            .collect()
+    }
+
+    #[test]
+    fn test_format_grid_columns() {
+        let names = cells(&["a", "bbbb", "cc", "d", "eeeeee"]);
+        // 全て1行に収まる
+        assert_eq!(format_grid(&names, 80, Layout::Columns), "a  bbbb  cc  d  eeeeee");
+        // 上から下へ並べる
+        assert_eq!(
+            format_grid(&names, 12, Layout::Columns),
+            "a     d\nbbbb  eeeeee\ncc"
+        );
+        // 幅が足りない場合は1列
+        assert_eq!(format_grid(&names, 3, Layout::Columns), "a\nbbbb\ncc\nd\neeeeee");
+        assert_eq!(format_grid(&[], 80, Layout::Columns), "");
+    }
+
+    #[test]
+    fn test_format_grid_across() {
+        let names = cells(&["a", "bbbb", "cc", "d", "eeeeee"]);
+        assert_eq!(
+            format_grid(&names, 12, Layout::Across),
+            "a       bbbb\ncc      d\neeeeee"
+        );
+        assert_eq!(format_grid(&names, 80, Layout::Across), "a  bbbb  cc  d  eeeeee");
+    }
+
+    #[test]
+    fn test_format_grid_one_line() {
+        let names = cells(&["a", "bbbb"]);
+        assert_eq!(format_grid(&names, 80, Layout::OneLine), "a\nbbbb");
+    }
+
+    #[test]
+    fn test_format_grid_escape_width() {
+        // エスケープシーケンスは幅に含めない
+        let names = vec![
+            Cell::new("\x1b[01;34mdir\x1b[0m".to_string(), "dir"),
+            Cell::new("f".to_string(), "f"),
+            Cell::new("g".to_string(), "g"),
+        ];
+        assert_eq!(
+            format_grid(&names, 6, Layout::Columns),
+            "\x1b[01;34mdir\x1b[0m  g\nf"
+        );
+    }
+}
@@ -1,6 +1,5 @@
-use std::{error::Error, path::PathBuf, fs::{metadata, read_dir}, os::unix::fs::MetadataExt};
+use std::{env, error::Error, io, path::{Path, PathBuf}, fs::{metadata, read_dir, Metadata}, os::unix::fs::MetadataExt};
 
-use chrono::{DateTime, Local};
 use clap::{App, Arg};
 use tabular::{Table, Row};
 use users::{get_user_by_uid, get_group_by_gid};
@@ -10,6 +9,29 @@ mod owner;
 use owner::Owner;
 use owner::Owner::*;
 
+mod color;
+use color::{ColorMode, LsColors};
+
+mod grid;
+use grid::{Cell, Layout};
+
+mod timestamp;
+use timestamp::{TimeField, TimeStyle};
+
+mod attrs;
+
+mod sort;
+use sort::SortOrder;
+
+mod tree;
+use tree::TreeOptions;
+
+mod filter;
+use filter::{EntryKind, Filter};
+
+mod output;
+use output::{Entry, Format};
+
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
@@ -17,6 +39,25 @@ pub struct Config {
     paths: Vec<String>,
     long: bool,
     show_hidden: bool,
+    color: ColorMode,
+    list_dirs: bool,
+    show_inode: bool,
+    show_size: bool,
+    layout: Layout,
+    time_style: TimeStyle,
+    sort: SortOrder,
+    dirs_first: bool, // --group-directories-first
+    tree: bool,
+    depth: Option<usize>, // --treeで表示する深さの上限
+    filter: Filter,
+    format: Format, // 長い形式の出力形式
+}
+
+// find_filesの結果: ファイル引数をまとめたグループと、ディレクトリ引数ごとのグループに分ける
+#[derive(Debug, Default)]
+struct FileGroup {
+    dir: Option<PathBuf>, // 展開したディレクトリ: ファイル引数のグループはNone
+    paths: Vec<PathBuf>,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -45,70 +86,414 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Show all files")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Colorize the output [auto|always|never]")
+                .takes_value(true)
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("directory")
+                .short("d")
+                .long("directory")
+                .help("List directories themselves, not their contents")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("inode")
+                .short("i")
+                .long("inode")
+                .help("Show inode numbers")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("size")
+                .short("s")
+                .long("size")
+                .help("Show allocated size in 1K blocks")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("time")
+                .long("time")
+                .value_name("WORD")
+                .help("Show the given timestamp in the long listing [atime|ctime|mtime]")
+                .takes_value(true)
+                .default_value("mtime"),
+        )
+        .arg(
+            Arg::with_name("full_time")
+                .long("full-time")
+                .help("Like --long with full ISO 8601 timestamps")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("one")
+                .short("1")
+                .help("List one file per line")
+                .takes_value(false)
+                .multiple(true), // ls と同様に複数回の指定や他の形式との併用を許可
+        )
+        .arg(
+            Arg::with_name("columns")
+                .short("C")
+                .help("List entries by columns")
+                .takes_value(false)
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("across")
+                .short("x")
+                .help("List entries by lines instead of by columns")
+                .takes_value(false)
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("sort")
+                .long("sort")
+                .value_name("WORD")
+                .help("Sort entries by name, ignoring case [none|name|natural]; natural compares numbers in names by value")
+                .takes_value(true)
+                .default_value("none"),
+        )
+        .arg(
+            Arg::with_name("group_directories_first")
+                .long("group-directories-first")
+                .help("List directories before files")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("tree")
+                .long("tree")
+                .help("List contents of directories recursively as a tree")
+                .takes_value(false)
+                .conflicts_with("directory"),
+        )
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .value_name("N")
+                .help("Descend at most N levels below the given paths with --tree")
+                .takes_value(true)
+                .requires("tree"),
+        )
+        .arg(
+            Arg::with_name("only_dirs")
+                .long("only-dirs")
+                .help("List only directories")
+                .takes_value(false)
+                .conflicts_with_all(&["only_files", "tree"]),
+        )
+        .arg(
+            Arg::with_name("only_files")
+                .long("only-files")
+                .help("List only entries that are not directories")
+                .takes_value(false)
+                .conflicts_with("tree"),
+        )
+        .arg(
+            Arg::with_name("match")
+                .long("match")
+                .value_name("PATTERN")
+                .help("List only entries whose name matches the glob PATTERN")
+                .takes_value(true)
+                .conflicts_with("tree"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format of the long listing [plain|json|csv]")
+                .takes_value(true)
+                .default_value("plain"),
+        )
         .get_matches();
 
+    // -1, -C, -x は最後に指定したものを優先: 指定がない場合は端末への出力時のみ複数列
+    let layout = [
+        ("one", Layout::OneLine),
+        ("columns", Layout::Columns),
+        ("across", Layout::Across),
+    ]
+    .into_iter()
+    .filter_map(|(name, layout)| matches.indices_of(name).and_then(|i| i.max()).map(|i| (i, layout)))
+    .max_by_key(|(i, _)| *i)
+    .map(|(_, layout)| layout)
+    .unwrap_or(if atty::is(atty::Stream::Stdout) {
+        Layout::Columns
+    } else {
+        Layout::OneLine
+    });
+
+    let full_time = matches.is_present("full_time");
+    let time_style = TimeStyle {
+        field: matches.value_of("time").unwrap().parse()?, // デフォルト値があるのでunwrap可能
+        full: full_time,
+    };
+
+    let depth = matches.value_of("depth")
+        .map(|val| val.parse().map_err(|_| format!("Invalid --depth \"{}\"", val)))
+        .transpose()?;
+
+    let kind = if matches.is_present("only_dirs") {
+        Some(EntryKind::Dirs)
+    } else if matches.is_present("only_files") {
+        Some(EntryKind::Files)
+    } else {
+        None
+    };
+    let filter = Filter::new(kind, matches.value_of("match"))?;
+
+    let long = matches.is_present("long") || full_time; // ls と同様に--full-timeは長い形式を含む
+    let format = matches.value_of("format").unwrap().parse()?; // デフォルト値があるのでunwrap可能
+    if format != Format::Plain {
+        // 機械可読な形式は長い形式の項目を出力するため、表示方法の指定とは併用できない
+        if !long {
+            return Err("--format requires --long".into());
+        }
+        if matches.is_present("tree") {
+            return Err("--format cannot be used with --tree".into());
+        }
+    }
+
     Ok(
         Config {
             paths: matches.values_of_lossy("paths").unwrap(),
-            long: matches.is_present("long"),
+            long,
             show_hidden: matches.is_present("all"),
+            color: matches.value_of("color").unwrap().parse()?, // デフォルト値があるのでunwrap可能
+            list_dirs: matches.is_present("directory"),
+            show_inode: matches.is_present("inode"),
+            show_size: matches.is_present("size"),
+            layout,
+            time_style,
+            sort: matches.value_of("sort").unwrap().parse()?, // デフォルト値があるのでunwrap可能
+            dirs_first: matches.is_present("group_directories_first"),
+            tree: matches.is_present("tree"),
+            depth,
+            filter,
+            format,
         }
     )
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let paths = find_files(&config.paths, config.show_hidden)?;
-
-    if config.long {
-        println!("{}", format_output(&paths)?);
+    // 色付けが有効な場合のみLS_COLORSを読み込む
+    let colors = if config.color.enabled() {
+        Some(LsColors::from_env())
     } else {
-        for path in paths {
-            println!("{}", path.display()) // displayにより(非unicodeデータがパス名に含まれていても)安全にパスを出力できる
+        None
+    };
+    if config.tree {
+        return print_trees(&config, colors.as_ref());
+    }
+
+    let groups = find_files(&config.paths, config.show_hidden, config.list_dirs, &config.filter)?;
+
+    if config.format != Format::Plain {
+        // 全てのグループのエントリをまとめて1つの文書として出力する: 合計ブロック数は含めない
+        let mut entries = vec![];
+        for mut group in groups {
+            sort::sort_paths(&mut group.paths, config.sort, config.dirs_first);
+            entries.extend(collect_entries(&group.paths, &config.time_style)?);
+        }
+        let mut out = io::stdout().lock();
+        return match config.format {
+            Format::Json => output::write_json(&mut out, &entries),
+            _ => output::write_csv(&mut out, &entries),
+        };
+    }
+
+    for mut group in groups {
+        sort::sort_paths(&mut group.paths, config.sort, config.dirs_first); // 長い形式と複数列の両方に適用する
+        // ディレクトリの中身の合計ブロック数を先頭に出力
+        if group.dir.is_some() && (config.long || config.show_size) {
+            println!("total {}", total_blocks(&group.paths)?);
+        }
+        if group.paths.is_empty() {
+            continue;
+        }
+        if config.long {
+            println!(
+                "{}",
+                format_output(
+                    &group.paths,
+                    colors.as_ref(),
+                    config.show_inode,
+                    config.show_size,
+                    &config.time_style,
+                )?
+            );
+        } else {
+            let mut cells = vec![];
+            for path in &group.paths {
+                let mut fields = vec![];
+                if config.show_inode {
+                    fields.push(path.metadata()?.ino().to_string()); // inode番号を先頭に付ける
+                }
+                if config.show_size {
+                    fields.push(alloc_size(&path.metadata()?).to_string());
+                }
+                let prefix: String = fields.iter().map(|field| format!("{} ", field)).collect();
+                cells.push(Cell::new(
+                    format!("{}{}", prefix, format_name(path, colors.as_ref())), // displayにより(非unicodeデータがパス名に含まれていても)安全にパスを出力できる
+                    &format!("{}{}", prefix, path.display()), // 表示幅は色付けのエスケープシーケンスを除いて数える
+                ));
+            }
+            println!("{}", grid::format_grid(&cells, term_width(), config.layout));
         }
     }
 
     Ok(())
 }
 
+// 引数ごとにツリーを出力し、ツリーの間は空行で区切る: 長い形式では各行の名前の前に罫線を付ける
+fn print_trees(config: &Config, colors: Option<&LsColors>) -> MyResult<()> {
+    let options = TreeOptions {
+        show_hidden: config.show_hidden,
+        depth: config.depth,
+        sort: config.sort,
+        dirs_first: config.dirs_first,
+    };
+    let mut printed = false;
+    for root in &config.paths {
+        let nodes = tree::walk(root, &options);
+        if nodes.is_empty() {
+            continue; // 起点が存在しない場合はエラーを出力済み
+        }
+        if printed {
+            println!();
+        }
+        printed = true;
+
+        let names: Vec<_> = nodes
+            .iter()
+            .map(|node| {
+                let name = match colors {
+                    Some(colors) => colors.paint(&node.path, &node.name),
+                    None => node.name.clone(),
+                };
+                format!("{}{}", node.prefix, name)
+            })
+            .collect();
+        if config.long {
+            let paths: Vec<_> = nodes.into_iter().map(|node| node.path).collect();
+            let entries = collect_entries(&paths, &config.time_style)?;
+            print!("{}", format_table(&entries, &names, config.show_inode, config.show_size));
+            continue;
+        }
+        for (node, name) in nodes.iter().zip(names) {
+            let mut fields = vec![];
+            if config.show_inode {
+                fields.push(node.path.metadata()?.ino().to_string());
+            }
+            if config.show_size {
+                fields.push(alloc_size(&node.path.metadata()?).to_string());
+            }
+            let prefix: String = fields.iter().map(|field| format!("{} ", field)).collect();
+            println!("{}{}", prefix, name);
+        }
+    }
+    Ok(())
+}
+
+// 出力先の端末の幅: 環境変数COLUMNSを優先し、端末でない場合は80桁
+fn term_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|val| val.parse().ok())
+        .filter(|&width| width > 0)
+        .or_else(|| terminal_size::terminal_size().map(|(width, _)| width.0 as usize))
+        .unwrap_or(80)
+}
+
 // ディレクトリまたはファイルパスを探索: 引数がディレクトリの場合は子ファイルまたは子ディレクトリを羅列(ただし孫以上の再帰処理はしない!)
+// list_dirsがtrueの場合はディレクトリを展開せず、ディレクトリ自体を返す
+// 絞り込みはファイル引数と展開したディレクトリの中身に適用する: 展開するディレクトリ引数自体は対象外
 fn find_files(
     paths: &[String],
     show_hidden: bool,
-) -> MyResult<Vec<PathBuf>> {
-    let mut results = vec![];
+    list_dirs: bool,
+    filter: &Filter,
+) -> MyResult<Vec<FileGroup>> {
+    let mut files = FileGroup::default();
+    let mut dirs = vec![];
     for name in paths {
         match metadata(name) {
             Err(e) => eprintln!("{}: {}", name, e),
             Ok(meta) => {
-                if meta.is_dir() {
+                if meta.is_dir() && !list_dirs {
                     // ディレクトリ内を展開
+                    let mut group = FileGroup {
+                        dir: Some(PathBuf::from(name)),
+                        paths: vec![],
+                    };
                     for entry in read_dir(name)? {
                         let entry = entry?;
                         let path = entry.path();
                         // '.'ドットで始まる隠しファイルか否かを判定
-                        let is_hidden = path.file_name().map_or(false, |file_name| {
+                        let is_hidden = path.file_name().is_some_and(|file_name| {
                             file_name.to_string_lossy().starts_with('.')
                         });
-                        if !is_hidden || show_hidden {
-                            results.push(entry.path());
+                        if (!is_hidden || show_hidden) && filter.matches(&path) {
+                            group.paths.push(path);
                         }
                     }
-                } else {
-                    results.push(PathBuf::from(name));
+                    dirs.push(group);
+                } else if filter.matches(Path::new(name)) {
+                    files.paths.push(PathBuf::from(name));
                 }
             }
         }
     }
+    // ls と同様にファイル引数を先に、ディレクトリ引数を後にまとめて出力する
+    let mut results = vec![];
+    if !files.paths.is_empty() {
+        results.push(files);
+    }
+    results.extend(dirs);
     Ok(results)
 }
 
-fn format_output(paths: &[PathBuf]) -> MyResult<String> {
-    // ls -l のフォーマットを作成
-    let fmt = "{:<}{:<}  {:>}  {:<}  {:<}  {:>}  {:<}  {:<}";
+// 割り当て済みのサイズを1024バイトのブロック数で返す: blocks()は512バイト単位
+fn alloc_size(metadata: &Metadata) -> u64 {
+    metadata.blocks().div_ceil(2)
+}
 
-    // フォーマットに値を当てはめるためのテーブルを生成
-    let mut table = Table::new(fmt);
+fn total_blocks(paths: &[PathBuf]) -> MyResult<u64> {
+    let mut total = 0;
+    for path in paths {
+        total += alloc_size(&path.metadata()?);
+    }
+    Ok(total)
+}
+
+// パス名を出力用の文字列に変換: 色付けが有効な場合はエスケープシーケンスで囲む
+fn format_name(path: &Path, colors: Option<&LsColors>) -> String {
+    let name = path.display().to_string();
+    match colors {
+        Some(colors) => colors.paint(path, &name),
+        None => name,
+    }
+}
+
+fn format_output(
+    paths: &[PathBuf],
+    colors: Option<&LsColors>,
+    show_inode: bool,
+    show_size: bool,
+    time_style: &TimeStyle,
+) -> MyResult<String> {
+    let entries = collect_entries(paths, time_style)?;
+    let names: Vec<_> = paths.iter().map(|path| format_name(path, colors)).collect();
+    Ok(format_table(&entries, &names, show_inode, show_size))
+}
 
+// 長い形式で出力する各パスのメタ情報を集める
+fn collect_entries(paths: &[PathBuf], time_style: &TimeStyle) -> MyResult<Vec<Entry>> {
+    let mut entries = vec![];
     for path in paths {
         // ファイルまたはディレクトリのメタ情報を取得
         let metadata = path.metadata()?;
@@ -123,34 +508,77 @@ fn format_output(paths: &[PathBuf]) -> MyResult<String> {
             .map(|g| g.name().to_string_lossy().into_owned())
             .unwrap_or_else(|| gid.to_string()); // グループ名またはgidを返す
 
-        let file_type = if path.is_dir() {
+        entries.push(Entry {
+            path: path.display().to_string(),
+            kind: if metadata.is_dir() { "dir" } else { "file" },
+            perms: format_mode(metadata.mode()), // ユーザ/グループ/その他のパーミッション文字列
+            nlink: metadata.nlink(),
+            user,
+            group,
+            size: metadata.len(),
+            mtime: TimeField::Modified.local_time(&metadata).to_rfc3339(),
+            inode: metadata.ino(),
+            blocks: alloc_size(&metadata),
+            indicator: attrs::indicator(path), // 拡張属性やACLがあれば印を付ける
+            timestamp: time_style.format(&metadata), // 指定された種類のタイムスタンプ
+        });
+    }
+    Ok(entries)
+}
+
+// 長い形式の表: 名前の列にはnamesの同じ位置の文字列を出力する
+fn format_table(entries: &[Entry], names: &[String], show_inode: bool, show_size: bool) -> String {
+    // ls -l のフォーマットを作成
+    let fmt = "{:<}{:<}  {:>}  {:<}  {:<}  {:>}  {:<}  {:<}";
+    // ブロック数を表示する場合は右寄せの列を先頭に追加
+    let fmt = if show_size {
+        format!("{{:>}}  {}", fmt)
+    } else {
+        fmt.to_string()
+    };
+    // inode番号を表示する場合は右寄せの列を先頭に追加
+    let fmt = if show_inode {
+        format!("{{:>}}  {}", fmt)
+    } else {
+        fmt
+    };
+
+    // フォーマットに値を当てはめるためのテーブルを生成
+    let mut table = Table::new(&fmt);
+
+    for (entry, name) in entries.iter().zip(names) {
+        let file_type = if entry.kind == "dir" {
             "d"
         } else {
             "-"
         };
 
-        // ユーザ/グループ/その他のパーミッション文字列を取得
-        let perms = format_mode(metadata.mode());
+        let mut perms = entry.perms.clone();
+        perms.extend(entry.indicator);
 
-        // 更新日時を取得
-        let modified: DateTime<Local> = DateTime::from(metadata.modified()?);
+        let mut row = Row::new();
+        if show_inode {
+            row.add_cell(entry.inode); // inode number
+        }
+        if show_size {
+            row.add_cell(entry.blocks); // allocated blocks
+        }
 
         // レコード形式で(左端の列から)順に値を代入
         table.add_row(
-            Row::new()
-                // ファイルのメタデータから各値を取得
+            row
                 .with_cell(file_type) // file type: d or -
                 .with_cell(perms) // permission
-                .with_cell(metadata.nlink()) // number of links
-                .with_cell(user) // user name
-                .with_cell(group) // group name
-                .with_cell(metadata.len()) // size
-                .with_cell(modified.format("%b %d %y %H:%M")) // modification timestamp
-                .with_cell(path.display()) // path
+                .with_cell(entry.nlink) // number of links
+                .with_cell(&entry.user) // user name
+                .with_cell(&entry.group) // group name
+                .with_cell(entry.size) // size
+                .with_cell(&entry.timestamp) // modification (or --time) timestamp
+                .with_cell(name) // path
         );
     }
 
-    Ok(format!("{}", table))
+    format!("{}", table)
 }
 
 // 3本スラッシュでdocコメントを定義可能: "cargo doc --open --document-private-items" でドキュメントを生成してブラウザで開く
@@ -196,21 +624,26 @@ pub fn mk_triple(mode: u32, owner: Owner) -> String {
 // --------------------------------------------------
 #[cfg(test)]
 mod test {
+    use super::alloc_size;
+    use super::collect_entries;
     use super::find_files;
+    use super::{EntryKind, Filter};
     use super::format_mode;
     use super::format_output;
     use super::mk_triple;
     use super::Owner;
-    use std::path::PathBuf;
+    use super::TimeStyle;
+    use std::{os::unix::fs::MetadataExt, path::PathBuf};
 
     #[test]
     fn test_find_files() {
         // Find all non-hidden entries in a directory
-        let res = find_files(&["tests/inputs".to_string()], false);
+        let res = find_files(&["tests/inputs".to_string()], false, false, &Filter::default());
         assert!(res.is_ok());
         let mut filenames: Vec<_> = res
             .unwrap()
             .iter()
+            .flat_map(|group| &group.paths)
             .map(|entry| entry.display().to_string())
             .collect();
         filenames.sort();
@@ -225,11 +658,12 @@ mod test {
         );
 
         // Any existing file should be found even if hidden
-        let res = find_files(&["tests/inputs/.hidden".to_string()], false);
+        let res = find_files(&["tests/inputs/.hidden".to_string()], false, false, &Filter::default());
         assert!(res.is_ok());
         let filenames: Vec<_> = res
             .unwrap()
             .iter()
+            .flat_map(|group| &group.paths)
             .map(|entry| entry.display().to_string())
             .collect();
         assert_eq!(filenames, ["tests/inputs/.hidden"]);
@@ -241,11 +675,14 @@ mod test {
                 "tests/inputs/dir".to_string(),
             ],
             false,
+            false,
+            &Filter::default(),
         );
         assert!(res.is_ok());
         let mut filenames: Vec<_> = res
             .unwrap()
             .iter()
+            .flat_map(|group| &group.paths)
             .map(|entry| entry.display().to_string())
             .collect();
         filenames.sort();
@@ -255,14 +692,41 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_find_files_directory() {
+        // Directories are returned as-is instead of being expanded
+        let res = find_files(
+            &[
+                "tests/inputs".to_string(),
+                "tests/inputs/dir".to_string(),
+                "tests/inputs/fox.txt".to_string(),
+            ],
+            false,
+            true,
+            &Filter::default(),
+        );
+        assert!(res.is_ok());
+        let filenames: Vec<_> = res
+            .unwrap()
+            .iter()
+            .flat_map(|group| &group.paths)
+            .map(|entry| entry.display().to_string())
+            .collect();
+        assert_eq!(
+            filenames,
+            ["tests/inputs", "tests/inputs/dir", "tests/inputs/fox.txt"]
+        );
+    }
+
     #[test]
     fn test_find_files_hidden() {
         // Find all entries in a directory including hidden
-        let res = find_files(&["tests/inputs".to_string()], true);
+        let res = find_files(&["tests/inputs".to_string()], true, false, &Filter::default());
         assert!(res.is_ok());
         let mut filenames: Vec<_> = res
             .unwrap()
             .iter()
+            .flat_map(|group| &group.paths)
             .map(|entry| entry.display().to_string())
             .collect();
         filenames.sort();
@@ -278,6 +742,89 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_find_files_groups() {
+        // File arguments come first, then each directory as its own group
+        let res = find_files(
+            &[
+                "tests/inputs/dir".to_string(),
+                "tests/inputs/fox.txt".to_string(),
+                "tests/inputs/empty.txt".to_string(),
+            ],
+            false,
+            false,
+            &Filter::default(),
+        );
+        assert!(res.is_ok());
+        let groups = res.unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].dir, None);
+        assert_eq!(
+            groups[0].paths,
+            [
+                PathBuf::from("tests/inputs/fox.txt"),
+                PathBuf::from("tests/inputs/empty.txt"),
+            ]
+        );
+        assert_eq!(groups[1].dir, Some(PathBuf::from("tests/inputs/dir")));
+        assert_eq!(
+            groups[1].paths,
+            [PathBuf::from("tests/inputs/dir/spiders.txt")]
+        );
+    }
+
+    #[test]
+    fn test_find_files_filter() {
+        let names = |paths: &[&str], show_hidden, list_dirs, filter: &Filter| -> Vec<String> {
+            let paths: Vec<_> = paths.iter().map(|path| path.to_string()).collect();
+            let mut names: Vec<_> = find_files(&paths, show_hidden, list_dirs, filter)
+                .unwrap()
+                .iter()
+                .flat_map(|group| &group.paths)
+                .map(|entry| entry.display().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+
+        // Only directories, with or without hidden entries
+        let dirs = Filter::new(Some(EntryKind::Dirs), None).unwrap();
+        assert_eq!(names(&["tests/inputs"], false, false, &dirs), ["tests/inputs/dir"]);
+        assert_eq!(names(&["tests/inputs"], true, false, &dirs), ["tests/inputs/dir"]);
+
+        // Only files, where --all adds the hidden ones
+        let files = Filter::new(Some(EntryKind::Files), None).unwrap();
+        assert_eq!(
+            names(&["tests/inputs"], false, false, &files),
+            ["tests/inputs/bustle.txt", "tests/inputs/empty.txt", "tests/inputs/fox.txt"]
+        );
+        assert_eq!(names(&["tests/inputs"], true, false, &files).len(), 4);
+
+        // The glob is matched against the file name, and hidden entries still need --all
+        let hidden = Filter::new(None, Some(".*")).unwrap();
+        assert!(names(&["tests/inputs"], false, false, &hidden).is_empty());
+        assert_eq!(names(&["tests/inputs"], true, false, &hidden), ["tests/inputs/.hidden"]);
+
+        // Both conditions must hold
+        let filter = Filter::new(Some(EntryKind::Files), Some("[be]*")).unwrap();
+        assert_eq!(
+            names(&["tests/inputs"], true, false, &filter),
+            ["tests/inputs/bustle.txt", "tests/inputs/empty.txt"]
+        );
+
+        // File arguments are filtered too, while directory arguments are still expanded
+        let filter = Filter::new(None, Some("*.txt")).unwrap();
+        assert_eq!(
+            names(&["tests/inputs/fox.txt", "tests/inputs/dir", "tests/inputs/dir/.gitkeep"], true, false, &filter),
+            ["tests/inputs/dir/spiders.txt", "tests/inputs/fox.txt"]
+        );
+        // With -d the directory arguments themselves are filtered
+        assert_eq!(
+            names(&["tests/inputs/fox.txt", "tests/inputs/dir"], false, true, &dirs),
+            ["tests/inputs/dir"]
+        );
+    }
+
     fn long_match(
         line: &str,
         expected_name: &str,
@@ -285,9 +832,9 @@ mod test {
         expected_size: Option<&str>,
     ) {
         let parts: Vec<_> = line.split_whitespace().collect();
-        assert!(parts.len() > 0 && parts.len() <= 10);
+        assert!(!parts.is_empty() && parts.len() <= 10);
 
-        let perms = parts.get(0).unwrap();
+        let perms = parts.first().unwrap();
         assert_eq!(perms, &expected_perms);
 
         if let Some(size) = expected_size {
@@ -304,7 +851,7 @@ mod test {
         let bustle_path = "tests/inputs/bustle.txt";
         let bustle = PathBuf::from(bustle_path);
 
-        let res = format_output(&[bustle]);
+        let res = format_output(&[bustle], None, false, false, &TimeStyle::default());
         assert!(res.is_ok());
 
         let out = res.unwrap();
@@ -313,7 +860,7 @@ mod test {
         assert_eq!(lines.len(), 1);
 
         let line1 = lines.first().unwrap();
-        long_match(&line1, bustle_path, "-rw-r--r--", Some("193"));
+        long_match(line1, bustle_path, "-rw-r--r--", Some("193"));
     }
 
     #[test]
@@ -321,7 +868,7 @@ mod test {
         let res = format_output(&[
             PathBuf::from("tests/inputs/dir"),
             PathBuf::from("tests/inputs/empty.txt"),
-        ]);
+        ], None, false, false, &TimeStyle::default());
         assert!(res.is_ok());
 
         let out = res.unwrap();
@@ -332,14 +879,70 @@ mod test {
 
         let empty_line = lines.remove(0);
         long_match(
-            &empty_line,
+            empty_line,
             "tests/inputs/empty.txt",
             "-rw-r--r--",
             Some("0"),
         );
 
         let dir_line = lines.remove(0);
-        long_match(&dir_line, "tests/inputs/dir", "drwxr-xr-x", None);
+        long_match(dir_line, "tests/inputs/dir", "drwxr-xr-x", None);
+    }
+
+    #[test]
+    fn test_format_output_inode() {
+        let bustle_path = "tests/inputs/bustle.txt";
+        let bustle = PathBuf::from(bustle_path);
+        let inode = bustle.metadata().unwrap().ino().to_string();
+
+        let res = format_output(&[bustle], None, true, false, &TimeStyle::default());
+        assert!(res.is_ok());
+
+        let out = res.unwrap();
+        let parts: Vec<_> = out.split_whitespace().collect();
+        assert_eq!(parts.first().unwrap(), &inode);
+        assert_eq!(parts.get(1).unwrap(), &"-rw-r--r--");
+        assert_eq!(parts.last().unwrap(), &bustle_path);
+    }
+
+    #[test]
+    fn test_format_output_size() {
+        let bustle_path = "tests/inputs/bustle.txt";
+        let bustle = PathBuf::from(bustle_path);
+        let metadata = bustle.metadata().unwrap();
+        let inode = metadata.ino().to_string();
+        let size = alloc_size(&metadata).to_string();
+
+        let res = format_output(&[bustle], None, true, true, &TimeStyle::default());
+        assert!(res.is_ok());
+
+        let out = res.unwrap();
+        let parts: Vec<_> = out.split_whitespace().collect();
+        assert_eq!(parts.first().unwrap(), &inode);
+        assert_eq!(parts.get(1).unwrap(), &size);
+        assert_eq!(parts.get(2).unwrap(), &"-rw-r--r--");
+        assert_eq!(parts.last().unwrap(), &bustle_path);
+    }
+
+    #[test]
+    fn test_collect_entries() {
+        let paths = [PathBuf::from("tests/inputs/fox.txt"), PathBuf::from("tests/inputs/dir")];
+        let res = collect_entries(&paths, &TimeStyle::default());
+        assert!(res.is_ok());
+
+        // Entries keep the order of the paths
+        let entries = res.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "tests/inputs/fox.txt");
+        assert_eq!(entries[0].kind, "file");
+        assert_eq!(entries[0].perms, "rw-------");
+        assert_eq!(entries[0].size, 45);
+        assert_eq!(entries[1].path, "tests/inputs/dir");
+        assert_eq!(entries[1].kind, "dir");
+        assert_eq!(entries[1].perms, "rwxr-xr-x");
+
+        // A missing path is an error
+        assert!(collect_entries(&[PathBuf::from("tests/inputs/none")], &TimeStyle::default()).is_err());
     }
 
     #[test]
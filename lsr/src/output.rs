@@ -0,0 +1,134 @@
+use std::{io::Write, str::FromStr};
+
+use serde::Serialize;
+
+use crate::MyResult;
+
+// 長い形式の出力形式: plainは従来の桁を揃えた表
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Plain,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("Invalid --format \"{}\"", s)),
+        }
+    }
+}
+
+// 長い形式の1行分のメタ情報: 表の出力にのみ使う項目は機械可読な形式に含めない
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Entry {
+    pub path: String,
+    #[serde(rename = "type")]
+    pub kind: &'static str, // "dir"または"file"
+    pub perms: String,      // "rwxr-xr-x"の形式: 拡張属性やACLの印は含めない
+    pub nlink: u64,
+    pub user: String,
+    pub group: String,
+    pub size: u64,
+    pub mtime: String, // RFC 3339形式の更新日時: --timeの指定に関わらない
+    #[serde(skip)]
+    pub inode: u64,
+    #[serde(skip)]
+    pub blocks: u64, // 1024バイト単位の割り当て済みサイズ
+    #[serde(skip)]
+    pub indicator: Option<char>, // パーミッションの後に付ける印
+    #[serde(skip)]
+    pub timestamp: String, // --timeと--full-timeに従って整形した日時
+}
+
+// 全てのエントリを1つのJSON配列として出力する
+pub fn write_json(out: &mut impl Write, entries: &[Entry]) -> MyResult<()> {
+    serde_json::to_writer(&mut *out, entries)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+// 見出し行に続けて1エントリを1行として出力する: エントリがない場合も見出し行は出力する
+pub fn write_csv(out: &mut impl Write, entries: &[Entry]) -> MyResult<()> {
+    let mut wtr = csv::Writer::from_writer(out);
+    if entries.is_empty() {
+        wtr.write_record(["path", "type", "perms", "nlink", "user", "group", "size", "mtime"])?;
+    }
+    for entry in entries {
+        wtr.serialize(entry)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod test {
+    use super::{write_csv, write_json, Entry, Format};
+
+    fn entry(path: &str, kind: &'static str, size: u64) -> Entry {
+        Entry {
+            path: path.to_string(),
+            kind,
+            perms: "rw-r--r--".to_string(),
+            nlink: 1,
+            user: "user".to_string(),
+            group: "staff".to_string(),
+            size,
+            mtime: "2010-01-02T03:04:05+00:00".to_string(),
+            inode: 42,
+            blocks: 4,
+            indicator: Some('@'),
+            timestamp: "Jan 02 10 03:04".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("plain".parse::<Format>(), Ok(Format::Plain));
+        assert_eq!("json".parse::<Format>(), Ok(Format::Json));
+        assert_eq!("csv".parse::<Format>(), Ok(Format::Csv));
+        assert_eq!("tsv".parse::<Format>(), Err("Invalid --format \"tsv\"".to_string()));
+    }
+
+    #[test]
+    fn test_write_json() {
+        let mut out = vec![];
+        write_json(&mut out, &[entry("a.txt", "file", 193), entry("dir", "dir", 4096)]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[{\"path\":\"a.txt\",\"type\":\"file\",\"perms\":\"rw-r--r--\",\"nlink\":1,\"user\":\"user\",\
+             \"group\":\"staff\",\"size\":193,\"mtime\":\"2010-01-02T03:04:05+00:00\"},\
+             {\"path\":\"dir\",\"type\":\"dir\",\"perms\":\"rw-r--r--\",\"nlink\":1,\"user\":\"user\",\
+             \"group\":\"staff\",\"size\":4096,\"mtime\":\"2010-01-02T03:04:05+00:00\"}]\n"
+        );
+
+        let mut out = vec![];
+        write_json(&mut out, &[]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_write_csv() {
+        // Fields containing commas are quoted
+        let mut out = vec![];
+        write_csv(&mut out, &[entry("a.txt", "file", 193), entry("b, c.txt", "file", 0)]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "path,type,perms,nlink,user,group,size,mtime\n\
+             a.txt,file,rw-r--r--,1,user,staff,193,2010-01-02T03:04:05+00:00\n\
+             \"b, c.txt\",file,rw-r--r--,1,user,staff,0,2010-01-02T03:04:05+00:00\n"
+        );
+
+        // The header is written even without entries
+        let mut out = vec![];
+        write_csv(&mut out, &[]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "path,type,perms,nlink,user,group,size,mtime\n");
+    }
+}
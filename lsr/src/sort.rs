@@ -0,0 +1,135 @@
+use std::{cmp::Ordering, path::PathBuf, str::FromStr};
+
+// 名前の並べ替え方
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortOrder {
+    None,    // 並べ替えない: 引数は指定順、ディレクトリの中身は読み込み順
+    Name,    // 大文字と小文字を区別しない名前順
+    Natural, // 名前の中の数字を数値として比べる: "file2"は"file10"より前
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(SortOrder::None),
+            "name" => Ok(SortOrder::Name),
+            "natural" => Ok(SortOrder::Natural),
+            _ => Err(format!("Invalid --sort \"{}\"", s)),
+        }
+    }
+}
+
+// 出力前にパスを並べ替える: ディレクトリを先にする場合も、同じ種類の中では指定した順序に従う
+pub fn sort_paths(paths: &mut [PathBuf], order: SortOrder, dirs_first: bool) {
+    if order != SortOrder::None {
+        paths.sort_by(|a, b| compare_names(&a.to_string_lossy(), &b.to_string_lossy(), order));
+    }
+    if dirs_first {
+        paths.sort_by_cached_key(|path| !path.is_dir()); // 安定ソートのため名前の順序は保たれる
+    }
+}
+
+// 大文字と小文字だけが異なる名前は、並びが実行ごとに変わらないようにバイト順で決める
+pub fn compare_names(a: &str, b: &str, order: SortOrder) -> Ordering {
+    let ordering = match order {
+        SortOrder::None => return Ordering::Equal,
+        SortOrder::Name => a.to_lowercase().cmp(&b.to_lowercase()),
+        SortOrder::Natural => {
+            let (mut a_chunks, mut b_chunks) = (chunks(a), chunks(b));
+            loop {
+                match (a_chunks.next(), b_chunks.next()) {
+                    (Some(a_chunk), Some(b_chunk)) => match compare_chunks(a_chunk, b_chunk) {
+                        Ordering::Equal => continue,
+                        ordering => break ordering,
+                    },
+                    (a_chunk, b_chunk) => break a_chunk.is_some().cmp(&b_chunk.is_some()), // 短い方が前
+                }
+            }
+        }
+    };
+    ordering.then_with(|| a.cmp(b))
+}
+
+// 名前を数字の並びとそれ以外の並びに区切る: "file10.txt"は"file", "10", ".txt"
+fn chunks(name: &str) -> impl Iterator<Item = &str> {
+    let mut rest = name;
+    std::iter::from_fn(move || {
+        let digit = rest.chars().next()?.is_ascii_digit();
+        let end = rest.find(|c: char| c.is_ascii_digit() != digit).unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+// 数字同士は先頭の0を除いた桁数と値で比べ、それ以外は大文字と小文字を区別せずに比べる
+fn compare_chunks(a: &str, b: &str) -> Ordering {
+    let is_number = |chunk: &str| chunk.starts_with(|c: char| c.is_ascii_digit());
+    if is_number(a) && is_number(b) {
+        let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+        a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+    } else {
+        a.to_lowercase().cmp(&b.to_lowercase())
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod test {
+    use super::{chunks, compare_names, sort_paths, SortOrder};
+    use std::{cmp::Ordering, path::PathBuf};
+
+    #[test]
+    fn test_parse_sort_order() {
+        assert_eq!("none".parse::<SortOrder>(), Ok(SortOrder::None));
+        assert_eq!("name".parse::<SortOrder>(), Ok(SortOrder::Name));
+        assert_eq!("natural".parse::<SortOrder>(), Ok(SortOrder::Natural));
+        assert_eq!("size".parse::<SortOrder>(), Err("Invalid --sort \"size\"".to_string()));
+    }
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(chunks("file10.txt").collect::<Vec<_>>(), ["file", "10", ".txt"]);
+        assert_eq!(chunks("2024-01").collect::<Vec<_>>(), ["2024", "-", "01"]);
+        assert_eq!(chunks("").count(), 0);
+    }
+
+    #[test]
+    fn test_compare_names() {
+        use SortOrder::*;
+        assert_eq!(compare_names("file10", "file2", Name), Ordering::Less);
+        assert_eq!(compare_names("file10", "file2", Natural), Ordering::Greater);
+        assert_eq!(compare_names("File3", "file20", Natural), Ordering::Less);
+        assert_eq!(compare_names("b", "A", Name), Ordering::Greater);
+        assert_eq!(compare_names("file", "file1", Natural), Ordering::Less);
+        // 大文字と小文字や先頭の0のみが異なる場合もEqualにはしない
+        assert_eq!(compare_names("A", "a", Name), Ordering::Less);
+        assert_eq!(compare_names("file02", "file2", Natural), Ordering::Less);
+        assert_eq!(compare_names("b", "a", None), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_sort_paths() {
+        let sorted = |names: &[&str], order, dirs_first| {
+            let mut paths: Vec<PathBuf> = names.iter().map(PathBuf::from).collect();
+            sort_paths(&mut paths, order, dirs_first);
+            paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>()
+        };
+        let names = ["tests/inputs/fox.txt", "tests/inputs/dir", "tests/inputs/Bustle.txt"];
+        assert_eq!(sorted(&names, SortOrder::None, false), names);
+        assert_eq!(
+            sorted(&names, SortOrder::Name, false),
+            ["tests/inputs/Bustle.txt", "tests/inputs/dir", "tests/inputs/fox.txt"]
+        );
+        assert_eq!(
+            sorted(&names, SortOrder::Name, true),
+            ["tests/inputs/dir", "tests/inputs/Bustle.txt", "tests/inputs/fox.txt"]
+        );
+        assert_eq!(
+            sorted(&names, SortOrder::None, true),
+            ["tests/inputs/dir", "tests/inputs/fox.txt", "tests/inputs/Bustle.txt"]
+        );
+    }
+}
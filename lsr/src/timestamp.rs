@@ -0,0 +1,106 @@
+use std::{fs::Metadata, os::unix::fs::MetadataExt, str::FromStr};
+
+use chrono::{DateTime, Local, TimeZone};
+
+// 表示するタイムスタンプの種類
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TimeField {
+    Modified, // 内容の更新日時(mtime)
+    Accessed, // 最終アクセス日時(atime)
+    Changed,  // ステータスの変更日時(ctime)
+}
+
+impl FromStr for TimeField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mtime" => Ok(TimeField::Modified),
+            "atime" => Ok(TimeField::Accessed),
+            "ctime" => Ok(TimeField::Changed),
+            _ => Err(format!("Invalid --time \"{}\"", s)),
+        }
+    }
+}
+
+// 長い形式でのタイムスタンプの表示方法
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TimeStyle {
+    pub field: TimeField,
+    pub full: bool, // --full-time: ナノ秒とタイムゾーンまで含めたISO 8601形式
+}
+
+impl Default for TimeStyle {
+    fn default() -> Self {
+        TimeStyle {
+            field: TimeField::Modified,
+            full: false,
+        }
+    }
+}
+
+impl TimeField {
+    // 秒とナノ秒の組からローカル時刻に変換: 範囲外の値はエポックとして扱う
+    pub fn local_time(&self, metadata: &Metadata) -> DateTime<Local> {
+        let (secs, nsecs) = match self {
+            TimeField::Modified => (metadata.mtime(), metadata.mtime_nsec()),
+            TimeField::Accessed => (metadata.atime(), metadata.atime_nsec()),
+            TimeField::Changed => (metadata.ctime(), metadata.ctime_nsec()),
+        };
+        Local
+            .timestamp_opt(secs, nsecs as u32)
+            .single()
+            .unwrap_or_else(|| DateTime::from(DateTime::UNIX_EPOCH))
+    }
+}
+
+impl TimeStyle {
+    pub fn format(&self, metadata: &Metadata) -> String {
+        self.format_time(&self.field.local_time(metadata))
+    }
+
+    fn format_time<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        if self.full {
+            time.format("%Y-%m-%d %H:%M:%S%.9f %z").to_string()
+        } else {
+            time.format("%b %d %y %H:%M").to_string()
+        }
+    }
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod test {
+    use super::{TimeField, TimeStyle};
+    use chrono::{FixedOffset, TimeZone};
+
+    #[test]
+    fn test_parse_time_field() {
+        assert_eq!("mtime".parse::<TimeField>(), Ok(TimeField::Modified));
+        assert_eq!("atime".parse::<TimeField>(), Ok(TimeField::Accessed));
+        assert_eq!("ctime".parse::<TimeField>(), Ok(TimeField::Changed));
+        assert_eq!(
+            "birth".parse::<TimeField>(),
+            Err("Invalid --time \"birth\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_time() {
+        let time = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2001, 2, 3, 4, 5, 6)
+            .unwrap();
+        let style = TimeStyle::default();
+        assert_eq!(style.format_time(&time), "Feb 03 01 04:05");
+
+        let style = TimeStyle {
+            full: true,
+            ..Default::default()
+        };
+        assert_eq!(style.format_time(&time), "2001-02-03 04:05:06.000000000 +0900");
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use walkdir::{DirEntry, WalkDir};
+
+use crate::sort::{self, SortOrder};
+
+// ツリー表示の1行
+#[derive(Debug, PartialEq)]
+pub struct Node {
+    pub prefix: String, // 罫線による字下げ: 起点は空
+    pub name: String,   // 起点は指定されたパス、それ以外はファイル名
+    pub path: PathBuf,
+}
+
+// ツリーの探索方法
+#[derive(Debug, Clone, Copy)]
+pub struct TreeOptions {
+    pub show_hidden: bool,
+    pub depth: Option<usize>, // 起点からの深さの上限: Noneは無制限
+    pub sort: SortOrder,
+    pub dirs_first: bool,
+}
+
+// 起点から再帰的に探索し、表示する順に返す: 読み込めなかったディレクトリはエラーを出力して飛ばす
+pub fn walk(root: &str, options: &TreeOptions) -> Vec<Node> {
+    let mut walker = WalkDir::new(root);
+    if let Some(depth) = options.depth {
+        walker = walker.max_depth(depth);
+    }
+    // 同じディレクトリの中身は一覧の表示と同じ順序に並べる
+    let (order, dirs_first) = (options.sort, options.dirs_first);
+    if order != SortOrder::None || dirs_first {
+        walker = walker.sort_by(move |a, b| {
+            let kind = |entry: &DirEntry| !entry.file_type().is_dir();
+            let by_kind = if dirs_first {
+                kind(a).cmp(&kind(b))
+            } else {
+                std::cmp::Ordering::Equal
+            };
+            by_kind.then_with(|| {
+                sort::compare_names(&a.file_name().to_string_lossy(), &b.file_name().to_string_lossy(), order)
+            })
+        });
+    }
+    let entries: Vec<_> = walker
+        .into_iter()
+        // 隠しファイルは中身ごと除外する: 起点は隠しファイルでも表示する
+        .filter_entry(|entry| entry.depth() == 0 || options.show_hidden || !is_hidden(entry))
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                match (e.path(), e.io_error()) {
+                    (Some(path), Some(err)) => eprintln!("{}: {}", path.display(), err),
+                    _ => eprintln!("{}", e),
+                }
+                None
+            }
+        })
+        .collect();
+
+    let depths: Vec<_> = entries.iter().map(DirEntry::depth).collect();
+    let lasts = last_siblings(&depths);
+
+    // 祖先が最後の子かどうかで、縦線を続けるかを決める
+    let mut ancestors: Vec<bool> = vec![];
+    let mut nodes = vec![];
+    for ((entry, depth), last) in entries.into_iter().zip(depths).zip(lasts) {
+        let (prefix, name) = if depth == 0 {
+            (String::new(), root.to_string())
+        } else {
+            let mut prefix: String = ancestors[1..depth]
+                .iter()
+                .map(|&last| if last { "    " } else { "│   " })
+                .collect();
+            prefix.push_str(if last { "└── " } else { "├── " });
+            (prefix, entry.file_name().to_string_lossy().into_owned())
+        };
+        ancestors.truncate(depth);
+        ancestors.push(last);
+        nodes.push(Node { prefix, name, path: entry.into_path() });
+    }
+    nodes
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry.file_name().to_string_lossy().starts_with('.')
+}
+
+// 深さ優先の順に並んだ各項目が、同じ親の最後の子かどうか
+// 後ろから辿り、同じ深さの弟がすでに現れたかを深さごとに記録する
+fn last_siblings(depths: &[usize]) -> Vec<bool> {
+    let mut has_next: Vec<bool> = vec![];
+    let mut lasts = vec![false; depths.len()];
+    for (i, &depth) in depths.iter().enumerate().rev() {
+        has_next.resize(depth + 1, false); // より深い記録は別の親の子のものなので捨てる
+        lasts[i] = !has_next[depth];
+        has_next[depth] = true;
+    }
+    lasts
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod test {
+    use super::{last_siblings, walk, TreeOptions};
+    use crate::sort::SortOrder;
+    use std::fs;
+
+    #[test]
+    fn test_last_siblings() {
+        // root, a, a/x, a/y, b, b/z
+        assert_eq!(
+            last_siblings(&[0, 1, 2, 2, 1, 2]),
+            [true, false, false, true, true, true]
+        );
+        assert!(last_siblings(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_walk() {
+        let root = std::env::temp_dir().join(format!("lsr-tree-{}", std::process::id()));
+        fs::create_dir_all(root.join("b/c")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("a.txt"), "").unwrap();
+        fs::write(root.join("b/c/d.txt"), "").unwrap();
+        fs::write(root.join("b/e.txt"), "").unwrap();
+        let name = root.display().to_string();
+
+        let options = TreeOptions {
+            show_hidden: false,
+            depth: None,
+            sort: SortOrder::Name,
+            dirs_first: false,
+        };
+        let lines: Vec<_> = walk(&name, &options)
+            .iter()
+            .map(|node| format!("{}{}", node.prefix, node.name))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                name.as_str(),
+                "├── a.txt",
+                "└── b",
+                "    ├── c",
+                "    │   └── d.txt",
+                "    └── e.txt",
+            ]
+        );
+
+        let options = TreeOptions { show_hidden: true, depth: Some(1), dirs_first: true, ..options };
+        let names: Vec<_> = walk(&name, &options).into_iter().map(|node| node.name).collect();
+        assert_eq!(names, [name.as_str(), ".git", "b", "a.txt"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,7 +1,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::{error::Error, fs};
+use std::{error::Error, fs, os::unix::fs::MetadataExt, time::Duration};
 
 type TestResult = Result<(), Box<dyn Error>>;
 
@@ -64,12 +64,12 @@ fn run_short(arg: &str) -> TestResult {
 // --------------------------------------------------
 fn run_long(filename: &str, permissions: &str, size: &str) -> TestResult {
     let cmd = Command::cargo_bin(PRG)?
-        .args(&["--long", filename])
+        .args(["--long", filename])
         .assert()
         .success();
     let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
     let parts: Vec<_> = stdout.split_whitespace().collect();
-    assert_eq!(parts.get(0).unwrap(), &permissions);
+    assert_eq!(parts.first().unwrap(), &permissions);
     assert_eq!(parts.get(4).unwrap(), &size);
     assert_eq!(parts.last().unwrap(), &filename);
     Ok(())
@@ -127,7 +127,7 @@ fn dir_short(args: &[&str], expected: &[&str]) -> TestResult {
         stdout.split("\n").filter(|s| !s.is_empty()).collect();
     assert_eq!(lines.len(), expected.len());
     for filename in expected {
-        assert!(lines.contains(&filename));
+        assert!(lines.contains(filename));
     }
     Ok(())
 }
@@ -176,18 +176,21 @@ fn dir2_all() -> TestResult {
 fn dir_long(args: &[&str], expected: &[(&str, &str, &str)]) -> TestResult {
     let cmd = Command::cargo_bin(PRG)?.args(args).assert().success();
     let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
-    let lines: Vec<&str> =
-        stdout.split("\n").filter(|s| !s.is_empty()).collect();
+    // ディレクトリの合計ブロック数の行は除いて比較する
+    let lines: Vec<&str> = stdout
+        .split("\n")
+        .filter(|s| !s.is_empty() && !s.starts_with("total "))
+        .collect();
     assert_eq!(lines.len(), expected.len());
 
     let mut check = vec![];
     for line in lines {
         let parts: Vec<_> = line.split_whitespace().collect();
-        let path = parts.last().unwrap().clone();
-        let permissions = parts.get(0).unwrap().clone();
+        let path = *parts.last().unwrap();
+        let permissions = *parts.first().unwrap();
         let size = match permissions.chars().next() {
             Some('d') => "",
-            _ => parts.get(4).unwrap().clone(),
+            _ => *parts.get(4).unwrap(),
         };
         check.push((path, permissions, size));
     }
@@ -245,3 +248,546 @@ fn dir2_long_all() -> TestResult {
         ],
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_color() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color", "foo", EMPTY])
+        .assert()
+        .failure()
+        .stderr("Invalid --color \"foo\"\n");
+    Ok(())
+}
+
+#[test]
+fn color_never() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--color=never", "tests/inputs/dir"])
+        .assert()
+        .success()
+        .stdout("tests/inputs/dir/spiders.txt\n");
+    Ok(())
+}
+
+#[test]
+fn color_always_ls_colors() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env("LS_COLORS", "*.txt=01;35")
+        .args(["--color=always", "tests/inputs/dir"])
+        .assert()
+        .success()
+        .stdout("\x1b[01;35mtests/inputs/dir/spiders.txt\x1b[0m\n");
+    Ok(())
+}
+
+#[test]
+fn color_always_dir_long() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env_remove("LS_COLORS")
+        .args(["--color=always", "--long", "tests/inputs"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[01;34mtests/inputs/dir\x1b[0m"));
+    Ok(())
+}
+
+#[test]
+fn color_auto_not_tty() -> TestResult {
+    // The captured stdout is not a terminal, so no colors are emitted
+    Command::cargo_bin(PRG)?
+        .args(["--color=auto", "tests/inputs/dir"])
+        .assert()
+        .success()
+        .stdout("tests/inputs/dir/spiders.txt\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn directory() -> TestResult {
+    dir_short(&["-d", "tests/inputs", FOX], &["tests/inputs", FOX])
+}
+
+#[test]
+fn directory_long() -> TestResult {
+    dir_long(
+        &["--directory", "-l", "tests/inputs/dir", EMPTY],
+        &[
+            ("tests/inputs/dir", "drwxr-xr-x", ""),
+            (EMPTY, "-rw-r--r--", "0"),
+        ],
+    )
+}
+
+#[test]
+fn inode() -> TestResult {
+    let inode = fs::metadata(FOX)?.ino();
+    Command::cargo_bin(PRG)?
+        .args(["-i", FOX])
+        .assert()
+        .success()
+        .stdout(format!("{} {}\n", inode, FOX));
+    Ok(())
+}
+
+#[test]
+fn inode_long() -> TestResult {
+    let inode = fs::metadata("tests/inputs/dir")?.ino();
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-ldi", "tests/inputs/dir"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let parts: Vec<_> = stdout.split_whitespace().collect();
+    assert_eq!(parts.first().unwrap(), &inode.to_string());
+    assert_eq!(parts.get(1).unwrap(), &"drwxr-xr-x");
+    assert_eq!(parts.last().unwrap(), &"tests/inputs/dir");
+    Ok(())
+}
+
+// --------------------------------------------------
+fn blocks(path: &str) -> Result<u64, Box<dyn Error>> {
+    Ok(fs::metadata(path)?.blocks().div_ceil(2))
+}
+
+#[test]
+fn size() -> TestResult {
+    let spiders = "tests/inputs/dir/spiders.txt";
+    let size = blocks(spiders)?;
+    Command::cargo_bin(PRG)?
+        .args(["-s", "tests/inputs/dir"])
+        .assert()
+        .success()
+        .stdout(format!("total {}\n{} {}\n", size, size, spiders));
+    Ok(())
+}
+
+#[test]
+fn size_file_no_total() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--size", FOX])
+        .assert()
+        .success()
+        .stdout(format!("{} {}\n", blocks(FOX)?, FOX));
+    Ok(())
+}
+
+#[test]
+fn long_total() -> TestResult {
+    let total: u64 = [BUSTLE, EMPTY, FOX, "tests/inputs/dir"]
+        .iter()
+        .map(|path| blocks(path))
+        .sum::<Result<_, _>>()?;
+    Command::cargo_bin(PRG)?
+        .args(["-l", "tests/inputs"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!("total {}\n", total)));
+    Ok(())
+}
+
+// --------------------------------------------------
+const GRID_FILES: [&str; 4] = [
+    "tests/inputs/empty.txt",
+    "tests/inputs/fox.txt",
+    "tests/inputs/bustle.txt",
+    "tests/inputs/dir/spiders.txt",
+];
+
+fn run_grid(args: &[&str], columns: &str, expected: &str) -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env("COLUMNS", columns)
+        .args(args)
+        .args(GRID_FILES)
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+    Ok(())
+}
+
+#[test]
+fn columns() -> TestResult {
+    run_grid(
+        &["-C"],
+        "60",
+        "tests/inputs/empty.txt  tests/inputs/bustle.txt\n\
+         tests/inputs/fox.txt    tests/inputs/dir/spiders.txt\n",
+    )?;
+    // 幅が足りない場合は1列
+    run_grid(&["-C"], "50", &format!("{}\n", GRID_FILES.join("\n")))
+}
+
+#[test]
+fn across() -> TestResult {
+    run_grid(
+        &["-x"],
+        "60",
+        "tests/inputs/empty.txt   tests/inputs/fox.txt\n\
+         tests/inputs/bustle.txt  tests/inputs/dir/spiders.txt\n",
+    )
+}
+
+#[test]
+fn one_per_line() -> TestResult {
+    // 最後に指定した形式が優先される
+    run_grid(&["-C", "-1"], "200", &format!("{}\n", GRID_FILES.join("\n")))?;
+    run_grid(
+        &["-1", "-x"],
+        "200",
+        "tests/inputs/empty.txt  tests/inputs/fox.txt  tests/inputs/bustle.txt  tests/inputs/dir/spiders.txt\n",
+    )
+}
+
+// --------------------------------------------------
+// 既知のアクセス/更新日時を設定したフィクスチャのコピーを作成する
+fn timed_copy(name: &str) -> Result<String, Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("lsr-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("fox.txt");
+    fs::copy(FOX, &path)?;
+    let epoch = std::time::UNIX_EPOCH;
+    let times = fs::FileTimes::new()
+        .set_accessed(epoch + Duration::new(981_173_106, 0)) // 2001-02-03 04:05:06 UTC
+        .set_modified(epoch + Duration::new(1_262_401_445, 123_456_789)); // 2010-01-02 03:04:05 UTC
+    fs::File::options().write(true).open(&path)?.set_times(times)?;
+    Ok(path.display().to_string())
+}
+
+fn run_time(name: &str, args: &[&str], expected: &str) -> TestResult {
+    let path = timed_copy(name)?;
+    Command::cargo_bin(PRG)?
+        .env("TZ", "UTC")
+        .args(args)
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!("  {}  {}\n", expected, path)));
+    fs::remove_dir_all(std::path::Path::new(&path).parent().unwrap())?;
+    Ok(())
+}
+
+#[test]
+fn time_mtime_default() -> TestResult {
+    run_time("mtime", &["-l"], "Jan 02 10 03:04")
+}
+
+#[test]
+fn time_atime() -> TestResult {
+    run_time("atime", &["-l", "--time=atime"], "Feb 03 01 04:05")
+}
+
+#[test]
+fn full_time() -> TestResult {
+    // --full-time は長い形式を含む
+    run_time("full", &["--full-time"], "2010-01-02 03:04:05.123456789 +0000")
+}
+
+#[test]
+fn full_time_atime() -> TestResult {
+    run_time("full-atime", &["--full-time", "--time", "atime"], "2001-02-03 04:05:06.000000000 +0000")
+}
+
+#[test]
+fn full_time_ctime() -> TestResult {
+    // ctimeは設定できないが、更新日時やアクセス日時とは異なる
+    let path = timed_copy("ctime")?;
+    let cmd = Command::cargo_bin(PRG)?
+        .env("TZ", "UTC")
+        .args(["--full-time", "--time=ctime", &path])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert!(!stdout.contains("2010-01-02") && !stdout.contains("2001-02-03"));
+    assert!(stdout.contains(" +0000 "));
+    fs::remove_dir_all(std::path::Path::new(&path).parent().unwrap())?;
+    Ok(())
+}
+
+#[test]
+fn dies_bad_time() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--time", "birth", EMPTY])
+        .assert()
+        .failure()
+        .stderr("Invalid --time \"birth\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+// 並べ替えを確かめるためのファイルとディレクトリを作成する
+fn sort_fixture(name: &str) -> Result<String, Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("lsr-{}-{}", name, std::process::id()));
+    fs::create_dir_all(dir.join("notes"))?;
+    fs::create_dir_all(dir.join("Archive"))?;
+    for file in ["file10.txt", "file2.txt", "File1.txt", "readme"] {
+        fs::write(dir.join(file), "")?;
+    }
+    Ok(dir.display().to_string())
+}
+
+fn run_sort(name: &str, args: &[&str], expected: &[&str]) -> TestResult {
+    let dir = sort_fixture(name)?;
+    let expected: String = expected.iter().map(|name| format!("{}/{}\n", dir, name)).collect();
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .arg(&dir)
+        .assert()
+        .success()
+        .stdout(expected);
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn sort_name() -> TestResult {
+    run_sort(
+        "sort-name",
+        &["-1", "--sort", "name"],
+        &["Archive", "File1.txt", "file10.txt", "file2.txt", "notes", "readme"],
+    )
+}
+
+#[test]
+fn sort_natural() -> TestResult {
+    run_sort(
+        "sort-natural",
+        &["-1", "--sort=natural"],
+        &["Archive", "File1.txt", "file2.txt", "file10.txt", "notes", "readme"],
+    )
+}
+
+#[test]
+fn sort_group_directories_first() -> TestResult {
+    run_sort(
+        "sort-dirs-first",
+        &["-1", "--sort=natural", "--group-directories-first"],
+        &["Archive", "notes", "File1.txt", "file2.txt", "file10.txt", "readme"],
+    )
+}
+
+#[test]
+fn sort_group_directories_first_long() -> TestResult {
+    let dir = sort_fixture("sort-dirs-first-long")?;
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-l", "--sort=name", "--group-directories-first", &dir])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let names: Vec<_> = stdout
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with("total "))
+        .map(|line| line.rsplit('/').next().unwrap())
+        .collect();
+    assert_eq!(names, ["Archive", "notes", "File1.txt", "file10.txt", "file2.txt", "readme"]);
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn only_dirs_match() -> TestResult {
+    run_sort("only-dirs", &["-1", "--sort=name", "--only-dirs"], &["Archive", "notes"])?;
+    run_sort("only-dirs-match", &["-1", "--only-dirs", "--match", "n*"], &["notes"])
+}
+
+#[test]
+fn only_files_match() -> TestResult {
+    run_sort(
+        "only-files",
+        &["-1", "--sort=natural", "--only-files"],
+        &["File1.txt", "file2.txt", "file10.txt", "readme"],
+    )?;
+    run_sort(
+        "only-files-match",
+        &["-1", "--sort=name", "--only-files", "--match", "file*.txt"],
+        &["file10.txt", "file2.txt"],
+    )
+}
+
+#[test]
+fn only_dirs_long_match() -> TestResult {
+    let dir = sort_fixture("only-dirs-long")?;
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-l", "--only-dirs", "--match", "[AN]*", &dir])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<_> = stdout.lines().filter(|line| !line.is_empty()).collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("total "));
+    assert!(lines[1].starts_with('d') && lines[1].ends_with("/Archive"));
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn dies_bad_match() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--match", "a[b", EMPTY])
+        .assert()
+        .failure()
+        .stderr("Invalid --match \"a[b\"\n");
+    Ok(())
+}
+
+#[test]
+fn dies_only_dirs_and_only_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--only-dirs", "--only-files", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_sort() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--sort", "size", EMPTY])
+        .assert()
+        .failure()
+        .stderr("Invalid --sort \"size\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn tree() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--tree", "--sort", "name", "tests/inputs", FOX])
+        .assert()
+        .success()
+        .stdout(concat!(
+            "tests/inputs\n",
+            "├── bustle.txt\n",
+            "├── dir\n",
+            "│   └── spiders.txt\n",
+            "├── empty.txt\n",
+            "└── fox.txt\n",
+            "\n",
+            "tests/inputs/fox.txt\n",
+        ));
+    Ok(())
+}
+
+#[test]
+fn tree_all_depth() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--tree", "-a", "--depth", "1", "--sort=name", "--group-directories-first", "tests/inputs"])
+        .assert()
+        .success()
+        .stdout(concat!(
+            "tests/inputs\n",
+            "├── dir\n",
+            "├── .hidden\n",
+            "├── bustle.txt\n",
+            "├── empty.txt\n",
+            "└── fox.txt\n",
+        ));
+    Ok(())
+}
+
+#[test]
+fn tree_long() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--tree", "-l", "--sort", "name", "tests/inputs/dir"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("drwxr-xr-x") && lines[0].ends_with("  tests/inputs/dir"));
+    assert!(lines[1].starts_with("-rw-r--r--") && lines[1].ends_with("  └── spiders.txt"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_depth() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--tree", "--depth", "x", EMPTY])
+        .assert()
+        .failure()
+        .stderr("Invalid --depth \"x\"\n");
+    Ok(())
+}
+
+#[test]
+fn dies_depth_without_tree() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--depth", "1", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--tree"));
+    Ok(())
+}
+
+// --------------------------------------------------
+// 機械可読な長い形式: ユーザ名とグループ名は環境によって異なるため、前後の項目で確かめる
+fn run_format(name: &str, format: &str) -> Result<String, Box<dyn Error>> {
+    let path = timed_copy(name)?;
+    let cmd = Command::cargo_bin(PRG)?
+        .env("TZ", "UTC")
+        .args(["-l", "--time=atime", "--format", format, &path])
+        .assert()
+        .success();
+    fs::remove_dir_all(std::path::Path::new(&path).parent().unwrap())?;
+    Ok(String::from_utf8(cmd.get_output().stdout.clone())?.replace(&path, "PATH"))
+}
+
+#[test]
+fn format_json() -> TestResult {
+    // --timeに関わらず更新日時を出力する
+    let stdout = run_format("json", "json")?;
+    assert!(stdout.starts_with("[{\"path\":\"PATH\",\"type\":\"file\",\"perms\":\"rw-------\",\"nlink\":1,\"user\":"));
+    assert!(stdout.ends_with(",\"size\":45,\"mtime\":\"2010-01-02T03:04:05.123456789+00:00\"}]\n"));
+    Ok(())
+}
+
+#[test]
+fn format_csv() -> TestResult {
+    let stdout = run_format("csv", "csv")?;
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], "path,type,perms,nlink,user,group,size,mtime");
+    assert!(lines[1].starts_with("PATH,file,rw-------,1,"));
+    assert!(lines[1].ends_with(",45,2010-01-02T03:04:05.123456789+00:00"));
+    Ok(())
+}
+
+#[test]
+fn format_json_dirs() -> TestResult {
+    // ディレクトリ引数の中身もファイル引数と同じ配列にまとめ、合計ブロック数は出力しない
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["-l", "--format", "json", "--sort", "name", FOX, "tests/inputs"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let paths: Vec<_> = stdout.match_indices("\"path\":").map(|(i, _)| stdout[i..].split('"').nth(3).unwrap()).collect();
+    assert_eq!(
+        paths,
+        [FOX, BUSTLE, "tests/inputs/dir", EMPTY, FOX]
+    );
+    assert!(stdout.contains("\"path\":\"tests/inputs/dir\",\"type\":\"dir\",\"perms\":\"rwxr-xr-x\","));
+    assert!(!stdout.contains("total"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_format() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--format", "xml", EMPTY])
+        .assert()
+        .failure()
+        .stderr("Invalid --format \"xml\"\n");
+    Command::cargo_bin(PRG)?
+        .args(["--format", "json", EMPTY])
+        .assert()
+        .failure()
+        .stderr("--format requires --long\n");
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--tree", "--format", "csv", EMPTY])
+        .assert()
+        .failure()
+        .stderr("--format cannot be used with --tree\n");
+    Ok(())
+}
@@ -0,0 +1,339 @@
+use std::{
+    fs::{self, File, Metadata},
+    io::{Read, Seek, SeekFrom, Write},
+    os::unix::fs::MetadataExt,
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use crate::MyResult;
+
+// 追跡方法: descriptorは開いたファイルを追い続け、nameはファイル名が指す実体を追い続ける
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FollowMode {
+    Descriptor,
+    Name,
+}
+
+impl FromStr for FollowMode {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val {
+            "descriptor" => Ok(FollowMode::Descriptor),
+            "name" => Ok(FollowMode::Name),
+            _ => Err(format!("illegal follow mode -- {}", val)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FollowConfig {
+    pub mode: FollowMode,
+    pub max_unchanged_stats: u64,
+    pub sleep_interval: Duration,
+    pub retry: bool, // -F: 開けないファイルや削除されたファイルを開き直し続ける
+}
+
+// ファイルの同一性: デバイス番号とinode番号の組が変われば別のファイル
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct FileId {
+    dev: u64,
+    ino: u64,
+}
+
+impl FileId {
+    fn of(metadata: &Metadata) -> Self {
+        FileId {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        }
+    }
+}
+
+// 開いているファイルとその同一性
+#[derive(Debug)]
+struct Opened {
+    file: File,
+    id: FileId,
+}
+
+impl Opened {
+    fn open(name: &str) -> MyResult<Self> {
+        let file = File::open(name)?;
+        let id = FileId::of(&file.metadata()?);
+        Ok(Opened { file, id })
+    }
+}
+
+// 追跡中のファイル: --retryで開けるようになるのを待っている間はopenedがNone
+#[derive(Debug)]
+pub struct Followed {
+    name: String,
+    opened: Option<Opened>,
+    pos: u64,       // 出力済みの位置
+    unchanged: u64, // サイズが変わらなかった連続回数
+}
+
+impl Followed {
+    // 末尾までは出力済みとして追跡を開始する
+    pub fn new(name: &str, mut file: File) -> MyResult<Self> {
+        let id = FileId::of(&file.metadata()?);
+        let pos = file.seek(SeekFrom::End(0))?;
+        Ok(Followed {
+            name: name.to_string(),
+            opened: Some(Opened { file, id }),
+            pos,
+            unchanged: 0,
+        })
+    }
+
+    // まだ開けないファイル: 現れた時点で先頭から出力する
+    pub fn missing(name: &str) -> Self {
+        Followed {
+            name: name.to_string(),
+            opened: None,
+            pos: 0,
+            unchanged: 0,
+        }
+    }
+
+    // 切り詰めとローテーションを検出し、必要であれば読み込み位置や対象ファイルを切り替える
+    fn check(&mut self, config: &FollowConfig) -> MyResult<()> {
+        let Some(opened) = &self.opened else {
+            // 開けるようになるまで毎回試す: 開けない理由は最初の1回だけ報告済み
+            if let Ok(opened) = Opened::open(&self.name) {
+                self.opened = Some(opened);
+                self.pos = 0;
+                self.unchanged = 0;
+                eprintln!("tailr: '{}' has appeared; following new file", self.name);
+            }
+            return Ok(());
+        };
+        let len = opened.file.metadata()?.len();
+        if len < self.pos {
+            eprintln!("tailr: {}: file truncated", self.name);
+            self.pos = 0;
+        }
+        if len != self.pos {
+            self.unchanged = 0;
+            return Ok(());
+        }
+        self.unchanged += 1;
+        // 変化のない状態が続いたらファイル名を確認し直す: 旧ファイルへの書き込みを読み切ってから切り替えるため
+        if config.mode == FollowMode::Name && self.unchanged >= config.max_unchanged_stats {
+            self.unchanged = 0;
+            match fs::metadata(&self.name) {
+                Ok(metadata) if FileId::of(&metadata) != opened.id => {
+                    self.opened = Some(Opened::open(&self.name)?);
+                    self.pos = 0;
+                    eprintln!("tailr: '{}' has been replaced; following new file", self.name);
+                },
+                Ok(_) => {},
+                // --retryの場合は閉じて再び現れるのを待つ
+                Err(err) if config.retry => {
+                    self.opened = None;
+                    self.pos = 0;
+                    eprintln!("tailr: '{}' has become inaccessible: {}", self.name, err);
+                },
+                Err(_) => {}, // 削除されただけの場合は元のファイルを追い続ける
+            }
+        }
+        Ok(())
+    }
+
+    // 前回以降に追記された内容を読み込む
+    fn read_new(&mut self, buf: &mut Vec<u8>) -> MyResult<()> {
+        if let Some(opened) = &mut self.opened {
+            opened.file.seek(SeekFrom::Start(self.pos))?;
+            self.pos += opened.file.read_to_end(buf)? as u64;
+        }
+        Ok(())
+    }
+}
+
+// 一定間隔でファイルを確認して追記された内容を出力し続ける: 複数ファイルの場合は出力元が変わるたびにヘッダーを付与
+pub fn follow(
+    mut files: Vec<Followed>,
+    config: &FollowConfig,
+    headers: bool,
+    mut last: Option<usize>,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let mut buf = vec![];
+    loop {
+        last = poll(&mut files, config, headers, last, &mut buf, out)?;
+        thread::sleep(config.sleep_interval);
+    }
+}
+
+// 全ファイルを1回ずつ確認し、最後に出力したファイルの番号を返す
+fn poll(
+    files: &mut [Followed],
+    config: &FollowConfig,
+    headers: bool,
+    mut last: Option<usize>,
+    buf: &mut Vec<u8>,
+    out: &mut impl Write,
+) -> MyResult<Option<usize>> {
+    for (i, followed) in files.iter_mut().enumerate() {
+        if let Err(err) = followed.check(config) {
+            eprintln!("{}: {}", followed.name, err);
+            continue;
+        }
+        buf.clear();
+        followed.read_new(buf)?;
+        if buf.is_empty() {
+            continue;
+        }
+        if headers && last != Some(i) {
+            writeln!(out, "\n==> {} <==", followed.name)?;
+        }
+        last = Some(i);
+        out.write_all(buf)?;
+        out.flush()?;
+    }
+    Ok(last)
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{poll, FollowConfig, FollowMode, Followed};
+    use std::{
+        fs::{self, File, OpenOptions},
+        io::Write,
+        path::PathBuf,
+        time::Duration,
+    };
+
+    fn config(mode: FollowMode) -> FollowConfig {
+        FollowConfig {
+            mode,
+            max_unchanged_stats: 2,
+            sleep_interval: Duration::from_millis(10),
+            retry: false,
+        }
+    }
+
+    fn tmp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("tailr-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn append(path: &PathBuf, contents: &str) {
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+    }
+
+    // 1回確認して出力された内容を返す
+    fn poll_once(followed: &mut Followed, config: &FollowConfig) -> String {
+        let mut out = vec![];
+        poll(std::slice::from_mut(followed), config, false, None, &mut vec![], &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_follow_append_and_truncate() {
+        let path = tmp_file("append", "old\n");
+        let config = config(FollowMode::Descriptor);
+        let mut followed = Followed::new("append", File::open(&path).unwrap()).unwrap();
+        assert_eq!(poll_once(&mut followed, &config), "");
+
+        append(&path, "new\n");
+        assert_eq!(poll_once(&mut followed, &config), "new\n");
+        assert_eq!(poll_once(&mut followed, &config), "");
+
+        // 切り詰められた場合は先頭から読み直す
+        fs::write(&path, "x\n").unwrap();
+        assert_eq!(poll_once(&mut followed, &config), "x\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_follow_rotation() {
+        for mode in [FollowMode::Descriptor, FollowMode::Name] {
+            let path = tmp_file(&format!("{:?}", mode), "a\n");
+            let rotated = path.with_extension("1");
+            let config = config(mode);
+            let mut followed =
+                Followed::new(path.to_str().unwrap(), File::open(&path).unwrap()).unwrap();
+
+            // ローテーション: 旧ファイルへの最後の書き込みは読み切る
+            fs::rename(&path, &rotated).unwrap();
+            append(&rotated, "b\n");
+            fs::write(&path, "c\n").unwrap();
+            assert_eq!(poll_once(&mut followed, &config), "b\n");
+
+            // max_unchanged_stats回変化がなければ、nameの場合のみ新しいファイルに切り替える
+            assert_eq!(poll_once(&mut followed, &config), "");
+            let expected = match mode {
+                FollowMode::Descriptor => "",
+                FollowMode::Name => "c\n",
+            };
+            assert_eq!(poll_once(&mut followed, &config), expected);
+
+            append(&rotated, "d\n");
+            let expected = match mode {
+                FollowMode::Descriptor => "d\n",
+                FollowMode::Name => "",
+            };
+            assert_eq!(poll_once(&mut followed, &config), expected);
+            fs::remove_file(&path).unwrap();
+            fs::remove_file(&rotated).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_follow_retry() {
+        let path = std::env::temp_dir().join(format!("tailr-{}-retry", std::process::id()));
+        let config = FollowConfig {
+            retry: true,
+            ..config(FollowMode::Name)
+        };
+        let mut followed = Followed::missing(path.to_str().unwrap());
+        assert_eq!(poll_once(&mut followed, &config), "");
+
+        // 現れたファイルは先頭から出力する
+        fs::write(&path, "a\n").unwrap();
+        assert_eq!(poll_once(&mut followed, &config), "a\n");
+        append(&path, "b\n");
+        assert_eq!(poll_once(&mut followed, &config), "b\n");
+
+        // 削除されたらmax_unchanged_stats回の確認の後に閉じ、再作成されたファイルを追う
+        fs::remove_file(&path).unwrap();
+        assert_eq!(poll_once(&mut followed, &config), "");
+        assert_eq!(poll_once(&mut followed, &config), "");
+        assert!(followed.opened.is_none());
+        fs::write(&path, "c\n").unwrap();
+        assert_eq!(poll_once(&mut followed, &config), "c\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_follow_headers() {
+        let path1 = tmp_file("headers1", "");
+        let path2 = tmp_file("headers2", "");
+        let config = config(FollowMode::Descriptor);
+        let mut files = vec![
+            Followed::new("one", File::open(&path1).unwrap()).unwrap(),
+            Followed::new("two", File::open(&path2).unwrap()).unwrap(),
+        ];
+        append(&path2, "2\n");
+        let mut out = vec![];
+        let last = poll(&mut files, &config, true, Some(1), &mut vec![], &mut out).unwrap();
+        assert_eq!(last, Some(1));
+        append(&path1, "1\n");
+        append(&path2, "2\n");
+        let last = poll(&mut files, &config, true, last, &mut vec![], &mut out).unwrap();
+        assert_eq!(last, Some(1));
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "2\n\n==> one <==\n1\n\n==> two <==\n2\n"
+        );
+        fs::remove_file(&path1).unwrap();
+        fs::remove_file(&path2).unwrap();
+    }
+}
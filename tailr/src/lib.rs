@@ -1,16 +1,25 @@
-use std::{error::Error, fs::File, io::{BufRead, Read, Seek, BufReader, SeekFrom}};
+use std::{collections::VecDeque, error::Error, fs::File, io::{self, BufRead, Read, Seek, BufReader, SeekFrom, Write}, time::Duration};
 
 use clap::{App, Arg};
 use once_cell::sync::OnceCell;
 use regex::Regex;
 
+mod follow;
+mod offset;
+mod section;
 use crate::TakeValue::*;
+use follow::{FollowConfig, FollowMode, Followed};
+use offset::{write_line, OffsetFormat};
+use section::Section;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 // 再利用可能な正規表現をstatic変数で定義: constはコンパイル時に値が決まる変数、staticはコンパイル時に(値の)格納先が決まる変数
 static NUM_RE: OnceCell<Regex> = OnceCell::new();
 
+// 末尾から逆方向に読み込む際のブロックサイズ
+const BLOCK_SIZE: usize = 64 * 1024;
+
 #[derive(Debug, PartialEq)]
 enum TakeValue {
     PlusZero,
@@ -21,7 +30,30 @@ pub struct Config {
     files: Vec<String>,
     lines: TakeValue,
     bytes: Option<TakeValue>,
-    quiet: bool,
+    headers: HeaderMode,
+    reverse: bool,
+    delimiter: u8, // 行の区切り文字: -zではNUL
+    follow: Option<FollowConfig>,
+    offsets: Option<OffsetFormat>, // --print-offsets: 各行の前に付けるバイト位置の表記
+    section: Option<Section>,      // --since, --until: 行数を数える対象範囲の区切り
+}
+
+// ファイル名のヘッダー(==> FILE <==)の出力方針
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum HeaderMode {
+    Auto,   // 複数ファイルの場合のみ出力
+    Always, // -v: 常に出力
+    Never,  // -q: 出力しない
+}
+
+impl HeaderMode {
+    fn show(&self, num_files: usize) -> bool {
+        match self {
+            HeaderMode::Auto => num_files > 1,
+            HeaderMode::Always => true,
+            HeaderMode::Never => false,
+        }
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -56,10 +88,93 @@ pub fn get_args() -> MyResult<Config> {
             Arg::with_name("quiet")
                 .short("q")
                 .long("quiet")
-                .help("Suppress headers"),
+                .visible_alias("silent")
+                .help("Suppress headers")
+                .overrides_with("verbose"), // 後に指定されたオプションを優先する
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Always print headers giving file names")
+                .overrides_with("quiet"),
+        )
+        .arg(
+            Arg::with_name("zero_terminated")
+                .short("z")
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline"),
+        )
+        .arg(
+            Arg::with_name("reverse")
+                .short("r")
+                .long("reverse")
+                .help("Print lines in reverse order")
+                .conflicts_with("bytes"),
+        )
+        .arg(
+            Arg::with_name("follow")
+                .short("f")
+                .long("follow")
+                .value_name("HOW")
+                .help("Output appended data as the file grows [descriptor|name]")
+                .takes_value(true)
+                .min_values(0) // 値の省略を許可: 省略時はdescriptor
+                .require_equals(true) // 後続のファイル名を値として消費しないように`--follow=HOW`の形式に限定
+                .conflicts_with("reverse"),
+        )
+        .arg(
+            Arg::with_name("follow_retry")
+                .short("F")
+                .help("Same as --follow=name, but keep trying to open missing or deleted files")
+                .conflicts_with("reverse"),
+        )
+        .arg(
+            Arg::with_name("max_unchanged_stats")
+                .long("max-unchanged-stats")
+                .value_name("N")
+                .help("With --follow=name, reopen a file whose size has not changed after N checks")
+                .default_value("5"),
+        )
+        .arg(
+            Arg::with_name("sleep_interval")
+                .short("s")
+                .long("sleep-interval")
+                .value_name("SECONDS")
+                .help("With --follow, seconds to sleep between checks")
+                .default_value("1.0"),
+        )
+        .arg(
+            Arg::with_name("print_offsets")
+                .long("print-offsets")
+                .help("Prefix each line with its byte offset in the file")
+                .conflicts_with_all(&["bytes", "follow", "follow_retry"]),
+        )
+        .arg(
+            Arg::with_name("offset_format")
+                .long("offset-format")
+                .value_name("FORMAT")
+                .help("With --print-offsets, print offsets in FORMAT [dec|hex|oct]")
+                .requires("print_offsets"),
+        )
+        .arg(
+            Arg::with_name("since")
+                .long("since")
+                .value_name("PATTERN")
+                .help("Start from the last line matching PATTERN")
+                .conflicts_with("bytes"),
+        )
+        .arg(
+            Arg::with_name("until")
+                .long("until")
+                .value_name("PATTERN")
+                .help("Stop after the first line matching PATTERN")
+                .conflicts_with_all(&["bytes", "follow", "follow_retry"]),
         )
         .get_matches();
 
+    let reverse = matches.is_present("reverse");
+
     let lines = matches.value_of("lines")
         .map(parse_num)
         .transpose()
@@ -70,12 +185,68 @@ pub fn get_args() -> MyResult<Config> {
         .transpose()
         .map_err(|e| format!("illegal byte count -- {}", e))?;
 
+    let section = Section::new(matches.value_of("since"), matches.value_of("until"))?;
+
+    // 逆順出力や範囲の区切りで行数の指定がない場合は対象範囲全体を出力する
+    let lines = if (reverse || section.is_some()) && matches.occurrences_of("lines") == 0 {
+        PlusZero
+    } else {
+        lines.unwrap()
+    };
+
+    // デフォルト値があるのでunwrap可能
+    let max_unchanged_stats = matches.value_of("max_unchanged_stats").unwrap();
+    let max_unchanged_stats = max_unchanged_stats.parse()
+        .map_err(|_| format!("illegal max unchanged stats -- {}", max_unchanged_stats))?;
+    let sleep_interval = matches.value_of("sleep_interval").unwrap();
+    let sleep_interval = sleep_interval.parse()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok()) // 負の値や無限大は不可
+        .ok_or_else(|| format!("illegal sleep interval -- {}", sleep_interval))?;
+
+    // -Fは--follow=nameと再試行の組み合わせ
+    let retry = matches.is_present("follow_retry");
+    let follow = if retry || matches.is_present("follow") {
+        let mode = if retry {
+            FollowMode::Name
+        } else {
+            matches.value_of("follow").unwrap_or("descriptor").parse::<FollowMode>()?
+        };
+        Some(FollowConfig {
+            mode,
+            max_unchanged_stats,
+            sleep_interval,
+            retry,
+        })
+    } else {
+        None
+    };
+
+    let offsets = if matches.is_present("print_offsets") {
+        Some(matches.value_of("offset_format").unwrap_or("dec").parse::<OffsetFormat>()?)
+    } else {
+        None
+    };
+
+    let headers = if matches.is_present("quiet") {
+        HeaderMode::Never
+    } else if matches.is_present("verbose") {
+        HeaderMode::Always
+    } else {
+        HeaderMode::Auto
+    };
+
     Ok(
         Config {
             files: matches.values_of_lossy("files").unwrap(),
-            lines: lines.unwrap(),
+            lines,
             bytes,
-            quiet: matches.is_present("quiet"),
+            headers,
+            reverse,
+            delimiter: if matches.is_present("zero_terminated") { b'\0' } else { b'\n' },
+            follow,
+            offsets,
+            section,
         }
     )
 }
@@ -104,14 +275,25 @@ fn parse_num(val: &str) -> MyResult<TakeValue> {
     }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let num_files = config.files.len();
+// 全てのファイルを出力できた場合はtrue: 開けないファイルなどのエラーは出力して次のファイルに進む
+pub fn run(config: Config) -> MyResult<bool> {
+    let headers = config.headers.show(config.files.len());
+    let mut followed = vec![];
+    let mut last = None; // 最後にヘッダーを出力したファイル
+    let mut num_errors = 0;
     for (file_num, filename) in config.files.iter().enumerate() {
         // stdinは非対応なので、ファイルとして直接開く
-        match File::open(&filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
+        match File::open(filename) {
+            Err(err) => {
+                eprintln!("{}: {}", filename, err);
+                num_errors += 1;
+                // 開けるようになった時点で先頭から出力する
+                if config.follow.is_some_and(|follow| follow.retry) {
+                    followed.push(Followed::missing(filename));
+                }
+            },
             Ok(file) => {
-                if !config.quiet && num_files > 1 {
+                if headers {
                     println!(
                         "{}==> {} <==",
                         if file_num > 0 {
@@ -122,27 +304,99 @@ pub fn run(config: Config) -> MyResult<()> {
                         filename,
                     );
                 }
-                let (total_lines, total_bytes) = count_lines_bytes(&filename)?;
-                let file = BufReader::new(file);
-                if let Some(num_bytes) = &config.bytes {
-                    print_bytes(file, num_bytes, total_bytes)?;
-                } else {
-                    print_lines(file, &config.lines, total_lines)?;
+                match print_file(file, filename, &config) {
+                    Err(err) => {
+                        eprintln!("{}: {}", filename, err);
+                        num_errors += 1;
+                    },
+                    // 追跡はシーク可能なファイルのみ
+                    Ok(Some(file)) if config.follow.is_some() => {
+                        followed.push(Followed::new(filename, file)?);
+                        last = Some(followed.len() - 1);
+                    },
+                    Ok(_) => {},
                 }
             },
         }
     }
-    Ok(())
+    if let Some(follow_config) = &config.follow {
+        io::stdout().flush()?; // print!で出力した内容を追跡開始前に書き出す
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        follow::follow(followed, follow_config, headers, last, &mut out)?;
+    }
+    Ok(num_errors == 0)
+}
+
+// 1つのファイルの末尾を出力する: シーク可能なファイルは追跡に使えるように返す
+fn print_file(file: File, filename: &str, config: &Config) -> MyResult<Option<File>> {
+    let (delimiter, offsets) = (config.delimiter, config.offsets);
+    // FIFOやキャラクタデバイスはシークも事前の行数カウントもできないため、一度だけ読みながら出力する
+    let seekable = file.metadata()?.is_file();
+    let mut file = BufReader::new(file);
+    if !seekable {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        if let Some(num_bytes) = &config.bytes {
+            stream_bytes(&mut file, num_bytes, &mut out)?;
+        } else if let Some(section) = &config.section {
+            let (start, lines) = section.read(&mut file, delimiter)?;
+            stream_section(&mut lines.as_slice(), start, config, &mut out)?;
+        } else {
+            stream_section(&mut file, 0, config, &mut out)?;
+        }
+        out.flush()?;
+        return Ok(None);
+    }
+    if let Some(num_bytes) = &config.bytes {
+        // バイト数はファイル末尾へのシーク位置から求める
+        let total_bytes = file.seek(SeekFrom::End(0))? as i64;
+        print_bytes(&mut file, num_bytes, total_bytes)?;
+    } else if let Some(section) = &config.section {
+        // 範囲の区切りを求め、範囲内をシークできない入力と同様に読みながら行数を適用する
+        let (start, end) = section.bounds(&mut file, delimiter, BLOCK_SIZE)?;
+        file.seek(SeekFrom::Start(start))?;
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        stream_section(&mut (&mut file).take(end - start), start, config, &mut out)?;
+        out.flush()?;
+    } else if config.reverse {
+        // 対象範囲の開始位置を求め、末尾から逆方向に行を出力する
+        let start = match config.lines {
+            PlusZero => 0,
+            TakeNum(num) if num <= 0 => {
+                find_tail_start(&mut file, num.unsigned_abs(), delimiter, BLOCK_SIZE)?
+            },
+            TakeNum(num) => skip_lines(&mut file, num as u64 - 1, delimiter)?,
+        };
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        print_reverse_lines(&mut file, start, delimiter, offsets, &mut out, BLOCK_SIZE)?;
+        out.flush()?;
+    } else {
+        match config.lines {
+            // 末尾からの行数指定: ファイル末尾だけを逆方向に読む
+            TakeNum(num) if num <= 0 => {
+                print_tail_lines(&mut file, num.unsigned_abs(), delimiter, offsets, BLOCK_SIZE)?
+            },
+            // 先頭からの行数指定: 総行数を数えてから出力する
+            _ => {
+                let (total_lines, _) = count_lines_bytes(filename, delimiter)?;
+                print_lines(&mut file, &config.lines, total_lines, delimiter, offsets)?;
+            },
+        }
+    }
+    Ok(Some(file.into_inner()))
 }
 
-fn count_lines_bytes(filename: &str) -> MyResult<(i64, i64)> {
+fn count_lines_bytes(filename: &str, delimiter: u8) -> MyResult<(i64, i64)> {
     let mut file = BufReader::new(File::open(filename)?);
     let mut num_lines = 0;
     let mut num_bytes = 0;
     let mut buf = vec![]; // 空のバイト配列
     loop {
-        // 改行コードまでバイト配列として読み込む
-        let bytes_read = file.read_until(b'\n', &mut buf)?;
+        // 区切り文字までバイト配列として読み込む
+        let bytes_read = file.read_until(delimiter, &mut buf)?;
         if bytes_read == 0 {
             break;
         }
@@ -183,26 +437,306 @@ fn get_start_index(take_val: &TakeValue, total: i64) -> Option<u64> {
 }
 
 // BufReadを実装するファイルを受け取る
-fn print_lines(mut file: impl BufRead, num_lines: &TakeValue, total_lines: i64) -> MyResult<()> {
+fn print_lines(
+    mut file: impl BufRead,
+    num_lines: &TakeValue,
+    total_lines: i64,
+    delimiter: u8,
+    offsets: Option<OffsetFormat>,
+) -> MyResult<()> {
     // インデックス位置がNoneでなければ出力処理を開始
     if let Some(start) = get_start_index(num_lines, total_lines) {
         let mut line_num = 0;
+        let mut pos = 0; // 行の先頭のバイト位置
         let mut buf = vec![];
         loop {
-            let byte_read = file.read_until(b'\n', &mut buf)?; // 行単位でバイト配列を取得
+            let byte_read = file.read_until(delimiter, &mut buf)?; // 行単位でバイト配列を取得
             if byte_read == 0 {
                 break;
             }
             if line_num >= start { // インデックス位置以降であれば出力
+                if let Some(format) = offsets {
+                    print!("{}", format.format(pos));
+                }
                 print!("{}", String::from_utf8_lossy(&buf));
             }
             line_num += 1;
+            pos += byte_read as u64;
             buf.clear()
         }
     }
     Ok(())
 }
 
+// 末尾num_lines行の開始位置を、ファイル末尾からブロック単位で逆方向に区切り文字を数えて求める
+fn find_tail_start<T: Read + Seek>(file: &mut T, num_lines: u64, delimiter: u8, block_size: usize) -> MyResult<u64> {
+    let total_bytes = file.seek(SeekFrom::End(0))?;
+    if num_lines == 0 {
+        return Ok(total_bytes);
+    }
+    let mut pos = total_bytes;
+    let mut newlines = 0;
+    let mut buffer = vec![0; block_size];
+    while pos > 0 {
+        let len = block_size.min(pos as usize);
+        pos -= len as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buffer[..len])?;
+        for (i, byte) in buffer[..len].iter().enumerate().rev() {
+            let index = pos + i as u64;
+            // 最終行の末尾の区切り文字は行の区切りとして数えない
+            if *byte == delimiter && index + 1 != total_bytes {
+                newlines += 1;
+                if newlines == num_lines {
+                    return Ok(index + 1);
+                }
+            }
+        }
+    }
+    Ok(0) // 行数が足りない場合はファイル先頭から
+}
+
+fn print_tail_lines<T: BufRead + Seek>(
+    mut file: T,
+    num_lines: u64,
+    delimiter: u8,
+    offsets: Option<OffsetFormat>,
+    block_size: usize,
+) -> MyResult<()> {
+    let start = find_tail_start(&mut file, num_lines, delimiter, block_size)?;
+    file.seek(SeekFrom::Start(start))?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    copy_lines(&mut file, start, delimiter, offsets, &mut out)?; // 末尾部分のみを出力
+    out.flush()?;
+    Ok(())
+}
+
+// 先頭からnum_lines行を読み飛ばした位置を求める: 行数が足りない場合はファイル末尾
+fn skip_lines<T: BufRead + Seek>(file: &mut T, num_lines: u64, delimiter: u8) -> MyResult<u64> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut pos = 0;
+    let mut buf = vec![];
+    for _ in 0..num_lines {
+        let bytes_read = file.read_until(delimiter, &mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        pos += bytes_read as u64;
+        buf.clear();
+    }
+    Ok(pos)
+}
+
+// start以降の行を、ファイル末尾からブロック単位で逆方向に読みながら逆順に出力する
+fn print_reverse_lines<T: Read + Seek>(
+    file: &mut T,
+    start: u64,
+    delimiter: u8,
+    offsets: Option<OffsetFormat>,
+    out: &mut impl Write,
+    block_size: usize,
+) -> MyResult<()> {
+    let mut end = file.seek(SeekFrom::End(0))?;
+    if start >= end {
+        return Ok(());
+    }
+    // 最終行の末尾の区切り文字は行の区切りとして扱わない
+    let mut last = [0; 1];
+    file.seek(SeekFrom::Start(end - 1))?;
+    file.read_exact(&mut last)?;
+    if last[0] == delimiter {
+        end -= 1;
+    }
+    let mut pos = end;
+    let mut line = vec![]; // ブロックをまたぐ行の断片
+    let mut buffer = vec![0; block_size];
+    while pos > start {
+        let len = block_size.min((pos - start) as usize);
+        pos -= len as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buffer[..len])?;
+        let mut line_end = len;
+        for i in (0..len).rev() {
+            if buffer[i] == delimiter {
+                write_line(out, &buffer[i + 1..line_end], pos + i as u64 + 1, offsets)?;
+                out.write_all(&line)?;
+                out.write_all(&[delimiter])?;
+                line.clear();
+                line_end = i;
+            }
+        }
+        // 区切り文字までたどり着かなかった部分は前のブロックの続きと連結する
+        line.splice(0..0, buffer[..line_end].iter().copied());
+    }
+    write_line(out, &line, start, offsets)?;
+    out.write_all(&[delimiter])?;
+    Ok(())
+}
+
+// 先頭からnum_lines行を読み捨てる: シークできない入力用で、読み捨てたバイト数を返す
+fn discard_lines(file: &mut impl BufRead, num_lines: u64, delimiter: u8) -> MyResult<u64> {
+    let mut pos = 0;
+    let mut buf = vec![];
+    for _ in 0..num_lines {
+        let bytes_read = file.read_until(delimiter, &mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        pos += bytes_read as u64;
+        buf.clear();
+    }
+    Ok(pos)
+}
+
+// 最後のnum_lines行だけをリングバッファに保持しながら末尾まで読み込む: 各行はposから数えた先頭のバイト位置と組にする
+fn read_last_lines(
+    file: &mut impl BufRead,
+    num_lines: u64,
+    delimiter: u8,
+    mut pos: u64,
+) -> MyResult<VecDeque<(u64, Vec<u8>)>> {
+    let mut lines = VecDeque::new();
+    if num_lines == 0 {
+        return Ok(lines);
+    }
+    loop {
+        let mut buf = vec![];
+        let bytes_read = file.read_until(delimiter, &mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        lines.push_back((pos, buf));
+        pos += bytes_read as u64;
+        if lines.len() as u64 > num_lines {
+            lines.pop_front();
+        }
+    }
+    Ok(lines)
+}
+
+// posの位置から末尾までを出力する: 位置の表記がなければそのまま流す
+fn copy_lines(
+    file: &mut impl BufRead,
+    mut pos: u64,
+    delimiter: u8,
+    offsets: Option<OffsetFormat>,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    if offsets.is_none() {
+        io::copy(file, out)?;
+        return Ok(());
+    }
+    let mut buf = vec![];
+    loop {
+        let bytes_read = file.read_until(delimiter, &mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        write_line(out, &buf, pos, offsets)?;
+        pos += bytes_read as u64;
+        buf.clear();
+    }
+    Ok(())
+}
+
+// 入力の行を先頭から一度だけ読みながら出力する: startは入力の先頭のファイル内でのバイト位置
+fn stream_section(file: &mut impl BufRead, start: u64, config: &Config, out: &mut impl Write) -> MyResult<()> {
+    if config.reverse {
+        stream_reverse_lines(file, &config.lines, config.delimiter, config.offsets, start, out)
+    } else {
+        stream_lines(file, &config.lines, config.delimiter, config.offsets, start, out)
+    }
+}
+
+// シークできない入力の行を出力する: 先頭からの指定は読み飛ばした後をそのまま流す
+fn stream_lines(
+    file: &mut impl BufRead,
+    num_lines: &TakeValue,
+    delimiter: u8,
+    offsets: Option<OffsetFormat>,
+    start: u64,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    match num_lines {
+        TakeNum(num) if *num < 0 => {
+            for (pos, line) in read_last_lines(file, num.unsigned_abs(), delimiter, start)? {
+                write_line(out, &line, pos, offsets)?;
+            }
+        },
+        TakeNum(0) => {},
+        TakeNum(num) => {
+            let pos = discard_lines(file, *num as u64 - 1, delimiter)?;
+            copy_lines(file, start + pos, delimiter, offsets, out)?;
+        },
+        PlusZero => {
+            copy_lines(file, start, delimiter, offsets, out)?;
+        },
+    }
+    Ok(())
+}
+
+// シークできない入力の対象範囲の行を逆順に出力する: 対象範囲はメモリに保持する
+fn stream_reverse_lines(
+    file: &mut impl BufRead,
+    num_lines: &TakeValue,
+    delimiter: u8,
+    offsets: Option<OffsetFormat>,
+    start: u64,
+    out: &mut impl Write,
+) -> MyResult<()> {
+    let lines = match num_lines {
+        TakeNum(num) if *num <= 0 => read_last_lines(file, num.unsigned_abs(), delimiter, start)?,
+        TakeNum(num) => {
+            let pos = discard_lines(file, *num as u64 - 1, delimiter)?;
+            read_last_lines(file, u64::MAX, delimiter, start + pos)?
+        },
+        PlusZero => read_last_lines(file, u64::MAX, delimiter, start)?,
+    };
+    for (pos, line) in lines.iter().rev() {
+        // 最終行に区切り文字がない場合も区切り文字を付けて出力する
+        write_line(out, line.strip_suffix(&[delimiter]).unwrap_or(line), *pos, offsets)?;
+        out.write_all(&[delimiter])?;
+    }
+    Ok(())
+}
+
+// シークできない入力のバイトを出力する: 末尾からの指定では最後のnum_bytesバイトだけを保持する
+fn stream_bytes(file: &mut impl Read, num_bytes: &TakeValue, out: &mut impl Write) -> MyResult<()> {
+    match num_bytes {
+        TakeNum(num) if *num < 0 => {
+            let num = num.unsigned_abs();
+            let mut ring = VecDeque::new();
+            let mut buffer = vec![0; BLOCK_SIZE];
+            loop {
+                let bytes_read = match file.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                ring.extend(&buffer[..bytes_read]);
+                if ring.len() as u64 > num {
+                    let excess = ring.len() - num as usize;
+                    ring.drain(..excess);
+                }
+            }
+            let (front, back) = ring.as_slices();
+            out.write_all(front)?;
+            out.write_all(back)?;
+        },
+        TakeNum(0) => {},
+        TakeNum(num) => {
+            io::copy(&mut file.take(*num as u64 - 1), &mut io::sink())?;
+            io::copy(file, out)?;
+        },
+        PlusZero => {
+            io::copy(file, out)?;
+        },
+    }
+    Ok(())
+}
+
 // ReadとSeek(カーソルと同義)を実装するジェネリクス型のファイルを受け取る: 返り値の前で where T: Read + Seek でもOK
 fn print_bytes<T: Read + Seek>(mut file: T, num_bytes: &TakeValue, total_bytes: i64) -> MyResult<()> {
     if let Some(start) = get_start_index(num_bytes, total_bytes) {
@@ -220,8 +754,17 @@ fn print_bytes<T: Read + Seek>(mut file: T, num_bytes: &TakeValue, total_bytes:
 #[cfg(test)]
 mod tests {
     use super::{
-        get_start_index, count_lines_bytes, parse_num, TakeValue::*,
+        find_tail_start, get_start_index, count_lines_bytes, parse_num, print_reverse_lines,
+        skip_lines, stream_bytes, stream_lines, stream_reverse_lines, HeaderMode, OffsetFormat, TakeValue,
+        TakeValue::*,
     };
+    use std::io::Cursor;
+
+    fn reverse(input: &str, start: u64, block_size: usize) -> String {
+        let mut out = vec![];
+        print_reverse_lines(&mut Cursor::new(input), start, b'\n', None, &mut out, block_size).unwrap();
+        String::from_utf8(out).unwrap()
+    }
 
     #[test]
     fn test_get_start_index() {
@@ -258,13 +801,170 @@ mod tests {
         assert_eq!(get_start_index(&TakeNum(-20), 10), Some(0));
     }
 
+    #[test]
+    fn test_find_tail_start() {
+        // 小さいブロックサイズでブロック境界をまたぐ場合も確認する
+        for block_size in [1, 2, 3, 1024] {
+            let mut file = Cursor::new("a\nbb\nccc\n");
+            let mut start = |num| find_tail_start(&mut file, num, b'\n', block_size).unwrap();
+            assert_eq!(start(0), 9);
+            assert_eq!(start(1), 5);
+            assert_eq!(start(2), 2);
+            assert_eq!(start(3), 0);
+            assert_eq!(start(4), 0);
+
+            // 最終行に改行がない場合
+            let mut file = Cursor::new("a\nbb\nccc");
+            assert_eq!(find_tail_start(&mut file, 1, b'\n', block_size).unwrap(), 5);
+            assert_eq!(find_tail_start(&mut file, 2, b'\n', block_size).unwrap(), 2);
+
+            // 空ファイル
+            let mut file = Cursor::new("");
+            assert_eq!(find_tail_start(&mut file, 1, b'\n', block_size).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn test_print_reverse_lines() {
+        for block_size in [1, 2, 3, 1024] {
+            assert_eq!(reverse("a\nbb\nccc\n", 0, block_size), "ccc\nbb\na\n");
+            assert_eq!(reverse("a\nbb\nccc\n", 2, block_size), "ccc\nbb\n");
+            assert_eq!(reverse("a\nbb\nccc\n", 9, block_size), "");
+
+            // 最終行に改行がない場合も改行を付けて出力する
+            assert_eq!(reverse("a\nbb\nccc", 0, block_size), "ccc\nbb\na\n");
+
+            // 空行を含む場合
+            assert_eq!(reverse("a\n\nb\n", 0, block_size), "b\n\na\n");
+            assert_eq!(reverse("\n", 0, block_size), "\n");
+            assert_eq!(reverse("", 0, block_size), "");
+        }
+    }
+
+    // 改行区切りの行として読み込む
+    fn lines(file: &mut &'static [u8], num: &TakeValue, out: &mut Vec<u8>) -> crate::MyResult<()> {
+        stream_lines(file, num, b'\n', None, 0, out)
+    }
+
+    fn reverse_lines(file: &mut &'static [u8], num: &TakeValue, out: &mut Vec<u8>) -> crate::MyResult<()> {
+        stream_reverse_lines(file, num, b'\n', None, 0, out)
+    }
+
+    // シークできない入力として、バイトスライスから読み込む
+    fn stream(
+        f: fn(&mut &'static [u8], &TakeValue, &mut Vec<u8>) -> crate::MyResult<()>,
+        input: &'static str,
+        num: TakeValue,
+    ) -> String {
+        let mut out = vec![];
+        f(&mut input.as_bytes(), &num, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_stream_lines() {
+        let input = "a\nbb\nccc\n";
+        assert_eq!(stream(lines, input, TakeNum(-2)), "bb\nccc\n");
+        assert_eq!(stream(lines, input, TakeNum(-5)), input);
+        assert_eq!(stream(lines, input, TakeNum(0)), "");
+        assert_eq!(stream(lines, input, TakeNum(2)), "bb\nccc\n");
+        assert_eq!(stream(lines, input, TakeNum(4)), "");
+        assert_eq!(stream(lines, input, PlusZero), input);
+        assert_eq!(stream(lines, "a\nbb", TakeNum(-1)), "bb");
+        assert_eq!(stream(lines, "", TakeNum(-1)), "");
+    }
+
+    #[test]
+    fn test_stream_reverse_lines() {
+        let input = "a\nbb\nccc";
+        assert_eq!(stream(reverse_lines, input, PlusZero), "ccc\nbb\na\n");
+        assert_eq!(stream(reverse_lines, input, TakeNum(-2)), "ccc\nbb\n");
+        assert_eq!(stream(reverse_lines, input, TakeNum(2)), "ccc\nbb\n");
+        assert_eq!(stream(reverse_lines, input, TakeNum(0)), "");
+    }
+
+    #[test]
+    fn test_stream_bytes() {
+        let input = "a\nbb\nccc\n";
+        assert_eq!(stream(stream_bytes, input, TakeNum(-4)), "ccc\n");
+        assert_eq!(stream(stream_bytes, input, TakeNum(-20)), input);
+        assert_eq!(stream(stream_bytes, input, TakeNum(0)), "");
+        assert_eq!(stream(stream_bytes, input, TakeNum(3)), "bb\nccc\n");
+        assert_eq!(stream(stream_bytes, input, TakeNum(20)), "");
+        assert_eq!(stream(stream_bytes, input, PlusZero), input);
+    }
+
+    #[test]
+    fn test_nul_delimiter() {
+        // NUL区切りのレコードでは改行は通常の文字として扱う
+        let input = "a\nb\0cc\0d\n\0";
+        let mut file = Cursor::new(input);
+        assert_eq!(find_tail_start(&mut file, 2, b'\0', 2).unwrap(), 4);
+        assert_eq!(skip_lines(&mut file, 1, b'\0').unwrap(), 4);
+
+        let mut out = vec![];
+        stream_lines(&mut input.as_bytes(), &TakeNum(-1), b'\0', None, 0, &mut out).unwrap();
+        assert_eq!(out, b"d\n\0");
+
+        let mut out = vec![];
+        print_reverse_lines(&mut Cursor::new(input), 0, b'\0', None, &mut out, 3).unwrap();
+        assert_eq!(out, b"d\n\0cc\0a\nb\0");
+
+        let mut out = vec![];
+        stream_reverse_lines(&mut "a\0b".as_bytes(), &PlusZero, b'\0', None, 0, &mut out).unwrap();
+        assert_eq!(out, b"b\0a\0");
+    }
+
+    #[test]
+    fn test_offsets() {
+        let input = "a\nbb\nccc";
+        let dec = Some(OffsetFormat::Decimal);
+
+        // Offsets count from the start of the input, including skipped lines
+        let mut out = vec![];
+        stream_lines(&mut input.as_bytes(), &TakeNum(-2), b'\n', dec, 0, &mut out).unwrap();
+        assert_eq!(out, b"2\tbb\n5\tccc");
+
+        let mut out = vec![];
+        stream_lines(&mut input.as_bytes(), &TakeNum(2), b'\n', dec, 0, &mut out).unwrap();
+        assert_eq!(out, b"2\tbb\n5\tccc");
+
+        let mut out = vec![];
+        stream_reverse_lines(&mut input.as_bytes(), &TakeNum(2), b'\n', dec, 0, &mut out).unwrap();
+        assert_eq!(out, b"5\tccc\n2\tbb\n");
+
+        // Each line keeps its own offset when read backwards across blocks
+        for block_size in [1, 2, 1024] {
+            let mut out = vec![];
+            print_reverse_lines(&mut Cursor::new(input), 0, b'\n', dec, &mut out, block_size).unwrap();
+            assert_eq!(out, b"5\tccc\n2\tbb\n0\ta\n");
+        }
+    }
+
+    #[test]
+    fn test_header_mode() {
+        assert!(!HeaderMode::Auto.show(1));
+        assert!(HeaderMode::Auto.show(2));
+        assert!(HeaderMode::Always.show(1));
+        assert!(!HeaderMode::Never.show(2));
+    }
+
+    #[test]
+    fn test_skip_lines() {
+        let mut file = Cursor::new("a\nbb\nccc\n");
+        assert_eq!(skip_lines(&mut file, 0, b'\n').unwrap(), 0);
+        assert_eq!(skip_lines(&mut file, 1, b'\n').unwrap(), 2);
+        assert_eq!(skip_lines(&mut file, 3, b'\n').unwrap(), 9);
+        assert_eq!(skip_lines(&mut file, 4, b'\n').unwrap(), 9);
+    }
+
     #[test]
     fn test_count_lines_bytes() {
-        let res = count_lines_bytes("tests/inputs/one.txt");
+        let res = count_lines_bytes("tests/inputs/one.txt", b'\n');
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), (1, 24));
 
-        let res = count_lines_bytes("tests/inputs/ten.txt");
+        let res = count_lines_bytes("tests/inputs/ten.txt", b'\n');
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), (10, 49));
     }
@@ -1,8 +1,12 @@
 use std::process::exit;
 
 fn main() {
-    if let Err(e) = tailr::get_args().and_then(tailr::run) {
-        eprintln!("{}", e);
-        exit(1);
+    match tailr::get_args().and_then(tailr::run) {
+        Ok(true) => {},
+        Ok(false) => exit(1), // いずれかのファイルを出力できなかった
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
     }
 }
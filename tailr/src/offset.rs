@@ -0,0 +1,68 @@
+use std::{io::{self, Write}, str::FromStr};
+
+// --print-offsetsで各行の前に出力する、ファイル先頭からのバイト位置の表記
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OffsetFormat {
+    Decimal,
+    Hex,
+    Octal,
+}
+
+impl FromStr for OffsetFormat {
+    type Err = String;
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val {
+            "dec" => Ok(OffsetFormat::Decimal),
+            "hex" => Ok(OffsetFormat::Hex),
+            "oct" => Ok(OffsetFormat::Octal),
+            _ => Err(format!("illegal offset format -- {}", val)),
+        }
+    }
+}
+
+impl OffsetFormat {
+    // 位置と行の内容はタブで区切る: 行の内容は加工しない
+    pub fn format(&self, offset: u64) -> String {
+        match self {
+            OffsetFormat::Decimal => format!("{}\t", offset),
+            OffsetFormat::Hex => format!("{:x}\t", offset),
+            OffsetFormat::Octal => format!("{:o}\t", offset),
+        }
+    }
+}
+
+// 1行を出力する: 位置の表記が指定されていれば、行の先頭のバイト位置を前に付ける
+pub fn write_line(out: &mut impl Write, line: &[u8], offset: u64, offsets: Option<OffsetFormat>) -> io::Result<()> {
+    if let Some(format) = offsets {
+        out.write_all(format.format(offset).as_bytes())?;
+    }
+    out.write_all(line)
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{write_line, OffsetFormat};
+
+    #[test]
+    fn test_parse_offset_format() {
+        assert_eq!("dec".parse::<OffsetFormat>(), Ok(OffsetFormat::Decimal));
+        assert_eq!("hex".parse::<OffsetFormat>(), Ok(OffsetFormat::Hex));
+        assert_eq!("oct".parse::<OffsetFormat>(), Ok(OffsetFormat::Octal));
+        assert_eq!(
+            "bin".parse::<OffsetFormat>(),
+            Err("illegal offset format -- bin".to_string())
+        );
+    }
+
+    #[test]
+    fn test_write_line() {
+        let mut out = vec![];
+        write_line(&mut out, b"a\n", 255, None).unwrap();
+        write_line(&mut out, b"b\n", 255, Some(OffsetFormat::Decimal)).unwrap();
+        write_line(&mut out, b"c\n", 255, Some(OffsetFormat::Hex)).unwrap();
+        write_line(&mut out, b"d", 255, Some(OffsetFormat::Octal)).unwrap();
+        assert_eq!(out, b"a\n255\tb\nff\tc\n377\td");
+    }
+}
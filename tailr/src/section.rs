@@ -0,0 +1,222 @@
+use std::io::{BufRead, Read, Seek, SeekFrom};
+
+use regex::bytes::Regex;
+
+use crate::MyResult;
+
+// --since, --until: 正規表現に一致する行でファイルの対象範囲を区切る
+// 行数の指定(-n)は区切った範囲の中に対して適用する
+#[derive(Debug)]
+pub struct Section {
+    since: Option<Regex>, // 最後に一致した行から出力する: 一致しない場合はファイル先頭から
+    until: Option<Regex>, // 開始位置以降で最初に一致した行まで出力する: 一致した行も含む
+}
+
+impl Section {
+    // どちらも指定されていなければNone
+    pub fn new(since: Option<&str>, until: Option<&str>) -> MyResult<Option<Self>> {
+        if since.is_none() && until.is_none() {
+            return Ok(None);
+        }
+        let since = since
+            .map(|val| Regex::new(val).map_err(|_| format!("illegal since pattern -- {}", val)))
+            .transpose()?;
+        let until = until
+            .map(|val| Regex::new(val).map_err(|_| format!("illegal until pattern -- {}", val)))
+            .transpose()?;
+        Ok(Some(Section { since, until }))
+    }
+
+    // シーク可能なファイルの対象範囲のバイト位置(開始, 終了)を求める
+    // 開始位置は末尾から逆方向に探すため、最後の区切りより前は読まない
+    pub fn bounds<T: BufRead + Seek>(&self, file: &mut T, delimiter: u8, block_size: usize) -> MyResult<(u64, u64)> {
+        let found = match &self.since {
+            Some(since) => find_last_match(file, since, delimiter, block_size)?,
+            None => None,
+        };
+        let start = found.unwrap_or(0);
+        let end = match &self.until {
+            Some(until) => find_until_end(file, start, until, delimiter, found.is_some())?,
+            None => file.seek(SeekFrom::End(0))?,
+        };
+        Ok((start, end))
+    }
+
+    // シークできない入力を一度だけ読み、対象範囲の開始位置と内容を返す
+    // 後から開始位置の行が現れる可能性があるため、最後まで読んで範囲をメモリに保持する
+    pub fn read(&self, file: &mut impl BufRead, delimiter: u8) -> MyResult<(u64, Vec<u8>)> {
+        let mut start = 0;
+        let mut section = vec![];
+        let mut ended = false; // --untilの行まで読んだか
+        let mut pos = 0;
+        let mut buf = vec![];
+        loop {
+            let bytes_read = file.read_until(delimiter, &mut buf)?;
+            if bytes_read == 0 {
+                break;
+            }
+            let line = buf.strip_suffix(&[delimiter]).unwrap_or(&buf);
+            if self.since.as_ref().is_some_and(|since| since.is_match(line)) {
+                // 開始位置の行では--untilを判定しない
+                start = pos;
+                section.clear();
+                section.extend_from_slice(&buf);
+                ended = false;
+            } else if !ended {
+                section.extend_from_slice(&buf);
+                ended = self.until.as_ref().is_some_and(|until| until.is_match(line));
+            }
+            pos += bytes_read as u64;
+            buf.clear();
+        }
+        Ok((start, section))
+    }
+}
+
+// ファイル末尾からブロック単位で逆方向に行を組み立て、最後に一致した行の先頭位置を求める
+fn find_last_match<T: Read + Seek>(
+    file: &mut T,
+    pattern: &Regex,
+    delimiter: u8,
+    block_size: usize,
+) -> MyResult<Option<u64>> {
+    let mut end = file.seek(SeekFrom::End(0))?;
+    if end == 0 {
+        return Ok(None);
+    }
+    // 最終行の末尾の区切り文字は行の区切りとして扱わない
+    let mut last = [0; 1];
+    file.seek(SeekFrom::Start(end - 1))?;
+    file.read_exact(&mut last)?;
+    if last[0] == delimiter {
+        end -= 1;
+    }
+    let mut pos = end;
+    let mut line = vec![]; // ブロックをまたぐ行の断片
+    let mut buffer = vec![0; block_size];
+    while pos > 0 {
+        let len = block_size.min(pos as usize);
+        pos -= len as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buffer[..len])?;
+        let mut line_end = len;
+        for i in (0..len).rev() {
+            if buffer[i] == delimiter {
+                line.splice(0..0, buffer[i + 1..line_end].iter().copied());
+                if pattern.is_match(&line) {
+                    return Ok(Some(pos + i as u64 + 1));
+                }
+                line.clear();
+                line_end = i;
+            }
+        }
+        line.splice(0..0, buffer[..line_end].iter().copied());
+    }
+    Ok(pattern.is_match(&line).then_some(0))
+}
+
+// startから順に読み、最初に一致した行の末尾の位置を求める: 一致しない場合はファイル末尾
+// skip_firstの場合は開始位置の行を判定しない
+fn find_until_end<T: BufRead + Seek>(
+    file: &mut T,
+    start: u64,
+    pattern: &Regex,
+    delimiter: u8,
+    skip_first: bool,
+) -> MyResult<u64> {
+    file.seek(SeekFrom::Start(start))?;
+    let mut pos = start;
+    let mut buf = vec![];
+    loop {
+        let bytes_read = file.read_until(delimiter, &mut buf)?;
+        if bytes_read == 0 {
+            break;
+        }
+        let line = buf.strip_suffix(&[delimiter]).unwrap_or(&buf);
+        let matched = !(skip_first && pos == start) && pattern.is_match(line);
+        pos += bytes_read as u64;
+        if matched {
+            break;
+        }
+        buf.clear();
+    }
+    Ok(pos)
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{find_last_match, Section};
+    use regex::bytes::Regex;
+    use std::io::Cursor;
+
+    const LOG: &str = "boot\n== RESTART ==\na\nstop\n== RESTART ==\nb\nstop\nc\n";
+
+    fn bounds(since: Option<&str>, until: Option<&str>, input: &str, block_size: usize) -> (u64, u64) {
+        Section::new(since, until)
+            .unwrap()
+            .unwrap()
+            .bounds(&mut Cursor::new(input), b'\n', block_size)
+            .unwrap()
+    }
+
+    fn read(since: Option<&str>, until: Option<&str>, input: &str) -> (u64, String) {
+        let (start, section) = Section::new(since, until)
+            .unwrap()
+            .unwrap()
+            .read(&mut input.as_bytes(), b'\n')
+            .unwrap();
+        (start, String::from_utf8(section).unwrap())
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(Section::new(None, None).unwrap().is_none());
+        let res = Section::new(Some("("), None);
+        assert_eq!(res.unwrap_err().to_string(), "illegal since pattern -- (");
+        let res = Section::new(None, Some("a["));
+        assert_eq!(res.unwrap_err().to_string(), "illegal until pattern -- a[");
+    }
+
+    #[test]
+    fn test_find_last_match() {
+        let re = Regex::new("^== RESTART ==$").unwrap();
+        let empty = Regex::new("^$").unwrap();
+        for block_size in [1, 2, 3, 1024] {
+            let find = |input: &str, re: &Regex| {
+                find_last_match(&mut Cursor::new(input), re, b'\n', block_size).unwrap()
+            };
+            assert_eq!(find(LOG, &re), Some(26));
+            assert_eq!(find("== RESTART ==\na\n", &re), Some(0));
+            // 最終行に改行がない場合
+            assert_eq!(find("a\n== RESTART ==", &re), Some(2));
+            assert_eq!(find("a\nb\n", &re), None);
+            assert_eq!(find("", &re), None);
+            // 空行にも一致する
+            assert_eq!(find("a\n\nb\n", &empty), Some(2));
+        }
+    }
+
+    #[test]
+    fn test_bounds() {
+        for block_size in [1, 3, 1024] {
+            let len = LOG.len() as u64;
+            assert_eq!(bounds(Some("RESTART"), None, LOG, block_size), (26, len));
+            assert_eq!(bounds(Some("RESTART"), Some("stop"), LOG, block_size), (26, 47));
+            // 開始位置の行は--untilの判定に使わない
+            assert_eq!(bounds(Some("RESTART"), Some("RESTART"), LOG, block_size), (26, len));
+            assert_eq!(bounds(None, Some("stop"), LOG, block_size), (0, 26));
+            // 一致しない場合はファイル全体
+            assert_eq!(bounds(Some("none"), Some("none"), LOG, block_size), (0, len));
+        }
+    }
+
+    #[test]
+    fn test_read() {
+        assert_eq!(read(Some("RESTART"), None, LOG), (26, "== RESTART ==\nb\nstop\nc\n".to_string()));
+        assert_eq!(read(Some("RESTART"), Some("stop"), LOG), (26, "== RESTART ==\nb\nstop\n".to_string()));
+        assert_eq!(read(None, Some("stop"), LOG), (0, "boot\n== RESTART ==\na\nstop\n".to_string()));
+        assert_eq!(read(Some("none"), None, LOG), (0, LOG.to_string()));
+        assert_eq!(read(Some("RESTART"), None, ""), (0, String::new()));
+    }
+}
@@ -12,6 +12,7 @@ const ONE: &str = "tests/inputs/one.txt";
 const TWO: &str = "tests/inputs/two.txt";
 const THREE: &str = "tests/inputs/three.txt";
 const TEN: &str = "tests/inputs/ten.txt";
+const RESTART: &str = "tests/inputs/restart.log";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -49,7 +50,7 @@ fn dies_bad_bytes() -> TestResult {
     let bad = random_string();
     let expected = format!("illegal byte count -- {}", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-c", &bad, EMPTY])
+        .args(["-c", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -63,7 +64,7 @@ fn dies_bad_lines() -> TestResult {
     let bad = random_string();
     let expected = format!("illegal line count -- {}", &bad);
     Command::cargo_bin(PRG)?
-        .args(&["-n", &bad, EMPTY])
+        .args(["-n", &bad, EMPTY])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -78,7 +79,22 @@ fn dies_bytes_and_lines() -> TestResult {
                used with '--bytes <BYTES>'";
 
     Command::cargo_bin(PRG)?
-        .args(&["-n", "1", "-c", "2"])
+        .args(["-n", "1", "-c", "2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(msg));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bytes_and_reverse() -> TestResult {
+    let msg = "The argument '--bytes <BYTES>' cannot be \
+               used with '--reverse'";
+
+    Command::cargo_bin(PRG)?
+        .args(["-r", "-c", "2", TEN])
         .assert()
         .failure()
         .stderr(predicate::str::contains(msg));
@@ -92,8 +108,9 @@ fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error 2[)]", bad);
     Command::cargo_bin(PRG)?
-        .args(&[ONE, &bad, TWO])
+        .args([ONE, &bad, TWO])
         .assert()
+        .failure()
         .stderr(predicate::str::is_match(expected)?);
 
     Ok(())
@@ -829,3 +846,365 @@ fn multiple_files_c_plus_3() -> TestResult {
         "tests/expected/all.c+3.out",
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn ten_r() -> TestResult {
+    run(&["-r", TEN], "tests/expected/ten.txt.r.out")
+}
+
+#[test]
+fn ten_n3_r() -> TestResult {
+    run(&["--reverse", "-n", "3", TEN], "tests/expected/ten.txt.n3.r.out")
+}
+
+#[test]
+fn ten_n_plus_8_r() -> TestResult {
+    run(&["-r", "-n", "+8", TEN], "tests/expected/ten.txt.n+8.r.out")
+}
+
+#[test]
+fn multiple_files_n2_r() -> TestResult {
+    run(
+        &["-r", "-n", "2", TEN, EMPTY, ONE, THREE, TWO],
+        "tests/expected/all.n2.r.out",
+    )
+}
+
+// --------------------------------------------------
+// パイプ経由の/dev/stdinはFIFOなので、シークせずに読み込む
+fn run_pipe(args: &[&str], input_file: &str, expected_file: &str) -> TestResult {
+    let input = fs::read(input_file)?;
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .arg("/dev/stdin")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn pipe_ten_n3() -> TestResult {
+    run_pipe(&["-n", "3"], TEN, "tests/expected/ten.txt.n3.out")
+}
+
+#[test]
+fn pipe_ten_n_plus_2() -> TestResult {
+    run_pipe(&["-n", "+2"], TEN, "tests/expected/ten.txt.n+2.out")
+}
+
+#[test]
+fn pipe_ten_c8() -> TestResult {
+    run_pipe(&["-c", "8"], TEN, "tests/expected/ten.txt.c8.out")
+}
+
+#[test]
+fn pipe_ten_c_plus_2() -> TestResult {
+    run_pipe(&["-c", "+2"], TEN, "tests/expected/ten.txt.c+2.out")
+}
+
+#[test]
+fn pipe_ten_n3_r() -> TestResult {
+    run_pipe(&["-r", "-n", "3"], TEN, "tests/expected/ten.txt.n3.r.out")
+}
+
+#[test]
+fn pipe_empty_n3() -> TestResult {
+    run_pipe(&["-n", "3"], EMPTY, "tests/expected/empty.txt.n3.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_follow() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--follow=inode", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal follow mode -- inode"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_max_unchanged_stats() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "--max-unchanged-stats", "x", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal max unchanged stats -- x"));
+    Ok(())
+}
+
+#[test]
+fn dies_follow_and_reverse() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "-r", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_name_rotation() -> TestResult {
+    use std::{io::Write, process::Stdio, thread, time::Duration};
+
+    let path = std::env::temp_dir().join(format!("tailr-{}", random_string()));
+    let rotated = path.with_extension("1");
+    fs::write(&path, "one\ntwo\n")?;
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(["-n", "1", "--follow=name", "-s", "0.05", "--max-unchanged-stats", "2"])
+        .arg(&path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let wait = || thread::sleep(Duration::from_millis(500));
+    wait();
+    fs::OpenOptions::new().append(true).open(&path)?.write_all(b"three\n")?;
+    wait();
+    fs::rename(&path, &rotated)?;
+    fs::write(&path, "four\n")?;
+    wait();
+    child.kill()?;
+    let output = child.wait_with_output()?;
+    fs::remove_file(&path)?;
+    fs::remove_file(&rotated)?;
+
+    assert_eq!(String::from_utf8(output.stdout)?, "two\nthree\nfour\n");
+    assert!(String::from_utf8(output.stderr)?.contains("has been replaced; following new file"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_retry() -> TestResult {
+    use std::{process::Stdio, thread, time::Duration};
+
+    let path = std::env::temp_dir().join(format!("tailr-{}", random_string()));
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(["-F", "-s", "0.05", "--max-unchanged-stats", "2"])
+        .arg(&path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let wait = || thread::sleep(Duration::from_millis(500));
+    wait();
+    fs::write(&path, "one\ntwo\n")?;
+    wait();
+    fs::remove_file(&path)?;
+    wait();
+    fs::write(&path, "three\n")?;
+    wait();
+    child.kill()?;
+    let output = child.wait_with_output()?;
+    fs::remove_file(&path)?;
+
+    assert_eq!(String::from_utf8(output.stdout)?, "one\ntwo\nthree\n");
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("No such file or directory"));
+    assert_eq!(stderr.matches("has appeared; following new file").count(), 2);
+    assert!(stderr.contains("has become inaccessible: No such file or directory"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_bad_file_outputs_rest() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/one.txt.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-q", &bad, ONE])
+        .assert()
+        .code(1)
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_directory() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-q", "tests/inputs", ONE])
+        .assert()
+        .failure()
+        .stdout(fs::read_to_string("tests/expected/one.txt.out")?)
+        .stderr(predicate::str::starts_with("tests/inputs: "));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn verbose_single_file() -> TestResult {
+    let expected = format!("==> {} <==\n{}", ONE, fs::read_to_string("tests/expected/one.txt.out")?);
+    Command::cargo_bin(PRG)?
+        .args(["-v", ONE])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn verbose_quiet_last_wins() -> TestResult {
+    run(&["-v", "-q", "-n", "1", TEN, EMPTY, ONE, THREE, TWO], "tests/expected/all.n1.q.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-q", "--verbose", "-n", "1", ONE])
+        .assert()
+        .stdout(predicate::str::starts_with(format!("==> {} <==\n", ONE)));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-n", "2"])
+        .arg("tests/inputs/records.bin")
+        .assert()
+        .success()
+        .stdout(&b"two\nlines\0three\0"[..]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated_from_start_and_reverse() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-n", "+2", "tests/inputs/records.bin"])
+        .assert()
+        .stdout(&b"two\nlines\0three\0"[..]);
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-r", "tests/inputs/records.bin"])
+        .assert()
+        .stdout(&b"three\0two\nlines\0one\0"[..]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn print_offsets() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--print-offsets", "-n", "3", TEN])
+        .assert()
+        .success()
+        .stdout("34\teight\n40\tnine\n45\tten\n");
+    Command::cargo_bin(PRG)?
+        .args(["--print-offsets", "-n", "+9", "--offset-format", "hex", TEN])
+        .assert()
+        .success()
+        .stdout("28\tnine\n2d\tten\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn print_offsets_reverse() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--print-offsets", "-r", "-n", "2", TEN])
+        .assert()
+        .success()
+        .stdout("45\tten\n40\tnine\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_offset_format() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--print-offsets", "--offset-format", "bin", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal offset format -- bin"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_print_offsets_and_bytes() -> TestResult {
+    let msg = "The argument '--bytes <BYTES>' cannot be \
+               used with '--print-offsets'";
+    Command::cargo_bin(PRG)?
+        .args(["--print-offsets", "-c", "2", TEN])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(msg));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn since() -> TestResult {
+    let last = "=== RESTART ===\nsecond session\nerror: disk full\nstop\nidle\n";
+    Command::cargo_bin(PRG)?
+        .args(["--since", "^=== RESTART ===$", RESTART])
+        .assert()
+        .success()
+        .stdout(last);
+    // シークできない入力でも最後に一致した行から出力する
+    Command::cargo_bin(PRG)?
+        .args(["--since", "RESTART", "/dev/stdin"])
+        .pipe_stdin(RESTART)?
+        .assert()
+        .success()
+        .stdout(last);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn since_until() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--since", "RESTART", "--until", "^stop$", RESTART])
+        .assert()
+        .success()
+        .stdout("=== RESTART ===\nsecond session\nerror: disk full\nstop\n");
+    Command::cargo_bin(PRG)?
+        .args(["--until", "stop", "-n", "2", RESTART])
+        .assert()
+        .success()
+        .stdout("first session\nstop\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn since_lines_offsets() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--since", "RESTART", "-n", "+2", "--print-offsets", RESTART])
+        .assert()
+        .success()
+        .stdout("56\tsecond session\n71\terror: disk full\n88\tstop\n93\tidle\n");
+    Command::cargo_bin(PRG)?
+        .args(["--since", "RESTART", "-r", RESTART])
+        .assert()
+        .success()
+        .stdout("idle\nstop\nerror: disk full\nsecond session\n=== RESTART ===\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_since() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--since", "(", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("illegal since pattern -- ("));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_until_and_follow() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--until", "stop", "-f", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
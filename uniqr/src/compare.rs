@@ -0,0 +1,97 @@
+// 隣り合う行が同じグループに属するかの判定: 行は末尾の区切り文字を除いて渡す
+pub trait Comparator {
+    fn same(&self, a: &str, b: &str) -> bool;
+}
+
+// 完全に一致する行
+#[derive(Debug, Clone, Copy)]
+pub struct Exact;
+
+impl Comparator for Exact {
+    fn same(&self, a: &str, b: &str) -> bool {
+        a == b
+    }
+}
+
+// 大文字と小文字の違いを無視して、内側の判定で比較する
+pub struct IgnoreCase(pub Box<dyn Comparator>);
+
+impl Comparator for IgnoreCase {
+    fn same(&self, a: &str, b: &str) -> bool {
+        self.0.same(&a.to_lowercase(), &b.to_lowercase())
+    }
+}
+
+// 空白の有無や数の違いのみを無視する
+#[derive(Debug, Clone, Copy)]
+pub struct Whitespace;
+
+impl Comparator for Whitespace {
+    fn same(&self, a: &str, b: &str) -> bool {
+        let visible = |line: &str| line.chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+        visible(a) == visible(b)
+    }
+}
+
+// 文字の挿入、削除、置換の回数(レーベンシュタイン距離)が指定した値以下の行
+#[derive(Debug, Clone, Copy)]
+pub struct EditDistance(pub usize);
+
+impl Comparator for EditDistance {
+    fn same(&self, a: &str, b: &str) -> bool {
+        within_distance(a, b, self.0)
+    }
+}
+
+// いずれかの判定で同じとみなされる行
+pub struct Any(pub Vec<Box<dyn Comparator>>);
+
+impl Comparator for Any {
+    fn same(&self, a: &str, b: &str) -> bool {
+        self.0.iter().any(|comparator| comparator.same(a, b))
+    }
+}
+
+// 編集距離がmax以下か: 2行分の表で計算し、距離がmaxを超えることが確定した時点で打ち切る
+fn within_distance(a: &str, b: &str, max: usize) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return false; // 長さの差だけ挿入か削除が必要
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        // 行の最小値は以降の行で小さくならない
+        if curr.iter().all(|&distance| distance > max) {
+            return false;
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()] <= max
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{Any, Comparator, EditDistance, Exact, IgnoreCase, Whitespace};
+
+    #[test]
+    fn test_ignore_case() {
+        let comparator = IgnoreCase(Box::new(Exact));
+        assert!(comparator.same("Hello World", "hello WORLD"));
+        assert!(comparator.same("ÄBC", "äbc"));
+        assert!(!comparator.same("hello", "hello!"));
+
+        // 編集距離や空白の判定と組み合わせられる
+        let comparator = IgnoreCase(Box::new(Any(vec![Box::new(EditDistance(1)), Box::new(Whitespace)])));
+        assert!(comparator.same("GET /api/users", "get /api/user"));
+        assert!(comparator.same("GET  /API", "get /api"));
+        assert!(!comparator.same("GET /api/users", "post /api/users"));
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+// 並べ替えていない入力の重複除去: 隣接する行ではなく、これまでに現れたすべての行と比べる
+// 行そのものではなく64ビットのハッシュ値のみを保持するため、ごく稀に異なる行を重複とみなす可能性がある
+
+// 実行ごとに結果が変わらないよう、固定の鍵のハッシュ関数を使う
+pub fn hash_key(key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+// HashSet<u64>の1要素あたりのおおよそのメモリ使用量: 値と制御バイト
+const BYTES_PER_ENTRY: usize = 9;
+
+// 既出の行のハッシュ値の集合
+// 上限を指定した場合、集合が上限を超えるとブルームフィルタに切り替える: 以降は上限内に収まる代わりに、
+// 既出でない行を偽陽性により重複とみなして出力しない場合がある
+#[derive(Debug)]
+pub enum Seen {
+    Exact { hashes: HashSet<u64>, max_memory: Option<usize> },
+    Bloom(Bloom),
+}
+
+impl Seen {
+    pub fn new(max_memory: Option<usize>) -> Self {
+        Seen::Exact { hashes: HashSet::new(), max_memory }
+    }
+
+    // 初めて現れたハッシュ値であればtrue
+    pub fn insert(&mut self, hash: u64) -> bool {
+        match self {
+            Seen::Exact { hashes, max_memory } => {
+                let inserted = hashes.insert(hash);
+                if let Some(max) = *max_memory {
+                    if hashes.capacity() * BYTES_PER_ENTRY > max {
+                        let mut bloom = Bloom::new(max, hashes.len());
+                        hashes.iter().for_each(|&hash| {
+                            bloom.insert(hash);
+                        });
+                        *self = Seen::Bloom(bloom);
+                    }
+                }
+                inserted
+            }
+            Seen::Bloom(bloom) => bloom.insert(hash),
+        }
+    }
+}
+
+// ビット配列とk個のハッシュ関数によるブルームフィルタ
+#[derive(Debug)]
+pub struct Bloom {
+    bits: Vec<u64>,
+    num_hashes: u32,
+}
+
+impl Bloom {
+    // sizeバイトのビット配列: 切り替え時の件数の倍まで入ると見込んでハッシュ関数の数を決める
+    fn new(size: usize, count: usize) -> Self {
+        let words = (size / 8).max(1);
+        let bits_per_entry = (words * 64) as f64 / (count.max(1) * 2) as f64;
+        Bloom {
+            bits: vec![0; words],
+            num_hashes: (bits_per_entry * std::f64::consts::LN_2).round().clamp(1.0, 16.0) as u32,
+        }
+    }
+
+    // 64ビットのハッシュ値を上下32ビットに分け、二重ハッシュ法でk個の位置を求める
+    fn insert(&mut self, hash: u64) -> bool {
+        let num_bits = self.bits.len() as u64 * 64;
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let mut inserted = false;
+        for i in 0..self.num_hashes as u64 {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % num_bits;
+            let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+            if self.bits[word] & mask == 0 {
+                self.bits[word] |= mask;
+                inserted = true;
+            }
+        }
+        inserted
+    }
+}
+
+// -cで数える場合: 出力のために各行の初出の内容を保持する
+#[derive(Debug, Default)]
+pub struct Counter {
+    index: HashMap<u64, usize>,
+    counted: Vec<(usize, String)>, // 初出順の件数と行
+}
+
+impl Counter {
+    pub fn add(&mut self, hash: u64, line: String) {
+        match self.index.get(&hash) {
+            Some(&i) => self.counted[i].0 += 1,
+            None => {
+                self.index.insert(hash, self.counted.len());
+                self.counted.push((1, line));
+            }
+        }
+    }
+
+    pub fn into_counted(self) -> Vec<(usize, String)> {
+        self.counted
+    }
+}
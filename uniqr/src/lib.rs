@@ -1,14 +1,141 @@
-use std::{error::Error, io::{BufRead, BufReader, Write, stdin, stdout}, fs::File};
+use std::{cmp::{Ordering, Reverse}, collections::{BinaryHeap, HashMap, VecDeque}, error::Error, io::{self, BufRead, BufReader, Write, stderr, stdin, stdout}, fs::{self, File}, str::FromStr};
 
 use clap::{App, Arg};
 
+pub mod compare;
+use compare::{Any, Comparator, EditDistance, Exact, IgnoreCase, Whitespace};
+
+mod hashed;
+use hashed::{Counter, Seen};
+
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
-    in_file: String,
+    in_files: Vec<String>,
     out_file: Option<String>,
+    merge: bool, // 各入力をソート済みとみなしてマージする
     count: bool,
+    sort_by_count: Option<SortOrder>,
+    group: Option<GroupMethod>,
+    check_chars: Option<usize>,
+    fuzzy: Option<usize>, // 編集距離がN以下、または空白のみが異なる行を重複とみなす
+    ignore_case: bool, // 大文字と小文字の違いを無視して比較する
+    all_repeated: Option<RepeatedMethod>,
+    delimiter: u8, // 行の区切り文字: 出力も同じ区切り文字で終端する
+    hash: bool, // 並べ替えていない入力から、既出の行をすべて除く
+    max_memory: Option<usize>, // --hashで既出の行の記録に使うメモリの上限(バイト)
+    summary: Option<SummaryStream>, // 処理後に集計を出力する先
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GroupMethod {
+    Separate, // グループ間に空行
+    Prepend,  // 各グループの前に空行
+    Append,   // 各グループの後に空行
+    Both,     // 各グループの前と最終グループの後に空行
+}
+
+impl FromStr for GroupMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "separate" => Ok(GroupMethod::Separate),
+            "prepend" => Ok(GroupMethod::Prepend),
+            "append" => Ok(GroupMethod::Append),
+            "both" => Ok(GroupMethod::Both),
+            _ => Err(format!("invalid argument '{}' for '--group'", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RepeatedMethod {
+    Plain,    // 区切りなし
+    Prepend,  // 各グループの前に空行
+    Separate, // グループ間に空行
+}
+
+impl FromStr for RepeatedMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(RepeatedMethod::Plain),
+            "prepend" => Ok(RepeatedMethod::Prepend),
+            "separate" => Ok(RepeatedMethod::Separate),
+            _ => Err(format!("invalid argument '{}' for '--all-repeated'", s)),
+        }
+    }
+}
+
+// -cの出力を出現回数で並べ替える順序: 同じ回数のグループは入力順のまま
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            _ => Err(format!("invalid argument '{}' for '--sort-by-count'", s)),
+        }
+    }
+}
+
+// --summaryの出力先: 出力ファイルを指定した場合も標準出力に出力する
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SummaryStream {
+    Stdout,
+    Stderr,
+}
+
+impl FromStr for SummaryStream {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stdout" => Ok(SummaryStream::Stdout),
+            "stderr" => Ok(SummaryStream::Stderr),
+            _ => Err(format!("invalid argument '{}' for '--summary'", s)),
+        }
+    }
+}
+
+// --summaryで出力する集計: 重複を判定したグループ単位で数える
+#[derive(Debug, Default)]
+struct Summary {
+    lines: usize,            // 読み込んだ行数
+    unique: usize,           // グループの数: 重複を除いた出力の行数
+    duplicate_groups: usize, // 2行以上からなるグループの数
+    max_group: usize,        // 最大のグループの行数
+}
+
+impl Summary {
+    fn add_group(&mut self, size: usize) {
+        if size == 0 {
+            return;
+        }
+        self.lines += size;
+        self.unique += 1;
+        if size > 1 {
+            self.duplicate_groups += 1;
+        }
+        self.max_group = self.max_group.max(size);
+    }
+}
+
+fn write_summary(out: &mut impl Write, summary: &Summary) -> io::Result<()> {
+    writeln!(out, "lines: {}", summary.lines)?;
+    writeln!(out, "unique: {}", summary.unique)?;
+    writeln!(out, "duplicate groups: {}", summary.duplicate_groups)?;
+    writeln!(out, "max group size: {}", summary.max_group)
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -17,15 +144,26 @@ pub fn get_args() -> MyResult<Config> {
         .author("kazuki.ogiwara")
         .about("Rust uniq")
         .arg(
-            Arg::with_name("in_file")
-                .value_name("IN_FILE")
-                .help("Input file")
+            Arg::with_name("in_files")
+                .value_name("FILE")
+                .help("Input file(s); with exactly two files and no -o/--merge, the second is the output file and must be new or empty")
+                .multiple(true)
                 .default_value("-"),
         )
         .arg(
-            Arg::with_name("out_file")
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
                 .value_name("OUT_FILE")
-                .help("Output file"),
+                .help("Output file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("merge")
+                .short("m")
+                .long("merge")
+                .help("Merge already sorted input files instead of concatenating them")
+                .takes_value(false),
         )
         .arg(
             Arg::with_name("count")
@@ -34,67 +172,567 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Show counts")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("sort_by_count")
+                .long("sort-by-count")
+                .value_name("ORDER")
+                .help("With -c, print groups ordered by count [desc|asc]; keeps one line per group in memory until the input ends")
+                .takes_value(true)
+                .min_values(0) // 値の省略を許可: 省略時はdesc
+                .require_equals(true)
+                .requires("count"),
+        )
+        .arg(
+            Arg::with_name("group")
+                .long("group")
+                .value_name("METHOD")
+                .help("Show all items, separating groups with an empty line [separate|prepend|append|both]")
+                .takes_value(true)
+                .min_values(0) // 値の省略を許可: 省略時はseparate
+                .require_equals(true) // 後続の位置引数を値として消費しないように`--group=METHOD`の形式に限定
+                .conflicts_with("count"),
+        )
+        .arg(
+            Arg::with_name("check_chars")
+                .short("w")
+                .long("check-chars")
+                .value_name("N")
+                .help("Compare no more than N characters in lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("ignore_case")
+                .short("i")
+                .long("ignore-case")
+                .help("Ignore differences in case when comparing lines (also with --fuzzy)")
+                .takes_value(false)
+                .conflicts_with("hash"),
+        )
+        .arg(
+            Arg::with_name("fuzzy")
+                .long("fuzzy")
+                .value_name("N")
+                .help("Treat lines within edit distance N of the group's first line, or differing only in whitespace, as duplicates")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("all_repeated")
+                .short("D")
+                .long("all-repeated")
+                .value_name("METHOD")
+                .help("Print all duplicate lines [none|prepend|separate]")
+                .takes_value(true)
+                .min_values(0) // 値の省略を許可: 省略時はnone
+                .require_equals(true)
+                .conflicts_with_all(&["count", "group"]),
+        )
+        .arg(
+            Arg::with_name("zero_terminated")
+                .short("z")
+                .long("zero-terminated")
+                .help("Line delimiter is NUL, not newline")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("delimiter")
+                .long("delimiter")
+                .value_name("CHAR")
+                .help("Line delimiter (a single byte, or \\0, \\t, \\n)")
+                .takes_value(true)
+                .conflicts_with("zero_terminated"),
+        )
+        .arg(
+            Arg::with_name("hash")
+                .long("hash")
+                .help("Remove every repeated line, not only adjacent ones, without requiring sorted input")
+                .takes_value(false)
+                .conflicts_with_all(&["group", "all_repeated", "fuzzy"]),
+        )
+        .arg(
+            Arg::with_name("max_memory")
+                .long("max-memory")
+                .value_name("SIZE")
+                .help("With --hash, switch to a Bloom filter of SIZE bytes (K, M or G suffix) once seen lines need more; a few unique lines may then be dropped")
+                .takes_value(true)
+                .requires("hash")
+                .conflicts_with("count"),
+        )
+        .arg(
+            Arg::with_name("summary")
+                .long("summary")
+                .value_name("STREAM")
+                .help("After processing, print the number of lines, unique lines, duplicate groups and the largest group size [stderr|stdout]")
+                .takes_value(true)
+                .min_values(0) // 値の省略を許可: 省略時はstderr
+                .require_equals(true)
+                .conflicts_with("max_memory"), // 上限内では各行の件数を数えられない
+        )
         .get_matches();
 
+    let group = if matches.is_present("group") {
+        Some(
+            matches.value_of("group")
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or(GroupMethod::Separate),
+        )
+    } else {
+        None
+    };
+
+    let sort_by_count = if matches.is_present("sort_by_count") {
+        Some(
+            matches.value_of("sort_by_count")
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or(SortOrder::Desc),
+        )
+    } else {
+        None
+    };
+
+    let check_chars = matches
+        .value_of("check_chars")
+        .map(|val| {
+            val.parse::<usize>()
+                .map_err(|_| format!("{}: invalid number of bytes to compare", val))
+        })
+        .transpose()?;
+
+    let fuzzy = matches
+        .value_of("fuzzy")
+        .map(|val| {
+            val.parse::<usize>()
+                .map_err(|_| format!("invalid argument '{}' for '--fuzzy'", val))
+        })
+        .transpose()?;
+
+    let all_repeated = if matches.is_present("all_repeated") {
+        Some(
+            matches.value_of("all_repeated")
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or(RepeatedMethod::Plain),
+        )
+    } else {
+        None
+    };
+
+    let delimiter = if matches.is_present("zero_terminated") {
+        b'\0'
+    } else {
+        matches.value_of("delimiter")
+            .map(parse_delimiter)
+            .transpose()?
+            .unwrap_or(b'\n')
+    };
+
+    let summary = if matches.is_present("summary") {
+        Some(
+            matches.value_of("summary")
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or(SummaryStream::Stderr),
+        )
+    } else {
+        None
+    };
+
+    let max_memory = matches.value_of("max_memory")
+        .map(parse_size)
+        .transpose()?;
+
+    // 従来のIN_FILE OUT_FILE形式: -oも--mergeもなく2つ指定された場合は2つ目を出力先とする
+    // 入力のつもりで指定したファイルを消さないよう、内容のある既存のファイルは上書きしない
+    let merge = matches.is_present("merge");
+    let mut in_files = matches.values_of_lossy("in_files").unwrap();
+    let out_file = match matches.value_of("output") {
+        Some(out_file) => Some(out_file.to_string()),
+        None if in_files.len() == 2 && !merge => {
+            let out_file = in_files.pop().unwrap();
+            if fs::metadata(&out_file).is_ok_and(|meta| meta.len() > 0) {
+                return Err(format!(
+                    "{}: refusing to overwrite a non-empty file; use -o to write to it, or -o - to read it as an input",
+                    out_file
+                )
+                .into());
+            }
+            Some(out_file)
+        }
+        None => None,
+    };
+
     Ok(
         Config {
-            in_file: matches.value_of_lossy("in_file").map(Into::into).unwrap(),
-            out_file: matches.value_of_lossy("out_file").map(String::from), // Optionのまま中身をCowからStringに変換
-            count: matches.is_present("count")
+            in_files,
+            out_file,
+            merge,
+            count: matches.is_present("count"),
+            sort_by_count,
+            group,
+            check_chars,
+            fuzzy,
+            ignore_case: matches.is_present("ignore_case"),
+            all_repeated,
+            delimiter,
+            hash: matches.is_present("hash"),
+            max_memory,
+            summary,
         }
     )
 }
 
+// 区切り文字は1バイトの文字か、エスケープ表記(\0, \t, \n)で指定する
+fn parse_delimiter(val: &str) -> MyResult<u8> {
+    match val.as_bytes() {
+        [byte] => Ok(*byte),
+        b"\\0" => Ok(b'\0'),
+        b"\\t" => Ok(b'\t'),
+        b"\\n" => Ok(b'\n'),
+        _ => Err(format!("invalid argument '{}' for '--delimiter'", val).into()),
+    }
+}
+
+// メモリの上限: 1024倍ごとの単位(K, M, G)を付けられる
+fn parse_size(val: &str) -> MyResult<usize> {
+    let (num, unit) = match val.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&val[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&val[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&val[..i], 1 << 30),
+        _ => (val, 1),
+    };
+    num.parse::<usize>()
+        .ok()
+        .and_then(|num| num.checked_mul(unit))
+        .filter(|&size| size > 0)
+        .ok_or_else(|| format!("invalid argument '{}' for '--max-memory'", val).into())
+}
+
+// 同一とみなす行が連続するグループ: 行は先頭行のみ、または--groupと--all-repeatedでは全ての行を保持する
+#[derive(Debug, Default)]
+struct Group {
+    lines: Vec<String>,
+    count: usize,
+}
+
 pub fn run(config: Config) -> MyResult<()> {
-    let mut file = open(&config.in_file)
-        .map_err(|e| format!("{}: {}", config.in_file, e))?;
+    let mut readers = vec![];
+    for filename in &config.in_files {
+        let file = open(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        readers.push(LineReader { file, delimiter: config.delimiter });
+    }
+    let lines: Box<dyn Iterator<Item = MyResult<String>>> = if config.merge {
+        Box::new(Merge::new(readers)?)
+    } else {
+        Box::new(Concat { readers: readers.into() })
+    };
 
-    let mut out_file: Box<dyn Write> = match &config.out_file {
+    let mut out_file: Box<dyn Write> = match config.out_file.as_deref() {
+        Some("-") | None => Box::new(stdout()),
         Some(out_filename) => Box::new(File::create(out_filename)?),
-        _ => Box::new(stdout()),
     };
 
+    let mut summary = Summary::default();
+    if config.hash {
+        run_hashed(&config, lines, &mut out_file, &mut summary)?;
+        return report(config.summary, &summary, &mut out_file);
+    }
+
+    let mut num_groups: u64 = 0;
+
+    // 並べ替える場合は全グループを読み終えるまで出力できないため、各グループの先頭行と件数のみを保持する
+    let mut counted: Vec<(usize, String)> = vec![];
+
     // mutableでなければコンパイルエラーになる: (外部から所有している)out_fileの内容が(追記されるごとに)変化するため
-    let mut write = |count: u64, text: &str| -> MyResult<()> {
-        if count > 0 {
-            if config.count {
-                write!(out_file, "{:>4} {}", count, text)?;
-            } else {
-                write!(out_file, "{}", text)?;
+    // 1行ずつではなく、同一行のグループ全体を受け取って出力する
+    let mut write = |group: &Group| -> MyResult<()> {
+        summary.add_group(group.count);
+        if let Some(first) = group.lines.first() {
+            if let Some(method) = config.all_repeated {
+                // 重複のない行は出力しない
+                if group.count > 1 {
+                    if method == RepeatedMethod::Prepend
+                        || (method == RepeatedMethod::Separate && num_groups > 0)
+                    {
+                        out_file.write_all(&[config.delimiter])?;
+                    }
+                    for line in &group.lines {
+                        write!(out_file, "{}", line)?;
+                    }
+                    num_groups += 1;
+                }
+                return Ok(());
+            }
+            match config.group {
+                Some(method) => {
+                    if method == GroupMethod::Prepend
+                        || method == GroupMethod::Both
+                        || (method == GroupMethod::Separate && num_groups > 0)
+                    {
+                        out_file.write_all(&[config.delimiter])?;
+                    }
+                    for line in &group.lines {
+                        write!(out_file, "{}", line)?;
+                    }
+                    if method == GroupMethod::Append {
+                        out_file.write_all(&[config.delimiter])?;
+                    }
+                }
+                None if config.count && config.sort_by_count.is_some() => counted.push((group.count, first.clone())),
+                None if config.count => write!(out_file, "{:>4} {}", group.count, first)?,
+                None => write!(out_file, "{}", first)?,
             }
+            num_groups += 1;
         };
         Ok(())
     };
 
-    let mut line = String::new();
-    let mut previous = String::new();
-    let mut count: u64 = 0;
+    let comparator: Box<dyn Comparator> = match config.fuzzy {
+        Some(max) => Box::new(Any(vec![Box::new(EditDistance(max)), Box::new(Whitespace)])),
+        None => Box::new(Exact),
+    };
+    let comparator = if config.ignore_case {
+        Box::new(IgnoreCase(comparator))
+    } else {
+        comparator
+    };
 
-    loop {
-        let bytes = file.read_line(&mut line)?;
-        if bytes == 0 {
-            break;
+    // 全ての行を出力する場合のみ、グループの2行目以降も保持する
+    let keep_lines = config.group.is_some() || config.all_repeated.is_some();
+    let mut group = Group::default();
+
+    for line in lines {
+        let line = line?;
+        // グループの先頭行と比較する: 近い行が連なって先頭から離れていくことを防ぐ
+        if group.lines.first().is_some_and(|first| {
+            !comparator.same(
+                compare_key(&line, config.delimiter, config.check_chars),
+                compare_key(first, config.delimiter, config.check_chars),
+            )
+        }) {
+            write(&group)?;
+            group = Group::default(); // グループをリセット
         }
-        if line.trim_end() != previous.trim_end() {
-            // if count > 0 { // 先頭行で即出力されないように条件分岐
-            //     print!("{:>4} {}", count, previous);
-            // }
-            write(count, &previous)?;
-            previous = line.clone();
-            count = 0; // カウントをリセット
+        if keep_lines || group.lines.is_empty() {
+            group.lines.push(line);
         }
-        count += 1;
-        line.clear();
+        group.count += 1;
     }
 
-    // if count > 0 { // 先頭行と最終行が出力されないことを防止するために条件分岐
-    //     print!("{:>4} {}", count, previous);
-    // }
-    write(count, &previous)?;
+    // 最終グループが出力されないことを防止する: 空の場合は何も出力しない
+    write(&group)?;
 
+    if config.group == Some(GroupMethod::Both) && num_groups > 0 {
+        out_file.write_all(&[config.delimiter])?; // 最終グループの後に空行
+    }
+
+    if let Some(order) = config.sort_by_count {
+        sort_counted(&mut counted, order);
+        write_counted(&mut out_file, counted, config.delimiter)?;
+    }
+
+    report(config.summary, &summary, &mut out_file)
+}
+
+// 集計を指定した出力先に出力する: 標準出力の場合は重複除去の結果の後に出力する
+fn report(stream: Option<SummaryStream>, summary: &Summary, out_file: &mut impl Write) -> MyResult<()> {
+    match stream {
+        Some(SummaryStream::Stdout) => {
+            out_file.flush()?;
+            write_summary(&mut stdout(), summary)?
+        }
+        Some(SummaryStream::Stderr) => write_summary(&mut stderr(), summary)?,
+        None => {}
+    }
     Ok(())
 }
 
+// --hash: 初めて現れた行のみを入力順に出力する
+// -cの場合は入力を読み終えてから初出順(または件数順)に出力する
+fn run_hashed(
+    config: &Config,
+    lines: impl Iterator<Item = MyResult<String>>,
+    out_file: &mut impl Write,
+    summary: &mut Summary,
+) -> MyResult<()> {
+    if config.count {
+        let mut counter = Counter::default();
+        for line in lines {
+            let line = line?;
+            counter.add(hashed::hash_key(compare_key(&line, config.delimiter, config.check_chars)), line);
+        }
+        let mut counted = counter.into_counted();
+        counted.iter().for_each(|(count, _)| summary.add_group(*count));
+        if let Some(order) = config.sort_by_count {
+            sort_counted(&mut counted, order);
+        }
+        return write_counted(out_file, counted, config.delimiter);
+    }
+
+    // 集計する場合のみ、既出の行の件数を数える
+    let mut seen = Seen::new(config.max_memory);
+    let mut counts: HashMap<u64, usize> = HashMap::new();
+    for line in lines {
+        let line = line?;
+        let hash = hashed::hash_key(compare_key(&line, config.delimiter, config.check_chars));
+        if config.summary.is_some() {
+            *counts.entry(hash).or_default() += 1;
+        }
+        if seen.insert(hash) {
+            write!(out_file, "{}", line)?;
+        }
+    }
+    counts.values().for_each(|&count| summary.add_group(count));
+    Ok(())
+}
+
+// 安定ソートで同じ件数のグループは入力順を保つ
+fn sort_counted(counted: &mut [(usize, String)], order: SortOrder) {
+    match order {
+        SortOrder::Asc => counted.sort_by_key(|(count, _)| *count),
+        SortOrder::Desc => counted.sort_by_key(|(count, _)| Reverse(*count)),
+    }
+}
+
+fn write_counted(out_file: &mut impl Write, counted: Vec<(usize, String)>, delimiter: u8) -> MyResult<()> {
+    for (count, mut line) in counted {
+        // 区切り文字のない最終行が並べ替えで途中に来ても次の行とつながらないようにする
+        if !line.ends_with(delimiter as char) {
+            line.push(delimiter as char);
+        }
+        write!(out_file, "{:>4} {}", count, line)?;
+    }
+    Ok(())
+}
+
+// 比較対象となる行の先頭部分: 末尾の改行を除き、最大N文字まで
+fn compare_key(line: &str, delimiter: u8, check_chars: Option<usize>) -> &str {
+    let line = line.strip_suffix(delimiter as char).unwrap_or(line).trim_end();
+    match check_chars.and_then(|n| line.char_indices().nth(n)) {
+        Some((end, _)) => &line[..end],
+        None => line,
+    }
+}
+
+// 入力ファイルを区切り文字ごとに読み込む
+struct LineReader {
+    file: Box<dyn BufRead>,
+    delimiter: u8,
+}
+
+impl LineReader {
+    // 区切り文字を含めた1行を返す: 末尾の行のみ区切り文字がない場合がある
+    fn next_line(&mut self) -> MyResult<Option<String>> {
+        let mut buf = vec![];
+        let bytes = self.file.read_until(self.delimiter, &mut buf)?; // 区切り文字までをバイト配列として読み込む
+        if bytes == 0 {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(&buf).into_owned()))
+    }
+
+    // 他の入力の行と続けて出力するため、区切り文字がなければ補う
+    fn terminate(&self, mut line: String) -> String {
+        if !line.ends_with(self.delimiter as char) {
+            line.push(self.delimiter as char);
+        }
+        line
+    }
+}
+
+// 入力ファイルを順に連結して読み込む
+struct Concat {
+    readers: VecDeque<LineReader>,
+}
+
+impl Iterator for Concat {
+    type Item = MyResult<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let reader = self.readers.front_mut()?;
+            match reader.next_line() {
+                Ok(None) => {
+                    self.readers.pop_front();
+                }
+                // 最後の入力以外は末尾の行が次の入力の先頭行とつながらないようにする
+                Ok(Some(line)) if self.readers.len() > 1 => {
+                    return Some(Ok(self.readers[0].terminate(line)))
+                }
+                res => return res.transpose(),
+            }
+        }
+    }
+}
+
+// マージ中の各入力の先頭行: 区切り文字を除いた内容で比較し、同じ内容なら先に指定された入力を優先する
+struct Head {
+    line: String,
+    source: usize,
+    delimiter: char,
+}
+
+impl Head {
+    fn key(&self) -> (&str, usize) {
+        (self.line.strip_suffix(self.delimiter).unwrap_or(&self.line), self.source)
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+// ソート済みの入力ファイルをヒープでk-wayマージする
+struct Merge {
+    readers: Vec<LineReader>,
+    heap: BinaryHeap<Reverse<Head>>, // 最小の行を取り出すためReverseで包む
+}
+
+impl Merge {
+    fn new(mut readers: Vec<LineReader>) -> MyResult<Self> {
+        let mut heap = BinaryHeap::new();
+        for (source, reader) in readers.iter_mut().enumerate() {
+            if let Some(line) = reader.next_line()? {
+                let delimiter = reader.delimiter as char;
+                heap.push(Reverse(Head { line: reader.terminate(line), source, delimiter }));
+            }
+        }
+        Ok(Merge { readers, heap })
+    }
+}
+
+impl Iterator for Merge {
+    type Item = MyResult<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse(Head { line, source, delimiter }) = self.heap.pop()?;
+        // 取り出した行の入力から次の行を補充する
+        let reader = &mut self.readers[source];
+        match reader.next_line() {
+            Ok(Some(next)) => self.heap.push(Reverse(Head { line: reader.terminate(next), source, delimiter })),
+            Ok(None) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        Some(Ok(line))
+    }
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(stdin()))),
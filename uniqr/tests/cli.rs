@@ -124,7 +124,7 @@ fn run(test: &Test) -> TestResult {
 fn run_count(test: &Test) -> TestResult {
     let expected = fs::read_to_string(test.out_count)?;
     Command::cargo_bin(PRG)?
-        .args(&[test.input, "-c"])
+        .args([test.input, "-c"])
         .assert()
         .success()
         .stdout(expected);
@@ -162,12 +162,12 @@ fn run_outfile(test: &Test) -> TestResult {
     let outfile = NamedTempFile::new()?;
     let outpath = &outfile.path().to_str().unwrap();
     Command::cargo_bin(PRG)?
-        .args(&[test.input, outpath])
+        .args([test.input, outpath])
         .assert()
         .success()
         .stdout("");
 
-    let contents = fs::read_to_string(&outpath)?;
+    let contents = fs::read_to_string(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -179,13 +179,13 @@ fn run_outfile_count(test: &Test) -> TestResult {
     let outpath = &outfile.path().to_str().unwrap();
 
     Command::cargo_bin(PRG)?
-        .args(&[test.input, outpath, "--count"])
+        .args([test.input, outpath, "--count"])
         .assert()
         .success()
         .stdout("");
 
     let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(&outpath)?;
+    let contents = fs::read_to_string(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -198,13 +198,13 @@ fn run_stdin_outfile_count(test: &Test) -> TestResult {
     let outpath = &outfile.path().to_str().unwrap();
 
     Command::cargo_bin(PRG)?
-        .args(&["-", outpath, "-c"])
+        .args(["-", outpath, "-c"])
         .write_stdin(input)
         .assert()
         .stdout("");
 
     let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(&outpath)?;
+    let contents = fs::read_to_string(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -605,3 +605,535 @@ fn t6_outfile_count() -> TestResult {
 fn t6_stdin_outfile_count() -> TestResult {
     run_stdin_outfile_count(&T6)
 }
+
+// --------------------------------------------------
+fn run_group(input: &str, args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read_to_string(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .arg(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn t5_group() -> TestResult {
+    run_group(T5.input, &["--group"], "tests/expected/t5.txt.group.out")
+}
+
+#[test]
+fn three_group_separate() -> TestResult {
+    run_group(
+        THREE.input,
+        &["--group=separate"],
+        "tests/expected/three.txt.group.separate.out",
+    )
+}
+
+#[test]
+fn three_group_prepend() -> TestResult {
+    run_group(
+        THREE.input,
+        &["--group=prepend"],
+        "tests/expected/three.txt.group.prepend.out",
+    )
+}
+
+#[test]
+fn three_group_append() -> TestResult {
+    run_group(
+        THREE.input,
+        &["--group=append"],
+        "tests/expected/three.txt.group.append.out",
+    )
+}
+
+#[test]
+fn three_group_both() -> TestResult {
+    run_group(
+        THREE.input,
+        &["--group=both"],
+        "tests/expected/three.txt.group.both.out",
+    )
+}
+
+#[test]
+fn empty_group_both() -> TestResult {
+    run_group(EMPTY.input, &["--group=both"], EMPTY.out)
+}
+
+#[test]
+fn dies_bad_group_method() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--group=foo", T5.input])
+        .assert()
+        .failure()
+        .stderr("invalid argument 'foo' for '--group'\n");
+    Ok(())
+}
+
+#[test]
+fn dies_group_with_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--group", "-c", T5.input])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn three_sort_by_count() -> TestResult {
+    run_group(
+        THREE.input,
+        &["-c", "--sort-by-count"],
+        "tests/expected/three.txt.c.sort_desc.out",
+    )
+}
+
+#[test]
+fn three_sort_by_count_asc() -> TestResult {
+    run_group(
+        THREE.input,
+        &["-c", "--sort-by-count=asc"],
+        "tests/expected/three.txt.c.sort_asc.out",
+    )
+}
+
+#[test]
+fn t4_sort_by_count_asc() -> TestResult {
+    // 改行のない最終行が先頭に並んでも次の行とつながらない
+    run_group(
+        T4.input,
+        &["--sort-by-count=asc", "-c"],
+        "tests/expected/t4.txt.c.sort_asc.out",
+    )
+}
+
+#[test]
+fn dies_bad_sort_by_count_order() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--sort-by-count=foo", T5.input])
+        .assert()
+        .failure()
+        .stderr("invalid argument 'foo' for '--sort-by-count'\n");
+    Ok(())
+}
+
+#[test]
+fn dies_sort_by_count_without_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--sort-by-count", T5.input])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn prefix_check_chars() -> TestResult {
+    run_group(
+        "tests/inputs/prefix.txt",
+        &["-w", "5"],
+        "tests/expected/prefix.txt.w5.out",
+    )
+}
+
+#[test]
+fn prefix_check_chars_count() -> TestResult {
+    run_group(
+        "tests/inputs/prefix.txt",
+        &["--check-chars", "5", "-c"],
+        "tests/expected/prefix.txt.w5.c.out",
+    )
+}
+
+#[test]
+fn dies_bad_check_chars() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-w", "foo", T5.input])
+        .assert()
+        .failure()
+        .stderr("foo: invalid number of bytes to compare\n");
+    Ok(())
+}
+
+#[test]
+fn three_all_repeated() -> TestResult {
+    run_group(THREE.input, &["-D"], "tests/expected/three.txt.D.out")
+}
+
+#[test]
+fn three_all_repeated_prepend() -> TestResult {
+    run_group(
+        THREE.input,
+        &["--all-repeated=prepend"],
+        "tests/expected/three.txt.D.prepend.out",
+    )
+}
+
+#[test]
+fn three_all_repeated_separate() -> TestResult {
+    run_group(
+        THREE.input,
+        &["--all-repeated=separate"],
+        "tests/expected/three.txt.D.separate.out",
+    )
+}
+
+#[test]
+fn prefix_all_repeated_separate_check_chars() -> TestResult {
+    run_group(
+        "tests/inputs/prefix.txt",
+        &["--all-repeated=separate", "-w", "6"],
+        "tests/expected/prefix.txt.D.separate.w6.out",
+    )
+}
+
+#[test]
+fn dies_bad_all_repeated_method() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--all-repeated=foo", T5.input])
+        .assert()
+        .failure()
+        .stderr("invalid argument 'foo' for '--all-repeated'\n");
+    Ok(())
+}
+
+#[test]
+fn dies_all_repeated_with_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-D", "-c", T5.input])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn zero_terminated() -> TestResult {
+    run_group("tests/inputs/zero.txt", &["-z"], "tests/expected/zero.txt.z.out")
+}
+
+#[test]
+fn zero_terminated_count() -> TestResult {
+    run_group(
+        "tests/inputs/zero.txt",
+        &["--zero-terminated", "-c"],
+        "tests/expected/zero.txt.z.c.out",
+    )
+}
+
+#[test]
+fn delimiter_escape() -> TestResult {
+    run_group(
+        "tests/inputs/zero.txt",
+        &["--delimiter", "\\0"],
+        "tests/expected/zero.txt.z.out",
+    )
+}
+
+#[test]
+fn delimiter_group() -> TestResult {
+    run_group(
+        "tests/inputs/semicolon.txt",
+        &["--delimiter", ";", "--group"],
+        "tests/expected/semicolon.txt.delimiter.group.out",
+    )
+}
+
+#[test]
+fn dies_bad_delimiter() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--delimiter", "ab", T5.input])
+        .assert()
+        .failure()
+        .stderr("invalid argument 'ab' for '--delimiter'\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+const SORTED1: &str = "tests/inputs/sorted1.txt";
+const SORTED2: &str = "tests/inputs/sorted2.txt";
+
+fn run_multiple(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read_to_string(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn multiple_concat() -> TestResult {
+    // 1つ目の末尾の行に改行がなくても次の入力の行とはつながらない
+    run_multiple(
+        &["-c", "-o", "-", SORTED1, SORTED2],
+        "tests/expected/sorted.txt.concat.c.out",
+    )
+}
+
+#[test]
+fn dies_two_files_overwrite() -> TestResult {
+    // 2つ目のファイルは出力先とみなすが、内容があれば入力の指定誤りとして上書きしない
+    let dir = tempfile::tempdir()?;
+    let second = dir.path().join("b.log");
+    fs::write(&second, "b\n")?;
+    let second = second.to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args([SORTED1, second])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with(format!(
+            "{}: refusing to overwrite a non-empty file",
+            second
+        )));
+    assert_eq!(fs::read_to_string(second)?, "b\n");
+
+    // -oを指定すれば両方を入力として読み込む
+    Command::cargo_bin(PRG)?
+        .args(["-o", "-", SORTED1, second])
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with("b\n"));
+    Ok(())
+}
+
+#[test]
+fn multiple_merge() -> TestResult {
+    run_multiple(
+        &["-c", "--merge", SORTED1, SORTED2],
+        "tests/expected/sorted.txt.merge.c.out",
+    )
+}
+
+#[test]
+fn multiple_merge_outfile() -> TestResult {
+    let outfile = NamedTempFile::new()?;
+    let outpath = &outfile.path().to_str().unwrap();
+    Command::cargo_bin(PRG)?
+        .args(["-m", "-c", SORTED2, SORTED1, SORTED2, "--output", outpath])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(fs::read_to_string(outpath)?, "   3 a\n   2 b\n   2 c\n   3 d\n");
+    Ok(())
+}
+
+#[test]
+fn dies_bad_file_merge() -> TestResult {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--merge", SORTED1, &bad])
+        .assert()
+        .failure()
+        .stderr(predicate::str::starts_with(format!("{}: ", bad)));
+    Ok(())
+}
+
+// --------------------------------------------------
+const FUZZY: &str = "tests/inputs/fuzzy.txt";
+
+#[test]
+fn fuzzy() -> TestResult {
+    run_group(FUZZY, &["--fuzzy", "1"], "tests/expected/fuzzy.txt.fuzzy1.out")
+}
+
+#[test]
+fn fuzzy_count() -> TestResult {
+    // 2行目は編集距離1、3行目は空白のみの違いで先頭行と同じグループになる
+    run_group(FUZZY, &["-c", "--fuzzy=1"], "tests/expected/fuzzy.txt.fuzzy1.c.out")
+}
+
+#[test]
+fn fuzzy_zero_ignores_whitespace() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--fuzzy", "0"])
+        .write_stdin("a b\na  b\nab\nac\n")
+        .assert()
+        .success()
+        .stdout("   3 a b\n   1 ac\n");
+    Ok(())
+}
+
+#[test]
+fn ignore_case() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-c", "-i"])
+        .write_stdin("Apple\napple\nAPPLE\nbanana\nBanana!\n")
+        .assert()
+        .success()
+        .stdout("   3 Apple\n   1 banana\n   1 Banana!\n");
+
+    // --fuzzyと組み合わせると、大文字と小文字の違いを除いた編集距離で比較する
+    Command::cargo_bin(PRG)?
+        .args(["-c", "--ignore-case", "--fuzzy", "1"])
+        .write_stdin("GET /api/users\nget /api/user\nPOST /api/users\n")
+        .assert()
+        .success()
+        .stdout("   2 GET /api/users\n   1 POST /api/users\n");
+    Ok(())
+}
+
+#[test]
+fn dies_ignore_case_with_hash() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-i", "--hash", FUZZY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_fuzzy() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--fuzzy", "x", FUZZY])
+        .assert()
+        .failure()
+        .stderr("invalid argument 'x' for '--fuzzy'\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+const UNSORTED: &str = "tests/inputs/unsorted.txt";
+
+#[test]
+fn hash() -> TestResult {
+    run_group(UNSORTED, &["--hash"], "tests/expected/unsorted.txt.hash.out")
+}
+
+#[test]
+fn hash_count() -> TestResult {
+    // 件数は初出の位置に出力する: 最終行の区切り文字は補う
+    run_group(UNSORTED, &["--hash", "-c"], "tests/expected/unsorted.txt.hash.c.out")
+}
+
+#[test]
+fn hash_count_sort_by_count() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--hash", "-c", "--sort-by-count=asc"])
+        .write_stdin("x\ny\nx\nz\ny\nx\n")
+        .assert()
+        .success()
+        .stdout("   1 z\n   2 y\n   3 x\n");
+    Ok(())
+}
+
+#[test]
+fn hash_multiple_files() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--hash", "-m", SORTED1, SORTED2, UNSORTED])
+        .assert()
+        .success()
+        .stdout(predicate::function(|out: &str| {
+            let lines: Vec<_> = out.lines().collect();
+            let mut unique = lines.clone();
+            unique.sort();
+            unique.dedup();
+            lines.len() == unique.len()
+        }));
+    Ok(())
+}
+
+#[test]
+fn hash_max_memory() -> TestResult {
+    // 上限を超えた後はブルームフィルタで判定する: 重複は出力せず、偽陽性で除かれる行はごく僅か
+    let input: String = (0..2000).chain(0..2000).map(|n| format!("line {}\n", n)).collect();
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["--hash", "--max-memory", "4K"])
+        .write_stdin(input)
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let mut lines: Vec<_> = stdout.lines().collect();
+    assert!(lines.len() > 1980 && lines.len() <= 2000);
+    assert_eq!(lines[0], "line 0");
+    lines.sort();
+    lines.dedup();
+    assert!(lines.len() > 1980);
+    Ok(())
+}
+
+#[test]
+fn dies_bad_max_memory() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--hash", "--max-memory", "0", UNSORTED])
+        .assert()
+        .failure()
+        .stderr("invalid argument '0' for '--max-memory'\n");
+    Ok(())
+}
+
+#[test]
+fn dies_hash_with_group() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--hash", "--group", UNSORTED])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn summary() -> TestResult {
+    // 既定では標準エラーに出力し、重複除去の結果は変わらない
+    Command::cargo_bin(PRG)?
+        .args(["--summary", THREE.input])
+        .assert()
+        .success()
+        .stdout(fs::read_to_string(THREE.out)?)
+        .stderr("lines: 13\nunique: 6\nduplicate groups: 4\nmax group size: 4\n");
+
+    // 空の入力
+    Command::cargo_bin(PRG)?
+        .args(["--summary", "-c", EMPTY.input])
+        .assert()
+        .success()
+        .stdout("")
+        .stderr("lines: 0\nunique: 0\nduplicate groups: 0\nmax group size: 0\n");
+    Ok(())
+}
+
+#[test]
+fn summary_stdout() -> TestResult {
+    // 標準出力では結果の後に出力する
+    Command::cargo_bin(PRG)?
+        .args(["--summary=stdout", "-D"])
+        .write_stdin("x\nx\ny\nz\nz\nz\n")
+        .assert()
+        .success()
+        .stdout("x\nx\nz\nz\nz\nlines: 6\nunique: 3\nduplicate groups: 2\nmax group size: 3\n")
+        .stderr("");
+    Ok(())
+}
+
+#[test]
+fn summary_hash() -> TestResult {
+    // 隣接していない重複も同じグループとして数える
+    Command::cargo_bin(PRG)?
+        .args(["--hash", "--summary=stderr", THREE.input])
+        .assert()
+        .success()
+        .stderr("lines: 13\nunique: 4\nduplicate groups: 4\nmax group size: 4\n");
+    Command::cargo_bin(PRG)?
+        .args(["--hash", "-c", "--summary"])
+        .write_stdin("x\ny\nx\n")
+        .assert()
+        .success()
+        .stdout("   2 x\n   1 y\n")
+        .stderr("lines: 3\nunique: 2\nduplicate groups: 1\nmax group size: 2\n");
+    Ok(())
+}
+
+#[test]
+fn dies_bad_summary() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--summary=stdin", THREE.input])
+        .assert()
+        .failure()
+        .stderr("invalid argument 'stdin' for '--summary'\n");
+    Ok(())
+}
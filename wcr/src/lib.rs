@@ -1,6 +1,19 @@
-use std::{error::Error, io::{BufRead, stdin, BufReader}, fs::File};
+use std::{error::Error, io::{BufRead, stdin, BufReader}, fs::{self, File}, str::FromStr, time::Duration};
 
 use clap::{App, Arg};
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+mod output;
+mod tree;
+mod watch;
+mod words;
+
+use output::{Columns, Format, Printer};
+use tree::{Entry, Subtotals};
+use watch::Watched;
+pub use words::{Frequencies, Tokenizer};
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
@@ -11,14 +24,101 @@ pub struct Config {
     words: bool,
     bytes: bool,
     chars: bool,
+    graphemes: bool,
+    width: bool,
+    format: Format,
+    total: TotalPolicy,
+    interval: Option<Duration>,
+    recursive: bool,
+    max_depth: Option<usize>,
+    words_regex: Option<Regex>, // 単語の定義: 未指定の場合は空白文字で区切る
+    top: Option<usize>,         // 出現回数の多い単語を出力する数
+    stdin_label: Option<String>, // 標準入力の行に付ける名前
 }
 
-#[derive(Debug, PartialEq)]
+/// Counts for one input, as returned by [`count`]
+#[derive(Debug, PartialEq, Default, Clone)]
 pub struct FileInfo {
-    num_lines: usize,
-    num_words: usize,
-    num_bytes: usize,
-    num_chars: usize,
+    pub num_lines: usize,
+    pub num_words: usize,
+    pub num_bytes: usize,
+    pub num_chars: usize,
+    pub num_graphemes: usize, // CountOptions::graphemesを指定した場合のみ数える
+    pub num_width: usize,     // CountOptions::widthを指定した場合のみ数える
+}
+
+impl FileInfo {
+    /// Add the counts of another input, e.g. to build a total
+    pub fn add(&mut self, info: &FileInfo) {
+        self.num_lines += info.num_lines;
+        self.num_words += info.num_words;
+        self.num_bytes += info.num_bytes;
+        self.num_chars += info.num_chars;
+        self.num_graphemes += info.num_graphemes;
+        self.num_width += info.num_width;
+    }
+}
+
+// 合計行の出力方針
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TotalPolicy {
+    Auto,   // 複数ファイルの場合のみ出力
+    Always, // 常に出力
+    Only,   // 合計のみを出力: ファイルごとの行は出力しない
+    Never,  // 出力しない
+}
+
+impl FromStr for TotalPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(TotalPolicy::Auto),
+            "always" => Ok(TotalPolicy::Always),
+            "only" => Ok(TotalPolicy::Only),
+            "never" => Ok(TotalPolicy::Never),
+            _ => Err(format!("Invalid --total \"{}\"", s)),
+        }
+    }
+}
+
+// 全ファイルの合計
+#[derive(Debug, Default)]
+struct Totals {
+    info: FileInfo,
+    num_files: usize, // 指定されたファイル数: 読み込みに失敗したファイルも含む
+    words: Option<Frequencies>, // --top: 全ファイルの単語の出現回数
+}
+
+impl Totals {
+    fn new(num_files: usize) -> Self {
+        Totals {
+            num_files,
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, info: &FileInfo) {
+        self.info.add(info);
+    }
+
+    // 方針に従って合計を出力する場合のみ返す
+    fn report(&self, policy: TotalPolicy) -> Option<&FileInfo> {
+        match policy {
+            TotalPolicy::Auto if self.num_files > 1 => Some(&self.info),
+            TotalPolicy::Always | TotalPolicy::Only => Some(&self.info),
+            _ => None,
+        }
+    }
+}
+
+/// What [`count`] counts besides lines, words, bytes and characters
+// 計算コストの高いカウントを行うかどうか: 未指定の場合は0のままにする
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountOptions<'a> {
+    pub graphemes: bool,
+    pub width: bool,
+    pub tokenizer: Tokenizer<'a>,
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -62,77 +162,281 @@ pub fn get_args() -> MyResult<Config> {
                 .takes_value(false)
                 .conflicts_with("bytes"),
         )
+        .arg(
+            Arg::with_name("graphemes")
+                .long("graphemes")
+                .help("Show grapheme cluster count")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .help("Show display width in columns")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format [plain|json|tsv]")
+                .takes_value(true)
+                .default_value("plain"),
+        )
+        .arg(
+            Arg::with_name("total")
+                .long("total")
+                .value_name("WHEN")
+                .help("When to print a line with total counts [auto|always|only|never]")
+                .takes_value(true)
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .long("interval")
+                .value_name("SECONDS")
+                .help("Re-count the files every SECONDS and print updated counts")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .short("r")
+                .long("recursive")
+                .help("Count files in directories recursively, with a subtotal per directory")
+                .takes_value(false)
+                .conflicts_with("interval"),
+        )
+        .arg(
+            Arg::with_name("max_depth")
+                .long("max-depth")
+                .value_name("DEPTH")
+                .help("With --recursive, descend at most DEPTH levels below each directory")
+                .takes_value(true)
+                .requires("recursive"),
+        )
+        .arg(
+            Arg::with_name("words_regex")
+                .long("words-regex")
+                .value_name("REGEX")
+                .help("Count each match of REGEX as a word instead of whitespace-separated words")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("top")
+                .long("top")
+                .value_name("N")
+                .help("Also print the N most frequent words")
+                .takes_value(true)
+                .conflicts_with("interval"), // 監視中は追記部分のみを数えるため
+        )
+        .arg(
+            Arg::with_name("stdin_label")
+                .long("stdin-label")
+                .visible_alias("label")
+                .value_name("NAME")
+                .help("Print NAME instead of \"-\" for counts read from STDIN")
+                .takes_value(true),
+        )
         .get_matches();
 
     let mut lines = matches.is_present("lines");
     let mut words = matches.is_present("words");
     let mut bytes = matches.is_present("bytes");
     let chars = matches.is_present("chars");
+    let graphemes = matches.is_present("graphemes");
+    let width = matches.is_present("width");
 
     // if [words, bytes, chars, lines].iter().all(|v| v == &false) { // boolの参照を比較: 全てfalseの参照ならば条件に一致と判定
-    if [words, bytes, chars, lines].iter().all(|v| !v) {
+    if [words, bytes, chars, lines, graphemes, width].iter().all(|v| !v) {
             // 全てのフラグが未指定の場合のデフォルト設定
         lines = true;
         words = true;
         bytes = true;
     }
 
+    // デフォルト値があるのでunwrap可能
+    let format = matches.value_of("format").unwrap().parse()?;
+    let total = match matches.value_of("total").unwrap().parse()? {
+        // JSONは構造化されているので、autoでは1ファイルでも合計を含める
+        TotalPolicy::Auto if format == Format::Json => TotalPolicy::Always,
+        total => total,
+    };
+
+    let files = matches.values_of_lossy("files").unwrap();
+    let interval = matches.value_of("interval")
+        .map(|val| {
+            val.parse()
+                .ok()
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok()) // 負の値や無限大は不可
+                .ok_or_else(|| format!("Invalid --interval \"{}\"", val))
+        })
+        .transpose()?;
+    if interval.is_some() {
+        // 前回の位置から読み直せる通常のファイルのみ監視できる: JSONは全体を1つのオブジェクトとして出力するため不可
+        if files.iter().any(|file| file == "-") {
+            return Err(From::from("--interval cannot be used with STDIN"));
+        }
+        if format == Format::Json {
+            return Err(From::from("--interval cannot be used with --format json"));
+        }
+    }
+
+    let max_depth = matches.value_of("max_depth")
+        .map(|val| val.parse().map_err(|_| format!("Invalid --max-depth \"{}\"", val)))
+        .transpose()?;
+
+    let words_regex = matches.value_of("words_regex")
+        .map(|val| Regex::new(val).map_err(|_| format!("Invalid --words-regex \"{}\"", val)))
+        .transpose()?;
+    let top = matches.value_of("top")
+        .map(|val| {
+            val.parse()
+                .ok()
+                .filter(|&n: &usize| n > 0)
+                .ok_or_else(|| format!("Invalid --top \"{}\"", val))
+        })
+        .transpose()?;
+
     Ok(
         Config {
-            files: matches.values_of_lossy("files").unwrap(),
+            files,
             lines,
             words,
             bytes,
-            chars
+            chars,
+            graphemes,
+            width,
+            format,
+            total,
+            interval,
+            recursive: matches.is_present("recursive"),
+            max_depth,
+            words_regex,
+            top,
+            stdin_label: matches.value_of("stdin_label").map(String::from),
         }
     )
 }
 
 pub fn run(config: Config) -> MyResult<()> {
-    let mut total_num_lines = 0;
-    let mut total_num_words = 0;
-    let mut total_num_bytes = 0;
-    let mut total_num_chars = 0;
+    let mut totals = Totals::new(config.files.len());
+    if config.top.is_some() {
+        totals.words = Some(Frequencies::default());
+    }
+    let mut printer = Printer::new(
+        config.format,
+        Columns {
+            lines: config.lines,
+            words: config.words,
+            bytes: config.bytes,
+            chars: config.chars,
+            graphemes: config.graphemes,
+            width: config.width,
+        },
+    )
+    // ファイルと混在する場合は、ラベルの指定がなくても標準入力の行を"-"として区別する
+    .stdin_label(
+        config
+            .stdin_label
+            .clone()
+            .or_else(|| (config.files.len() > 1).then(|| "-".to_string())),
+    );
+    let options = CountOptions {
+        graphemes: config.graphemes,
+        width: config.width,
+        tokenizer: config.words_regex.as_ref().map_or(Tokenizer::Whitespace, Tokenizer::Regex),
+    };
+
+    if let Some(interval) = config.interval {
+        let files = config.files.iter().map(|file| Watched::new(file)).collect();
+        return watch::watch(files, interval, options, config.total, &mut printer);
+    }
 
+    // バイト数のみを出力する場合は、通常ファイルの読み込みを省略できる
+    let bytes_only = config.bytes
+        && !(config.lines || config.words || config.chars || config.graphemes || config.width)
+        && config.top.is_none();
+
+    let show_rows = config.total != TotalPolicy::Only;
     for filename in &config.files {
-        match open(filename) {
-            Err(e) => eprintln!("{}: {}", filename, e),
-            Ok(file) => {
-                if let Ok(info) = count(file) {
-                    println!(
-                        "{}{}{}{}{}",
-                        format_field(info.num_lines, config.lines),
-                        format_field(info.num_words, config.words),
-                        format_field(info.num_bytes, config.bytes),
-                        format_field(info.num_chars, config.chars),
-                        if filename == "-" {
-                            "".to_string()
-                        } else {
-                            format!(" {}", filename)
-                        }
-                    );
-                    total_num_lines += info.num_lines;
-                    total_num_words += info.num_words;
-                    total_num_bytes += info.num_bytes;
-                    total_num_chars += info.num_chars;
-                }
-            },
+        if config.recursive && filename != "-" && fs::metadata(filename).is_ok_and(|meta| meta.is_dir()) {
+            let num_files = count_tree(filename, config.max_depth, bytes_only, options, show_rows, &mut printer, &mut totals);
+            // 合計の出力判定ではディレクトリを中のファイル数として扱う
+            totals.num_files = totals.num_files - 1 + num_files;
+            continue;
+        }
+        if let Some(info) = count_file(filename, bytes_only, options, totals.words.as_mut()) {
+            if show_rows {
+                printer.file(filename, &info);
+            }
+            totals.add(&info);
         }
     }
 
-    if config.files.len() > 1 {
-        println!(
-            "{}{}{}{} total",
-            format_field(total_num_lines, config.lines),
-            format_field(total_num_words, config.words),
-            format_field(total_num_bytes, config.bytes),
-            format_field(total_num_chars, config.chars),
-        );
+    if let (Some(n), Some(words)) = (config.top, &totals.words) {
+        printer.top(&words.top(n));
     }
+    // 合計のみの場合はファイル名の代わりの"total"も付けない
+    printer.total(totals.report(config.total), config.total != TotalPolicy::Only)?;
 
     Ok(())
 }
 
+// 1ファイルを数える: 読み込めない場合はNone
+fn count_file(
+    filename: &str,
+    bytes_only: bool,
+    options: CountOptions,
+    words: Option<&mut Frequencies>,
+) -> Option<FileInfo> {
+    if let Some(info) = bytes_only.then(|| file_size(filename)).flatten() {
+        return Some(info);
+    }
+    match open(filename) {
+        Err(e) => {
+            eprintln!("{}: {}", filename, e);
+            None
+        },
+        Ok(file) => count(file, options, words).ok(),
+    }
+}
+
+// ディレクトリ内のファイルを再帰的に数え、各ディレクトリの中身の後にその小計を"DIR/"として出力する
+// 見つかったファイル数を返す
+fn count_tree(
+    dirname: &str,
+    max_depth: Option<usize>,
+    bytes_only: bool,
+    options: CountOptions,
+    show_rows: bool,
+    printer: &mut Printer,
+    totals: &mut Totals,
+) -> usize {
+    let mut subtotals = Subtotals::default();
+    let mut num_files = 0;
+    for entry in tree::walk(dirname, max_depth) {
+        match entry {
+            Err(e) => eprintln!("{}", e),
+            Ok(Entry::File { path, depth }) => {
+                num_files += 1;
+                if let Some(info) = count_file(&path, bytes_only, options, totals.words.as_mut()) {
+                    if show_rows {
+                        printer.file(&path, &info);
+                    }
+                    subtotals.add_file(depth, &info);
+                    totals.add(&info);
+                }
+            },
+            Ok(Entry::Dir { path, depth }) => {
+                let subtotal = subtotals.close_dir(depth);
+                if show_rows {
+                    printer.file(&format!("{}/", path.trim_end_matches('/')), &subtotal);
+                }
+            },
+        }
+    }
+    num_files
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(stdin()))),
@@ -140,11 +444,30 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
-fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
+// 通常ファイルのバイト数をメタデータから取得する: 標準入力やパイプなどはNoneとして読み込みに任せる
+fn file_size(filename: &str) -> Option<FileInfo> {
+    if filename == "-" {
+        return None;
+    }
+    fs::metadata(filename)
+        .ok()
+        .filter(|meta| meta.is_file())
+        .map(|meta| FileInfo {
+            num_bytes: meta.len() as usize,
+            ..Default::default()
+        })
+}
+
+/// Count the lines, words, bytes and characters of an input,
+/// also adding each word to `words` when given
+// wordsを指定した場合は単語ごとの出現回数も数える
+pub fn count(mut file: impl BufRead, options: CountOptions, mut words: Option<&mut Frequencies>) -> MyResult<FileInfo> {
     let mut num_lines = 0;
     let mut num_words = 0;
     let mut num_bytes = 0;
     let mut num_chars = 0;
+    let mut num_graphemes = 0;
+    let mut num_width = 0;
 
     let mut line = String::new();
 
@@ -154,9 +477,18 @@ fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
             break; // EOF
         }
         num_lines += 1;
-        num_words += line.split_whitespace().count(); // 空白文字の区切りでカウント
+        num_words += options.tokenizer.count(&line);
+        if let Some(words) = words.as_deref_mut() {
+            options.tokenizer.add_words(&line, words);
+        }
         num_bytes += line_bytes;
         num_chars += line.chars().count(); // Unicode文字の区切りでカウント
+        if options.graphemes {
+            num_graphemes += line.graphemes(true).count(); // 書記素クラスタの区切りでカウント: \r\nは1つ
+        }
+        if options.width {
+            num_width += line.trim_end_matches(['\r', '\n']).width(); // 改行コードを除いた表示幅
+        }
 
         line.clear();
     }
@@ -166,7 +498,9 @@ fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
             num_lines,
             num_words,
             num_bytes,
-            num_chars
+            num_chars,
+            num_graphemes,
+            num_width,
         }
     )
 }
@@ -182,14 +516,16 @@ fn format_field(value: usize, show: bool) -> String { // 可変なので&strで
 #[cfg(test)] // testの時のみにコンパイルされる
 mod tests {
 // testsモジュールとして定義
-    use super::{count, format_field, FileInfo}; // 親モジュール(wcr)からインポート
+    use super::{count, file_size, format_field, words::Tokenizer, CountOptions, FileInfo, TotalPolicy, Totals}; // 親モジュール(wcr)からインポート
     use std::io::Cursor;
 
     #[test]
     fn test_count() {
         let text = "I don't want the world. I just want your half.\r\n";
         let info = count(
-            Cursor::new(text) // Read,Writeを実装するバッファに文字列を格納: テスト用の擬似ファイルハンドラとして利用
+            Cursor::new(text), // Read,Writeを実装するバッファに文字列を格納: テスト用の擬似ファイルハンドラとして利用
+            CountOptions::default(),
+            None,
         );
         assert!(info.is_ok());
         let expected = FileInfo {
@@ -197,10 +533,122 @@ mod tests {
             num_words: 10,
             num_bytes: 48,
             num_chars: 48,
+            num_graphemes: 0,
+            num_width: 0,
         };
         assert_eq!(info.unwrap(), expected); // 内部要素を部分比較: PartialEqを実装しているため
     }
 
+    #[test]
+    fn test_count_graphemes_width() {
+        let text = "日本語\ne\u{301}👍🏽\r\n";
+        let options = CountOptions {
+            graphemes: true,
+            width: true,
+            ..Default::default()
+        };
+        let info = count(Cursor::new(text), options, None);
+        assert!(info.is_ok());
+        let expected = FileInfo {
+            num_lines: 2,
+            num_words: 2,
+            num_bytes: 23,
+            num_chars: 10,
+            num_graphemes: 7,
+            num_width: 9,
+        };
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_count_words_regex() {
+        let text = "I don't want the world. I just want your half.\r\n";
+        let re = regex::Regex::new(r"[A-Za-z']+").unwrap();
+        let options = CountOptions {
+            tokenizer: Tokenizer::Regex(&re),
+            ..Default::default()
+        };
+        let mut words = Default::default();
+        let info = count(Cursor::new(text), options, Some(&mut words)).unwrap();
+        assert_eq!(info.num_words, 10);
+        assert_eq!(words.top(2), [("I", 2), ("want", 2)]);
+    }
+
+    #[test]
+    fn test_file_size() {
+        let info = file_size("tests/inputs/fox.txt");
+        assert_eq!(
+            info,
+            Some(FileInfo {
+                num_bytes: 48,
+                ..Default::default()
+            })
+        );
+
+        // 標準入力やディレクトリ、存在しないファイルは読み込みで処理する
+        assert_eq!(file_size("-"), None);
+        assert_eq!(file_size("tests/inputs"), None);
+        assert_eq!(file_size("tests/inputs/missing.txt"), None);
+    }
+
+    fn totals(num_files: usize) -> Totals {
+        let mut totals = Totals::new(num_files);
+        let info = FileInfo {
+            num_lines: 1,
+            num_words: 2,
+            num_bytes: 3,
+            ..Default::default()
+        };
+        for _ in 0..num_files {
+            totals.add(&info);
+        }
+        totals
+    }
+
+    #[test]
+    fn test_parse_total_policy() {
+        assert_eq!("auto".parse::<TotalPolicy>(), Ok(TotalPolicy::Auto));
+        assert_eq!("always".parse::<TotalPolicy>(), Ok(TotalPolicy::Always));
+        assert_eq!("only".parse::<TotalPolicy>(), Ok(TotalPolicy::Only));
+        assert_eq!("never".parse::<TotalPolicy>(), Ok(TotalPolicy::Never));
+        assert_eq!(
+            "sometimes".parse::<TotalPolicy>(),
+            Err("Invalid --total \"sometimes\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_totals_add() {
+        let expected = FileInfo {
+            num_lines: 2,
+            num_words: 4,
+            num_bytes: 6,
+            ..Default::default()
+        };
+        assert_eq!(totals(2).report(TotalPolicy::Always), Some(&expected));
+    }
+
+    #[test]
+    fn test_totals_report_auto() {
+        assert!(totals(1).report(TotalPolicy::Auto).is_none());
+        assert!(totals(2).report(TotalPolicy::Auto).is_some());
+    }
+
+    #[test]
+    fn test_totals_report_always_only() {
+        for policy in [TotalPolicy::Always, TotalPolicy::Only] {
+            assert!(totals(0).report(policy).is_some());
+            assert!(totals(1).report(policy).is_some());
+            assert!(totals(2).report(policy).is_some());
+        }
+    }
+
+    #[test]
+    fn test_totals_report_never() {
+        assert!(totals(1).report(TotalPolicy::Never).is_none());
+        assert!(totals(2).report(TotalPolicy::Never).is_none());
+    }
+
     #[test]
     fn test_format_field() {
         assert_eq!(format_field(1, false), "");
@@ -0,0 +1,282 @@
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::{format_field, FileInfo, MyResult};
+
+// 出力形式: plainは従来の固定幅のカラム表示
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    Plain,
+    Json,
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!("Invalid format \"{}\"", s)),
+        }
+    }
+}
+
+// 出力対象のカウント種別
+#[derive(Debug, Clone, Copy)]
+pub struct Columns {
+    pub lines: bool,
+    pub words: bool,
+    pub bytes: bool,
+    pub chars: bool,
+    pub graphemes: bool,
+    pub width: bool,
+}
+
+// JSONの1レコード: 出力対象外のカウントはキーごと省略する
+#[derive(Debug, PartialEq, Serialize)]
+struct Record {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    words: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chars: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    graphemes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<usize>,
+}
+
+// --top: 出現回数の多い単語
+#[derive(Debug, PartialEq, Serialize)]
+struct WordCount {
+    word: String,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    files: &'a [Record],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<Record>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top: Option<&'a [WordCount]>,
+}
+
+impl Record {
+    fn new(name: Option<&str>, info: &FileInfo, columns: Columns) -> Self {
+        let pick = |value: usize, show: bool| if show { Some(value) } else { None };
+        Record {
+            name: name.map(String::from),
+            lines: pick(info.num_lines, columns.lines),
+            words: pick(info.num_words, columns.words),
+            bytes: pick(info.num_bytes, columns.bytes),
+            chars: pick(info.num_chars, columns.chars),
+            graphemes: pick(info.num_graphemes, columns.graphemes),
+            width: pick(info.num_width, columns.width),
+        }
+    }
+}
+
+// ファイルごとの結果と合計を指定の形式で出力する: JSONは全体を1つのオブジェクトにするため最後にまとめて出力
+pub struct Printer {
+    format: Format,
+    columns: Columns,
+    records: Vec<Record>,
+    top: Option<Vec<WordCount>>,
+    stdin: Option<String>, // 標準入力("-")の代わりに出力する名前: Noneの場合plainでは名前を省略する
+}
+
+impl Printer {
+    pub fn new(format: Format, columns: Columns) -> Self {
+        Printer {
+            format,
+            columns,
+            records: vec![],
+            top: None,
+            stdin: None,
+        }
+    }
+
+    pub fn stdin_label(mut self, label: Option<String>) -> Self {
+        self.stdin = label;
+        self
+    }
+
+    // 合計の後に出力する単語の一覧: totalより前に渡す
+    pub fn top(&mut self, words: &[(&str, usize)]) {
+        self.top = Some(
+            words
+                .iter()
+                .map(|(word, count)| WordCount {
+                    word: word.to_string(),
+                    count: *count,
+                })
+                .collect(),
+        );
+    }
+
+    pub fn file(&mut self, filename: &str, info: &FileInfo) {
+        let filename = match (filename, &self.stdin) {
+            ("-", Some(label)) => label.as_str(),
+            _ => filename,
+        };
+        match self.format {
+            Format::Plain => println!(
+                "{}{}",
+                self.plain_fields(info),
+                if filename == "-" && self.stdin.is_none() {
+                    "".to_string()
+                } else {
+                    format!(" {}", filename)
+                }
+            ),
+            Format::Tsv => println!("{}\t{}", self.tsv_fields(info), filename),
+            Format::Json => self
+                .records
+                .push(Record::new(Some(filename), info, self.columns)),
+        }
+    }
+
+    // 合計が不要な場合はNone: labelがfalseの場合は"total"を付けずに数値のみ出力
+    pub fn total(&self, total: Option<&FileInfo>, label: bool) -> MyResult<()> {
+        match (self.format, total) {
+            (Format::Plain, Some(total)) => println!(
+                "{}{}",
+                self.plain_fields(total),
+                if label { " total" } else { "" }
+            ),
+            (Format::Tsv, Some(total)) => println!(
+                "{}{}",
+                self.tsv_fields(total),
+                if label { "\ttotal" } else { "" }
+            ),
+            (Format::Json, total) => println!("{}", self.json(total)?),
+            _ => {}
+        }
+        // カウントの行と区別するため空行の後に出力する
+        if let (Some(top), Format::Plain | Format::Tsv) = (&self.top, self.format) {
+            println!();
+            for WordCount { word, count } in top {
+                match self.format {
+                    Format::Tsv => println!("{}\t{}", count, word),
+                    _ => println!("{} {}", format_field(*count, true), word),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn plain_fields(&self, info: &FileInfo) -> String {
+        format!(
+            "{}{}{}{}{}{}",
+            format_field(info.num_lines, self.columns.lines),
+            format_field(info.num_words, self.columns.words),
+            format_field(info.num_bytes, self.columns.bytes),
+            format_field(info.num_chars, self.columns.chars),
+            format_field(info.num_graphemes, self.columns.graphemes),
+            format_field(info.num_width, self.columns.width),
+        )
+    }
+
+    fn tsv_fields(&self, info: &FileInfo) -> String {
+        [
+            (info.num_lines, self.columns.lines),
+            (info.num_words, self.columns.words),
+            (info.num_bytes, self.columns.bytes),
+            (info.num_chars, self.columns.chars),
+            (info.num_graphemes, self.columns.graphemes),
+            (info.num_width, self.columns.width),
+        ]
+        .iter()
+        .filter(|(_, show)| *show)
+        .map(|(value, _)| value.to_string())
+        .collect::<Vec<_>>()
+        .join("\t")
+    }
+
+    fn json(&self, total: Option<&FileInfo>) -> MyResult<String> {
+        let report = Report {
+            files: &self.records,
+            total: total.map(|total| Record::new(None, total, self.columns)),
+            top: self.top.as_deref(),
+        };
+        Ok(serde_json::to_string(&report)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Columns, Format, Printer};
+    use crate::FileInfo;
+
+    const COLUMNS: Columns = Columns {
+        lines: true,
+        words: true,
+        bytes: true,
+        chars: false,
+        graphemes: false,
+        width: false,
+    };
+
+    const FOX: FileInfo = FileInfo {
+        num_lines: 1,
+        num_words: 9,
+        num_bytes: 48,
+        num_chars: 48,
+        num_graphemes: 48,
+        num_width: 47,
+    };
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("plain".parse::<Format>(), Ok(Format::Plain));
+        assert_eq!("json".parse::<Format>(), Ok(Format::Json));
+        assert_eq!("tsv".parse::<Format>(), Ok(Format::Tsv));
+        assert_eq!(
+            "csv".parse::<Format>(),
+            Err("Invalid format \"csv\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let printer = Printer::new(Format::Tsv, COLUMNS);
+        assert_eq!(printer.tsv_fields(&FOX), "1\t9\t48");
+        assert_eq!(printer.plain_fields(&FOX), "       1       9      48");
+    }
+
+    #[test]
+    fn test_json() {
+        let mut printer = Printer::new(Format::Json, COLUMNS);
+        printer.file("fox.txt", &FOX);
+        let json = printer.json(Some(&FOX));
+        assert!(json.is_ok());
+        assert_eq!(
+            json.unwrap(),
+            r#"{"files":[{"name":"fox.txt","lines":1,"words":9,"bytes":48}],"total":{"lines":1,"words":9,"bytes":48}}"#
+        );
+
+        // 合計が不要な場合はキーごと省略する
+        let json = printer.json(None);
+        assert_eq!(
+            json.unwrap(),
+            r#"{"files":[{"name":"fox.txt","lines":1,"words":9,"bytes":48}]}"#
+        );
+
+        printer.top(&[("the", 2), ("fox", 1)]);
+        let json = printer.json(None);
+        assert_eq!(
+            json.unwrap(),
+            r#"{"files":[{"name":"fox.txt","lines":1,"words":9,"bytes":48}],"top":[{"word":"the","count":2},{"word":"fox","count":1}]}"#
+        );
+    }
+}
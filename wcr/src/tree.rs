@@ -0,0 +1,115 @@
+use walkdir::WalkDir;
+
+use crate::FileInfo;
+
+// ディレクトリの階層ごとの小計: levels[d]は深さdのディレクトリ(処理中のもの)の小計
+#[derive(Debug, Default)]
+pub struct Subtotals {
+    levels: Vec<FileInfo>,
+}
+
+impl Subtotals {
+    // 深さdepthのファイルのカウントを、それを含むディレクトリの小計に加える
+    pub fn add_file(&mut self, depth: usize, info: &FileInfo) {
+        self.level(depth - 1).add(info);
+    }
+
+    // 深さdepthのディレクトリの小計を確定させて返す: 中身を全て処理した後に呼び出す
+    pub fn close_dir(&mut self, depth: usize) -> FileInfo {
+        let subtotal = std::mem::take(self.level(depth));
+        if depth > 0 {
+            self.level(depth - 1).add(&subtotal);
+        }
+        subtotal
+    }
+
+    fn level(&mut self, depth: usize) -> &mut FileInfo {
+        if self.levels.len() <= depth {
+            self.levels.resize_with(depth + 1, FileInfo::default);
+        }
+        &mut self.levels[depth]
+    }
+}
+
+// 木構造を辿る際の1要素: ディレクトリは中身の後に現れる
+#[derive(Debug, PartialEq)]
+pub enum Entry {
+    File { path: String, depth: usize },
+    Dir { path: String, depth: usize },
+}
+
+// ディレクトリを名前順に辿り、ファイルとディレクトリを出力順に返す
+// 辿らなかったmax_depthの位置のディレクトリは小計が空になるため含めない
+pub fn walk(dirname: &str, max_depth: Option<usize>) -> impl Iterator<Item = Result<Entry, walkdir::Error>> {
+    let mut walker = WalkDir::new(dirname)
+        .contents_first(true)
+        .sort_by_file_name();
+    if let Some(max_depth) = max_depth {
+        walker = walker.max_depth(max_depth);
+    }
+    walker.into_iter().filter_map(move |entry| {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e)),
+        };
+        let path = entry.path().display().to_string();
+        let depth = entry.depth();
+        if !entry.file_type().is_dir() {
+            Some(Ok(Entry::File { path, depth }))
+        } else if depth > 0 && Some(depth) == max_depth {
+            None
+        } else {
+            Some(Ok(Entry::Dir { path, depth }))
+        }
+    })
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{walk, Entry, Subtotals};
+    use crate::FileInfo;
+
+    fn lines(num_lines: usize) -> FileInfo {
+        FileInfo {
+            num_lines,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_subtotals() {
+        // root/{a, sub/{b, c}, d}
+        let mut subtotals = Subtotals::default();
+        subtotals.add_file(1, &lines(1));
+        subtotals.add_file(2, &lines(2));
+        subtotals.add_file(2, &lines(3));
+        assert_eq!(subtotals.close_dir(1), lines(5));
+        subtotals.add_file(1, &lines(4));
+        assert_eq!(subtotals.close_dir(0), lines(10));
+
+        // 確定させた小計は次のディレクトリに持ち越さない
+        assert_eq!(subtotals.close_dir(1), lines(0));
+    }
+
+    #[test]
+    fn test_walk() {
+        let entries: Vec<_> = walk("tests/inputs/tree", None).map(Result::unwrap).collect();
+        assert_eq!(
+            entries,
+            vec![
+                Entry::File { path: "tests/inputs/tree/fox.txt".to_string(), depth: 1 },
+                Entry::File { path: "tests/inputs/tree/sub/deep/unicode.txt".to_string(), depth: 3 },
+                Entry::Dir { path: "tests/inputs/tree/sub/deep".to_string(), depth: 2 },
+                Entry::File { path: "tests/inputs/tree/sub/empty.txt".to_string(), depth: 2 },
+                Entry::Dir { path: "tests/inputs/tree/sub".to_string(), depth: 1 },
+                Entry::Dir { path: "tests/inputs/tree".to_string(), depth: 0 },
+            ]
+        );
+
+        // max_depthより深い位置は辿らない
+        let entries: Vec<_> = walk("tests/inputs/tree", Some(2)).map(Result::unwrap).collect();
+        assert_eq!(entries.len(), 4);
+        assert!(!entries.iter().any(|entry| matches!(entry, Entry::Dir { depth: 2, .. })));
+    }
+}
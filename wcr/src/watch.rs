@@ -0,0 +1,138 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    thread,
+    time::Duration,
+};
+
+use crate::{count, output::Printer, CountOptions, FileInfo, MyResult, TotalPolicy, Totals};
+
+// 監視中のファイル: 前回までに数えた完全な行の位置とカウントを保持し、次回はその位置から数え直す
+#[derive(Debug)]
+pub struct Watched {
+    name: String,
+    offset: u64,      // 改行まで読み終えた位置
+    base: FileInfo,   // offsetまでのカウント
+    info: FileInfo,   // 改行で終わっていない末尾を含めた現在のカウント
+    len: Option<u64>, // 前回確認したファイルサイズ: 未確認の場合はNone
+}
+
+impl Watched {
+    pub fn new(name: &str) -> Self {
+        Watched {
+            name: name.to_string(),
+            offset: 0,
+            base: FileInfo::default(),
+            info: FileInfo::default(),
+            len: None,
+        }
+    }
+
+    // サイズが変わっていれば前回の位置から数え直し、カウントが変わった場合にtrueを返す
+    fn update(&mut self, options: CountOptions) -> MyResult<bool> {
+        let len = fs::metadata(&self.name)?.len();
+        if self.len == Some(len) {
+            return Ok(false);
+        }
+        // 切り詰められた場合は先頭から数え直す
+        if len < self.offset {
+            self.offset = 0;
+            self.base = FileInfo::default();
+        }
+        self.len = Some(len);
+
+        let mut file = File::open(&self.name)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut buf = vec![];
+        file.take(len - self.offset).read_to_end(&mut buf)?;
+
+        // 改行までの部分は確定させ、行の途中で終わる末尾は毎回数え直す
+        let split = buf.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        self.base.add(&count(&buf[..split], options, None)?);
+        self.offset += split as u64;
+        let mut info = count(&buf[split..], options, None)?;
+        info.add(&self.base);
+
+        let changed = info != self.info;
+        self.info = info;
+        Ok(changed)
+    }
+}
+
+// 一定間隔でファイルを数え直し、カウントが変わったファイルの行と合計を出力し続ける
+pub fn watch(
+    mut files: Vec<Watched>,
+    interval: Duration,
+    options: CountOptions,
+    policy: TotalPolicy,
+    printer: &mut Printer,
+) -> MyResult<()> {
+    let mut first = true;
+    loop {
+        poll(&mut files, options, policy, first, printer)?;
+        first = false;
+        thread::sleep(interval);
+    }
+}
+
+// 全ファイルを1回ずつ数え直す: 初回は全ファイルの行を出力する
+fn poll(
+    files: &mut [Watched],
+    options: CountOptions,
+    policy: TotalPolicy,
+    first: bool,
+    printer: &mut Printer,
+) -> MyResult<()> {
+    let mut totals = Totals::new(files.len());
+    let mut changed = false;
+    for watched in files.iter_mut() {
+        match watched.update(options) {
+            Err(e) => eprintln!("{}: {}", watched.name, e),
+            Ok(updated) => {
+                if (first || updated) && policy != TotalPolicy::Only {
+                    printer.file(&watched.name, &watched.info);
+                }
+                changed |= updated;
+            },
+        }
+        totals.add(&watched.info);
+    }
+    if first || changed {
+        printer.total(totals.report(policy), policy != TotalPolicy::Only)?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::Watched;
+    use crate::{count, CountOptions};
+    use std::{fs, io::Write};
+
+    #[test]
+    fn test_update() {
+        let path = std::env::temp_dir().join(format!("wcr-watch-{}", std::process::id()));
+        fs::write(&path, "one two\nthr").unwrap();
+        let mut watched = Watched::new(path.to_str().unwrap());
+        let options = CountOptions::default();
+
+        let mut check = |appended: &str, expected_changed: bool| {
+            let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+            file.write_all(appended.as_bytes()).unwrap();
+            assert_eq!(watched.update(options).unwrap(), expected_changed);
+            assert_eq!(watched.info, count(fs::read(&path).unwrap().as_slice(), options, None).unwrap());
+        };
+        check("", true); // 初回は全体を数える
+        check("", false);
+        check("ee four\n", true); // 行の途中から追記された場合も単語を分割しない
+        check("five", true);
+
+        // 切り詰められた場合は先頭から数え直す
+        fs::write(&path, "six\n").unwrap();
+        assert!(watched.update(options).unwrap());
+        assert_eq!(watched.info, count("six\n".as_bytes(), options, None).unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
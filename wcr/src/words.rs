@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+// 単語の区切り方: 正規表現を指定した場合は一致した部分を単語とする
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Tokenizer<'a> {
+    #[default]
+    Whitespace,
+    Regex(&'a Regex),
+}
+
+impl Tokenizer<'_> {
+    pub fn count(&self, line: &str) -> usize {
+        match self {
+            Tokenizer::Whitespace => line.split_whitespace().count(), // 空白文字の区切りでカウント
+            Tokenizer::Regex(re) => re.find_iter(line).filter(|m| !m.is_empty()).count(), // 空の一致は単語としない
+        }
+    }
+
+    pub fn add_words(&self, line: &str, frequencies: &mut Frequencies) {
+        match self {
+            Tokenizer::Whitespace => line.split_whitespace().for_each(|word| frequencies.add(word)),
+            Tokenizer::Regex(re) => re
+                .find_iter(line)
+                .filter(|m| !m.is_empty())
+                .for_each(|m| frequencies.add(m.as_str())),
+        }
+    }
+}
+
+// 単語ごとの出現回数: 大文字と小文字は区別する
+#[derive(Debug, Default)]
+pub struct Frequencies {
+    counts: HashMap<String, usize>,
+}
+
+impl Frequencies {
+    pub fn add(&mut self, word: &str) {
+        // 既出の単語ではStringを確保しない
+        match self.counts.get_mut(word) {
+            Some(count) => *count += 1,
+            None => {
+                self.counts.insert(word.to_string(), 1);
+            },
+        }
+    }
+
+    // 出現回数の多い順にn個: 同じ回数の単語は辞書順
+    pub fn top(&self, n: usize) -> Vec<(&str, usize)> {
+        let mut words: Vec<_> = self.counts.iter().map(|(word, count)| (word.as_str(), *count)).collect();
+        words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        words.truncate(n);
+        words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frequencies, Tokenizer};
+    use regex::Regex;
+
+    #[test]
+    fn test_count() {
+        let line = "I don't want the world.\n";
+        assert_eq!(Tokenizer::Whitespace.count(line), 5);
+        let re = Regex::new(r"[A-Za-z]+").unwrap();
+        assert_eq!(Tokenizer::Regex(&re).count(line), 6); // don と t
+        let re = Regex::new(r"\w*").unwrap();
+        assert_eq!(Tokenizer::Regex(&re).count("a  b\n"), 2);
+    }
+
+    #[test]
+    fn test_top() {
+        let mut frequencies = Frequencies::default();
+        let re = Regex::new(r"\w+").unwrap();
+        Tokenizer::Regex(&re).add_words("b a c, a b a\n", &mut frequencies);
+        Tokenizer::Whitespace.add_words("c d A\n", &mut frequencies);
+        assert_eq!(frequencies.top(3), [("a", 3), ("b", 2), ("c", 2)]);
+        assert_eq!(frequencies.top(10).len(), 5);
+        assert_eq!(frequencies.top(0), []);
+    }
+}
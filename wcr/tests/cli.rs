@@ -9,6 +9,8 @@ const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const UNICODE: &str = "tests/inputs/unicode.txt";
+const TREE: &str = "tests/inputs/tree";
 
 // --------------------------------------------------
 fn gen_bad_file() -> String {
@@ -29,7 +31,7 @@ fn gen_bad_file() -> String {
 #[test]
 fn dies_chars_and_bytes() -> TestResult {
     Command::cargo_bin(PRG)?
-        .args(&["-m", "-c"])
+        .args(["-m", "-c"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(
@@ -212,3 +214,321 @@ fn test_all_words_lines() -> TestResult {
 fn test_all_bytes_lines() -> TestResult {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_format() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--format", "csv", FOX])
+        .assert()
+        .failure()
+        .stderr("Invalid format \"csv\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_plain() -> TestResult {
+    run(
+        &["--format", "plain", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_tsv() -> TestResult {
+    run(
+        &["--format", "tsv", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_json() -> TestResult {
+    run(
+        &["--format", "json", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_lines_chars_json() -> TestResult {
+    run(
+        &["--format", "json", "-l", "-m", FOX],
+        "tests/expected/fox.txt.lm.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn unicode_graphemes_width() -> TestResult {
+    run(
+        &["--graphemes", "--width", UNICODE],
+        "tests/expected/unicode.txt.graphemes.width.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn unicode_fox_chars_graphemes_width() -> TestResult {
+    run(
+        &["-m", "--graphemes", "--width", UNICODE, FOX],
+        "tests/expected/unicode.fox.m.graphemes.width.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_total() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--total", "sometimes", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --total \"sometimes\""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn all_total_only() -> TestResult {
+    run(
+        &["--total=only", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_only.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_total_never() -> TestResult {
+    run(
+        &["--total", "never", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_never.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_total_always() -> TestResult {
+    run(
+        &["--total", "always", FOX],
+        "tests/expected/fox.txt.total_always.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_total_never_json() -> TestResult {
+    run(
+        &["--format", "json", "--total", "never", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_never.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn all_total_only_tsv() -> TestResult {
+    run(
+        &["--format", "tsv", "--total", "only", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_only.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_interval() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--interval=-1", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --interval \"-1\""));
+    Ok(())
+}
+
+#[test]
+fn dies_interval_stdin() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--interval", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--interval cannot be used with STDIN"));
+    Ok(())
+}
+
+#[test]
+fn dies_interval_json() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--interval", "1", "--format", "json", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--interval cannot be used with --format json"));
+    Ok(())
+}
+
+#[test]
+fn interval_growing_file() -> TestResult {
+    use std::{io::Write, process::Stdio, thread, time::Duration};
+
+    let path = std::env::temp_dir().join(format!("wcr-{}", gen_bad_file()));
+    let name = path.display().to_string();
+    fs::write(&path, "one two\n")?;
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(["--interval", "0.05", "-lw", &name, FOX])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let wait = || thread::sleep(Duration::from_millis(500));
+    wait();
+    fs::OpenOptions::new().append(true).open(&path)?.write_all(b"three")?;
+    wait();
+    fs::OpenOptions::new().append(true).open(&path)?.write_all(b" four\n")?;
+    wait();
+    child.kill()?;
+    let output = child.wait_with_output()?;
+    fs::remove_file(&path)?;
+
+    // 初回は全ファイルを出力し、以降は変化したファイルと合計のみを出力する
+    let expected = [
+        format!("       1       2 {}", name),
+        format!("       1       9 {}", FOX),
+        "       2      11 total".to_string(),
+        format!("       2       3 {}", name), // 改行で終わらない末尾も1行として数える
+        "       3      12 total".to_string(),
+        format!("       2       4 {}", name),
+        "       3      13 total\n".to_string(),
+    ]
+    .join("\n");
+    assert_eq!(String::from_utf8(output.stdout)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn tree_fox_recursive() -> TestResult {
+    run(
+        &["-r", TREE, FOX],
+        "tests/expected/tree.fox.r.out",
+    )
+}
+
+#[test]
+fn tree_recursive_max_depth_tsv() -> TestResult {
+    run(
+        &["--recursive", "--max-depth", "2", "--format", "tsv", TREE],
+        "tests/expected/tree.r.max_depth2.tsv.out",
+    )
+}
+
+#[test]
+fn dies_bad_max_depth() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-r", "--max-depth", "x", TREE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid --max-depth \"x\""));
+    Ok(())
+}
+
+#[test]
+fn dies_max_depth_without_recursive() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--max-depth", "1", TREE])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_words_regex() -> TestResult {
+    // 小文字のみの単語を数える: "The"は含めない
+    Command::cargo_bin(PRG)?
+        .args(["-w", "--words-regex", r"\b[a-z]+\b", FOX])
+        .assert()
+        .success()
+        .stdout(format!("{:>8} {}\n", 8, FOX));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_top() -> TestResult {
+    run(&["--top", "3", ATLAMAL, EMPTY, FOX, UNICODE], "tests/expected/all.top3.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_words_regex_top_tsv() -> TestResult {
+    run(
+        &["-w", "--words-regex", "[A-Za-z]+", "--top", "2", "--format", "tsv", FOX],
+        "tests/expected/fox.txt.words_regex.top2.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_words_regex_top() -> TestResult {
+    for (args, expected) in [
+        (["--words-regex", "*", FOX], "Invalid --words-regex \"*\""),
+        (["--top", "0", FOX], "Invalid --top \"0\""),
+        (["--top", "x", FOX], "Invalid --top \"x\""),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_stdin_label() -> TestResult {
+    let input = fs::read_to_string(FOX)?;
+    Command::cargo_bin(PRG)?
+        .args(["--stdin-label", "build.log"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout("       1       9      48 build.log\n");
+    Command::cargo_bin(PRG)?
+        .args(["--label", "build.log", "--format", "tsv", "-", FOX])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(format!("1\t9\t48\tbuild.log\n1\t9\t48\t{}\n2\t18\t96\ttotal\n", FOX));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_stdin_mixed_twice() -> TestResult {
+    // STDINは1回しか読めないため、2回目は0として数える
+    Command::cargo_bin(PRG)?
+        .args([FOX, "-", "-", FOX])
+        .write_stdin(fs::read_to_string(FOX)?)
+        .assert()
+        .success()
+        .stdout(format!(
+            "       1       9      48 {0}\n       1       9      48 -\n       0       0       0 -\n       1       9      48 {0}\n       3      27     144 total\n",
+            FOX
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn library_count() -> TestResult {
+    let mut words = wcr::Frequencies::default();
+    let info = wcr::count(fs::read(FOX)?.as_slice(), wcr::CountOptions::default(), Some(&mut words))?;
+    assert_eq!((info.num_lines, info.num_words, info.num_bytes), (1, 9, 48));
+    assert_eq!(words.top(1), [("The", 1)]);
+
+    let mut total = wcr::FileInfo::default();
+    total.add(&info);
+    total.add(&info);
+    assert_eq!(total.num_chars, 96);
+    Ok(())
+}